@@ -0,0 +1,700 @@
+# Everything the applications say to the user, in English.  Other catalogs translate these same ids, and fall back
+# to this one for any they leave out.  Numbers, sizes and times arrive already formatted.
+
+## Choosing folders
+
+choose-reference = Choose the reference folder, which is never changed
+choose-folder-to-clean = Choose the folder to clean up
+choose-extra-root = Choose a folder to deduplicate along with the others
+choose-export-file = Export the duplicate report
+choose-dry-run-file = Save what a dry run would do
+choose-outcomes-file = Save what was done to each file
+data-folder = Data Folder: { $folder }
+
+## File manager integration
+
+integration-title = File Manager Integration
+integration-installed = Installed "Find duplicates here" for { $file_manager } at { $location }
+integration-failed = Failed to install file manager integration for { $file_manager }: { $error }
+
+## Filters
+
+filter-include = Only include:
+filter-include-example = e.g. *.jpg, *.png
+filter-exclude = Exclude:
+filter-exclude-example = e.g. node_modules, .git, *.tmp
+filter-size = Size in bytes:
+filter-min-size = at least, e.g. 1 to skip empty files
+filter-max-size = at most
+filter-skip-unique-sizes = only sizes shared by others
+filter-archives = look inside archives
+filter-min-size-invalid = The minimum size must be a number of bytes
+filter-max-size-invalid = The maximum size must be a number of bytes
+filter-symlinks = Symbolic links:
+filter-invalid = Invalid filter: { $error }
+profile-name-example = Profile name, e.g. Photos cleanup
+profile-save = Save as Profile
+
+## Scanning
+
+roots-heading = Along with the folders:
+roots-remove = Remove
+roots-add = Add Folder
+roots-overlap = '{ $folder }' and the reference '{ $reference }' overlap! Choose a separate folder.
+work-folder = Folder for deduplication: { $folder }
+work-reference = Compared against (never changed): { $folder }
+work-audit-mode = Audit mode: files are only scanned and reported, never changed.
+work-pipeline = Hash files while finding them
+work-scan = Scan
+scan-walking = Finding files...
+scan-walking-hashed = Finding files... hashed { $files } of the { $total_files } found so far ({ $bytes } of { $total_bytes })
+scan-hashing = Hashing { $files } of { $total_files } files ({ $bytes } of { $total_bytes })
+scan-rate = Hashing { $rate } a second
+scan-time-left = Hashing { $rate } a second, about { $left } left
+scan-pause = Pause
+scan-cancel = Cancel
+scan-pausing = Pausing, keeping what was hashed so far...
+scan-stopping = Stopping, keeping what was found so far...
+work-found-groups = Found { $groups } duplicate groups.
+work-partial = The scan stopped early, so there may be more. Resume it to hash only the files it didn't get to.
+work-hard-links = { $files } files already have several hard links. They aren't duplicates, so each was counted once.
+work-unreadable = { $files } files or folders couldn't be read.
+work-show-errors = Show Errors
+work-hide-errors = Hide Errors
+work-watch = Watch for changes
+watch-needs-scan = Scan again to watch for changes, the files found last time weren't kept.
+work-review = Review Duplicates
+work-scan-again = Scan Again
+work-resume-scan = Resume Scan
+work-find-images = Find Similar Images
+work-finding-images = Finding Similar Images...
+work-find-audio = Find Same Audio
+work-finding-audio = Finding Same Audio...
+work-find-archives = Find Same Archives
+work-finding-archives = Finding Same Archives...
+work-similar-images = { $groups } groups of pictures look the same, though their files differ.
+work-same-audio = { $groups } groups of recordings hold the same audio, though their files differ.
+work-same-archives = { $groups } groups of archives hold the same files, though they were packed apart.
+similar-unreadable = { $files } files couldn't be read.
+denylist-read-failed = Failed to read the ignored contents: { $error }
+scan-conflict = Can't scan now: { $error }
+project-save-failed = Failed to save project: { $error }
+project-read-failed = Failed to read the project: { $error }
+error-permission-denied = Permission denied
+error-vanished = Gone before it was read
+error-changed = Changed during the scan
+error-locked = Skipped while locked by another program
+error-io = Could not be read
+
+## Reviewing duplicates
+
+back = Back
+results-not-scanned = This project has not been scanned.
+group-summary = { $copies } copies of { $size }, { $wasted } wasted, { $reclaimable } of disk to reclaim, { $selected } selected{ $status }
+group-partly-resolved = , partly resolved: { $done } done, { $remaining } left
+group-resolved = , resolved
+group-never-flag = Never Flag
+file-reference-copy = Reference copy
+file-in-archive = Inside an archive
+file-mislabeled = Possibly mislabeled, the other copies suggest "{ $suggested }"
+results-all-folders = All Folders
+results-by-folder = View by Folder
+results-duplicate-folders = Duplicate Folders
+results-name-collisions = Name Collisions
+results-keep = Keep:
+results-select-duplicates = Select Duplicates
+results-select-archived = Select Copies of Archived Files
+results-summary = { $groups } duplicate groups, { $wasted } wasted ({ $allocated } of disk), { $selected } selected for removal
+results-reclaimable = Keeping the { $policy } copy of each group reclaims { $size } ({ $allocated } of disk)
+results-clear-selection = Clear Selection
+results-dry-run = Dry Run
+results-export-json = Export JSON
+results-export-csv = Export CSV
+results-compare-with = Compare with:
+results-search = Only groups with a path containing...
+results-sort = Sort by:
+sort-wasted = space wasted
+sort-size = file size
+sort-copies = number of copies
+results-previous-page = Previous
+results-page = Page { $page } of { $pages }
+results-next-page = Next
+file-preview = Preview
+file-open = Open
+file-reveal = Show in folder
+file-open-failed = Couldn't open { $file }: { $error }
+file-reveal-failed = Couldn't show { $file } in its folder: { $error }
+preview-title = Preview
+preview-close = Close
+preview-size = { $size } ({ $allocated } of disk)
+preview-created = Created { $time }
+preview-modified = Modified { $time }
+preview-binary = Not a picture or text that can be shown.
+preview-in-archive = Inside an archive, which has to be unpacked to look into it.
+preview-unreadable = Couldn't look into the file: { $error }
+folders-top-level = (top level)
+folders-open = Open
+folders-show = Show
+folders-summary = { $folder }: { $wasted } duplicated, { $files } files in { $groups } groups
+file-already-done = Already { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }
+results-by-group = View by Group
+folders-up = Up
+folders-show-all = Show All Here
+folders-holding-duplicates = { $folders } folders in it hold duplicates
+collisions-summary = { $names } names shared by different contents
+collision-summary = { $name }: { $versions } versions
+collision-version = { $hash }, { $size }, last changed { $modified }
+time-unknown = unknown
+folder-groups-summary = { $groups } groups of duplicate folders, { $wasted } wasted
+folder-group-summary = { $copies } copies of { $files } files, { $size } each, { $wasted } wasted
+similar-folders-summary = { $pairs } pairs of folders sharing at least { $similarity }% of their contents
+similar-folders-pair = { $similarity }% alike, { $shared } of { $total } contents: { $first } and { $second }
+comparison-summary = Keeping { $other } instead of { $policy } would remove { $removed } other files and spare { $spared }, freeing { $freed } { $direction ->
+        [less] less
+       *[more] more
+    }.
+rules-rule = Rule:
+rules-remove = Remove
+rules-under-example = folder
+rules-matching-example = glob, e.g. *.orig
+rules-containing-example = text, e.g. /originals/
+rules-add = Add Rule
+rules-select = Select by Rules
+empty-summary = { $files } empty files, not counted as duplicates
+empty-remove = Remove Empty Files
+
+## Acting on the selection
+
+apply-selected = { $action ->
+        [trash] Trash Selected
+        [delete] Delete Selected
+        [link] Link Selected
+        [symlink] Symlink Selected
+       *[clone] Clone Selected
+    }
+outcome-dry-run = Dry run: { $done } of { $files } files would be { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }, reclaiming { $size } ({ $allocated } of disk).
+outcome-applied = { $done } of { $files } files were { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }, reclaiming { $size } ({ $allocated } of disk).
+confirm-title = { $action ->
+        [trash] Trash these files?
+        [delete] Delete these files?
+        [link] Link these files?
+        [symlink] Symlink these files?
+       *[clone] Clone these files?
+    }
+confirm-summary = { $files } files will be { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }, reclaiming { $size } ({ $allocated } of disk).
+confirm-policy = Each group keeps the copy that "{ $policy }" picks, along with any not selected.
+confirm-empty = Empty files have no copy to keep, they are all trashed.
+confirm-retry = These failed last time, and each keeps the copy it was to keep then.
+confirm-step = { $file } (keeping { $keep })
+confirm-cross-device = { $copies } copies of { $keep } are on another filesystem than it, { $decision ->
+        [symlink] so they will be symlinked to it instead
+        [keep] so they are kept
+       *[refuse] so the group is left alone
+    }
+confirm-cancel = Cancel
+confirm-export-dry-run = Export Dry Run
+confirm-go = { $action ->
+        [trash] Trash Them
+        [delete] Delete Them
+        [link] Link Them
+        [symlink] Symlink Them
+       *[clone] Clone Them
+    }
+outcome-failed = Could not act on '{ $file }': { $error }
+outcome-show-report = Show Every File
+report-done = { $file }: { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }
+report-skipped = { $file }: skipped, another program has it open
+report-failed = { $file }: failed, { $error }
+report-retry = Try Again
+report-retry-failed = Try Again All Failed
+report-back = Back to Duplicates
+report-export = Export CSV
+rules-invalid = Invalid rule: { $error }
+folder-remove-failed = Failed to remove '{ $folder }': { $error }
+journal-write-failed = Stopped, the undo journal can't be written: { $error }
+export-failed = Failed to export to '{ $file }': { $error }
+
+## Ignored contents
+
+denylist-title = Ignored Contents
+denylist-intro = Files with these contents are never flagged as duplicates, in any project.
+denylist-empty = Nothing is ignored. Use "Never Flag" on a duplicate group to ignore its contents.
+denylist-allow = Stop Ignoring
+denylist-save-failed = Failed to save the ignored contents: { $error }
+
+## Settings
+
+settings-title = Settings
+settings-file = Kept in { $file }
+settings-save = Save
+settings-threads = Hashing threads:
+settings-threads-all = all cores
+settings-threads-invalid = The number of threads must be a whole number
+settings-hash = Hash:
+settings-io-limit = Read at most (MB/s):
+settings-io-limit-none = no limit
+settings-io-limit-invalid = The read limit must be a number of megabytes a second above 0
+settings-low-priority = Scan at low priority
+settings-cross-device = Across filesystems
+settings-language = Language:
+settings-language-system = system
+settings-theme = Theme:
+settings-accent = Accent color:
+settings-accent-example = #rrggbb, or the theme's own
+settings-accent-invalid = Unknown color '{ $accent }', expected one like #3366cc
+settings-filters = Filters for new projects, which keep their own once scanned
+settings-read-failed = Failed to read the settings: { $error }
+settings-save-failed = Failed to save the settings: { $error }
+settings-profiles = Profiles
+settings-no-profiles = No profiles yet.  Save one from the filters of a project.
+settings-remove-profile = Remove
+
+## Undo history
+
+interrupted-run = Deduplicating this folder was cut short before all { $files } files planned were dealt with.
+interrupted-resume = Finish It
+interrupted-roll-back = Take It Back
+interrupted-resume-failed = { $files } files still couldn't be dealt with
+interrupted-roll-back-failed = { $files } files couldn't be restored, roll back again to retry them
+interrupted-read-failed = Failed to read what was being deduplicated: { $error }
+
+history-title = Undo History
+history-intro = Restoring a file copies the kept file back to where it was, even when it was trashed.
+history-audit-mode = Audit mode is on, so nothing can be restored.
+history-empty = Nothing has been removed yet.
+history-restore = Restore
+history-action = { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }
+history-restore-failed = Failed to restore: { $error }
+history-read-failed = Failed to read the undo history: { $error }
+
+## Help
+
+help-title = Help
+help-search = Search, e.g. trash or symbolic links
+help-nothing-found = No topic mentions all of those words.
+
+help-include = Only hash files whose names match one of these glob patterns, e.g. *.jpg.
+help-exclude = Skip files and folders whose names match one of these glob patterns, e.g. node_modules or *.tmp. New projects skip the files macOS keeps beside yours, like .DS_Store and ._ resource forks, until you remove them.
+help-min-size = Skip files smaller than this many bytes. 1 skips empty files.
+help-skip-empty = Leave empty files out of the scan, as a minimum size of 1 does.
+help-max-size = Skip files larger than this many bytes.
+help-skip-unique-sizes = Skip files no other file has the size of, as they can't have copies. The folders are walked twice, but far less is kept in memory on large trees. Similar images and audio are then only found among files of the same size.
+help-archives = Look inside zip and tar archives, finding copies of the files in them too. Files in an archive are only ever read: they are listed with their copies, but nothing is done to them. Compressed tars, like .tar.gz, are left closed.
+help-symlinks = What to do with symbolic links: skip, follow or report them.
+help-symlinks-skip = Leave symbolic links out altogether.
+help-symlinks-follow = Treat links as what they point at, entering linked folders. Loops are reported as errors.
+help-symlinks-report = List links, without hashing what they point at.
+help-profile = Start from a profile saved in the settings, such as "Photos cleanup": its filters, and the keep policy and action it names.
+help-hash-algo = How contents are compared: sha256, blake3 or xxh128. xxh128 is fastest but only meant to tell files apart, the others are cryptographic.
+help-threads = How many files to hash at once. By default every core is used, once there is enough to hash to be worth it.
+help-pipeline = Hash files as soon as they are found, rather than after finding them all. Faster where listing folders is slow, as on network drives.
+help-mmap-threshold = Map files at least this large into memory to hash them, which is faster for large files such as videos.
+help-no-mmap = Read every file in chunks, rather than mapping large ones into memory. A mapped file that is truncated while being hashed crashes the scan.
+help-change-retries = Read a file that changes while it is hashed again this many times, in case it was only being saved, before listing it as changed during the scan.
+help-retry-locked = Try the files another program had locked once more at the end of the scan, rather than only listing them as locked.
+help-io-limit = Read at most this many bytes a second, so a scan in the background leaves the disk to other programs. Large files are then read in chunks rather than mapped into memory.
+help-low-priority = Hash at a lower priority, so the machine stays usable during a scan, which may then take longer.
+help-roots = Deduplicate more folders along with the first, e.g. a copy of it on another drive. Copies found across them are duplicates like any other, and a folder inside another is only scanned once.
+help-reference = Only look for copies of files in a reference folder, which is never changed.
+help-resume = Carry on from the last scan, e.g. one that was paused or ran out of time, only hashing files it didn't get to or that changed since.
+help-no-cache = Hash every file, rather than trusting the hashes of files unchanged since an earlier scan.
+help-watch = Keep the duplicate groups up to date as files are added, changed or removed, hashing only what changed.
+help-errors = List the files that couldn't be walked or hashed, grouped by what went wrong: permission denied, gone before they were read, changed while they were read, locked by another program, or any other error reading them.
+help-max-duration = Stop after this long, e.g. 90s, 30m or 6h, keeping what was found so far.
+help-keep = Which copy of each group to keep: oldest, newest, shortest, shallowest, alphabetical, capture-name, dated-folder or regex:PATTERN,...
+help-keep-oldest = Keep the file created first, going by when it was last modified where the filesystem doesn't record creation times.
+help-keep-newest = Keep the file created last, going by when it was last modified where the filesystem doesn't record creation times.
+help-keep-shortest = Keep the file with the fewest characters in its path.
+help-keep-shallowest = Keep the file in the fewest nested folders.
+help-keep-alphabetical = Keep the file whose path sorts first.
+help-keep-capture-name = For photos, keep the file whose name holds the date it was taken, e.g. IMG_20240131_101500.jpg, going by the date the camera recorded in it. Other files are kept as by alphabetical.
+help-keep-dated-folder = For photos, keep the file inside folders named after the date it was taken, e.g. 2024/01 or 2024-01-31, going by the date the camera recorded in it. Other files are kept as by alphabetical.
+help-keep-regex = Keep the file matching the earliest of a list of regular expressions, e.g. regex:/originals/,/photos/. Files matching none rank below every file that does.
+help-action = What to do with each duplicate: trash, link, reflink or delete.
+help-trash = Move each duplicate to the trash, where it can still be recovered.
+help-link = Replace each duplicate with a hard link to the kept file.
+help-reflink = Replace each duplicate with a copy-on-write clone of the kept file, sharing its storage. Needs Btrfs or XFS on Linux, or APFS on macOS.
+help-delete = Delete each duplicate permanently.
+help-symlink = Replace each duplicate with a symlink pointing to the kept file. Only used for copies on another filesystem than the kept file, see across filesystems.
+help-cross-device = Hard links and clones can't reach another filesystem. For a group with copies on one, refuse leaves the whole group alone, symlink points those copies at the kept file instead, and keep leaves just them be.
+help-verify = Compare every copy with the kept file byte for byte before acting on it.
+help-against-archives = Only trash loose files whose contents are already inside an archive. Archives are never changed.
+help-selected = Only act on the files marked for removal, leaving the rest of their groups for later.
+help-empty-files = Every empty file has the same contents, so rather than one huge duplicate group they are listed apart, and removed on their own. None of them is kept, and they are trashed unless the action deletes.
+help-duplicate-folders = Folders holding the same files under the same names, all the way down, so a copied folder can be removed whole. Only folders whose every file was scanned count, and the keep policy picks which folder survives. Once its files are trashed or deleted, what is left of a copy is removed too.
+help-rules = Mark the copies to remove by rules, applied in order to every duplicate group: "mark under FOLDER", "keep matching GLOB" or "prefer containing TEXT", with mark, keep or prefer and under, matching or containing. Keep rules win over the rest, prefer keeps the best of the files it matches and marks the other copies, and a group never has every copy marked.
+help-dry-run = Show what would be done and how much would be reclaimed, changing nothing.
+help-reclaimable = What removing every copy but the one kept would free. Hard links to one file share its disk space, so they count once, and not at all when linked to the copy kept.
+help-images = Find pictures that look the same though their files differ, e.g. after being recompressed or having their metadata edited. Only JPEG, PNG and WebP images are looked at.
+help-audio = Find recordings holding the same audio though their tags or cover art differ, e.g. after being retagged by a music player. Only MP3, FLAC, Ogg and WAV files are looked at.
+help-same-archives = Find zip and tar archives holding the same files though the archives differ, e.g. when the same folder was packed twice. The paths and contents of the files count, not when they were changed.
+help-similar-folders = Folders holding mostly the same contents, whatever the files are called, such as snapshots of one backup. Two folders are as similar as the share of the contents in either that both hold.
+help-min-similarity = How much of their contents, in percent, two folders must share to be listed as similar.
+help-name-collisions = Files with the same name but different contents, such as copies of a backup that were changed apart. Each version is listed with when it last changed, to tell which to keep by hand.
+help-max-distance = How many of the 64 bits of two pictures' perceptual hashes may differ for them to count as the same. 0 only matches pictures alike to the last detail, around 10 also some that merely look similar.
+help-config = Keep the configuration and the data of every project in DIR, rather than where the platform keeps them, e.g. ~/.config and ~/.local/share on Linux.
+help-audit = Only scan and report. Nothing outside the configuration and data folders is changed.
+help-never-flag = Put these contents on the denylist, so they are never flagged as duplicates again, in any project.
+help-undo = Every removal is recorded, and a removed file can be restored from the undo history by copying the kept file back to where it was.
+
+help-scanning = Scanning
+help-scanning-intro = A scan walks a folder, then hashes every file it found. Files with the same hash form a duplicate group. Scans can be paused and resumed, and a rescan only hashes the files that changed.
+help-filters = Filters
+help-filters-intro = Filters choose which files a scan looks at. Each project remembers its filters for later scans.
+help-keep-policies = Keep policies
+help-keep-policies-intro = A keep policy picks the copy of each group that survives. Copies in a reference folder or inside an archive are never removed, and a real file is kept over a followed link. Except with regex, the copy taking the least disk space comes first. Ties are broken by path, so the choice never changes between runs.
+help-actions = Actions
+help-actions-intro = An action is what happens to the copies that aren't kept. The action and keep policy are remembered by the project.
+help-similar-files = Similar files
+help-similar-files-intro = Besides exact duplicates, files holding the same picture or recording in different ways can be found. They are reported apart from exact duplicates, as they aren't the same byte for byte, and are never removed automatically.
+help-safety = Safety
+help-safety-intro = Nothing is removed without a kept copy of it, and there are several ways to be sure nothing is removed that shouldn't be.
+help-hash-algo-name = Hash
+help-threads-name = Threads
+help-pipeline-name = Pipeline
+help-mmap-threshold-name = Memory map threshold
+help-no-mmap-name = No memory maps
+help-change-retries-name = Change retries
+help-retry-locked-name = Retry locked files
+help-io-limit-name = I/O limit
+help-low-priority-name = Low priority
+help-roots-name = More folders
+help-reference-name = Reference folder
+help-resume-name = Resume
+help-no-cache-name = No cache
+help-max-duration-name = Maximum duration
+help-watch-name = Watch
+help-errors-name = Errors
+help-include-name = Include
+help-exclude-name = Exclude
+help-min-size-name = Minimum size
+help-max-size-name = Maximum size
+help-skip-unique-sizes-name = Skip unique sizes
+help-archives-name = Archives
+help-symlinks-name = Symbolic links
+help-symlinks-skip-name = skip
+help-symlinks-follow-name = follow
+help-symlinks-report-name = report
+help-profile-name = Profile
+help-keep-oldest-name = oldest
+help-keep-newest-name = newest
+help-keep-shortest-name = shortest
+help-keep-shallowest-name = shallowest
+help-keep-alphabetical-name = alphabetical
+help-keep-capture-name-name = capture-name
+help-keep-dated-folder-name = dated-folder
+help-keep-regex-name = regex
+help-trash-name = trash
+help-link-name = link
+help-reflink-name = reflink
+help-delete-name = delete
+help-symlink-name = symlink
+help-cross-device-name = Across filesystems
+help-verify-name = Verify
+help-against-archives-name = Against archives
+help-empty-files-name = Empty files
+help-duplicate-folders-name = Duplicate folders
+help-rules-name = Selection rules
+help-selected-name = Selected
+help-dry-run-name = Dry run
+help-reclaimable-name = Reclaimable
+help-images-name = Similar images
+help-max-distance-name = Maximum distance
+help-audio-name = Same audio
+help-same-archives-name = Same archives
+help-similar-folders-name = Similar folders
+help-min-similarity-name = Minimum similarity
+help-name-collisions-name = Name collisions
+help-audit-name = Audit mode
+help-never-flag-name = Never flag
+help-undo-name = Undo history
+
+## Starting
+
+app-title = File Deduplicator
+app-about = Find and remove duplicate files
+app-dir-help = Folder to deduplicate instead of asking with the folder picker.
+app-autostart-help = Begin scanning DIR as soon as the window opens.
+app-scan-help = Shorthand for `DIR --autostart', used by the file manager integration.
+app-audit-help = Start in audit mode, which only scans and reports and never changes any files.
+instance-running = File Deduplicator is already running (process { $pid }). Switch to it?
+instance-unreachable = Failed to contact the running instance: { $error }
+instance-lock-failed = Continuing without the instance lock: { $error }
+settings-defaults = Using the default settings: { $error }
+menu-file = File
+menu-deduplicate = Deduplicate Directory
+menu-compare = Compare Against Reference Folder
+menu-integration = Install File Manager Integration
+menu-help = Help
+menu-help-topics = Help Topics
+init-choose-folder = Choose Folder
+init-audit-mode = Audit mode: never change any files
+init-profile = Start from profile:
+init-no-profile = none
+init-previous-projects = Previous Projects
+init-resume = Resume
+init-scanned = (scanned)
+init-not-scanned = (not scanned)
+init-last-changed = last changed { $time }
+problem-no-folder = Failed to get target folder from file picker! Try again.
+problem-missing = Folder '{ $folder }' does not exist! Try again.
+problem-project = Failed to open project: { $error }
+problem-locked = Project is in use: { $error }
+problem-overlapping = '{ $folder }' and the reference '{ $reference }' overlap! Choose separate folders.
+problem-reference-overlaps = The folder and its reference overlap! Choose separate folders.
+## Command line
+
+cli-error = Error: { $error }
+cli-duration-not-number = '{ $duration }' doesn't start with a number
+cli-duration-unknown-unit = Unknown unit '{ $unit }', expected s, m or h
+cli-list = { $rest } and { $last }
+cli-not-a-directory = '{ $dir }' is not a directory
+cli-reference-overlaps = '{ $root }' and the reference '{ $reference }' overlap
+cli-log-unavailable = Not logging this scan: { $error }
+cli-cache-unavailable = Not using the hash cache: { $error }
+cli-cache-update-failed = Failed to update the hash cache: { $error }
+cli-walking = Walking { $dirs }
+cli-walking-and-hashing = Walking and hashing { $dirs }
+cli-walking-reference = Walking the reference { $reference }
+cli-hashing = Hashing { $files } files ({ $size })
+cli-progress = { $files }/{ $total_files } files, { $bytes }/{ $total_bytes } ({ $percent }%)
+cli-progress-rate = , { $rate }/s
+cli-progress-left = , { $rate }/s, { $left } left
+cli-hard-links = Found { $files } files with several hard links, each counted once
+cli-symbolic-links = Found { $links } symbolic links, which won't be hashed
+cli-denied = Ignored { $contents } contents on the denylist
+cli-time-limit = Stopped at the time limit, run `scan --resume` to carry on
+cli-scan-found = Found { $groups } duplicate groups ({ $errors } errors), saved to { $file }
+cli-empty-files = { $files } empty files aren't counted as duplicates, `dedupe --empty` removes them
+cli-list-errors = Run with --errors to list them
+cli-estimate-candidates = { $candidates } of { $files } files share their size with another, in { $groups } groups.
+cli-estimate-sampled = Hashed { $groups } of those groups ({ $size }), finding { $found } of duplicates.
+cli-estimate-exact = A scan would reclaim { $reclaimable }.
+cli-estimate-range = A scan would reclaim about { $reclaimable }, give or take { $margin }, and at most { $most }.
+cli-estimate-unreadable = { $files } files could not be read
+cli-not-scanned = '{ $dir }' has not been scanned yet, run `scan` first
+cli-scan-unfinished = The scan of '{ $dir }' did not finish, run `scan` again
+cli-walk-unsaved = The files found by the scan of '{ $dir }' weren't saved, run `scan` again
+cli-reclaimable = Keeping the { $policy } copy of each group would reclaim { $size } ({ $allocated } of disk)
+cli-imported = Imported { $files } files, in { $groups } duplicate groups
+cli-known = { $known } of { $files } files ({ $size }) are in '{ $archive }' already
+cli-known-partial = A scan stopped early, so more may be in the archive, run `scan --resume` to finish it
+cli-keep-invalid = Invalid keep policy: { $error }
+cli-compare-only = Only { $policy } removes { $files } files ({ $size }):
+cli-compare-more = { $against } frees { $size } more than { $keep }
+cli-compare-less = { $against } frees { $size } less than { $keep }
+cli-unknown-filesystem = an unknown filesystem
+cli-reflinks = '{ $dir }' is on { $filesystem }, which supports reflinks
+cli-no-reflinks = '{ $dir }' is on { $filesystem }, which doesn't support reflinks
+cli-audit-refused = `dedupe` changes files, which audit mode forbids.  Try --dry-run
+cli-skipped-in-use = Skipped '{ $file }', another program has it open
+cli-cross-device = { $copies } copies of '{ $keep }' are on another filesystem than it, { $decision ->
+        [symlink] so they are symlinked to it instead
+        [keep] so they are kept
+       *[refuse] so the group is left alone
+    }
+cli-action-failed = Failed to { $action ->
+        [trash] trash
+        [delete] delete
+        [link] link
+        [symlink] symlink
+       *[clone] clone
+    } '{ $file }': { $error }
+cli-would = Would { $action ->
+        [trash] trash
+        [delete] delete
+        [link] link
+        [symlink] symlink
+       *[clone] clone
+    } '{ $file }', keeping '{ $keep }'
+cli-could-not = Could not { $action ->
+        [trash] trash
+        [delete] delete
+        [link] link
+        [symlink] symlink
+       *[clone] clone
+    } '{ $file }': { $error }
+cli-dedupe-done = { $done } of { $files } files done, { $reclaimed } reclaimed ({ $allocated } of disk)
+cli-dedupe-busy = { $files } files were in use and left alone, run `dedupe` again once they are closed
+cli-dedupe-failed = { $files } files failed
+cli-run-interrupted = Deduplicating '{ $dir }' was cut short, run `resume` on it to finish, or `resume --roll-back` to take it back, first
+cli-nothing-interrupted = Nothing was cut short while deduplicating '{ $dir }'
+cli-resume-audit-refused = `resume` changes files, which audit mode forbids
+cli-resumed = { $done } of { $files } files left were done
+cli-rolled-back = { $done } of { $files } files were restored
+cli-roll-back-failed = { $files } files couldn't be restored, run `resume --roll-back` again to retry them
+cli-dry-run-done = Dry run: { $done } of { $files } files would be done, reclaiming { $reclaimable } ({ $allocated } of disk)
+cli-images-found = Found { $groups } groups of similar images
+cli-audio-found = Found { $groups } groups of the same recordings
+cli-archives-found = Found { $groups } groups of archives holding the same files
+cli-duplicate-folders-found = Found { $groups } groups of duplicate folders, { $wasted } wasted
+cli-similar-folders-found = Found { $pairs } pairs of similar folders
+cli-collisions-found = Found { $names } names shared by different contents
+cli-watching = Watching { $dirs } for changes, press Ctrl-C to stop
+cli-watch-groups = { $groups } duplicate groups
+cli-watch-hashed = hashed { $file }
+cli-watch-gone = gone { $file }
+cli-scans-finished = { $groups } groups, { $errors } errors{ $partial ->
+        [yes] , stopped at the time limit
+       *[no] {""}
+    }
+cli-scans-failed = failed: { $error }
+cli-scans-unfinished = did not finish
+cli-full-log = The full log is { $file }
+cli-no-topic = No help topic mentions "{ $query }"
+cli-denylist-unchanged = The denylist is unchanged
+cli-no-profile = There is no profile called "{ $name }"
+cli-write-report-failed = Failed to write report: { $error }
+cli-write-hashes-failed = Failed to write the hashes: { $error }
+cli-write-files-failed = Failed to write the files: { $error }
+cli-write-folders-failed = Failed to write folders: { $error }
+cli-write-comparison-failed = Failed to write comparison: { $error }
+cli-write-dry-run-failed = Failed to write the dry run: { $error }
+cli-write-images-failed = Failed to write images: { $error }
+cli-write-recordings-failed = Failed to write recordings: { $error }
+cli-write-archives-failed = Failed to write archives: { $error }
+cli-write-collisions-failed = Failed to write collisions: { $error }
+cli-audit-help = { help-audit } `dedupe` is refused.
+cli-profile-help = { help-profile } Options given as well win over it.
+cli-action-help = { help-action } Defaults to trash. Only delete removes files for good.
+cli-keep-help = { help-keep } Defaults to oldest.
+cli-selected-help = { help-selected } Files are marked in the app.
+cli-dry-run-help = { help-dry-run } Allowed in audit mode.
+cli-io-limit-help = { help-io-limit } Defaults to the limit in the settings, if any.
+cli-hash-help = { help-hash-algo } Defaults to the one in the settings, else sha256.
+cli-root-help = { help-roots } May be repeated, remembered for later scans.
+cli-reference-help = { help-reference } Remembered for later scans.
+cli-include-help = { help-include } May be repeated.
+cli-exclude-help = { help-exclude } May be repeated.
+cli-symlinks-help = { help-symlinks } Reported links are listed but not hashed.
+cli-about = Find and remove duplicate files without a display.
+cli-scan-about = Walk and hash DIR, saving the duplicate groups as a project.
+cli-estimate-about = Guess how much a scan of DIR would reclaim, by hashing a random sample of the files sharing a size. Nothing is saved.
+cli-estimate-groups-help = Hash at most N groups of same-sized files. More take longer, but narrow the margin.
+cli-estimate-threads-help = Maximum number of hashing threads.
+cli-estimate-max-duration-help = Stop sampling after DURATION, e.g. 30s, estimating from what was hashed so far.
+cli-report-about = Print the duplicate groups found by the last scan of DIR.
+cli-report-limit-help = Print at most N groups.
+cli-report-min-group-size-help = Skip groups with fewer than N copies.
+cli-folders-about = Print the folders holding the duplicates found by the last scan of DIR, most wasteful first, with the bytes their duplicates waste, how many there are and in how many groups. Folders count those below them too.
+cli-folders-limit-help = Print at most N folders.
+cli-export-about = Print the hashes found by the last scan of DIR in a format other tools read.
+cli-import-about = Take the hashes in FILE, written by another tool, for the results of a scan of DIR, replacing any. Relative paths in FILE are taken to be below the folder holding it.
+cli-import-hash-algo-help = The algorithm the hashes in FILE were made with. An fdupes index has none, its files are hashed with it.
+cli-known-about = List the files found by the last scan of DIR whose contents the last scan of ARCHIVE found too, each with a copy in ARCHIVE, e.g. the camera imports already in the archive. Neither folder is walked or hashed again.
+cli-known-archive-data-help = Read the scan of ARCHIVE from this data folder, e.g. one copied from another computer, rather than the usual one.
+cli-known-unknown-help = List the files whose contents ARCHIVE doesn't hold instead.
+cli-compare-about = Show which files two keep policies would remove from the last scan of DIR, without removing any.
+cli-compare-against-help = The policy to compare `--keep' with.
+cli-dedupe-about = Remove the duplicates found by the last scan of DIR, keeping one copy of each group. The action and keep policy are remembered, and used when not given next time.
+cli-resume-about = Finish deduplicating DIR where it was cut short, by a crash or power loss, trying again what failed.
+cli-resume-roll-back-help = Restore the files the interrupted run removed or replaced instead.
+cli-images-about = List the pictures that look the same among the files found by the last scan of DIR, though their contents differ. Exact copies are listed with them.
+cli-audio-about = List the recordings holding the same audio among the files found by the last scan of DIR, though their tags differ. Exact copies are listed with them.
+cli-archives-about = List the archives holding the same files among the files found by the last scan of DIR, though they were packed apart. Exact copies are listed with them.
+cli-duplicate-folders-about = List the folders holding the same as another among those scanned last in DIR, the names and contents of every file below them alike. Folders holding anything the scan left out are never listed.
+cli-similar-folders-about = List the pairs of folders holding mostly the same contents among those scanned last in DIR, such as snapshots of one backup, with the share of their contents both hold.
+cli-collisions-about = List the names that files with different contents share among the files found by the last scan of DIR, each version's files after its hash, newest version first.
+cli-watch-about = Keep the duplicate groups found by the last scan of DIR up to date as files change, hashing only what changed, until interrupted.
+cli-scans-about = List past scans from the scan log, and whether each finished. Attach the log to bug reports.
+cli-topics-about = Print the help topics on scanning, filters, keep policies, actions and safety, or only those mentioning every word of QUERY.
+cli-denylist-about = Manage the contents that are never flagged as duplicates, in any project.
+cli-profiles-about = Manage the profiles in the settings, which `--profile' starts scans and deduplication from.
+cli-profiles-list-about = Print every profile, as it is kept in the settings.
+cli-profiles-save-about = Keep the filters, action and keep policy given as profile NAME, replacing any profile called so.
+cli-profiles-remove-about = Forget profile NAME.
+cli-denylist-list-about = Print every hash on the denylist, with its algorithm and note.
+cli-denylist-add-about = Never flag contents with HASH, e.g. as printed by `report`.
+cli-denylist-add-hash-algo-help = The algorithm that made HASH: sha256, blake3 or xxh128.
+cli-denylist-add-note-help = A reminder of what the contents are.
+cli-denylist-remove-about = Flag contents with HASH again.
+cli-scan-threads-help = Hash with N threads, or one file at a time when N is 1. By default the settings decide, else every core is used once there is enough to hash to be worth it.
+cli-scan-device-threads-help = Maximum number of hashing threads per device. Defaults to 2 for spinning disks, and an even share of the rest of --threads for other devices.
+cli-format-fdupes-help = The paths of each group followed by a blank line, as fdupes and jdupes print them.
+cli-index-format-checksums-help = A line with the hash and path of every file, as sha256sum, b3sum and xxh128sum write.
+cli-index-format-fdupes-help = The paths of each set of duplicates followed by a blank line, as fdupes and jdupes print.
+cli-sort-wasted-help = Most bytes freed by deduplicating first.
+cli-sort-count-help = Most copies first.
+cli-sort-size-help = Largest files first.
+## Terminal
+
+tui-scanning = Scanning
+tui-scanning-keys = Esc: stop, keeping what was hashed so far
+tui-progress = { $files }/{ $total_files } files, { $bytes }/{ $total_bytes }
+tui-scan-panicked = The scan failed unexpectedly
+tui-about = Find and remove duplicate files in a terminal, e.g. over SSH: scan, review the groups, and act on the copies marked for removal.
+tui-dir-help = The directory to deduplicate. Its last scan is reviewed, if it has one.
+tui-rescan-help = Scan again, even if the directory has been scanned.
+tui-audit-help = { help-audit } Files can be marked, but not acted on.
+tui-groups = { $groups } duplicate groups
+tui-title = { $dir }: { $groups } duplicate groups, { $wasted } wasted, { $marked } marked for removal, keeping the { $policy } copy, action: { $action ->
+        [trash] Trash
+        [delete] Delete
+        [link] Link
+        [symlink] Symlink
+       *[clone] Clone
+    }
+tui-group = { $copies } copies of { $size }, { $wasted } wasted, { $marked } marked
+tui-review-keys =
+    ↑↓/jk: move  Enter: open group  Space: mark file  a: mark all but one  c: clear marks
+    p: keep policy  t: action  x: act on marked files  q: quit
+tui-cancelled = Nothing was done
+tui-reference-protected = Files in the reference folder are never removed
+tui-keeping = Keeping the { $policy } copy, press a to mark the others
+tui-audit = Audit mode, nothing can be changed
+tui-nothing-marked = Nothing is marked, press space on a file or a to mark every copy but one
+tui-confirm = { $action ->
+        [trash] Trash
+        [delete] Delete
+        [link] Link
+        [symlink] Symlink
+       *[clone] Clone
+    } the { $files } marked files? y to go ahead, any other key to cancel
+tui-done = { $done } of { $files } files were { $action ->
+        [trash] trashed
+        [delete] deleted
+        [link] linked
+        [symlink] symlinked
+       *[clone] cloned
+    }
+tui-cross-device = { $groups } groups have copies on another filesystem than the file kept, { $decision ->
+        [symlink] which were symlinked instead
+        [keep] which were kept
+       *[refuse] so they were left alone
+    }
@@ -0,0 +1,466 @@
+//! Act on duplicate groups: plan which files to remove with a keep policy, then carry the plan out.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::SystemTime,
+};
+use itertools::Itertools;
+use serde::{Serialize, Deserialize};
+use crate::{
+    archive,
+    folders::FolderGroup,
+    persist,
+    platform::{self, OpenFiles},
+    policy::{Resolution, Resolver},
+    relate::RelatedFiles,
+};
+
+/// What to do with each redundant copy.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    /// Move the copy to the trash or recycle bin, where it can still be recovered.
+    #[default]
+    Trash,
+    /// Remove the copy for good.
+    Delete,
+    /// Replace the copy with a hard link to the surviving file.
+    HardLink,
+    /// Replace the copy with a copy-on-write clone of the surviving file.  The two stay separate files,
+    /// but share storage until one is changed.  Needs a filesystem that supports it, see `platform::supports_reflinks'.
+    Reflink,
+    /// Replace the copy with a symbolic link to the surviving file.  Only planned in place of a link that can't
+    /// cross filesystems, see `CrossDevice'.
+    Symlink,
+}
+
+/// What to do about a hard link or clone that would cross filesystems, which neither can.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CrossDevice {
+    /// Leave the whole group alone.
+    #[default]
+    Refuse,
+    /// Replace the copies on other filesystems with symbolic links instead.
+    Symlink,
+    /// Leave the copies on other filesystems be, linking the rest.
+    Keep,
+}
+
+impl fmt::Display for CrossDevice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CrossDevice::Refuse => write!(f, "refuse"),
+            CrossDevice::Symlink => write!(f, "symlink"),
+            CrossDevice::Keep => write!(f, "keep"),
+        }
+    }
+}
+
+/// Parse the names produced by `Display'.
+impl FromStr for CrossDevice {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "refuse" => Ok(CrossDevice::Refuse),
+            "symlink" => Ok(CrossDevice::Symlink),
+            "keep" => Ok(CrossDevice::Keep),
+            _ => Err(format!("Unknown choice '{:}', expected one of refuse, symlink or keep", s)),
+        }
+    }
+}
+
+/// A group with copies on another filesystem than the file kept, and what `Plan::across_devices' did about them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crossing {
+    pub hash: String,
+    pub keep: PathBuf,
+    /// The copies on other filesystems, in plan order.
+    pub copies: Vec<PathBuf>,
+    pub decision: CrossDevice,
+}
+
+/// Proof that files may be changed.  Everything that removes or replaces files asks for one, so a run in
+/// audit mode, which never makes one, has no way to reach them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permit(());
+
+impl Permit {
+    /// Allow changes, unless `audit' asks for a read-only run.
+    pub fn unless_auditing(audit: bool) -> Option<Self> {
+        (!audit).then_some(Permit(()))
+    }
+}
+
+/// How carefully to carry out a `Plan'.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DedupOptions {
+    /// Compare each copy with the surviving file byte for byte before touching it, rather than trusting the hash.
+    /// A copy that differs, or changed since the scan, is left alone and its step fails.
+    pub verify: bool,
+    /// Only check that each step could be done, changing nothing.  The outcomes then tell what would happen.
+    pub dry_run: bool,
+}
+
+/// A single filesystem change.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Step {
+    pub action: Action,
+    /// The content hash of the group this step belongs to.
+    pub hash: String,
+    /// The redundant copy being acted on.
+    pub target: PathBuf,
+    /// The surviving copy.  An empty file has none, see `Plan::empty_files', and names itself.
+    pub keep: PathBuf,
+    /// Bytes freed once the step is done, judging by the file size.
+    pub size: u64,
+    /// Bytes of disk actually freed, which is less than `size' for a sparse copy.
+    pub allocated: u64,
+    /// When the copy was last modified as scanned, where the filesystem tells.  Together with `size', how a copy
+    /// edited since is told from the one scanned.
+    #[serde(default)]
+    pub modified: Option<SystemTime>,
+}
+
+/// Every change needed to deduplicate a set of groups, in a stable order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Plan {
+    pub steps: Vec<Step>,
+}
+
+/// How two plans for the same groups differ, to weigh keep policies against each other before acting.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlanDiff {
+    /// Steps acting on files only the first plan touches.
+    pub only_first: Vec<Step>,
+    /// Steps acting on files only the second plan touches.
+    pub only_second: Vec<Step>,
+}
+
+impl PlanDiff {
+    /// Bytes the second plan frees beyond the first.  Negative when it frees less.
+    pub fn net_reclaimable(&self) -> i128 {
+        let bytes = |steps: &[Step]| steps.iter().map(|step| step.size as i128).sum::<i128>();
+        bytes(&self.only_second) - bytes(&self.only_first)
+    }
+}
+
+/// The result of carrying out one `Step'.
+#[derive(Debug)]
+pub struct Outcome {
+    pub step: Step,
+    pub result: io::Result<()>,
+}
+
+impl Outcome {
+    /// Whether the step was skipped because another program had the file open.  Trying again once it is closed
+    /// may well succeed.
+    pub fn in_use(&self) -> bool {
+        self.result.as_ref().is_err_and(|e| e.kind() == io::ErrorKind::ResourceBusy)
+    }
+}
+
+impl Plan {
+    /// Plan to apply `action' to every copy that `resolver' doesn't keep.
+    pub fn new(related: &RelatedFiles, resolver: &Resolver, action: Action) -> Self {
+        Self::from_resolutions(resolver.resolve_all(related), action)
+    }
+
+    /// Plan to trash every loose file whose contents are already inside an archive.
+    /// Archive members themselves are never touched.
+    pub fn against_archives(related: &RelatedFiles, resolver: &Resolver) -> Self {
+        Self::from_resolutions(resolver.resolve_all_against_archives(related), Action::Trash)
+    }
+
+    /// Plan to apply `action' to the files the user picked, keyed by hash.  Each group keeps the file `resolver'
+    /// prefers among those not picked, and a group with every file picked is skipped, so one copy always survives.
+    pub fn selected(related: &RelatedFiles, selected: &BTreeMap<String, BTreeSet<PathBuf>>, resolver: &Resolver, action: Action) -> Self {
+        let mut steps = Vec::new();
+        for (hash, picked) in selected {
+            let Some(group) = related.files.get(hash) else { continue };
+            let Some(keep) = resolver.survivor(group.iter().filter(|info| !picked.contains(&*info.name))) else { continue };
+            steps.extend(
+                group
+                    .iter()
+                    .filter(|info| picked.contains(&*info.name) && !resolver.is_protected(info) && !info.in_archive())
+                    .sorted_by(|a, b| a.name.cmp(&b.name))
+                    .map(|info| Step {
+                        action,
+                        hash: hash.clone(),
+                        target: info.name.to_path_buf(),
+                        keep: keep.name.to_path_buf(),
+                        size: info.size,
+                        allocated: info.allocated(),
+                        modified: info.modified,
+                    }),
+            );
+        }
+        Self { steps }
+    }
+
+    /// Plan to remove every empty file that `resolver' doesn't protect, by `action' if that trashes or deletes, and
+    /// by trashing otherwise, as there is nothing to link them to.  None of them is kept, unlike a duplicate group.
+    pub fn empty_files(related: &RelatedFiles, resolver: &Resolver, action: Action) -> Self {
+        let action = match action {
+            Action::Delete => Action::Delete,
+            Action::Trash | Action::HardLink | Action::Reflink | Action::Symlink => Action::Trash,
+        };
+        let by_file = related.by_file();
+        let steps = related
+            .empty_files()
+            .into_iter()
+            .filter(|info| !resolver.is_protected(info) && !info.in_archive())
+            .map(|info| Step {
+                action,
+                hash: by_file[info].to_owned(),
+                target: info.name.to_path_buf(),
+                keep: info.name.to_path_buf(),
+                size: 0,
+                allocated: info.allocated(),
+                modified: info.modified,
+            })
+            .collect();
+        Self { steps }
+    }
+
+    /// Plan to apply `action' to every file in the copies of each of `groups' that `resolver' doesn't keep, each
+    /// file keeping the one in the same place in the folder kept.  Once they are done, `remove_emptied' removes what
+    /// is left of the copies.
+    pub fn folders(related: &RelatedFiles, groups: &[FolderGroup], resolver: &Resolver, action: Action) -> Self {
+        let mut steps = Vec::new();
+        for res in groups.iter().filter_map(|group| group.resolve(resolver)) {
+            for folder in res.remove {
+                let files = related.files.iter().flat_map(|(hash, group)| group.iter().map(move |info| (hash, info)));
+                let files = files.filter(|(_, info)| info.name.starts_with(&folder.name) && !info.in_archive());
+                steps.extend(files.filter_map(|(hash, info)| {
+                    let within = info.name.strip_prefix(&folder.name).ok()?;
+                    Some(Step {
+                        action,
+                        hash: hash.clone(),
+                        target: info.name.to_path_buf(),
+                        keep: res.keep.name.join(within),
+                        size: info.size,
+                        allocated: info.allocated(),
+                        modified: info.modified,
+                    })
+                }));
+            }
+        }
+        steps.sort_by(|a, b| a.target.cmp(&b.target));
+        Self { steps }
+    }
+
+    fn from_resolutions(resolutions: Vec<(&String, Resolution)>, action: Action) -> Self {
+        let steps = resolutions
+            .into_iter()
+            .flat_map(|(hash, res)| {
+                res.remove.into_iter().map(move |info| Step {
+                    action,
+                    hash: hash.clone(),
+                    target: info.name.to_path_buf(),
+                    keep: res.keep.name.to_path_buf(),
+                    size: info.size,
+                    allocated: info.allocated(),
+                    modified: info.modified,
+                })
+            })
+            .collect();
+        Self { steps }
+    }
+
+    /// Settle the hard links and clones that would cross filesystems as `policy' says, going by the devices
+    /// `related' found the files on, so that none fails halfway through.  Returns what was decided for each group
+    /// with any.  Links between files on devices the scan didn't tell are left to be tried.
+    pub fn across_devices(&mut self, related: &RelatedFiles, policy: CrossDevice) -> Vec<Crossing> {
+        let device = |hash: &str, path: &Path| related.files.get(hash)?.iter().find(|info| *info.name == *path)?.device();
+        let crosses = |step: &Step| {
+            matches!(step.action, Action::HardLink | Action::Reflink)
+                && matches!((device(&step.hash, &step.target), device(&step.hash, &step.keep)), (Some(a), Some(b)) if a != b)
+        };
+        let mut crossings = Vec::<Crossing>::new();
+        for step in self.steps.iter().filter(|step| crosses(step)) {
+            match crossings.iter_mut().find(|crossing| crossing.hash == step.hash) {
+                Some(crossing) => crossing.copies.push(step.target.clone()),
+                None => crossings.push(Crossing {
+                    hash: step.hash.clone(),
+                    keep: step.keep.clone(),
+                    copies: vec![step.target.clone()],
+                    decision: policy,
+                }),
+            }
+        }
+        match policy {
+            CrossDevice::Refuse => self.steps.retain(|step| !crossings.iter().any(|crossing| crossing.hash == step.hash)),
+            CrossDevice::Keep => self.steps.retain(|step| !crosses(step)),
+            CrossDevice::Symlink => {
+                for step in self.steps.iter_mut().filter(|step| crosses(step)) {
+                    step.action = Action::Symlink;
+                }
+            },
+        }
+        crossings
+    }
+
+    /// Bytes freed if every step succeeds, judging by file sizes.
+    pub fn reclaimable(&self) -> u64 {
+        self.steps.iter().map(|step| step.size).sum()
+    }
+
+    /// Bytes of disk freed if every step succeeds.  Sparse copies make this less than `reclaimable'.
+    pub fn reclaimable_allocated(&self) -> u64 {
+        self.steps.iter().map(|step| step.allocated).sum()
+    }
+
+    /// Compare with `other', by which files each acts on.
+    pub fn diff(&self, other: &Plan) -> PlanDiff {
+        let targets = |plan: &Plan| plan.steps.iter().map(|step| step.target.clone()).collect::<HashSet<PathBuf>>();
+        let (mine, theirs) = (targets(self), targets(other));
+        PlanDiff {
+            only_first: self.steps.iter().filter(|step| !theirs.contains(&step.target)).cloned().collect(),
+            only_second: other.steps.iter().filter(|step| !mine.contains(&step.target)).cloned().collect(),
+        }
+    }
+
+    /// Carry out every step.  A failing step doesn't stop the rest.
+    pub fn execute(&self, options: &DedupOptions, permit: &Permit) -> Vec<Outcome> {
+        let open = OpenFiles::read();
+        self.outcomes(|step| step.execute(options, &open, permit))
+    }
+
+    /// Check every step as a dry run would, whatever `options' says.  Needs no `Permit', since nothing is changed.
+    pub fn preview(&self, options: &DedupOptions) -> Vec<Outcome> {
+        let open = OpenFiles::read();
+        self.outcomes(|step| step.preview(options, &open))
+    }
+
+    fn outcomes(&self, run: impl Fn(&Step) -> io::Result<()>) -> Vec<Outcome> {
+        self.steps
+            .iter()
+            .map(|step| Outcome {
+                step: step.clone(),
+                result: run(step),
+            })
+            .collect()
+    }
+}
+
+impl Step {
+    /// Check that the step could be done, without changing anything: both files are there, no other program has
+    /// the copy open as `open' tells, and with `DedupOptions::verify' their contents match.
+    pub fn preview(&self, options: &DedupOptions, open: &OpenFiles) -> io::Result<()> {
+        self.check_target()?;
+        self.check_keep()?;
+        if self.action == Action::Reflink && !self.target.parent().is_some_and(platform::supports_reflinks) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "the filesystem doesn't support reflinks"));
+        }
+        if self.action == Action::Trash {
+            platform::check_trash(&self.target)?;
+        }
+        self.check_not_in_use(open)?;
+        self.verify(options)
+    }
+
+    /// Refuse to touch a copy unless the file kept for it is still there, as large as when scanned, so the last copy
+    /// is never the one removed.  The kept file may have been moved or removed since, e.g. when acting on a project
+    /// scanned days ago.  A file kept in an archive must still be in it, see `Plan::against_archives'.
+    fn check_keep(&self) -> io::Result<()> {
+        if self.keep == self.target {
+            return Ok(());
+        }
+        let size = match archive::find_member(&self.keep)? {
+            Some(member) => member.size,
+            None => fs::metadata(&self.keep)?.len(),
+        };
+        if size != self.size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("'{:}' changed since the scan", self.keep.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse to touch a copy that was edited since the scan, as it is no longer a copy, and removing or replacing it
+    /// would lose the edits.  Times are only compared where both the scan and the filesystem have them.
+    fn check_target(&self) -> io::Result<()> {
+        let metadata = fs::metadata(&self.target)?;
+        let modified = metadata.modified().ok();
+        if metadata.len() != self.size || (self.modified.is_some() && modified.is_some() && modified != self.modified) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("'{:}' changed since the scan", self.target.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Refuse to touch a copy another program has open, since removing or replacing it could lose that program's
+    /// work.  See `Outcome::in_use'.
+    fn check_not_in_use(&self, open: &OpenFiles) -> io::Result<()> {
+        if open.contains(&self.target) {
+            return Err(io::Error::new(io::ErrorKind::ResourceBusy, "the file is in use by another program"));
+        }
+        Ok(())
+    }
+
+    fn verify(&self, options: &DedupOptions) -> io::Result<()> {
+        if options.verify && !archive::contents_identical(&self.target, &self.keep)? {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("contents differ from '{:}'", self.keep.display()),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Carry out the step, unless `preview' finds it couldn't be done.  `open' is read once for a whole plan, see
+    /// `OpenFiles'.
+    pub fn execute(&self, options: &DedupOptions, open: &OpenFiles, permit: &Permit) -> io::Result<()> {
+        if options.dry_run {
+            return self.preview(options, open);
+        }
+        self.check_target()?;
+        self.check_keep()?;
+        self.check_not_in_use(open)?;
+        self.verify(options)?;
+        match self.action {
+            Action::Trash => platform::trash(&self.target, permit),
+            Action::Delete => platform::delete(&self.target, permit),
+            // The link or clone replaces the copy in one rename, so a failure leaves the copy as it was.
+            Action::HardLink => persist::replace_with(&self.target, |temp| fs::hard_link(&self.keep, temp), permit),
+            // The clone takes the copy's permissions and times, not the kept file's, as a clone on macOS starts with.
+            Action::Reflink => {
+                let metadata = fs::metadata(&self.target)?;
+                let clone = |temp: &Path| {
+                    platform::reflink(&self.keep, temp, permit)
+                        .and_then(|_| platform::set_times(temp, &metadata))
+                        .and_then(|_| fs::set_permissions(temp, metadata.permissions()))
+                };
+                persist::replace_with(&self.target, clone, permit)
+            },
+            // Relative to where the app was started the link would point nowhere.
+            Action::Symlink => {
+                let keep = std::path::absolute(&self.keep)?;
+                persist::replace_with(&self.target, |temp| platform::symlink(&keep, temp, permit), permit)
+            },
+        }
+    }
+}
+
+/// Remove `folder' and every folder below it once nothing but folders is left, as after trashing or deleting all
+/// its files with `Plan::folders'.  Folders still holding anything, such as a file whose step failed, stay.
+pub fn remove_emptied(folder: &Path, permit: &Permit) -> io::Result<()> {
+    for entry in walkdir::WalkDir::new(folder).contents_first(true) {
+        let entry = entry.map_err(io::Error::from)?;
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        match platform::remove_empty_dir(entry.path(), permit) {
+            Err(e) if e.kind() != io::ErrorKind::DirectoryNotEmpty => return Err(e),
+            _ => (),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,99 @@
+//! Where the duplication lies: how many duplicates each folder of a project holds, and how much removing them would
+//! free, so the folders worth cleaning stand out before any file is picked.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Bound,
+    path::{Path, PathBuf},
+};
+use serde::Serialize;
+use crate::relate::RelatedFiles;
+
+/// The duplication in one folder and every folder below it.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FolderStats {
+    pub folder: PathBuf,
+    /// Bytes freed by removing the copies in the folder, keeping one elsewhere, or one here when every copy is.
+    pub wasted: u64,
+    /// Files in the folder with a copy somewhere.
+    pub duplicates: usize,
+    /// Groups with a member in the folder.
+    pub groups: usize,
+}
+
+/// The duplication in every folder holding any, from the root of a project down.
+#[derive(Debug, Clone, Default)]
+pub struct Analysis {
+    pub root: PathBuf,
+    folders: BTreeMap<PathBuf, FolderStats>,
+}
+
+impl Analysis {
+    /// Add up the duplicates of `related' in each folder below `root', and `root' itself.  Files outside it count
+    /// towards their own folder alone.
+    pub fn new(related: &RelatedFiles, root: &Path) -> Self {
+        let mut folders = BTreeMap::<PathBuf, FolderStats>::new();
+        for group in related.duplicate_groups() {
+            let mut members = HashMap::<&Path, usize>::new();
+            for fi in &group.files {
+                for folder in ancestors(root, &fi.name) {
+                    *members.entry(folder).or_default() += 1;
+                }
+            }
+            for (folder, n) in members {
+                let stats = folders.entry(folder.to_path_buf()).or_insert_with(|| FolderStats { folder: folder.to_path_buf(), ..Default::default() });
+                let kept = if n == group.files.len() { 1 } else { 0 };
+                stats.wasted += group.size * (n - kept) as u64;
+                stats.duplicates += n;
+                stats.groups += 1;
+            }
+        }
+        Self { root: root.to_path_buf(), folders }
+    }
+
+    /// The duplication in `folder', or `None' if it holds none.
+    pub fn get(&self, folder: &Path) -> Option<&FolderStats> {
+        self.folders.get(folder)
+    }
+
+    /// The folders directly in `folder' holding duplicates, most wasteful first.
+    pub fn children(&self, folder: &Path) -> Vec<&FolderStats> {
+        let mut children = self.folders
+            .range::<Path, _>((Bound::Included(folder), Bound::Unbounded))
+            .skip_while(|(path, _)| *path == folder)
+            .take_while(|(path, _)| path.starts_with(folder))
+            .filter(|(path, _)| path.parent() == Some(folder))
+            .map(|(_, stats)| stats)
+            .collect::<Vec<&FolderStats>>();
+        sort(&mut children);
+        children
+    }
+
+    /// Whether any folder below `folder' holds duplicates.
+    pub fn has_children(&self, folder: &Path) -> bool {
+        // Folders sort right before those below them.
+        self.folders
+            .range::<Path, _>((Bound::Included(folder), Bound::Unbounded))
+            .find(|(path, _)| *path != folder)
+            .is_some_and(|(path, _)| path.starts_with(folder))
+    }
+
+    /// Every folder holding duplicates, most wasteful first.  A folder counts everything below it too, so a parent
+    /// never comes after its own folders.
+    pub fn most_wasteful(&self) -> Vec<&FolderStats> {
+        let mut folders = self.folders.values().collect::<Vec<&FolderStats>>();
+        sort(&mut folders);
+        folders
+    }
+}
+
+fn sort(folders: &mut [&FolderStats]) {
+    folders.sort_by(|a, b| b.wasted.cmp(&a.wasted).then_with(|| a.folder.cmp(&b.folder)));
+}
+
+/// The folders holding `path', from its own up to `root', or only its own if it isn't below `root'.
+fn ancestors<'a>(root: &'a Path, path: &'a Path) -> impl Iterator<Item = &'a Path> {
+    let parent = path.parent().unwrap_or(path);
+    let within = parent.starts_with(root);
+    parent.ancestors().take_while(move |folder| within && folder.starts_with(root)).chain((!within).then_some(parent))
+}
@@ -0,0 +1,248 @@
+//! Look inside zip and tar archives, so copies hiding in them are found too.  Each file in an archive is taken for
+//! one of its own, named by the archive's path, `!/' and its path inside, e.g. `photos.zip!/2019/beach.jpg'.  They
+//! are only ever read: nothing in an archive is removed or linked to, see `FileInfo::archive'.
+//!
+//! Compressed tars aren't looked into, as reading any one file in them means unpacking everything before it.
+//! Neither are archives inside archives.
+//!
+//! Archives themselves can be compared by the files in them rather than their bytes, see `ArchiveContents'.
+
+use std::{
+    ffi::OsString,
+    fs,
+    io::{self, BufReader, Read},
+    path::{Path, PathBuf},
+};
+use zip::{result::ZipError, ZipArchive};
+use crate::relate::{self, Comparators, ContentComparator, Error, ExactHash, FileInfo, HashAlgo, Hasher};
+
+/// Between an archive's path and the path of a file inside it.
+pub const SEPARATOR: &str = "!/";
+
+/// The file name extensions of the archives looked into, in lower case.
+pub const EXTENSIONS: &[&str] = &["zip", "tar"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Zip,
+    Tar,
+}
+
+fn kind(path: &Path) -> Option<Kind> {
+    match path.extension()?.to_string_lossy().to_lowercase().as_str() {
+        "zip" => Some(Kind::Zip),
+        "tar" => Some(Kind::Tar),
+        _ => None,
+    }
+}
+
+/// Whether `path' is named like an archive we can look into.  Archives whose paths aren't valid Unicode are
+/// left closed, as their files couldn't be found again by name.
+pub fn is_archive(path: &Path) -> bool {
+    path.to_str().is_some() && kind(path).is_some()
+}
+
+/// The name of `member', a path inside `archive'.
+pub fn member_path(archive: &Path, member: &str) -> PathBuf {
+    let mut name = OsString::from(archive.as_os_str());
+    name.push(SEPARATOR);
+    name.push(member);
+    name.into()
+}
+
+/// The path inside its archive of `info', when it is in one.
+pub fn member_name(info: &FileInfo) -> Option<&str> {
+    info.name.to_str()?.strip_prefix(info.archive.as_deref()?.to_str()?)?.strip_prefix(SEPARATOR)
+}
+
+fn zip_error(path: &Path, e: ZipError) -> Error {
+    match e {
+        ZipError::Io(e) => Error::io(path, e),
+        ZipError::FileNotFound => Error::io(path, io::Error::from(io::ErrorKind::NotFound)),
+        e => Error::content(path, e),
+    }
+}
+
+/// The files in `archive', found by a walk, as a walk finds files.  They are taken to be as old as the archive, so a
+/// changed archive is told by their times too.  Folders and links in it are left out.
+pub fn members(archive: &FileInfo) -> Result<Vec<FileInfo>, Error> {
+    let path = &archive.name;
+    let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+    let member = |name: &str, size| FileInfo {
+        name: member_path(path, name).into(),
+        size,
+        created: archive.created,
+        modified: archive.modified,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: Some(archive.name.clone()),
+    };
+    let mut members = Vec::new();
+    match kind(path) {
+        Some(Kind::Zip) => {
+            let mut zip = ZipArchive::new(BufReader::new(file)).map_err(|e| zip_error(path, e))?;
+            for i in 0..zip.len() {
+                // Raw, so nothing is set up for unpacking, nor refused for being encrypted.
+                let entry = zip.by_index_raw(i).map_err(|e| zip_error(path, e))?;
+                if entry.is_file() {
+                    members.push(member(entry.name(), entry.size()));
+                }
+            }
+        },
+        Some(Kind::Tar) => {
+            let mut tar = tar::Archive::new(file);
+            // Skipping over what each file holds rather than reading it.
+            for entry in tar.entries_with_seek().map_err(|e| Error::io(path, e))? {
+                let entry = entry.map_err(|e| Error::io(path, e))?;
+                if entry.header().entry_type().is_file() {
+                    let name = entry.path().map_err(|e| Error::io(path, e))?;
+                    members.push(member(&name.to_string_lossy(), entry.size()));
+                }
+            }
+        },
+        None => (),
+    }
+    Ok(members)
+}
+
+/// The file in an archive that `path' names, as `members' finds it, or `None' when `path' is a file of its own or
+/// doesn't go through an archive.  Fails when the archive is there but no longer holds it.
+pub fn find_member(path: &Path) -> Result<Option<FileInfo>, Error> {
+    let Some(name) = path.to_str() else {
+        return Ok(None);
+    };
+    if fs::symlink_metadata(path).is_ok() {
+        return Ok(None);
+    }
+    for (i, _) in name.match_indices(SEPARATOR) {
+        let archive = Path::new(&name[..i]);
+        if !is_archive(archive) || !archive.is_file() {
+            continue;
+        }
+        let found = members(&FileInfo::from_path(archive, true)?)?.into_iter().find(|member| *member.name == *path);
+        return found.map(Some).ok_or_else(|| Error::io(path, io::Error::from(io::ErrorKind::NotFound)));
+    }
+    Ok(None)
+}
+
+/// Like `relate::contents_identical', but `b' may be a file in an archive, see `find_member'.
+pub fn contents_identical(a: &Path, b: &Path) -> io::Result<bool> {
+    let Some(member) = find_member(b)? else {
+        return relate::contents_identical(a, b);
+    };
+    let mut file = fs::File::open(a)?;
+    if file.metadata()?.len() != member.size {
+        return Ok(false);
+    }
+    Ok(read_member(&member, |read| relate::streams_identical(&mut file, read))?)
+}
+
+/// Copy what `from' holds to `to', as `fs::copy' does, but `from' may be a file in an archive, see `find_member'.
+pub fn copy(from: &Path, to: &Path) -> io::Result<u64> {
+    let Some(member) = find_member(from)? else {
+        return fs::copy(from, to);
+    };
+    let mut file = fs::File::create(to)?;
+    Ok(read_member(&member, |read| io::copy(read, &mut file))?)
+}
+
+/// Hand `read' the contents of `info', a file in an archive, returning what it does.
+pub fn read_member<T>(info: &FileInfo, read: impl FnOnce(&mut dyn Read) -> io::Result<T>) -> Result<T, Error> {
+    let not_found = || Error::io(&info.name, io::Error::from(io::ErrorKind::NotFound));
+    let (Some(archive), Some(name)) = (info.archive.as_deref(), member_name(info)) else {
+        return Err(not_found());
+    };
+    let file = fs::File::open(archive).map_err(|e| Error::io(archive, e))?;
+    match kind(archive) {
+        Some(Kind::Zip) => {
+            let mut zip = ZipArchive::new(BufReader::new(file)).map_err(|e| zip_error(archive, e))?;
+            let mut member = zip.by_name(name).map_err(|e| zip_error(&info.name, e))?;
+            read(&mut member).map_err(|e| Error::io(&info.name, e))
+        },
+        Some(Kind::Tar) => {
+            let mut tar = tar::Archive::new(file);
+            for entry in tar.entries_with_seek().map_err(|e| Error::io(archive, e))? {
+                let mut entry = entry.map_err(|e| Error::io(archive, e))?;
+                let found = entry.header().entry_type().is_file() && entry.path().is_ok_and(|path| path.to_string_lossy() == name);
+                if found {
+                    return read(&mut entry).map_err(|e| Error::io(&info.name, e));
+                }
+            }
+            Err(not_found())
+        },
+        None => Err(not_found()),
+    }
+}
+
+/// Exact hashing with `algo', except for archives, compared by their `ArchiveContents'.
+pub fn comparators(algo: HashAlgo) -> Comparators {
+    let mut comparators = Comparators::new(ExactHash { algo });
+    for extension in EXTENSIONS {
+        comparators.register(extension, ArchiveContents { algo });
+    }
+    comparators
+}
+
+/// Compares archives by the files in them, hashed with `algo', so the same files packed again, at another time or
+/// compressed otherwise, are recognised.  What counts is each file's path inside the archive and what it holds,
+/// whatever order they were packed in; folders and the times files were changed are left out.  A zip and a tar of the
+/// same files are alike.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ArchiveContents {
+    pub algo: HashAlgo,
+}
+
+impl ArchiveContents {
+    /// The path of each file in the archive at `path', with the hash of what it holds.
+    fn hashes(&self, path: &Path) -> Result<Vec<(String, String)>, Error> {
+        let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        let hash = |read: &mut dyn Read| {
+            let mut hasher = Hasher::new(self.algo);
+            io::copy(read, &mut hasher).map_err(|e| Error::io(path, e))?;
+            Ok::<_, Error>(hasher.finish())
+        };
+        // Tars may name their files from `./'.
+        let name = |name: &str| name.trim_start_matches("./").to_owned();
+        let mut hashes = Vec::new();
+        match kind(path) {
+            Some(Kind::Zip) => {
+                let mut zip = ZipArchive::new(BufReader::new(file)).map_err(|e| zip_error(path, e))?;
+                for i in 0..zip.len() {
+                    let mut entry = zip.by_index(i).map_err(|e| zip_error(path, e))?;
+                    if entry.is_file() {
+                        hashes.push((name(entry.name()), hash(&mut entry)?));
+                    }
+                }
+            },
+            Some(Kind::Tar) => {
+                let mut tar = tar::Archive::new(file);
+                for entry in tar.entries().map_err(|e| Error::io(path, e))? {
+                    let mut entry = entry.map_err(|e| Error::io(path, e))?;
+                    if entry.header().entry_type().is_file() {
+                        let member = name(&entry.path().map_err(|e| Error::io(path, e))?.to_string_lossy());
+                        hashes.push((member, hash(&mut entry)?));
+                    }
+                }
+            },
+            None => return Err(Error::content(path, "not an archive")),
+        }
+        Ok(hashes)
+    }
+}
+
+impl ContentComparator for ArchiveContents {
+    fn key(&self, path: &Path) -> Result<String, Error> {
+        let mut hashes = self.hashes(path)?;
+        hashes.sort();
+        let mut hasher = Hasher::new(self.algo);
+        for (name, hash) in hashes {
+            // Ended by a byte no path nor hash holds, so no two lists run together alike.
+            hasher.update(name.as_bytes());
+            hasher.update(&[0]);
+            hasher.update(hash.as_bytes());
+            hasher.update(&[0]);
+        }
+        Ok(hasher.finish())
+    }
+}
@@ -0,0 +1,70 @@
+//! Recognise the same recording in files whose tags differ, e.g. a song retagged by a music player.  Rather than the
+//! whole file, only the packets of its audio stream are hashed, leaving out ID3 frames, Vorbis comments, cover art
+//! and any other metadata.  The packets are hashed as stored rather than decoded, which is much faster and tells the
+//! same recordings apart just as well: a file re-encoded from the same source has different audio either way.
+
+use std::{fs, io, path::Path};
+use symphonia::core::{
+    codecs::CODEC_TYPE_NULL,
+    errors::Error as AudioError,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+use crate::relate::{Comparators, ContentComparator, Error, ExactHash, HashAlgo, Hasher};
+
+/// The file name extensions of the recordings looked at, in lower case.
+pub const EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "wav"];
+
+/// Whether `path' is named like a recording we can read.
+pub fn is_audio(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Exact hashing with `algo', except for recordings, compared by their `AudioStream'.
+pub fn comparators(algo: HashAlgo) -> Comparators {
+    let mut comparators = Comparators::new(ExactHash { algo });
+    for extension in EXTENSIONS {
+        comparators.register(extension, AudioStream { algo });
+    }
+    comparators
+}
+
+/// Compares recordings by their audio stream alone, hashed with `algo'.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AudioStream {
+    pub algo: HashAlgo,
+}
+
+impl ContentComparator for AudioStream {
+    fn key(&self, path: &Path) -> Result<String, Error> {
+        let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut hint = Hint::new();
+        if let Some(extension) = path.extension().and_then(|extension| extension.to_str()) {
+            hint.with_extension(extension);
+        }
+        let source = MediaSourceStream::new(Box::new(file), Default::default());
+        let probed = symphonia::default::get_probe()
+            .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+            .map_err(|e| Error::content(path, e))?;
+        let mut format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| Error::content(path, "no audio"))?
+            .id;
+        let mut hasher = Hasher::new(self.algo);
+        loop {
+            match format.next_packet() {
+                Ok(packet) if packet.track_id() == track => hasher.update(packet.buf()),
+                Ok(_) => (),
+                Err(AudioError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(AudioError::IoError(e)) => return Err(Error::io(path, e)),
+                Err(e) => return Err(Error::content(path, e)),
+            }
+        }
+        Ok(hasher.finish())
+    }
+}
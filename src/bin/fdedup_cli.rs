@@ -0,0 +1,1373 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::mpsc, sync::mpsc::{Sender, Receiver},
+    thread,
+    time::{Duration, Instant},
+};
+use file_deduplicator::{
+    analysis::Analysis,
+    actions::{self, Action, DedupOptions, Outcome, Permit, Plan},
+    archive, audio,
+    cache::HashCache,
+    config::Dirs,
+    crosscheck::Crosscheck,
+    denylist::Denylist,
+    estimate::{self, EstimateConf},
+    journal,
+    platform,
+    format::Localized,
+    folders, manifest, naming,
+    help,
+    guard,
+    i18n,
+    images::{self, ImageConf, SimilarImages},
+    policy::{KeepPolicy, Reclaim, Resolver},
+    project::{self, Preferences, Project},
+    report::{self, Report},
+    rules::{self, Rule},
+    settings::{Profile, Settings},
+    similar::SimilarFiles,
+    telemetry::{self, Event, ScanConf, ScanLog, ScanOutcome},
+    relate::{self, FileInfo, Group, HashAlgo, Progress, RelatedFiles, Stage, SymlinkPolicy, WalkConf, WalkFilter, WalkInfo},
+    usage::{Meter, Throughput},
+    watch::{self, Update, Watch},
+    tr,
+};
+
+/// Find and remove duplicate files without a display.
+#[derive(Parser)]
+#[command(name = "fdedup_cli", version, about = tr!("cli-about"))]
+struct Cli {
+    #[arg(long, global = true, help = tr!("cli-audit-help"))]
+    audit: bool,
+    #[arg(long, global = true, value_name = "DIR", help = help::text(help::CONFIG))]
+    config: Option<PathBuf>,
+    #[arg(long, global = true, value_name = "NAME", help = tr!("cli-profile-help"))]
+    profile: Option<String>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    #[command(about = tr!("cli-scan-about"))]
+    Scan(ScanArgs),
+    #[command(about = tr!("cli-estimate-about"))]
+    Estimate {
+        dir: PathBuf,
+        #[arg(long, value_name = "N", default_value_t = EstimateConf::default().groups, help = tr!("cli-estimate-groups-help"))]
+        groups: usize,
+        #[arg(long, default_value_t = EstimateConf::default().max_threads, help = tr!("cli-estimate-threads-help"))]
+        threads: u16,
+        #[command(flatten)]
+        filters: Filters,
+        #[arg(long, value_name = "DURATION", value_parser = parse_duration, help = tr!("cli-estimate-max-duration-help"))]
+        max_duration: Option<Duration>,
+    },
+    #[command(about = tr!("cli-report-about"))]
+    Report {
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = Format::Json)]
+        format: Format,
+        #[arg(long, value_name = "N", help = tr!("cli-report-limit-help"))]
+        limit: Option<usize>,
+        #[arg(long, value_name = "N", default_value_t = 2, help = tr!("cli-report-min-group-size-help"))]
+        min_group_size: usize,
+        #[arg(long, value_enum, default_value_t = Sort::Wasted)]
+        sort: Sort,
+    },
+    #[command(about = tr!("cli-folders-about"))]
+    Folders {
+        dir: PathBuf,
+        #[arg(long, value_name = "N", help = tr!("cli-folders-limit-help"))]
+        limit: Option<usize>,
+    },
+    #[command(about = tr!("cli-export-about"))]
+    Export {
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = IndexFormat::Checksums)]
+        format: IndexFormat,
+    },
+    #[command(about = tr!("cli-import-about"))]
+    Import {
+        file: PathBuf,
+        dir: PathBuf,
+        #[arg(long, value_enum, default_value_t = IndexFormat::Checksums)]
+        format: IndexFormat,
+        #[arg(long = "hash", default_value_t = HashAlgo::Sha256, help = tr!("cli-import-hash-algo-help"))]
+        hash_algo: HashAlgo,
+    },
+    #[command(about = tr!("cli-known-about"))]
+    Known {
+        dir: PathBuf,
+        archive: PathBuf,
+        #[arg(long, value_name = "DIR", help = tr!("cli-known-archive-data-help"))]
+        archive_data: Option<PathBuf>,
+        #[arg(long, help = tr!("cli-known-unknown-help"))]
+        unknown: bool,
+    },
+    #[command(about = tr!("cli-compare-about"))]
+    Compare {
+        dir: PathBuf,
+        #[arg(long, help = help::text(help::KEEP))]
+        keep: KeepPolicy,
+        #[arg(long, help = tr!("cli-compare-against-help"))]
+        against: KeepPolicy,
+    },
+    #[command(about = tr!("cli-dedupe-about"))]
+    Dedupe {
+        dir: PathBuf,
+        #[arg(long, value_enum, help = tr!("cli-action-help"))]
+        action: Option<ActionArg>,
+        #[arg(long, help = tr!("cli-keep-help"))]
+        keep: Option<KeepPolicy>,
+        #[arg(long, help = help::text(help::VERIFY))]
+        verify: bool,
+        #[arg(long, conflicts_with = "action", help = help::text(help::AGAINST_ARCHIVES))]
+        against_archives: bool,
+        #[arg(long, conflicts_with = "against_archives", help = tr!("cli-selected-help"))]
+        selected: bool,
+        #[arg(long, conflicts_with_all = ["against_archives", "selected"], help = help::text(help::EMPTY_FILES))]
+        empty: bool,
+        #[arg(long, conflicts_with_all = ["against_archives", "selected", "empty"], help = help::text(help::DUPLICATE_FOLDERS))]
+        folders: bool,
+        #[arg(long = "rule", value_name = "RULE", conflicts_with_all = ["against_archives", "selected", "empty", "folders"], help = help::text(help::RULES))]
+        rules: Vec<Rule>,
+        #[arg(long, help = tr!("cli-dry-run-help"))]
+        dry_run: bool,
+    },
+    #[command(about = tr!("cli-resume-about"))]
+    Resume {
+        dir: PathBuf,
+        #[arg(long, help = tr!("cli-resume-roll-back-help"))]
+        roll_back: bool,
+    },
+    #[command(about = tr!("cli-images-about"))]
+    Images {
+        dir: PathBuf,
+        #[arg(long, value_name = "BITS", default_value_t = images::MAX_DISTANCE, help = help::text(help::MAX_DISTANCE))]
+        max_distance: u32,
+    },
+    #[command(about = tr!("cli-audio-about"))]
+    Audio {
+        dir: PathBuf,
+    },
+    #[command(about = tr!("cli-archives-about"))]
+    Archives {
+        dir: PathBuf,
+    },
+    #[command(about = tr!("cli-duplicate-folders-about"))]
+    DuplicateFolders {
+        dir: PathBuf,
+    },
+    #[command(about = tr!("cli-similar-folders-about"))]
+    SimilarFolders {
+        dir: PathBuf,
+        #[arg(
+            long, value_name = "PERCENT", default_value_t = folders::MIN_SIMILARITY, value_parser = clap::value_parser!(u8).range(0..=100),
+            help = help::text(help::MIN_SIMILARITY),
+        )]
+        min_similarity: u8,
+    },
+    #[command(about = tr!("cli-collisions-about"))]
+    Collisions {
+        dir: PathBuf,
+    },
+    #[command(about = tr!("cli-watch-about"))]
+    Watch {
+        dir: PathBuf,
+    },
+    #[command(about = tr!("cli-scans-about"))]
+    Scans,
+    #[command(about = tr!("cli-topics-about"))]
+    Topics {
+        query: Vec<String>,
+    },
+    #[command(about = tr!("cli-denylist-about"))]
+    Denylist {
+        #[command(subcommand)]
+        command: DenylistCommand,
+    },
+    #[command(about = tr!("cli-profiles-about"))]
+    Profiles {
+        #[command(subcommand)]
+        command: ProfileCommand,
+    },
+}
+
+#[derive(Subcommand)]
+enum ProfileCommand {
+    #[command(about = tr!("cli-profiles-list-about"))]
+    List,
+    #[command(about = tr!("cli-profiles-save-about"))]
+    Save {
+        name: String,
+        #[command(flatten)]
+        filters: Filters,
+        #[arg(long, value_enum, help = help::text(help::ACTION))]
+        action: Option<ActionArg>,
+        #[arg(long, help = help::text(help::KEEP))]
+        keep: Option<KeepPolicy>,
+    },
+    #[command(about = tr!("cli-profiles-remove-about"))]
+    Remove {
+        name: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum DenylistCommand {
+    #[command(about = tr!("cli-denylist-list-about"))]
+    List,
+    #[command(about = tr!("cli-denylist-add-about"))]
+    Add {
+        hash: String,
+        #[arg(long = "hash", default_value_t = HashAlgo::default(), help = tr!("cli-denylist-add-hash-algo-help"))]
+        hash_algo: HashAlgo,
+        #[arg(long, default_value = "", help = tr!("cli-denylist-add-note-help"))]
+        note: String,
+    },
+    #[command(about = tr!("cli-denylist-remove-about"))]
+    Remove {
+        hash: String,
+        #[arg(long = "hash", default_value_t = HashAlgo::default())]
+        hash_algo: HashAlgo,
+    },
+}
+
+#[derive(Args)]
+struct ScanArgs {
+    dir: PathBuf,
+    #[arg(long, value_name = "N", help = tr!("cli-scan-threads-help"))]
+    threads: Option<u16>,
+    #[arg(long, help = help::text(help::PIPELINE))]
+    pipeline: bool,
+    #[arg(long, value_name = "BYTES", default_value_t = relate::MMAP_THRESHOLD, help = help::text(help::MMAP_THRESHOLD))]
+    mmap_threshold: u64,
+    #[arg(long, help = help::text(help::NO_MMAP))]
+    no_mmap: bool,
+    #[arg(long, value_name = "N", default_value_t = relate::CHANGE_RETRIES, help = help::text(help::CHANGE_RETRIES))]
+    change_retries: u8,
+    #[arg(long, help = help::text(help::RETRY_LOCKED))]
+    retry_locked: bool,
+    #[arg(long, value_name = "BYTES", help = tr!("cli-io-limit-help"))]
+    io_limit: Option<u64>,
+    #[arg(long, help = help::text(help::LOW_PRIORITY))]
+    low_priority: bool,
+    #[arg(long, value_name = "N", help = tr!("cli-scan-device-threads-help"))]
+    device_threads: Option<u16>,
+    #[arg(long = "hash", help = tr!("cli-hash-help"))]
+    hash_algo: Option<HashAlgo>,
+    #[command(flatten)]
+    filters: Filters,
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration, help = help::text(help::MAX_DURATION))]
+    max_duration: Option<Duration>,
+    #[arg(long = "root", value_name = "DIR", help = tr!("cli-root-help"))]
+    roots: Vec<PathBuf>,
+    #[arg(long = "reference", value_name = "REF", help = tr!("cli-reference-help"))]
+    reference: Option<PathBuf>,
+    #[arg(long, help = help::text(help::RESUME))]
+    resume: bool,
+    #[arg(long, help = help::text(help::NO_CACHE))]
+    no_cache: bool,
+    #[arg(long, help = help::text(help::ERRORS))]
+    errors: bool,
+}
+
+/// Walk filters for `scan'.  Each is remembered by the project, and only replaced when given again.
+#[derive(Args)]
+struct Filters {
+    #[arg(long, value_name = "GLOB", help = tr!("cli-include-help"))]
+    include: Vec<String>,
+    #[arg(long, value_name = "GLOB", help = tr!("cli-exclude-help"))]
+    exclude: Vec<String>,
+    #[arg(long, value_name = "BYTES", help = help::text(help::MIN_SIZE))]
+    min_size: Option<u64>,
+    #[arg(long, help = help::text(help::SKIP_EMPTY))]
+    skip_empty: bool,
+    #[arg(long, value_name = "BYTES", help = help::text(help::MAX_SIZE))]
+    max_size: Option<u64>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true", help = help::text(help::SKIP_UNIQUE_SIZES))]
+    skip_unique_sizes: Option<bool>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true", help = help::text(help::ARCHIVES))]
+    archives: Option<bool>,
+    #[arg(long, help = tr!("cli-symlinks-help"))]
+    symlinks: Option<SymlinkPolicy>,
+}
+
+impl Filters {
+    fn apply(self, conf: &mut WalkConf) {
+        if !self.include.is_empty() || !self.exclude.is_empty() {
+            conf.include = self.include;
+            conf.exclude = self.exclude;
+        }
+        conf.min_size = self.min_size.or(conf.min_size);
+        if self.skip_empty {
+            conf.min_size = Some(conf.min_size.unwrap_or(0).max(1));
+        }
+        conf.max_size = self.max_size.or(conf.max_size);
+        conf.skip_unique_sizes = self.skip_unique_sizes.unwrap_or(conf.skip_unique_sizes);
+        conf.archives = self.archives.unwrap_or(conf.archives);
+        conf.symlinks = self.symlinks.unwrap_or(conf.symlinks);
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Csv,
+    #[value(help = tr!("cli-format-fdupes-help"))]
+    Fdupes,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum IndexFormat {
+    #[value(help = tr!("cli-index-format-checksums-help"))]
+    Checksums,
+    #[value(help = tr!("cli-index-format-fdupes-help"))]
+    Fdupes,
+}
+
+impl From<IndexFormat> for manifest::Format {
+    fn from(format: IndexFormat) -> Self {
+        match format {
+            IndexFormat::Checksums => manifest::Format::Checksums,
+            IndexFormat::Fdupes => manifest::Format::Fdupes,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Sort {
+    #[value(help = tr!("cli-sort-wasted-help"))]
+    Wasted,
+    #[value(help = tr!("cli-sort-count-help"))]
+    Count,
+    #[value(help = tr!("cli-sort-size-help"))]
+    Size,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ActionArg {
+    #[value(help = help::text(help::TRASH))]
+    Trash,
+    #[value(help = help::text(help::LINK))]
+    Link,
+    #[value(help = help::text(help::REFLINK))]
+    Reflink,
+    #[value(help = help::text(help::DELETE))]
+    Delete,
+}
+
+impl From<ActionArg> for Action {
+    fn from(arg: ActionArg) -> Self {
+        match arg {
+            ActionArg::Trash => Action::Trash,
+            ActionArg::Link => Action::HardLink,
+            ActionArg::Reflink => Action::Reflink,
+            ActionArg::Delete => Action::Delete,
+        }
+    }
+}
+
+/// Parse a number of seconds, minutes or hours, e.g. `90s', `30m' or `6h'.  A bare number is seconds.
+fn parse_duration(s: &str) -> Result<Duration, String> {
+    let (n, unit) = match s.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => s.split_at(i),
+        None => (s, "s"),
+    };
+    let n = n.parse::<u64>().map_err(|_| tr!("cli-duration-not-number", duration = s))?;
+    match unit {
+        "s" => Ok(Duration::from_secs(n)),
+        "m" => Ok(Duration::from_secs(n * 60)),
+        "h" => Ok(Duration::from_secs(n * 60 * 60)),
+        _ => Err(tr!("cli-duration-unknown-unit", unit = unit)),
+    }
+}
+
+/// `dirs' for a message, e.g. `/a, /b and /c'.
+fn list_dirs(dirs: &[PathBuf]) -> String {
+    let names = dirs.iter().map(|dir| dir.display().to_string()).collect::<Vec<String>>();
+    match names.split_last() {
+        Some((last, rest)) if !rest.is_empty() => tr!("cli-list", rest = rest.join(", "), last = last.as_str()),
+        _ => names.concat(),
+    }
+}
+
+/// Projects are keyed by path, so every spelling of a directory must agree, with the app's too.
+fn canonical_dir(dir: PathBuf) -> Result<PathBuf, String> {
+    match dir.canonicalize() {
+        Ok(dir) if dir.is_dir() => Ok(platform::plain_path(&dir)),
+        Ok(_) => Err(tr!("cli-not-a-directory", dir = dir.display().to_string())),
+        Err(e) => Err(format!("'{:}': {:}", dir.display(), e)),
+    }
+}
+
+fn scan(dirs: &Dirs, args: ScanArgs, profile: Option<&Profile>) -> Result<(), String> {
+    // The walk counts against the budget too.
+    let deadline = args.max_duration.map(|d| Instant::now() + d);
+    let dir = canonical_dir(args.dir)?;
+    let _lock = Project::new(dir.clone()).lock(&dirs.data).map_err(|e| e.to_string())?;
+    let settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+    let settings = Settings { threads: args.threads.or(settings.threads), hash_algo: args.hash_algo.unwrap_or(settings.hash_algo), ..settings };
+    let mut project = Project::open_with(&dirs.data, dir.clone(), &settings.filters).map_err(|e| e.to_string())?;
+    if let Some(profile) = profile {
+        project.apply_profile(profile);
+    }
+    args.filters.apply(&mut project.walk_conf);
+    if !args.roots.is_empty() {
+        project.set_extra_roots(args.roots.into_iter().map(canonical_dir).collect::<Result<Vec<PathBuf>, String>>()?);
+    }
+    if let Some(reference) = args.reference {
+        project.set_reference(Some(canonical_dir(reference)?));
+    }
+    if let Some(reference) = &project.reference {
+        if let Some(root) = project.roots().into_iter().find(|root| guard::overlaps(root, reference)) {
+            return Err(tr!("cli-reference-overlaps", root = root.display().to_string(), reference = reference.display().to_string()));
+        }
+    }
+    let _guards = guard::claim_project(&dirs.data, &project).map_err(|e| e.to_string())?;
+    let filter = WalkFilter::new(&project.walk_conf).map_err(|e| tr!("filter-invalid", error = e.to_string()))?;
+    let denylist = Denylist::load(&dirs.config).map_err(|e| e.to_string())?;
+    // Taken after a new reference has cleared the old results, which don't apply.
+    let previous = if args.resume { project.related.take() } else { None };
+    let strategy = settings.strategy();
+    let io_limit = args.io_limit.or(settings.io_limit);
+    let log = ScanLog::start(&dirs.data, ScanConf {
+        dir: dir.clone(),
+        extra_roots: project.extra_roots.clone(),
+        reference: project.reference.clone(),
+        walk: project.walk_conf.clone(),
+        strategy,
+        device_threads: args.device_threads,
+        hash_algo: settings.hash_algo,
+        mmap_threshold: Some(args.mmap_threshold).filter(|_| !args.no_mmap),
+        io_limit,
+        max_duration: args.max_duration,
+        resumed: previous.is_some(),
+        pipelined: args.pipeline,
+    });
+    // The log is there to explain crashes, not being able to keep it is no reason to stop.
+    let log = log.inspect_err(|e| eprintln!("{:}", tr!("cli-log-unavailable", error = e.to_string()))).ok();
+    let meter = Meter::start();
+    let locale = Localized::system();
+    // Like the log, the cache only saves time, a scan can do without it.
+    let mut cache = match args.no_cache {
+        true => None,
+        false => HashCache::load(&dirs.data).inspect_err(|e| eprintln!("{:}", tr!("cli-cache-unavailable", error = e.to_string()))).ok(),
+    };
+    let conf = relate::RelateConf {
+        strategy,
+        device_threads: args.device_threads,
+        hash_algo: settings.hash_algo,
+        mmap_threshold: Some(args.mmap_threshold).filter(|_| !args.no_mmap),
+        change_retries: args.change_retries,
+        retry_locked: args.retry_locked,
+        io_limit,
+        low_priority: args.low_priority || settings.low_priority,
+        deadline,
+        cancel: None,
+    };
+    let (progress_tx, progress_rx): (Sender<Progress>, Receiver<Progress>) = mpsc::channel();
+    let (walk, mut related, read) = if args.pipeline {
+        let roots = project.scanned();
+        eprintln!("{:}", tr!("cli-walking-and-hashing", dirs = list_dirs(&project.roots())));
+        // Results made with another algorithm can't be carried on from, as in `RelatedFiles::resume'.
+        let previous = previous.filter(|previous| previous.algo == settings.hash_algo);
+        let hashes = previous.as_ref().map(RelatedFiles::by_file).unwrap_or_default();
+        let known = |fi: &FileInfo| match (&previous, &cache) {
+            (Some(_), _) => hashes.get(fi).map(|hash| hash.to_string()),
+            (None, Some(cache)) => cache.get(fi, settings.hash_algo).map(str::to_owned),
+            (None, None) => None,
+        };
+        thread::scope(|s| {
+            let th = s.spawn(|| RelatedFiles::walk_and_relate(&roots, &filter, &conf, known, progress_tx));
+            let read = show_progress(progress_rx, 0, 0);
+            let (walk, related) = th.join().expect("Hashing thread panicked");
+            report_walk(&walk);
+            if let Some(log) = &log {
+                let _ = log.walked(walk.files.len(), walk.total_size);
+            }
+            (walk, related, read)
+        })
+    } else {
+        eprintln!("{:}", tr!("cli-walking", dirs = list_dirs(&project.roots())));
+        // Copies in the reference folder keep a size from being unique too.
+        let scanned = project.roots().into_iter().chain(project.reference.clone()).collect::<Vec<PathBuf>>();
+        let filter = filter.counting_sizes(&scanned, || false);
+        let mut walk = relate::WalkInfo::walk_roots(&project.roots(), &filter, &Default::default());
+        if let Some(reference) = &project.reference {
+            eprintln!("{:}", tr!("cli-walking-reference", reference = reference.display().to_string()));
+            walk = walk.merge(relate::WalkInfo::walk_filtered(reference.clone(), &filter));
+        }
+        report_walk(&walk);
+        if let Some(log) = &log {
+            let _ = log.walked(walk.files.len(), walk.total_size);
+        }
+        let previous = previous.or_else(|| cache.as_ref().map(|cache| cache.known(&walk, settings.hash_algo)));
+        eprintln!("{:}", tr!("cli-hashing", files = locale.number(walk.files.len() as u64), size = locale.size(walk.total_size)));
+        let (related, read) = thread::scope(|s| {
+            let th = s.spawn(|| match previous {
+                Some(previous) => previous.resume(&walk, &conf, progress_tx),
+                None => RelatedFiles::relate(&walk, &conf, progress_tx),
+            });
+            let read = show_progress(progress_rx, walk.files.len() as u64, walk.total_size);
+            (th.join().expect("Hashing thread panicked"), read)
+        });
+        (walk, related, read)
+    };
+    let usage = meter.finish(read);
+    eprintln!("{:}", locale.usage(&usage));
+    if let Some(cache) = &mut cache {
+        let roots = project.scanned();
+        cache.update(&roots.iter().map(PathBuf::as_path).collect::<Vec<&Path>>(), &walk, &related);
+        if let Err(e) = cache.save(&dirs.data) {
+            eprintln!("{:}", tr!("cli-cache-update-failed", error = e.to_string()));
+        }
+    }
+    if let Some(reference) = &project.reference {
+        related.retain_reference_duplicates(reference);
+    }
+    let ignored = related.remove_denied(&denylist);
+    if ignored > 0 {
+        eprintln!("{:}", tr!("cli-denied", contents = locale.number(ignored as u64)));
+    }
+    let errors = walk.errors.len() + related.errors.len();
+    if related.partial {
+        eprintln!("{:}", tr!("cli-time-limit"));
+    }
+    let (groups, empty) = (related.duplicate_groups().len(), related.empty_files().len());
+    let partial = related.partial;
+    project.walk = Some(walk);
+    project.related = Some(related);
+    project.usage = Some(usage);
+    let saved = project.save(&dirs.data).map_err(|e| e.to_string());
+    if let Some(log) = log {
+        let _ = match &saved {
+            Ok(_) => log.finished(ScanOutcome { groups, errors, partial, cancelled: false, usage: Some(usage) }),
+            Err(e) => log.failed(e.clone()),
+        };
+    }
+    let file = saved?;
+    eprintln!(
+        "{:}",
+        tr!("cli-scan-found", groups = locale.number(groups as u64), errors = locale.number(errors as u64), file = file.display().to_string()),
+    );
+    if empty > 0 {
+        eprintln!("{:}", tr!("cli-empty-files", files = locale.number(empty as u64)));
+    }
+    if args.errors {
+        let walk_errors = project.walk.iter().flat_map(|walk| walk.errors.iter());
+        let related_errors = project.related.iter().flat_map(|related| related.errors.iter());
+        report::write_errors(io::stdout().lock(), walk_errors.chain(related_errors)).map_err(|e| e.to_string())?;
+    } else if errors > 0 {
+        eprintln!("{:}", tr!("cli-list-errors"));
+    }
+    Ok(())
+}
+
+/// Mention what the walk set aside rather than hashing.
+fn report_walk(walk: &WalkInfo) {
+    if !walk.hard_links.is_empty() {
+        eprintln!("{:}", tr!("cli-hard-links", files = Localized::system().number(walk.hard_links.len() as u64)));
+    }
+    if !walk.links.is_empty() {
+        eprintln!("{:}", tr!("cli-symbolic-links", links = Localized::system().number(walk.links.len() as u64)));
+    }
+}
+
+/// Print hashing progress from `progress_rx' until the hashing is done, out of `total_files' files adding up to
+/// `total_bytes', plus any found along the way.  Returns the bytes read.
+fn show_progress(progress_rx: Receiver<Progress>, mut total_files: u64, mut total_bytes: u64) -> u64 {
+    let locale = Localized::system();
+    let (mut files, mut bytes, mut read) = (0, 0, 0);
+    // How far along each large file being hashed is, so the progress doesn't stall on them.
+    let mut reading = HashMap::<PathBuf, u64>::new();
+    let mut throughput = Throughput::new();
+    // Until hashing starts, more files may yet be found, so there's no telling how long is left.
+    let mut hashing = false;
+    for progress in progress_rx {
+        match progress {
+            Progress::FileReading { path, bytes: n } => {
+                reading.insert(path, n);
+            },
+            Progress::FileHashed { path, bytes: n } => {
+                reading.remove(&path);
+                files += 1;
+                bytes += n;
+                read += n;
+            },
+            // Counted as done, so the progress still reaches the total.
+            Progress::FileFailed { path, bytes: n } => {
+                reading.remove(&path);
+                files += 1;
+                bytes += n;
+            },
+            Progress::Resumed { files: n, bytes: b } => {
+                files += n as u64;
+                bytes += b;
+                // Carried over rather than hashed, so not part of the pace.
+                throughput = Throughput::new();
+            },
+            Progress::FileFound { bytes: n } => {
+                total_files += 1;
+                total_bytes += n;
+            },
+            Progress::StageChanged(stage) => {
+                hashing = stage == Stage::Hashing;
+                continue;
+            },
+            Progress::FileStarted(_) | Progress::Done => continue,
+        }
+        let done = bytes + reading.values().sum::<u64>();
+        throughput.record(done);
+        let percent = (done * 100).checked_div(total_bytes).unwrap_or(0);
+        let mut line = tr!(
+            "cli-progress",
+            files = locale.number(files), total_files = locale.number(total_files),
+            bytes = locale.size(done), total_bytes = locale.size(total_bytes), percent = percent,
+        );
+        match (throughput.bytes_per_sec(), throughput.time_left(total_bytes.saturating_sub(done)).filter(|_| hashing)) {
+            (Some(rate), Some(left)) => line.push_str(&tr!("cli-progress-left", rate = locale.size(rate), left = locale.duration(left))),
+            (Some(rate), None) => line.push_str(&tr!("cli-progress-rate", rate = locale.size(rate))),
+            (None, _) => (),
+        }
+        // Padded to wipe out the end of a longer line before.
+        eprint!("\r{:<100}", line);
+    }
+    eprintln!();
+    read
+}
+
+fn estimate(dirs: &Dirs, dir: PathBuf, conf: EstimateConf, filters: Filters, profile: Option<&Profile>) -> Result<(), String> {
+    let dir = canonical_dir(dir)?;
+    // The project is only read for its filters, so the estimate walks what a scan would.
+    let settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+    let mut project = Project::open_with(&dirs.data, dir, &settings.filters).map_err(|e| e.to_string())?;
+    if let Some(profile) = profile {
+        project.apply_profile(profile);
+    }
+    filters.apply(&mut project.walk_conf);
+    let filter = WalkFilter::new(&project.walk_conf).map_err(|e| tr!("filter-invalid", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-walking", dirs = list_dirs(&project.roots())));
+    let walk = relate::WalkInfo::walk_roots(&project.roots(), &filter, &Default::default());
+    let locale = Localized::system();
+    let estimate = estimate::estimate(&walk, &conf);
+    let mut out = io::stdout().lock();
+    let candidates = tr!(
+        "cli-estimate-candidates",
+        candidates = locale.number(estimate.candidate_files as u64), files = locale.number(walk.files.len() as u64),
+        groups = locale.number(estimate.candidate_groups as u64),
+    );
+    let sampled = tr!(
+        "cli-estimate-sampled",
+        groups = locale.number(estimate.sampled_groups as u64), size = locale.size(estimate.sampled_bytes), found = locale.size(estimate.found),
+    );
+    let reclaimable = match estimate.exact() {
+        true => tr!("cli-estimate-exact", reclaimable = locale.size(estimate.reclaimable)),
+        false => tr!(
+            "cli-estimate-range",
+            reclaimable = locale.size(estimate.reclaimable), margin = locale.size(estimate.margin), most = locale.size(estimate.upper_bound),
+        ),
+    };
+    let result = writeln!(out, "{:}\n{:}\n{:}", candidates, sampled, reclaimable);
+    result.map_err(|e| e.to_string())?;
+    if !estimate.errors.is_empty() {
+        eprintln!("{:}", tr!("cli-estimate-unreadable", files = locale.number(estimate.errors.len() as u64)));
+    }
+    Ok(())
+}
+
+/// Load the project for `dir', insisting that it has been scanned.
+fn load_scanned(dirs: &Dirs, dir: PathBuf) -> Result<Project, String> {
+    load_with(dirs, dir, Project::load)
+}
+
+/// Like `load_scanned', reading only the duplicate groups and empty files of the scan, for the commands that
+/// need no more, see `Project::load_duplicates'.
+fn load_duplicates(dirs: &Dirs, dir: PathBuf) -> Result<Project, String> {
+    load_with(dirs, dir, Project::load_duplicates)
+}
+
+fn load_with(dirs: &Dirs, dir: PathBuf, load: fn(&Path, &Path) -> Result<Option<Project>, project::Error>) -> Result<Project, String> {
+    let dir = canonical_dir(dir)?;
+    let project = load(&dirs.data, &dir).map_err(|e| e.to_string())?;
+    let Some(mut project) = project else {
+        return Err(tr!("cli-not-scanned", dir = dir.display().to_string()));
+    };
+    let Some(related) = &mut project.related else {
+        return Err(tr!("cli-scan-unfinished", dir = dir.display().to_string()));
+    };
+    // Contents denied since the scan are dropped here, so they are never reported or removed.
+    related.remove_denied(&Denylist::load(&dirs.config).map_err(|e| e.to_string())?);
+    Ok(project)
+}
+
+/// Which groups to print, and in what order.
+struct Selection {
+    limit: Option<usize>,
+    min_group_size: usize,
+    sort: Sort,
+}
+
+impl Selection {
+    fn apply<'a>(&self, mut groups: Vec<Group<'a>>) -> Vec<Group<'a>> {
+        groups.retain(|group| group.files.len() >= self.min_group_size);
+        // Groups arrive most wasteful first, and the sort is stable, so ties stay in that order.
+        match self.sort {
+            Sort::Wasted => (),
+            Sort::Count => groups.sort_by_key(|group| Reverse(group.files.len())),
+            Sort::Size => groups.sort_by_key(|group| Reverse(group.size)),
+        }
+        groups.truncate(self.limit.unwrap_or(usize::MAX));
+        groups
+    }
+}
+
+fn report(dirs: &Dirs, dir: PathBuf, format: Format, selection: Selection) -> Result<(), String> {
+    let project = load_duplicates(dirs, dir)?;
+    let mut report = Report::new(&project).expect("Scanned project has no results");
+    report.groups = selection.apply(report.groups);
+    let mut out = io::stdout().lock();
+    let result = match format {
+        Format::Json => report::to_json(&mut out, &report),
+        Format::Csv => report::to_csv(&mut out, &report),
+        Format::Fdupes => report::to_fdupes(&mut out, &report),
+    };
+    result.map_err(|e| tr!("cli-write-report-failed", error = e.to_string()))?;
+    let reclaim = report.groups.iter().filter_map(|group| report.reclaimable.get(group.hash)).copied().sum::<Reclaim>();
+    let locale = Localized::system();
+    eprintln!(
+        "{:}",
+        tr!("cli-reclaimable", policy = report.keep.to_string(), size = locale.size(reclaim.size), allocated = locale.size(reclaim.allocated)),
+    );
+    Ok(())
+}
+
+fn export(dirs: &Dirs, dir: PathBuf, format: IndexFormat) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    manifest::write(io::stdout().lock(), related, format.into()).map_err(|e| tr!("cli-write-hashes-failed", error = e.to_string()))
+}
+
+fn import(dirs: &Dirs, file: PathBuf, dir: PathBuf, format: IndexFormat, hash_algo: HashAlgo) -> Result<(), String> {
+    let dir = canonical_dir(dir)?;
+    let _lock = Project::new(dir.clone()).lock(&dirs.data).map_err(|e| e.to_string())?;
+    let mut project = Project::open(&dirs.data, dir).map_err(|e| e.to_string())?;
+    let input = fs::File::open(&file).map_err(|e| format!("'{:}': {:}", file.display(), e))?;
+    let base = file.canonicalize().ok().and_then(|file| file.parent().map(Path::to_path_buf)).unwrap_or_default();
+    let related = manifest::read(io::BufReader::new(input), &file, format.into(), hash_algo, &base).map_err(|e| e.to_string())?;
+    for e in &related.errors {
+        eprintln!("{:}", e);
+    }
+    let locale = Localized::system();
+    let files = related.files.values().map(|group| group.len()).sum::<usize>();
+    let groups = related.files.values().filter(|group| group.len() > 1).count();
+    eprintln!("{:}", tr!("cli-imported", files = locale.number(files as u64), groups = locale.number(groups as u64)));
+    // The files weren't walked, so there is nothing to watch or resume from.
+    project.walk = None;
+    project.related = Some(related);
+    project.usage = None;
+    project.save(&dirs.data).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn known(dirs: &Dirs, dir: PathBuf, archive: PathBuf, archive_data: Option<PathBuf>, unknown: bool) -> Result<(), String> {
+    let dir = canonical_dir(dir)?;
+    // The archive needn't be mounted, its scan is all that is read.
+    let archive = archive.canonicalize().map_or(archive, |archive| platform::plain_path(&archive));
+    let archive_data = archive_data.unwrap_or_else(|| dirs.data.clone());
+    let check = Crosscheck::load(&archive_data, &archive, &dirs.data, &dir).map_err(|e| e.to_string())?;
+    let locale = Localized::system();
+    eprintln!(
+        "{:}",
+        tr!(
+            "cli-known",
+            known = locale.number(check.known.len() as u64), files = locale.number((check.known.len() + check.unknown.len()) as u64),
+            size = locale.size(check.known_size()), archive = archive.display().to_string(),
+        ),
+    );
+    if check.partial {
+        eprintln!("{:}", tr!("cli-known-partial"));
+    }
+    let mut out = io::stdout().lock();
+    let result = match unknown {
+        true => check.unknown.iter().try_for_each(|fi| writeln!(out, "{:}", fi.name.display())),
+        false => check.known.iter().try_for_each(|known| writeln!(out, "{:}\t{:}", known.file.name.display(), known.copies[0].display())),
+    };
+    result.map_err(|e| tr!("cli-write-files-failed", error = e.to_string()))
+}
+
+fn folders(dirs: &Dirs, dir: PathBuf, limit: Option<usize>) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let analysis = Analysis::new(related, &project.path);
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let result = analysis.most_wasteful().into_iter().take(limit.unwrap_or(usize::MAX)).try_for_each(|stats| {
+        writeln!(
+            out, "{:}\t{:}\t{:}\t{:}",
+            locale.size(stats.wasted), locale.number(stats.duplicates as u64), locale.number(stats.groups as u64), stats.folder.display(),
+        )
+    });
+    result.map_err(|e| tr!("cli-write-folders-failed", error = e.to_string()))
+}
+
+/// Compile `keep' for `project', protecting its reference folder.
+fn resolver(project: &Project, keep: KeepPolicy) -> Result<Resolver, String> {
+    let resolver = Resolver::new(keep).map_err(|e| tr!("cli-keep-invalid", error = e.to_string()))?;
+    Ok(match &project.reference {
+        Some(reference) => resolver.with_reference(reference.clone()),
+        None => resolver,
+    })
+}
+
+fn compare(dirs: &Dirs, dir: PathBuf, keep: KeepPolicy, against: KeepPolicy) -> Result<(), String> {
+    let project = load_duplicates(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let plan = |keep: &KeepPolicy| resolver(&project, keep.clone()).map(|resolver| Plan::new(related, &resolver, Action::Delete));
+    let diff = plan(&keep)?.diff(&plan(&against)?);
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let result = [(&keep, &diff.only_first), (&against, &diff.only_second)].into_iter().try_for_each(|(policy, steps)| {
+        let bytes = steps.iter().map(|step| step.size).sum();
+        let heading = tr!("cli-compare-only", policy = policy.to_string(), files = locale.number(steps.len() as u64), size = locale.size(bytes));
+        writeln!(out, "{:}", heading)?;
+        steps.iter().try_for_each(|step| writeln!(out, "  {:}", step.target.display()))
+    });
+    result.map_err(|e| tr!("cli-write-comparison-failed", error = e.to_string()))?;
+    let net = diff.net_reclaimable();
+    let (against, size, keep) = (against.to_string(), locale.size(net.unsigned_abs() as u64), keep.to_string());
+    match net < 0 {
+        true => eprintln!("{:}", tr!("cli-compare-less", against = against, size = size, keep = keep)),
+        false => eprintln!("{:}", tr!("cli-compare-more", against = against, size = size, keep = keep)),
+    }
+    Ok(())
+}
+
+/// Refuse to reflink in `dir' unless its filesystem can, naming the filesystem either way.
+fn check_reflinks(dir: &Path) -> Result<(), String> {
+    let filesystem = platform::filesystem_name(dir).unwrap_or_else(|| tr!("cli-unknown-filesystem"));
+    if !platform::supports_reflinks(dir) {
+        return Err(tr!("cli-no-reflinks", dir = dir.display().to_string(), filesystem = filesystem));
+    }
+    eprintln!("{:}", tr!("cli-reflinks", dir = dir.display().to_string(), filesystem = filesystem));
+    Ok(())
+}
+
+/// Which duplicates `dedupe' acts on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Scope {
+    /// Every copy the keep policy doesn't keep.
+    All,
+    /// Loose files whose contents are inside an archive.
+    AgainstArchives,
+    /// The files marked for removal.
+    Selected,
+    /// Every empty file, none of them kept.
+    Empty,
+    /// Every folder holding the same as another that the keep policy doesn't keep.
+    Folders,
+    /// The files the rules mark, see `rules::select'.
+    Rules(Vec<Rule>),
+}
+
+fn dedupe(
+    dirs: &Dirs, dir: PathBuf, action: Option<Action>, keep: Option<KeepPolicy>, options: DedupOptions, scope: Scope,
+    permit: Option<&Permit>,
+) -> Result<(), String> {
+    // Finding duplicate folders takes every file, as a folder with a file of its own duplicates no other.
+    let mut project = match scope {
+        Scope::Folders => load_scanned(dirs, dir)?,
+        _ => load_duplicates(dirs, dir)?,
+    };
+    let _lock = project.lock(&dirs.data).map_err(|e| e.to_string())?;
+    if let Some(run) = journal::interrupted(&dirs.data).map_err(|e| e.to_string())?.filter(|_| !options.dry_run) {
+        return Err(tr!("cli-run-interrupted", dir = run.project.display().to_string()));
+    }
+    // Removing copies of archive members or empty files is a one-off, so it doesn't replace the remembered action.
+    let one_off = matches!(scope, Scope::AgainstArchives | Scope::Empty);
+    let action = match scope {
+        Scope::AgainstArchives => Action::Trash,
+        _ => action.or(project.preferences.action).unwrap_or_default(),
+    };
+    let keep = keep.or(project.preferences.keep.clone()).unwrap_or(KeepPolicy::OldestCreated);
+    let resolver = resolver(&project, keep.clone())?;
+    project.preferences = Preferences {
+        action: if one_off { project.preferences.action } else { Some(action) },
+        keep: Some(keep),
+        rules: match &scope {
+            Scope::Rules(rules) => rules.clone(),
+            _ => project.preferences.rules.clone(),
+        },
+    };
+    if action == Action::Reflink && scope != Scope::Empty {
+        for root in project.roots() {
+            check_reflinks(&root)?;
+        }
+    }
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let folder_groups = match &scope {
+        Scope::Folders => folders::duplicate_folders(related, &project.scanned()),
+        _ => Vec::new(),
+    };
+    let mut plan = match &scope {
+        Scope::All => Plan::new(related, &resolver, action),
+        Scope::AgainstArchives => Plan::against_archives(related, &resolver),
+        Scope::Selected => Plan::selected(related, &project.decisions.remove, &resolver, action),
+        Scope::Empty => Plan::empty_files(related, &resolver, action),
+        Scope::Folders => Plan::folders(related, &folder_groups, &resolver, action),
+        Scope::Rules(rules) => {
+            let selected = rules::select(related, rules, &resolver).map_err(|e| tr!("rules-invalid", error = e.to_string()))?;
+            Plan::selected(related, &selected, &resolver, action)
+        },
+    };
+    let (mut failed, mut busy) = (0, 0);
+    let (mut reclaimed, mut reclaimed_allocated) = (0, 0);
+    let locale = Localized::system();
+    if matches!(action, Action::HardLink | Action::Reflink) {
+        let settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+        for crossing in plan.across_devices(related, settings.cross_device) {
+            let (copies, keep) = (locale.number(crossing.copies.len() as u64), crossing.keep.display().to_string());
+            eprintln!("{:}", tr!("cli-cross-device", copies = copies, keep = keep, decision = crossing.decision.to_string()));
+        }
+    }
+    let (outcomes, journaled) = match permit {
+        Some(permit) if !options.dry_run => journal::execute(&dirs.data, &project.path, &plan, &options, permit),
+        _ => (plan.preview(&options), Ok(())),
+    };
+    if options.dry_run {
+        return dry_run(&outcomes);
+    }
+    project.record(&outcomes);
+    let saved = project.save_decisions(&dirs.data, &outcomes);
+    if let (Some(permit), Action::Trash | Action::Delete) = (permit, action) {
+        for folder in folder_groups.iter().filter_map(|group| group.resolve(&resolver)).flat_map(|res| res.remove) {
+            if let Err(e) = actions::remove_emptied(&folder.name, permit) {
+                eprintln!("{:}", tr!("folder-remove-failed", folder = folder.name.display().to_string(), error = e.to_string()));
+            }
+        }
+    }
+    for outcome in outcomes {
+        if outcome.in_use() {
+            busy += 1;
+            eprintln!("{:}", tr!("cli-skipped-in-use", file = outcome.step.target.display().to_string()));
+            continue;
+        }
+        match outcome.result {
+            Ok(()) => {
+                reclaimed += outcome.step.size;
+                reclaimed_allocated += outcome.step.allocated;
+            },
+            Err(e) => {
+                failed += 1;
+                eprintln!("{:}", tr!("cli-action-failed", action = verb(outcome.step.action), file = outcome.step.target.display().to_string(), error = e.to_string()));
+            },
+        }
+    }
+    saved.map_err(|e| e.to_string())?;
+    journaled.map_err(|e| tr!("journal-write-failed", error = e.to_string()))?;
+    eprintln!(
+        "{:}",
+        tr!(
+            "cli-dedupe-done",
+            done = locale.number((plan.steps.len() - failed - busy) as u64), files = locale.number(plan.steps.len() as u64),
+            reclaimed = locale.size(reclaimed), allocated = locale.size(reclaimed_allocated),
+        ),
+    );
+    if busy > 0 {
+        eprintln!("{:}", tr!("cli-dedupe-busy", files = locale.number(busy as u64)));
+    }
+    if failed > 0 {
+        Err(tr!("cli-dedupe-failed", files = locale.number(failed as u64)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Finish the run deduplicating `dir' that was cut short, or with `roll_back' take back what it did.
+fn resume(dirs: &Dirs, dir: PathBuf, roll_back: bool, permit: &Permit) -> Result<(), String> {
+    let mut project = load_scanned(dirs, dir)?;
+    let _lock = project.lock(&dirs.data).map_err(|e| e.to_string())?;
+    let run = journal::interrupted(&dirs.data).map_err(|e| e.to_string())?;
+    let Some(run) = run.filter(|run| run.project == project.path) else {
+        return Err(tr!("cli-nothing-interrupted", dir = project.path.display().to_string()));
+    };
+    let locale = Localized::system();
+    if roll_back {
+        let restored = journal::roll_back(&dirs.data, &run, permit).map_err(|e| e.to_string())?;
+        let failed = restored.iter().filter_map(|(_, result)| result.as_ref().err()).inspect(|e| eprintln!("{:}", e)).count();
+        eprintln!("{:}", tr!("cli-rolled-back", done = locale.number((restored.len() - failed) as u64), files = locale.number(restored.len() as u64)));
+        return match failed {
+            0 => Ok(()),
+            failed => Err(tr!("cli-roll-back-failed", files = locale.number(failed as u64))),
+        };
+    }
+    let (outcomes, journaled) = journal::resume(&dirs.data, &run, &DedupOptions::default(), permit);
+    project.record(&outcomes);
+    let mut failed = 0;
+    for outcome in &outcomes {
+        if let Err(e) = &outcome.result {
+            failed += 1;
+            eprintln!("{:}", tr!("cli-action-failed", action = verb(outcome.step.action), file = outcome.step.target.display().to_string(), error = e.to_string()));
+        }
+    }
+    project.save_decisions(&dirs.data, &outcomes).map_err(|e| e.to_string())?;
+    journaled.map_err(|e| tr!("journal-write-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-resumed", done = locale.number((outcomes.len() - failed) as u64), files = locale.number(outcomes.len() as u64)));
+    match failed {
+        0 => Ok(()),
+        failed => Err(tr!("cli-dedupe-failed", files = locale.number(failed as u64))),
+    }
+}
+
+/// How messages name `action', see the `$action' selectors of the catalogs.
+fn verb(action: Action) -> &'static str {
+    match action {
+        Action::Trash => "trash",
+        Action::Delete => "delete",
+        Action::HardLink => "link",
+        Action::Reflink => "clone",
+        Action::Symlink => "symlink",
+    }
+}
+
+/// Print what the previewed `outcomes' would do.
+fn dry_run(outcomes: &[Outcome]) -> Result<(), String> {
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let (mut steps, mut reclaimable, mut reclaimable_allocated) = (0, 0, 0);
+    let result = outcomes.iter().try_for_each(|outcome| {
+        let step = &outcome.step;
+        match &outcome.result {
+            Ok(()) => {
+                steps += 1;
+                reclaimable += step.size;
+                reclaimable_allocated += step.allocated;
+                writeln!(out, "{:}", tr!("cli-would", action = verb(step.action), file = step.target.display().to_string(), keep = step.keep.display().to_string()))
+            },
+            Err(e) => writeln!(out, "{:}", tr!("cli-could-not", action = verb(step.action), file = step.target.display().to_string(), error = e.to_string())),
+        }
+    });
+    result.map_err(|e| tr!("cli-write-dry-run-failed", error = e.to_string()))?;
+    eprintln!(
+        "{:}",
+        tr!(
+            "cli-dry-run-done",
+            done = locale.number(steps), files = locale.number(outcomes.len() as u64),
+            reclaimable = locale.size(reclaimable), allocated = locale.size(reclaimable_allocated),
+        ),
+    );
+    Ok(())
+}
+
+fn similar_images(dirs: &Dirs, dir: PathBuf, max_distance: u32) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let similar = SimilarImages::find(related, &ImageConf { max_distance, ..Default::default() });
+    for e in &similar.errors {
+        eprintln!("{:}", e);
+    }
+    print_similar(&similar.groups).map_err(|e| tr!("cli-write-images-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-images-found", groups = Localized::system().number(similar.groups.len() as u64)));
+    Ok(())
+}
+
+fn same_audio(dirs: &Dirs, dir: PathBuf) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let comparators = audio::comparators(related.algo);
+    let similar = SimilarFiles::find(related, &comparators, |path| comparators.registered(path), relate::available_threads());
+    for e in &similar.errors {
+        eprintln!("{:}", e);
+    }
+    print_similar(&similar.groups).map_err(|e| tr!("cli-write-recordings-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-audio-found", groups = Localized::system().number(similar.groups.len() as u64)));
+    Ok(())
+}
+
+fn same_archives(dirs: &Dirs, dir: PathBuf) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let comparators = archive::comparators(related.algo);
+    let similar = SimilarFiles::find(related, &comparators, |path| comparators.registered(path), relate::available_threads());
+    for e in &similar.errors {
+        eprintln!("{:}", e);
+    }
+    print_similar(&similar.groups).map_err(|e| tr!("cli-write-archives-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-archives-found", groups = Localized::system().number(similar.groups.len() as u64)));
+    Ok(())
+}
+
+fn duplicate_folders(dirs: &Dirs, dir: PathBuf) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let groups = folders::duplicate_folders(related, &project.scanned());
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let result = groups.iter().try_for_each(|group| {
+        for folder in &group.folders {
+            writeln!(out, "{:}\t{:}\t{:}", locale.size(group.size), locale.number(group.files as u64), folder.name.display())?;
+        }
+        writeln!(out)
+    });
+    result.map_err(|e| tr!("cli-write-folders-failed", error = e.to_string()))?;
+    let wasted = groups.iter().map(|group| group.wasted()).sum();
+    eprintln!("{:}", tr!("cli-duplicate-folders-found", groups = locale.number(groups.len() as u64), wasted = locale.size(wasted)));
+    Ok(())
+}
+
+fn similar_folders(dirs: &Dirs, dir: PathBuf, min_similarity: u8) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let pairs = folders::similar_folders(related, &project.scanned(), min_similarity);
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let result = pairs.iter().try_for_each(|pair| {
+        writeln!(
+            out, "{:}%\t{:} of {:}\t{:}\t{:}",
+            pair.similarity(), locale.number(pair.shared as u64), locale.number(pair.total as u64), pair.first.display(), pair.second.display(),
+        )
+    });
+    result.map_err(|e| tr!("cli-write-folders-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-similar-folders-found", pairs = locale.number(pairs.len() as u64)));
+    Ok(())
+}
+
+fn name_collisions(dirs: &Dirs, dir: PathBuf) -> Result<(), String> {
+    let project = load_scanned(dirs, dir)?;
+    let related = project.related.as_ref().expect("Scanned project has no results");
+    let collisions = naming::collisions(related);
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    let result = collisions.iter().try_for_each(|collision| {
+        for version in &collision.versions {
+            writeln!(out, "{:}\t{:}", version.hash, locale.size(version.files.first().map_or(0, |fi| fi.size)))?;
+            for fi in &version.files {
+                writeln!(out, "\t{:}", fi.name.display())?;
+            }
+        }
+        writeln!(out)
+    });
+    result.map_err(|e| tr!("cli-write-collisions-failed", error = e.to_string()))?;
+    eprintln!("{:}", tr!("cli-collisions-found", names = locale.number(collisions.len() as u64)));
+    Ok(())
+}
+
+/// Print each group of similar files, with their sizes, followed by an empty line.
+fn print_similar(groups: &[Vec<FileInfo>]) -> io::Result<()> {
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    for group in groups {
+        for fi in group {
+            writeln!(out, "{:}\t{:}", locale.size(fi.size), fi.name.display())?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn watch(dirs: &Dirs, dir: PathBuf) -> Result<(), String> {
+    let mut project = load_scanned(dirs, dir)?;
+    let _lock = project.lock(&dirs.data).map_err(|e| e.to_string())?;
+    let filter = WalkFilter::new(&project.walk_conf).map_err(|e| tr!("filter-invalid", error = e.to_string()))?;
+    let denylist = Denylist::load(&dirs.config).map_err(|e| e.to_string())?;
+    let roots = project.scanned();
+    let watch = Watch::start(&roots.iter().map(PathBuf::as_path).collect::<Vec<&Path>>()).map_err(|e| e.to_string())?;
+    let locale = Localized::system();
+    eprintln!("{:}", tr!("cli-watching", dirs = list_dirs(&project.roots())));
+    loop {
+        let changed = watch.changed(Duration::from_secs(60));
+        if changed.is_empty() {
+            continue;
+        }
+        let (Some(walk), Some(related)) = (&mut project.walk, &mut project.related) else {
+            return Err(tr!("cli-walk-unsaved", dir = project.path.display().to_string()));
+        };
+        let updates = watch.examine(&changed, &filter, related.algo);
+        for update in &updates {
+            match update {
+                Update::Hashed(fi, _) => println!("{:}", tr!("cli-watch-hashed", file = fi.name.display().to_string())),
+                Update::Failed(e) => eprintln!("{:}", e),
+                Update::Gone(path) if !path.exists() => println!("{:}", tr!("cli-watch-gone", file = path.display().to_string())),
+                Update::Gone(_) => (),
+            }
+        }
+        watch::apply(updates, walk, related);
+        if let Some(reference) = &project.reference {
+            related.retain_reference_duplicates(reference);
+        }
+        related.remove_denied(&denylist);
+        let groups = related.duplicate_groups().len();
+        project.save(&dirs.data).map_err(|e| e.to_string())?;
+        eprintln!("{:}", tr!("cli-watch-groups", groups = locale.number(groups as u64)));
+    }
+}
+
+fn scans(dirs: &Dirs) -> Result<(), String> {
+    let records = telemetry::records(&dirs.data).map_err(|e| e.to_string())?;
+    let locale = Localized::system();
+    let mut out = io::stdout().lock();
+    for started in records.iter() {
+        let Event::Started(conf) = &started.event else { continue };
+        let end = records.iter().find(|record| record.scan == started.scan && matches!(record.event, Event::Finished(_) | Event::Failed { .. }));
+        let status = match end.map(|record| &record.event) {
+            Some(Event::Finished(outcome)) => tr!(
+                "cli-scans-finished",
+                groups = locale.number(outcome.groups as u64), errors = locale.number(outcome.errors as u64),
+                partial = if outcome.partial { "yes" } else { "no" },
+            ),
+            Some(Event::Failed { error }) => tr!("cli-scans-failed", error = error.as_str()),
+            _ => tr!("cli-scans-unfinished"),
+        };
+        writeln!(out, "{:}  {:}  {:}", locale.time(started.time), conf.dir.display(), status).map_err(|e| e.to_string())?;
+    }
+    eprintln!("{:}", tr!("cli-full-log", file = telemetry::log_path(&dirs.data).display().to_string()));
+    Ok(())
+}
+
+fn topics(query: &str) -> Result<(), String> {
+    let topics = help::search(query);
+    if topics.is_empty() {
+        return Err(tr!("cli-no-topic", query = query));
+    }
+    let mut out = io::stdout().lock();
+    for topic in topics {
+        writeln!(out, "{:}\n\n{:}\n", help::text(topic.title), help::text(topic.intro)).map_err(|e| e.to_string())?;
+        for term in topic.terms {
+            writeln!(out, "  {:}: {:}", help::text(term.name), help::text(term.text)).map_err(|e| e.to_string())?;
+        }
+        writeln!(out).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn profiles(dirs: &Dirs, command: ProfileCommand) -> Result<(), String> {
+    let mut settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+    match command {
+        ProfileCommand::List => {
+            let mut out = io::stdout().lock();
+            for profile in &settings.profiles {
+                let profile = toml::to_string_pretty(profile).map_err(|e| e.to_string())?;
+                writeln!(out, "{:}", profile).map_err(|e| e.to_string())?;
+            }
+            return Ok(());
+        },
+        ProfileCommand::Save { name, filters, action, keep } => {
+            let mut conf = WalkConf::default();
+            filters.apply(&mut conf);
+            settings.save_profile(Profile { name: name.trim().to_owned(), filters: conf, keep, action: action.map(Action::from) });
+        },
+        ProfileCommand::Remove { name } => {
+            if !settings.remove_profile(&name) {
+                return Err(tr!("cli-no-profile", name = name));
+            }
+        },
+    }
+    settings.save(&dirs.config).map_err(|e| e.to_string())
+}
+
+fn denylist(dirs: &Dirs, command: DenylistCommand) -> Result<(), String> {
+    let mut denylist = Denylist::load(&dirs.config).map_err(|e| e.to_string())?;
+    let changed = match command {
+        DenylistCommand::List => {
+            for entry in &denylist.entries {
+                println!("{:} {:} {:}", entry.algo, entry.hash, entry.note);
+            }
+            return Ok(());
+        },
+        DenylistCommand::Add { hash, hash_algo, note } => denylist.add(hash_algo, &hash, note),
+        DenylistCommand::Remove { hash, hash_algo } => denylist.remove(hash_algo, &hash),
+    };
+    if !changed {
+        eprintln!("{:}", tr!("cli-denylist-unchanged"));
+        return Ok(());
+    }
+    denylist.save(&dirs.config).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let permit = Permit::unless_auditing(cli.audit);
+    let result = Dirs::locate(cli.config)
+        .map_err(|e| e.to_string())
+        .and_then(|dirs| {
+            let settings = Settings::load(&dirs.config);
+            i18n::set_language(settings.as_ref().ok().and_then(|settings| settings.language.as_deref()));
+            // Only a profile needs the settings here, the commands report a broken file themselves.
+            let profile = match &cli.profile {
+                Some(name) => {
+                    let settings = settings.map_err(|e| e.to_string())?;
+                    Some(settings.profile(name).cloned().ok_or_else(|| tr!("cli-no-profile", name = name.as_str()))?)
+                },
+                None => None,
+            };
+            Ok((dirs, profile))
+        })
+        .and_then(|(dirs, profile)| match cli.command {
+            Command::Scan(args) => scan(&dirs, args, profile.as_ref()),
+            Command::Estimate { dir, groups, threads, filters, max_duration } => {
+                let conf = EstimateConf {
+                    groups,
+                    max_threads: threads.max(1),
+                    deadline: max_duration.map(|d| Instant::now() + d),
+                    ..Default::default()
+                };
+                estimate(&dirs, dir, conf, filters, profile.as_ref())
+            },
+            Command::Report { dir, format, limit, min_group_size, sort } => {
+                report(&dirs, dir, format, Selection { limit, min_group_size, sort })
+            },
+            Command::Folders { dir, limit } => folders(&dirs, dir, limit),
+            Command::Export { dir, format } => export(&dirs, dir, format),
+            Command::Import { file, dir, format, hash_algo } => import(&dirs, file, dir, format, hash_algo),
+            Command::Known { dir, archive, archive_data, unknown } => known(&dirs, dir, archive, archive_data, unknown),
+            Command::Compare { dir, keep, against } => compare(&dirs, dir, keep, against),
+            Command::Dedupe { dir, action, keep, verify, against_archives, selected, empty, folders, rules, dry_run } => {
+                if permit.is_none() && !dry_run {
+                    Err(tr!("cli-audit-refused"))
+                } else {
+                    let options = DedupOptions { verify, dry_run };
+                    let scope = match (against_archives, selected, empty, folders) {
+                        (true, _, _, _) => Scope::AgainstArchives,
+                        (_, true, _, _) => Scope::Selected,
+                        (_, _, true, _) => Scope::Empty,
+                        (_, _, _, true) => Scope::Folders,
+                        _ if !rules.is_empty() => Scope::Rules(rules),
+                        _ => Scope::All,
+                    };
+                    let action = action.map(Action::from).or(profile.as_ref().and_then(|profile| profile.action));
+                    let keep = keep.or(profile.as_ref().and_then(|profile| profile.keep.clone()));
+                    dedupe(&dirs, dir, action, keep, options, scope, permit.as_ref())
+                }
+            },
+            Command::Resume { dir, roll_back } => match &permit {
+                Some(permit) => resume(&dirs, dir, roll_back, permit),
+                None => Err(tr!("cli-resume-audit-refused")),
+            },
+            Command::Images { dir, max_distance } => similar_images(&dirs, dir, max_distance),
+            Command::Audio { dir } => same_audio(&dirs, dir),
+            Command::Archives { dir } => same_archives(&dirs, dir),
+            Command::DuplicateFolders { dir } => duplicate_folders(&dirs, dir),
+            Command::SimilarFolders { dir, min_similarity } => similar_folders(&dirs, dir, min_similarity),
+            Command::Collisions { dir } => name_collisions(&dirs, dir),
+            Command::Watch { dir } => watch(&dirs, dir),
+            Command::Scans => scans(&dirs),
+            Command::Topics { query } => topics(&query.join(" ")),
+            Command::Denylist { command } => denylist(&dirs, command),
+            Command::Profiles { command } => profiles(&dirs, command),
+        });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{:}", tr!("cli-error", error = e));
+            ExitCode::FAILURE
+        },
+    }
+}
@@ -0,0 +1,472 @@
+use clap::Parser;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+    process::ExitCode,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+use file_deduplicator::{
+    actions::{Action, DedupOptions, Outcome, Permit, Plan},
+    config::Dirs,
+    denylist::Denylist,
+    format::Localized,
+    guard,
+    help, journal,
+    i18n, platform,
+    policy::{KeepPolicy, Resolver},
+    project::Project,
+    relate::{CancellationToken, FileInfo, HashAlgo, Progress, RelateConf, RelatedFiles, Stage, WalkFilter, WalkInfo},
+    settings::Settings,
+    store::{self, GroupSummary, Store},
+    usage::Throughput,
+    tr,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, Gauge, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+/// Find and remove duplicate files in a terminal, e.g. over SSH: scan, review the groups, and act on the copies
+/// marked for removal.
+#[derive(Parser)]
+#[command(name = "fdedup_tui", version, about = tr!("tui-about"))]
+struct Cli {
+    #[arg(help = tr!("tui-dir-help"))]
+    dir: PathBuf,
+    #[arg(long, help = tr!("tui-rescan-help"))]
+    rescan: bool,
+    #[arg(long, help = tr!("tui-audit-help"))]
+    audit: bool,
+    #[arg(long, value_name = "DIR", help = help::text(help::CONFIG))]
+    config: Option<PathBuf>,
+}
+
+const KEEP_POLICIES: [KeepPolicy; 5] = [
+    KeepPolicy::OldestCreated,
+    KeepPolicy::NewestCreated,
+    KeepPolicy::ShortestPath,
+    KeepPolicy::ShallowestDirectory,
+    KeepPolicy::Alphabetical,
+];
+
+const ACTIONS: [Action; 4] = [Action::Trash, Action::Delete, Action::HardLink, Action::Reflink];
+
+/// How often the screen is redrawn while nothing is pressed, to show scan progress.
+const TICK: Duration = Duration::from_millis(100);
+
+/// A scan running on another thread, and how far it has got.
+struct Scanning {
+    progress_rx: Receiver<Progress>,
+    scan: JoinHandle<(WalkInfo, RelatedFiles)>,
+    cancel: CancellationToken,
+    files: u64,
+    bytes: u64,
+    total_files: u64,
+    total_bytes: u64,
+    /// All files are found once hashing starts, so the totals stop growing.
+    hashing: bool,
+    throughput: Throughput,
+}
+
+impl Scanning {
+    fn start(project: &Project, conf: RelateConf, filter: WalkFilter) -> Self {
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let cancel = CancellationToken::default();
+        let conf = RelateConf { cancel: Some(cancel.clone()), ..conf };
+        let scanned = project.scanned();
+        let scan = thread::spawn(move || RelatedFiles::walk_and_relate(&scanned, &filter, &conf, |_: &FileInfo| None, progress_tx));
+        Scanning { progress_rx, scan, cancel, files: 0, bytes: 0, total_files: 0, total_bytes: 0, hashing: false, throughput: Throughput::new() }
+    }
+
+    /// Count the progress reported since last time.
+    fn update(&mut self) {
+        for progress in self.progress_rx.try_iter() {
+            match progress {
+                Progress::FileFound { bytes } => {
+                    self.total_files += 1;
+                    self.total_bytes += bytes;
+                },
+                Progress::FileHashed { bytes, .. } | Progress::FileFailed { bytes, .. } => {
+                    self.files += 1;
+                    self.bytes += bytes;
+                    self.throughput.record(self.bytes);
+                },
+                Progress::StageChanged(stage) => self.hashing = stage == Stage::Hashing,
+                _ => (),
+            }
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame, locale: &Localized) {
+        let [title, gauge, keys] = Layout::vertical([Constraint::Length(1), Constraint::Length(3), Constraint::Min(1)]).areas(frame.area());
+        frame.render_widget(Line::from(tr!("tui-scanning")).style(Style::new().add_modifier(Modifier::BOLD)), title);
+        let ratio = (self.bytes as f64 / self.total_bytes.max(1) as f64).min(1.0);
+        let mut label = tr!(
+            "tui-progress",
+            files = locale.number(self.files), total_files = locale.number(self.total_files),
+            bytes = locale.size(self.bytes), total_bytes = locale.size(self.total_bytes),
+        );
+        let left = self.throughput.time_left(self.total_bytes.saturating_sub(self.bytes)).filter(|_| self.hashing);
+        match (self.throughput.bytes_per_sec(), left) {
+            (Some(rate), Some(left)) => label.push_str(&tr!("cli-progress-left", rate = locale.size(rate), left = locale.duration(left))),
+            (Some(rate), None) => label.push_str(&tr!("cli-progress-rate", rate = locale.size(rate))),
+            (None, _) => (),
+        }
+        frame.render_widget(Gauge::default().block(Block::bordered()).ratio(ratio).label(label), gauge);
+        frame.render_widget(Paragraph::new(tr!("tui-scanning-keys")), keys);
+    }
+}
+
+/// A row of the group browser: a group, or one of the files of an expanded group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Row {
+    Group(String),
+    File(String, PathBuf),
+}
+
+/// Browse the duplicate groups and mark the copies to remove.  Marks are kept in the project's decisions, as in
+/// the app, so either can carry on from the other.  The groups are read from the saved scan as they are needed,
+/// so reviewing a scan of millions of files holds little more than the groups listed.
+struct Review {
+    store: Store,
+    algo: HashAlgo,
+    /// The duplicate groups, most wasteful first.
+    groups: Vec<GroupSummary>,
+    /// The files of each open group, sorted.
+    expanded: HashMap<String, Vec<PathBuf>>,
+    list: ListState,
+    policy: KeepPolicy,
+    action: Action,
+    /// Asking whether to act on the marked files.
+    confirming: bool,
+    /// What the last key did, or why it did nothing.
+    status: String,
+}
+
+impl Review {
+    /// Review the groups of the scan of `project' saved in `store', but those with contents in `denylist'.
+    fn new(project: &Project, store: Store, denylist: &Denylist) -> Result<Self, store::Error> {
+        let algo = store.hash_algo(&project.path)?.unwrap_or_default();
+        let mut groups = store.groups(&project.path)?;
+        // Contents denied since the scan are dropped here, so they are never reported or removed.
+        groups.retain(|group| !denylist.contains(algo, &group.hash));
+        let status = tr!("tui-groups", groups = groups.len());
+        Ok(Review {
+            store,
+            algo,
+            groups,
+            expanded: HashMap::new(),
+            list: ListState::default().with_selected(Some(0)),
+            policy: project.preferences.keep.clone().filter(|keep| KEEP_POLICIES.contains(keep)).unwrap_or(KeepPolicy::OldestCreated),
+            action: project.preferences.action.unwrap_or_default(),
+            confirming: false,
+            status,
+        })
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        let mut rows = Vec::new();
+        for group in &self.groups {
+            rows.push(Row::Group(group.hash.clone()));
+            let files = self.expanded.get(&group.hash).into_iter().flatten();
+            rows.extend(files.map(|path| Row::File(group.hash.clone(), path.clone())));
+        }
+        rows
+    }
+
+    /// The files of the groups with `hashes', read from the saved scan.
+    fn related<'a>(&self, project: &Project, hashes: impl IntoIterator<Item = &'a String>) -> Result<RelatedFiles, String> {
+        let mut files = HashMap::new();
+        for hash in hashes {
+            let group = self.store.group(&project.path, hash).map_err(|e| tr!("project-read-failed", error = e.to_string()))?;
+            files.insert(hash.clone(), group);
+        }
+        Ok(RelatedFiles { algo: self.algo, files, partial: false, errors: Vec::new() })
+    }
+
+    fn resolver(&self, project: &Project) -> Resolver {
+        let resolver = Resolver::new(self.policy.clone()).expect("Only regexes can be invalid");
+        match &project.reference {
+            Some(reference) => resolver.with_reference(reference.clone()),
+            None => resolver,
+        }
+    }
+
+    /// Handle `key', returning whether to quit.
+    fn key(&mut self, key: KeyCode, project: &mut Project, dirs: &Dirs, permit: Option<&Permit>) -> bool {
+        let rows = self.rows();
+        let selected = self.list.selected().unwrap_or(0).min(rows.len().saturating_sub(1));
+        if self.confirming {
+            self.confirming = false;
+            match (key, permit) {
+                (KeyCode::Char('y'), Some(permit)) => self.act(project, dirs, permit),
+                _ => self.status = tr!("tui-cancelled"),
+            }
+            return false;
+        }
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => return true,
+            KeyCode::Down | KeyCode::Char('j') => self.list.select(Some((selected + 1).min(rows.len().saturating_sub(1)))),
+            KeyCode::Up | KeyCode::Char('k') => self.list.select(Some(selected.saturating_sub(1))),
+            KeyCode::PageDown => self.list.select(Some((selected + 20).min(rows.len().saturating_sub(1)))),
+            KeyCode::PageUp => self.list.select(Some(selected.saturating_sub(20))),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Left => {
+                let hash = match rows.get(selected) {
+                    Some(Row::Group(hash) | Row::File(hash, _)) => hash.clone(),
+                    None => return false,
+                };
+                if self.expanded.remove(&hash).is_none() {
+                    match self.related(project, [&hash]) {
+                        Ok(related) => {
+                            let mut files = related.files.values().flatten().map(|fi| fi.name.to_path_buf()).collect::<Vec<PathBuf>>();
+                            files.sort();
+                            self.expanded.insert(hash.clone(), files);
+                        },
+                        Err(e) => self.status = e,
+                    }
+                }
+                let at = self.rows().iter().position(|row| *row == Row::Group(hash.clone()));
+                self.list.select(at);
+            },
+            KeyCode::Char(' ') => {
+                let Some(Row::File(hash, path)) = rows.get(selected) else { return false };
+                if !project.toggle_mark(hash, path) {
+                    self.status = tr!("tui-reference-protected");
+                    return false;
+                }
+                self.save(project, dirs, &[]);
+            },
+            KeyCode::Char('a') => {
+                // A group at a time, so that only one is held at once.
+                let resolver = self.resolver(project);
+                let mut remove = BTreeMap::new();
+                for group in &self.groups {
+                    let related = match self.related(project, [&group.hash]) {
+                        Ok(related) => related,
+                        Err(e) => {
+                            self.status = e;
+                            return false;
+                        },
+                    };
+                    for (hash, res) in resolver.resolve_all(&related) {
+                        remove.insert(hash.clone(), res.remove.into_iter().map(|fi| fi.name.to_path_buf()).collect::<BTreeSet<PathBuf>>());
+                    }
+                }
+                project.decisions.remove = remove;
+                self.save(project, dirs, &[]);
+            },
+            KeyCode::Char('c') => {
+                project.decisions.remove.clear();
+                self.save(project, dirs, &[]);
+            },
+            KeyCode::Char('p') => {
+                let next = KEEP_POLICIES.iter().position(|policy| *policy == self.policy).map_or(0, |i| (i + 1) % KEEP_POLICIES.len());
+                self.policy = KEEP_POLICIES[next].clone();
+                project.preferences.keep = Some(self.policy.clone());
+                self.status = tr!("tui-keeping", policy = self.policy.to_string());
+            },
+            KeyCode::Char('t') => {
+                let next = ACTIONS.iter().position(|action| *action == self.action).map_or(0, |i| (i + 1) % ACTIONS.len());
+                self.action = ACTIONS[next];
+                project.preferences.action = Some(self.action);
+                self.status = help::text(help::action(self.action));
+            },
+            KeyCode::Char('x') => {
+                let marked = project.decisions.remove.values().map(BTreeSet::len).sum::<usize>();
+                self.status = match (permit, marked) {
+                    (None, _) => tr!("tui-audit"),
+                    (_, 0) => tr!("tui-nothing-marked"),
+                    _ => {
+                        self.confirming = true;
+                        tr!("tui-confirm", action = action_key(self.action), files = Localized::system().number(marked as u64))
+                    },
+                };
+            },
+            _ => (),
+        }
+        false
+    }
+
+    /// Carry out `action' on the marked files, keeping one copy of each group, and drop the groups left without
+    /// duplicates.
+    fn act(&mut self, project: &mut Project, dirs: &Dirs, permit: &Permit) {
+        let related = match self.related(project, project.decisions.remove.keys()) {
+            Ok(related) => related,
+            Err(e) => {
+                self.status = e;
+                return;
+            },
+        };
+        let mut plan = Plan::selected(&related, &project.decisions.remove, &self.resolver(project), self.action);
+        let cross_device = Settings::load(&dirs.config).unwrap_or_default().cross_device;
+        let crossings = plan.across_devices(&related, cross_device).len();
+        let (outcomes, journaled) = journal::execute(&dirs.data, &project.path, &plan, &DedupOptions::default(), permit);
+        project.record(&outcomes);
+        let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).count();
+        self.save(project, dirs, &outcomes);
+        // Removed copies leave their groups, and groups down to one copy are no longer duplicates.
+        let acted = outcomes.iter().map(|outcome| &outcome.step.hash).collect::<HashSet<&String>>();
+        self.expanded.retain(|hash, _| !acted.contains(hash));
+        match self.store.groups(&project.path) {
+            Ok(left) => {
+                let left = left.into_iter().map(|group| group.hash).collect::<HashSet<String>>();
+                self.groups.retain(|group| left.contains(&group.hash));
+            },
+            Err(e) => self.status = tr!("project-read-failed", error = e.to_string()),
+        }
+        self.list.select(Some(0));
+        self.status = match journaled {
+            Ok(()) => {
+                let locale = Localized::system();
+                let (done, files) = (locale.number((outcomes.len() - failed) as u64), locale.number(outcomes.len() as u64));
+                let status = tr!("tui-done", done = done, files = files, action = action_key(self.action));
+                match crossings {
+                    0 => status,
+                    groups => format!("{:}; {:}", status, tr!("tui-cross-device", groups = locale.number(groups as u64), decision = cross_device.to_string())),
+                }
+            },
+            Err(e) => tr!("journal-write-failed", error = e.to_string()),
+        };
+    }
+
+    /// Save the decisions, along with the files `outcomes' removed, leaving the rest of the saved scan alone.
+    fn save(&mut self, project: &mut Project, dirs: &Dirs, outcomes: &[Outcome]) {
+        if let Err(e) = project.save_decisions(&dirs.data, outcomes) {
+            self.status = tr!("project-save-failed", error = e.to_string());
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame, project: &Project, locale: &Localized) {
+        let [title, list, status, keys] =
+            Layout::vertical([Constraint::Length(1), Constraint::Min(3), Constraint::Length(1), Constraint::Length(2)]).areas(frame.area());
+        let marked = |hash: &str| project.decisions.remove.get(hash);
+        let (mut wasted, mut selected_bytes) = (0, 0);
+        let mut items = Vec::new();
+        for row in self.rows() {
+            let item = match &row {
+                Row::Group(hash) => {
+                    let group = self.groups.iter().find(|group| group.hash == *hash).expect("A row for a group not listed");
+                    let (size, count) = (group.size, group.copies);
+                    let marked = marked(hash).map_or(0, |marked| marked.len() as u64);
+                    wasted += size * (count - 1);
+                    selected_bytes += size * marked;
+                    let open = if self.expanded.contains_key(hash) { "-" } else { "+" };
+                    let summary = tr!(
+                        "tui-group",
+                        copies = locale.number(count), size = locale.size(size), wasted = locale.size(size * (count - 1)),
+                        marked = locale.number(marked),
+                    );
+                    format!("{:} {:}", open, summary)
+                },
+                Row::File(hash, path) => {
+                    let mark = if marked(hash).is_some_and(|marked| marked.contains(path)) { "[x]" } else { "[ ]" };
+                    format!("    {:} {:}", mark, path.display())
+                },
+            };
+            items.push(ListItem::new(item));
+        }
+        frame.render_widget(
+            Line::from(tr!(
+                "tui-title",
+                dir = project.path.display().to_string(), groups = locale.number(self.groups.len() as u64), wasted = locale.size(wasted),
+                marked = locale.size(selected_bytes), policy = self.policy.to_string(), action = action_key(self.action),
+            )).style(Style::new().add_modifier(Modifier::BOLD)),
+            title,
+        );
+        let list_widget = List::new(items).block(Block::bordered()).highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list_widget, list, &mut self.list);
+        frame.render_widget(Paragraph::new(self.status.as_str()), status);
+        frame.render_widget(
+            Paragraph::new(tr!("tui-review-keys")),
+            keys,
+        );
+    }
+}
+
+/// How messages name `action', see the `$action' selectors of the catalogs.
+fn action_key(action: Action) -> &'static str {
+    match action {
+        Action::Trash => "trash",
+        Action::Delete => "delete",
+        Action::HardLink => "link",
+        Action::Reflink => "clone",
+        Action::Symlink => "symlink",
+    }
+}
+
+/// Scan `project' unless it has results, showing the progress, then let its groups be reviewed until quitting.
+fn run(terminal: &mut DefaultTerminal, dirs: &Dirs, mut project: Project, rescan: bool, permit: Option<Permit>) -> Result<(), String> {
+    let locale = Localized::system();
+    let store = Store::open(&dirs.data).map_err(|e| e.to_string())?;
+    if rescan || store.hash_algo(&project.path).map_err(|e| e.to_string())?.is_none() {
+        let _guards = guard::claim_project(&dirs.data, &project).map_err(|e| e.to_string())?;
+        let settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+        let filter = WalkFilter::new(&project.walk_conf).map_err(|e| tr!("filter-invalid", error = e.to_string()))?;
+        let mut scanning = Scanning::start(&project, settings.relate_conf(), filter);
+        while !scanning.scan.is_finished() {
+            scanning.update();
+            terminal.draw(|frame| scanning.draw(frame, &locale)).map_err(|e| e.to_string())?;
+            if event::poll(TICK).map_err(|e| e.to_string())? {
+                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                    if key.kind == KeyEventKind::Press && matches!(key.code, KeyCode::Esc | KeyCode::Char('q')) {
+                        scanning.cancel.cancel();
+                    }
+                }
+            }
+        }
+        let (walk, mut related) = scanning.scan.join().map_err(|_| tr!("tui-scan-panicked"))?;
+        if let Some(reference) = &project.reference {
+            related.retain_reference_duplicates(reference);
+        }
+        related.remove_denied(&Denylist::load(&dirs.config).map_err(|e| e.to_string())?);
+        project.walk = Some(walk);
+        project.related = Some(related);
+        project.usage = None;
+        project.save(&dirs.data).map_err(|e| e.to_string())?;
+        // The review reads the groups back from the store as it needs them.
+        project.walk = None;
+        project.related = None;
+    }
+    let denylist = Denylist::load(&dirs.config).map_err(|e| e.to_string())?;
+    let mut review = Review::new(&project, store, &denylist).map_err(|e| e.to_string())?;
+    loop {
+        terminal.draw(|frame| review.draw(frame, &project, &locale)).map_err(|e| e.to_string())?;
+        if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+            if key.kind == KeyEventKind::Press && review.key(key.code, &mut project, dirs, permit.as_ref()) {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let permit = Permit::unless_auditing(cli.audit);
+    let prepared = Dirs::locate(cli.config).map_err(|e| e.to_string()).and_then(|dirs| {
+        let settings = Settings::load(&dirs.config).map_err(|e| e.to_string())?;
+        i18n::set_language(settings.language.as_deref());
+        let dir = cli.dir.canonicalize().map(|dir| platform::plain_path(&dir)).map_err(|e| format!("'{:}': {:}", cli.dir.display(), e))?;
+        let project = Project::open_unscanned(&dirs.data, dir, &settings.filters).map_err(|e| e.to_string())?;
+        let lock = project.lock(&dirs.data).map_err(|e| e.to_string())?;
+        Ok((dirs, project, lock))
+    });
+    let result = prepared.and_then(|(dirs, project, _lock)| {
+        let mut terminal = ratatui::init();
+        let result = run(&mut terminal, &dirs, project, cli.rescan, permit);
+        ratatui::restore();
+        result
+    });
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{:}", tr!("cli-error", error = e));
+            ExitCode::FAILURE
+        },
+    }
+}
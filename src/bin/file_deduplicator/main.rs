@@ -0,0 +1,2899 @@
+mod style;
+
+use rfd::{FileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use clap::Parser;
+use std::{
+    env, fmt, fs,
+    io::BufWriter,
+    collections::{BTreeSet, HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    sync::mpsc as std_mpsc,
+    thread, time::{Duration, SystemTime},
+};
+use file_deduplicator::{
+    analysis::Analysis,
+    actions::{self, Action, CrossDevice, Crossing, DedupOptions, Outcome, Permit, Plan, PlanDiff},
+    archive, audio,
+    cache::HashCache,
+    config::Dirs,
+    denylist::Denylist,
+    journal,
+    format::Localized,
+    help, i18n, tr,
+    guard::{self, ScanGuard},
+    images::{ImageConf, SimilarImages},
+    integration::FileManager,
+    lock::{self, Lock},
+    folders::{self, FolderGroup, SimilarPair},
+    naming::{self, Collision},
+    platform,
+    policy::{KeepPolicy, Reclaim, Resolver},
+    preview::{self, Content, Preview},
+    project::{self, GroupStatus, Project, Summary},
+    report::{self, Report},
+    rules::{self, Rule, Target, Verb},
+    settings::{Accent, Profile, Settings, Theme},
+    similar::SimilarFiles,
+    telemetry::{ScanConf, ScanLog, ScanOutcome},
+    relate::{self, CancellationToken, FileInfo, HashAlgo, Progress, RelateConf, RelatedFiles, Stage, SymlinkPolicy, WalkConf, WalkFilter, WalkInfo},
+    usage::{Meter, ScanUsage, Throughput},
+    watch::{self, Update, Watch},
+};
+use iced::{
+    Task, Element, Subscription, window,
+    futures::{SinkExt, Stream, StreamExt, channel::mpsc},
+    Font, Length,
+    widget::{button, checkbox, column, container, image, pick_list, progress_bar, row, scrollable, slider, text, text_input, tooltip, Column},
+};
+use iced_aw::{
+    menu::{self, Item, Menu},
+    style::{menu_bar::primary, Status},
+    menu_bar, menu_items,
+    quad, widgets::InnerBounds,
+};
+
+fn get_target_dir_from_user() -> Option<PathBuf> {
+    FileDialog::new().pick_folder()
+}
+
+/// Ask for a reference folder, then for the folder to clean up against it.
+fn get_reference_dirs_from_user() -> Option<(PathBuf, PathBuf)> {
+    let reference = FileDialog::new().set_title(tr!("choose-reference")).pick_folder()?;
+    let path = FileDialog::new().set_title(tr!("choose-folder-to-clean")).pick_folder()?;
+    Some((path, reference))
+}
+
+/// Ask where to save an exported report, in the format named `format' with file extension `extension'.
+fn get_export_file_from_user(format: &str, extension: &str) -> Option<PathBuf> {
+    FileDialog::new()
+        .set_title(tr!("choose-export-file"))
+        .add_filter(format, &[extension])
+        .set_file_name(format!("duplicates.{:}", extension))
+        .save_file()
+}
+
+/// Ask under `title' where to save what was done to files, or would be, as CSV named `file_name' unless changed.
+fn get_outcomes_file_from_user(title: String, file_name: &str) -> Option<PathBuf> {
+    FileDialog::new()
+        .set_title(title)
+        .add_filter("CSV", &["csv"])
+        .set_file_name(file_name)
+        .save_file()
+}
+
+/// Startup options, so a terminal or file manager can jump straight into a folder.
+#[derive(Parser)]
+#[command(name = "file_deduplicator", version, about = tr!("app-about"))]
+struct Args {
+    #[arg(help = tr!("app-dir-help"))]
+    dir: Option<PathBuf>,
+    #[arg(long, requires = "dir", help = tr!("app-autostart-help"))]
+    autostart: bool,
+    #[arg(long, value_name = "DIR", conflicts_with = "dir", help = tr!("app-scan-help"))]
+    scan: Option<PathBuf>,
+    #[arg(long, help = tr!("app-audit-help"))]
+    audit: bool,
+    #[arg(long, value_name = "DIR", help = help::text(help::CONFIG))]
+    config: Option<PathBuf>,
+}
+
+impl Args {
+    /// The folder to open at startup, and whether to scan it right away.
+    fn target(self) -> Option<(PathBuf, bool)> {
+        match self.scan {
+            Some(dir) => Some((dir, true)),
+            None => self.dir.map(|dir| (dir, self.autostart)),
+        }
+    }
+}
+
+fn install_integration() {
+    let fm = FileManager::native();
+    let result = env::current_exe().and_then(|exe| fm.install(&exe));
+    let (level, description) = match result {
+        Ok(location) => (MessageLevel::Info, tr!("integration-installed", file_manager = format!("{:?}", fm), location = location.display().to_string())),
+        Err(e) => (MessageLevel::Error, tr!("integration-failed", file_manager = format!("{:?}", fm), error = e.to_string())),
+    };
+    MessageDialog::new()
+        .set_level(level)
+        .set_title(tr!("integration-title"))
+        .set_description(description)
+        .show();
+}
+
+#[derive(Clone)]
+struct Config {
+    dirs : Dirs,
+    settings : Settings,
+    locale : Localized,
+    /// Missing in audit mode, which keeps every action that changes files out of reach.
+    permit : Option<Permit>,
+    /// The name of the profile new work starts from, see `start_work'.
+    profile : Option<String>,
+}
+
+enum Problem {
+    NoFolder,
+    Missing(PathBuf),
+    Project(project::Error),
+    Locked(lock::Error),
+    Overlapping { path: PathBuf, reference: PathBuf },
+}
+
+struct Init {
+    config : Config,
+    problem : Result<(),Problem>,
+    projects : Vec<Summary>,
+}
+
+struct Work {
+    config : Config,
+    project : Project,
+    /// Held for as long as we may save `project'.
+    _lock : Lock,
+    /// Present while a scan is running in the background.
+    scan : Option<Scan>,
+    problem : Option<String>,
+    /// Only saved to the project once a scan starts.
+    filters : FilterForm,
+    /// The folders to deduplicate along with the project's, saved to it once a scan starts.
+    extra_roots : Vec<PathBuf>,
+    /// Hash files as they are found, see `RelatedFiles::walk_and_relate'.
+    pipeline : bool,
+    /// The name to save the filters and how to deduplicate under, see `Profile'.
+    profile_name : String,
+    /// The errors panel is open.
+    show_errors : bool,
+    /// Present while the results are kept up to date as files change.
+    watching : Option<Watching>,
+    /// Pictures that look alike in the results, once looked for.
+    images : Option<SimilarImages>,
+    finding_images : bool,
+    /// Recordings holding the same audio in the results, once looked for.
+    audio : Option<SimilarFiles>,
+    finding_audio : bool,
+    /// Archives holding the same files in the results, once looked for.
+    archives : Option<SimilarFiles>,
+    finding_archives : bool,
+    /// Deduplicating the project that was cut short, to finish or take back.
+    interrupted : Option<journal::Run>,
+}
+
+/// Stops watching for changes once dropped.
+struct Watching(CancellationToken);
+
+impl Drop for Watching {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// What we know about a running scan, built up from its progress messages.
+#[derive(Default)]
+struct Scan {
+    stage : Option<Stage>,
+    total_files : usize,
+    total_bytes : u64,
+    files : usize,
+    /// Of the files done, whether hashed or not.
+    bytes : u64,
+    /// How far along each large file being hashed is.
+    reading : HashMap<PathBuf, u64>,
+    current : Option<PathBuf>,
+    /// Shared with the scanning thread, to stop it early.
+    cancel : CancellationToken,
+    /// Stopped to be resumed later, rather than cancelled.
+    paused : bool,
+    /// How fast files have been hashed lately.
+    throughput : Throughput,
+}
+
+impl Scan {
+    fn update(&mut self, progress: Progress) {
+        match progress {
+            Progress::StageChanged(stage) => self.stage = Some(stage),
+            Progress::FileStarted(path) => self.current = Some(path),
+            Progress::FileReading { path, bytes } => {
+                self.reading.insert(path, bytes);
+                self.throughput.record(self.done_bytes());
+            },
+            Progress::FileHashed { path, bytes } | Progress::FileFailed { path, bytes } => {
+                self.reading.remove(&path);
+                self.files += 1;
+                self.bytes += bytes;
+                self.throughput.record(self.done_bytes());
+            },
+            Progress::Resumed { files, bytes } => {
+                self.files += files;
+                self.bytes += bytes;
+                // Carried over rather than hashed, so not part of the pace.
+                self.throughput = Throughput::new();
+            },
+            Progress::FileFound { bytes } => {
+                self.total_files += 1;
+                self.total_bytes += bytes;
+            },
+            Progress::Done => self.current = None,
+        }
+    }
+
+    /// Bytes hashed so far, including the part of the large files being hashed that is done.
+    fn done_bytes(&self) -> u64 {
+        self.bytes + self.reading.values().sum::<u64>()
+    }
+
+    fn fraction(&self) -> f32 {
+        if self.total_bytes == 0 {
+            0.0
+        } else {
+            self.done_bytes() as f32 / self.total_bytes as f32
+        }
+    }
+}
+
+/// Hands a value that can't be cloned through a `Message', which must be `Clone'.
+/// Only the first receiver to `take' it gets the value.
+struct Handoff<T>(Arc<Mutex<Option<T>>>);
+
+impl<T> Clone for Handoff<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+impl<T> Handoff<T> {
+    fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(Some(value))))
+    }
+
+    fn take(&self) -> Option<T> {
+        self.0.lock().ok()?.take()
+    }
+}
+
+impl<T> fmt::Debug for Handoff<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handoff")
+    }
+}
+
+/// Walk and hash `roots', and `reference' if there is one, on a background thread, reporting back through messages.
+/// Contents on `denylist' are left out of the results.  `guards' are released once the scan is over.
+/// Everything a scan needs, handed to the thread running it.
+struct ScanJob {
+    /// The directories being deduplicated, see `Project::roots'.
+    roots: Vec<PathBuf>,
+    reference: Option<PathBuf>,
+    filter: WalkFilter,
+    conf: RelateConf,
+    denylist: Denylist,
+    /// Results of an earlier scan to carry on from, see `RelatedFiles::resume'.
+    previous: Option<RelatedFiles>,
+    log: Option<ScanLog>,
+    /// Where the hash cache is, see `HashCache'.
+    data_dir: PathBuf,
+    /// Hash files as they are found, see `RelatedFiles::walk_and_relate'.
+    pipelined: bool,
+}
+
+fn scan(job: ScanJob, guards: Vec<ScanGuard>) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let _guards = guards;
+        let ScanJob { roots, reference, filter, conf, denylist, previous, log, data_dir, pipelined } = job;
+        let meter = Meter::start();
+        // The cache only saves time, a scan can do without it.
+        let mut cache = HashCache::load(&data_dir).ok();
+        let (progress_tx, progress_rx) = std_mpsc::channel();
+        // Passes progress on to the window until the hashing is done, returning the bytes read.
+        let forward = |progress_rx: std_mpsc::Receiver<Progress>| {
+            let mut read = 0;
+            for progress in progress_rx {
+                if let Progress::FileHashed { bytes, .. } = progress {
+                    read += bytes;
+                }
+                let _ = tx.unbounded_send(Message::ScanProgress(progress));
+            }
+            read
+        };
+        let (walk, mut related, read) = if pipelined {
+            let scanned = roots.iter().cloned().chain(reference.clone()).collect::<Vec<PathBuf>>();
+            // Results made with another algorithm can't be carried on from, as in `RelatedFiles::resume'.
+            let previous = previous.filter(|previous| previous.algo == conf.hash_algo);
+            let hashes = previous.as_ref().map(RelatedFiles::by_file).unwrap_or_default();
+            let known = |fi: &FileInfo| match (&previous, &cache) {
+                (Some(_), _) => hashes.get(fi).map(|hash| hash.to_string()),
+                (None, Some(cache)) => cache.get(fi, conf.hash_algo).map(str::to_owned),
+                (None, None) => None,
+            };
+            let ((walk, related), read) = thread::scope(|s| {
+                let th = s.spawn(|| RelatedFiles::walk_and_relate(&scanned, &filter, &conf, known, progress_tx));
+                let read = forward(progress_rx);
+                (th.join().expect("Hashing thread panicked"), read)
+            });
+            if let Some(log) = &log {
+                let _ = log.walked(walk.files.len(), walk.total_size);
+            }
+            (walk, related, read)
+        } else {
+            let _ = tx.unbounded_send(Message::ScanProgress(Progress::StageChanged(Stage::Walking)));
+            let cancel = conf.cancel.clone().unwrap_or_default();
+            // Copies in the reference folder keep a size from being unique too.
+            let scanned = roots.iter().cloned().chain(reference.clone()).collect::<Vec<PathBuf>>();
+            let filter = filter.counting_sizes(&scanned, || cancel.is_cancelled());
+            let mut walk = WalkInfo::walk_roots(&roots, &filter, &cancel);
+            if let Some(reference) = &reference {
+                walk = walk.merge(WalkInfo::walk_cancellable(reference.clone(), &filter, &cancel));
+            }
+            if let Some(log) = &log {
+                let _ = log.walked(walk.files.len(), walk.total_size);
+            }
+            let _ = tx.unbounded_send(Message::Walked { files: walk.files.len(), bytes: walk.total_size });
+            let previous = previous.or_else(|| cache.as_ref().map(|cache| cache.known(&walk, conf.hash_algo)));
+            let (related, read) = thread::scope(|s| {
+                let th = s.spawn(|| match previous {
+                    Some(previous) => previous.resume(&walk, &conf, progress_tx),
+                    None => RelatedFiles::relate(&walk, &conf, progress_tx),
+                });
+                let read = forward(progress_rx);
+                (th.join().expect("Hashing thread panicked"), read)
+            });
+            (walk, related, read)
+        };
+        let usage = meter.finish(read);
+        if let Some(cache) = &mut cache {
+            let scanned = roots.iter().chain(&reference).map(PathBuf::as_path).collect::<Vec<&Path>>();
+            cache.update(&scanned, &walk, &related);
+            let _ = cache.save(&data_dir);
+        }
+        if let Some(reference) = &reference {
+            related.retain_reference_duplicates(reference);
+        }
+        related.remove_denied(&denylist);
+        let _ = tx.unbounded_send(Message::ScanFinished(Handoff::new((walk, related, usage, log))));
+    });
+    Task::run(rx, |message| message)
+}
+
+/// Watch `roots' for changes on a background thread until `stop' is cancelled, sending each batch of changes back,
+/// hashed with `algo', to be applied to the results.
+fn watch(roots: Vec<PathBuf>, filter: WalkFilter, algo: HashAlgo, stop: CancellationToken) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let watch = match Watch::start(&roots.iter().map(PathBuf::as_path).collect::<Vec<&Path>>()) {
+            Ok(watch) => watch,
+            Err(e) => {
+                let _ = tx.unbounded_send(Message::WatchFailed(e.to_string()));
+                return;
+            },
+        };
+        while !stop.is_cancelled() {
+            let changed = watch.changed(Duration::from_secs(1));
+            if changed.is_empty() || stop.is_cancelled() {
+                continue;
+            }
+            if tx.unbounded_send(Message::Watched(Handoff::new(watch.examine(&changed, &filter, algo)))).is_err() {
+                break;
+            }
+        }
+    });
+    Task::run(rx, |message| message)
+}
+
+/// Look for pictures that look alike among the files of `related' on a background thread.
+fn find_images(related: RelatedFiles) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let similar = SimilarImages::find(&related, &ImageConf::default());
+        let _ = tx.unbounded_send(Message::ImagesFound(Handoff::new(similar)));
+    });
+    Task::run(rx, |message| message)
+}
+
+/// Look into `fi' on a background thread, as large pictures take a moment to shrink.
+fn load_preview(fi: FileInfo) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let _ = tx.unbounded_send(Message::Previewed(Handoff::new(preview::preview(&fi))));
+    });
+    Task::run(rx, |message| message)
+}
+
+/// Look for recordings holding the same audio among the files of `related' on a background thread.
+fn find_audio(related: RelatedFiles) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let comparators = audio::comparators(related.algo);
+        let similar = SimilarFiles::find(&related, &comparators, |path| comparators.registered(path), relate::available_threads());
+        let _ = tx.unbounded_send(Message::AudioFound(Handoff::new(similar)));
+    });
+    Task::run(rx, |message| message)
+}
+
+/// Look for archives holding the same files among the files of `related' on a background thread.
+fn find_archives(related: RelatedFiles) -> Task<Message> {
+    let (tx, rx) = mpsc::unbounded();
+    thread::spawn(move || {
+        let comparators = archive::comparators(related.algo);
+        let similar = SimilarFiles::find(&related, &comparators, |path| comparators.registered(path), relate::available_threads());
+        let _ = tx.unbounded_send(Message::ArchivesFound(Handoff::new(similar)));
+    });
+    Task::run(rx, |message| message)
+}
+
+impl Init {
+    fn new(config: Config, problem: Result<(),Problem>) -> Self {
+        // A broken project file shouldn't stop the user from starting or resuming other work.
+        let projects = match project::list(&config.dirs.data) {
+            Ok((projects, _)) => projects,
+            Err(_) => Vec::new(),
+        };
+        Init { config, problem, projects }
+    }
+
+    /// A placeholder, used while moving work between states.
+    fn empty() -> Self {
+        let config = Config { dirs: Dirs::in_one(PathBuf::new()), settings: Settings::default(), locale: Localized::default(), permit: None, profile: None };
+        Init { config, problem: Ok(()), projects: Vec::new() }
+    }
+}
+
+/// Begin work on `path', resuming the saved project for it if there is one.  The chosen profile, if any, replaces
+/// its filters and how it was deduplicated.
+fn start_work(config: &Config, path: PathBuf) -> Result<State, Problem> {
+    if !path.exists() {
+        return Err(Problem::Missing(path));
+    }
+    let lock = Project::new(path.clone()).lock(&config.dirs.data).map_err(Problem::Locked)?;
+    let mut project = Project::open_with(&config.dirs.data, path, &config.settings.filters).map_err(Problem::Project)?;
+    if let Some(profile) = config.profile.as_deref().and_then(|name| config.settings.profile(name)) {
+        project.apply_profile(profile);
+    }
+    Ok(State::Work(Box::new(Work::new(config, project, lock))))
+}
+
+/// Begin comparing `path' against `reference'.  A project for `path' that had another reference starts over,
+/// as its results don't apply.
+fn start_reference_work(config: &Config, path: PathBuf, reference: PathBuf) -> Result<State, Problem> {
+    if guard::overlaps(&path, &reference) {
+        return Err(Problem::Overlapping { path, reference });
+    }
+    if !reference.exists() {
+        return Err(Problem::Missing(reference));
+    }
+    let state = start_work(config, path)?;
+    let State::Work(mut work) = state else {
+        return Ok(state);
+    };
+    if let Some(root) = work.project.roots().into_iter().find(|root| guard::overlaps(root, &reference)) {
+        return Err(Problem::Overlapping { path: root, reference });
+    }
+    if work.project.set_reference(Some(reference)) {
+        work.project.save(&config.dirs.data).map_err(Problem::Project)?;
+    }
+    Ok(State::Work(work))
+}
+
+/// Resume the project saved for `path'.
+fn resume_work(config: &Config, path: &Path) -> Result<State, Problem> {
+    let project = Project::load(&config.dirs.data, path).map_err(Problem::Project)?;
+    let project = project.ok_or_else(|| Problem::Missing(path.to_path_buf()))?;
+    let lock = project.lock(&config.dirs.data).map_err(Problem::Locked)?;
+    Ok(State::Work(Box::new(Work::new(config, project, lock))))
+}
+
+const SYMLINK_POLICIES: [SymlinkPolicy; 3] = [SymlinkPolicy::Skip, SymlinkPolicy::Follow, SymlinkPolicy::Report];
+const HASH_ALGOS: [HashAlgo; 3] = [HashAlgo::Sha256, HashAlgo::Blake3, HashAlgo::Xxh128];
+const CROSS_DEVICES: [CrossDevice; 3] = [CrossDevice::Refuse, CrossDevice::Symlink, CrossDevice::Keep];
+const THEMES: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+/// The unit the read limit is typed in.
+const MEGABYTE: f64 = 1_000_000.0;
+
+/// A language to pick in the settings, by its catalog.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    /// The user's, as the system reports it.
+    System,
+    Named(&'static str),
+}
+
+impl Language {
+    fn all() -> Vec<Language> {
+        [Language::System].into_iter().chain(i18n::languages().into_iter().map(Language::Named)).collect()
+    }
+}
+
+impl fmt::Display for Language {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Language::System => write!(f, "{:}", tr!("settings-language-system")),
+            Language::Named(language) => write!(f, "{:}", language),
+        }
+    }
+}
+
+/// A profile to start from, picked on the start screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ProfileChoice {
+    /// The project's own filters, or the default ones for a new project.
+    None,
+    Named(String),
+}
+
+impl ProfileChoice {
+    fn all(settings: &Settings) -> Vec<ProfileChoice> {
+        [ProfileChoice::None].into_iter().chain(settings.profiles.iter().map(|profile| ProfileChoice::Named(profile.name.clone()))).collect()
+    }
+}
+
+impl fmt::Display for ProfileChoice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfileChoice::None => write!(f, "{:}", tr!("init-no-profile")),
+            ProfileChoice::Named(name) => write!(f, "{:}", name),
+        }
+    }
+}
+
+/// Split the patterns typed in the filter panel at commas, except those inside `{a,b}' alternatives.
+fn split_patterns(s: &str) -> Vec<String> {
+    let mut pats = vec![String::new()];
+    let mut depth = 0usize;
+    for c in s.chars() {
+        match c {
+            ',' if depth == 0 => pats.push(String::new()),
+            '{' | '}' => {
+                depth = if c == '{' { depth + 1 } else { depth.saturating_sub(1) };
+                pats.last_mut().expect("Never empty").push(c);
+            },
+            c => pats.last_mut().expect("Never empty").push(c),
+        }
+    }
+    pats.into_iter().map(|pat| pat.trim().to_owned()).filter(|pat| !pat.is_empty()).collect()
+}
+
+/// The filter panel, as typed.
+struct FilterForm {
+    include : String,
+    exclude : String,
+    min_size : String,
+    max_size : String,
+    skip_unique_sizes : bool,
+    archives : bool,
+    symlinks : SymlinkPolicy,
+}
+
+impl FilterForm {
+    fn new(walk_conf: &WalkConf) -> Self {
+        FilterForm {
+            include: walk_conf.include.join(", "),
+            exclude: walk_conf.exclude.join(", "),
+            min_size: walk_conf.min_size.map(|n| n.to_string()).unwrap_or_default(),
+            max_size: walk_conf.max_size.map(|n| n.to_string()).unwrap_or_default(),
+            skip_unique_sizes: walk_conf.skip_unique_sizes,
+            archives: walk_conf.archives,
+            symlinks: walk_conf.symlinks,
+        }
+    }
+
+    /// The filters as a `WalkConf' and its compiled filter, or what is wrong with them.
+    fn walk_conf(&self) -> Result<(WalkConf, WalkFilter), String> {
+        let size = |s: &str, invalid: String| match s.trim() {
+            "" => Ok(None),
+            s => s.parse().map(Some).map_err(|_| invalid),
+        };
+        let walk_conf = WalkConf {
+            include: split_patterns(&self.include),
+            exclude: split_patterns(&self.exclude),
+            min_size: size(&self.min_size, tr!("filter-min-size-invalid"))?,
+            max_size: size(&self.max_size, tr!("filter-max-size-invalid"))?,
+            symlinks: self.symlinks,
+            skip_unique_sizes: self.skip_unique_sizes,
+            archives: self.archives,
+        };
+        let filter = WalkFilter::new(&walk_conf).map_err(|e| tr!("filter-invalid", error = e.to_string()))?;
+        Ok((walk_conf, filter))
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        column![
+            row![
+                explained(text(tr!("filter-include")).width(150), help::INCLUDE),
+                text_input(&tr!("filter-include-example"), &self.include).on_input(Message::IncludeChanged),
+            ].spacing(10),
+            row![
+                explained(text(tr!("filter-exclude")).width(150), help::EXCLUDE),
+                text_input(&tr!("filter-exclude-example"), &self.exclude).on_input(Message::ExcludeChanged),
+            ].spacing(10),
+            row![
+                text(tr!("filter-size")).width(150),
+                explained(text_input(&tr!("filter-min-size"), &self.min_size).on_input(Message::MinSizeChanged), help::MIN_SIZE),
+                explained(text_input(&tr!("filter-max-size"), &self.max_size).on_input(Message::MaxSizeChanged), help::MAX_SIZE),
+                explained(checkbox(tr!("filter-skip-unique-sizes"), self.skip_unique_sizes).on_toggle(Message::ToggleSkipUniqueSizes), help::SKIP_UNIQUE_SIZES),
+            ].spacing(10),
+            row![
+                explained(text(tr!("filter-symlinks")).width(150), help::SYMLINKS),
+                explained(pick_list(SYMLINK_POLICIES, Some(self.symlinks), Message::ChooseSymlinks), help::symlinks(self.symlinks)),
+                explained(checkbox(tr!("filter-archives"), self.archives).on_toggle(Message::ToggleArchives), help::ARCHIVES),
+            ].spacing(10),
+        ].spacing(5)
+    }
+
+    /// Take in what was typed.  Returns whether `message' was meant for the form.
+    fn update(&mut self, message: &Message) -> bool {
+        match message {
+            Message::IncludeChanged(include) => self.include = include.clone(),
+            Message::ExcludeChanged(exclude) => self.exclude = exclude.clone(),
+            Message::MinSizeChanged(min_size) => self.min_size = min_size.clone(),
+            Message::MaxSizeChanged(max_size) => self.max_size = max_size.clone(),
+            Message::ToggleSkipUniqueSizes(skip) => self.skip_unique_sizes = *skip,
+            Message::ToggleArchives(archives) => self.archives = *archives,
+            Message::ChooseSymlinks(symlinks) => self.symlinks = *symlinks,
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl Work {
+    fn new(config: &Config, project: Project, lock: Lock) -> Self {
+        let filters = FilterForm::new(&project.walk_conf);
+        let extra_roots = project.extra_roots.clone();
+        let (interrupted, problem) = match journal::interrupted(&config.dirs.data) {
+            Ok(run) => (run.filter(|run| run.project == project.path), None),
+            Err(e) => (None, Some(tr!("interrupted-read-failed", error = e.to_string()))),
+        };
+        Work {
+            config: config.clone(), project, _lock: lock, scan: None, problem, filters, extra_roots, pipeline: false,
+            profile_name: config.profile.clone().unwrap_or_default(), show_errors: false, watching: None, images: None, finding_images: false, audio: None, finding_audio: false, archives: None, finding_archives: false,
+            interrupted,
+        }
+    }
+
+    /// The folders the next scan deduplicates along with the project's.
+    fn roots_panel(&self) -> Column<'_, Message> {
+        let mut col = Column::new().spacing(5);
+        if !self.extra_roots.is_empty() {
+            col = col.push(text(tr!("roots-heading")));
+        }
+        for (i, root) in self.extra_roots.iter().enumerate() {
+            col = col.push(row![text(root.to_str().unwrap_or("<directory>")), button(text(tr!("roots-remove"))).on_press(Message::RemoveRoot(i))].spacing(10));
+        }
+        col.push(explained(button(text(tr!("roots-add"))).on_press(Message::AddRoot), help::ROOTS))
+    }
+
+    fn filter_panel(&self) -> Column<'_, Message> {
+        let save_profile = button(text(tr!("profile-save"))).on_press_maybe((!self.profile_name.trim().is_empty()).then_some(Message::SaveProfile));
+        self.filters.view()
+            .push(explained(checkbox(tr!("work-pipeline"), self.pipeline).on_toggle(Message::TogglePipeline), help::PIPELINE))
+            .push(row![
+                text_input(&tr!("profile-name-example"), &self.profile_name).on_input(Message::ProfileNameChanged).width(300),
+                explained(save_profile, help::PROFILE),
+            ].spacing(10))
+    }
+
+    /// Keep the filters as typed and how the project was last deduplicated as a profile, for other projects to
+    /// start from.
+    fn save_profile(&mut self) -> Result<(), String> {
+        let (filters, _) = self.filters.walk_conf()?;
+        let name = self.profile_name.trim().to_owned();
+        let profile = Profile { name, filters, keep: self.project.preferences.keep.clone(), action: self.project.preferences.action };
+        // Reread, so changes made elsewhere since the app started aren't lost.
+        let mut settings = Settings::load(&self.config.dirs.config).map_err(|e| tr!("settings-read-failed", error = e.to_string()))?;
+        settings.save_profile(profile);
+        settings.save(&self.config.dirs.config).map_err(|e| tr!("settings-save-failed", error = e.to_string()))?;
+        self.config.settings = settings;
+        Ok(())
+    }
+
+    /// What couldn't be walked or hashed in the last scan, by kind.  Errors aren't saved, so this is empty for a
+    /// project scanned earlier.
+    fn errors_panel(&self) -> Column<'_, Message> {
+        let walk_errors = self.project.walk.iter().flat_map(|walk| walk.errors.iter());
+        let related_errors = self.project.related.iter().flat_map(|related| related.errors.iter());
+        let mut rows = Column::new().spacing(5);
+        for (kind, errors) in report::errors_by_kind(walk_errors.chain(related_errors)) {
+            rows = rows.push(text(format!("{:} ({:})", kind, self.config.locale.number(errors.len() as u64))).size(20));
+            for error in errors {
+                rows = rows.push(text(error.to_string()));
+            }
+        }
+        column![scrollable(rows).height(200)]
+    }
+
+    /// `groups' of files that hold the same though they differ, under `heading', mentioning the `errors' files that
+    /// couldn't be read.
+    fn similar_panel<'a>(&self, heading: String, groups: &'a [Vec<FileInfo>], errors: usize) -> Column<'a, Message> {
+        let locale = &self.config.locale;
+        let mut col = column![text(heading)].spacing(5);
+        if errors > 0 {
+            col = col.push(text(tr!("similar-unreadable", files = locale.number(errors as u64))));
+        }
+        let mut rows = Column::new().spacing(10);
+        for group in groups {
+            let files = group.iter().map(|fi| text(format!("{:}  {:}", locale.size(fi.size), fi.name.display())).into());
+            rows = rows.push(Column::with_children(files));
+        }
+        col.push(scrollable(rows).height(200))
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        let mut col = column![
+            text(tr!("data-folder", folder = self.config.dirs.data.to_str().unwrap_or("<directory>"))).size(50),
+            text(tr!("work-folder", folder = self.project.path.to_str().unwrap_or("<directory>"))).size(50),
+        ];
+        if let Some(reference) = &self.project.reference {
+            col = col.push(text(tr!("work-reference", folder = reference.to_str().unwrap_or("<directory>"))).size(30));
+        }
+        if self.config.permit.is_none() {
+            col = col.push(text(tr!("work-audit-mode")).size(30));
+        }
+        if let Some(problem) = &self.problem {
+            col = col.push(text(problem.clone()).size(30).style(style::problem));
+        }
+        if let Some(run) = &self.interrupted {
+            col = col.push(row![
+                text(tr!("interrupted-run", files = self.config.locale.number(run.steps.len() as u64))),
+                button(text(tr!("interrupted-resume"))).on_press_maybe(self.config.permit.map(|_| Message::ResumeRun)),
+                button(text(tr!("interrupted-roll-back"))).on_press_maybe(self.config.permit.map(|_| Message::RollBackRun)),
+            ].spacing(10));
+        }
+        match (&self.scan, &self.project.related) {
+            (Some(scan), _) => {
+                let locale = &self.config.locale;
+                col = col.push(text(match scan.stage {
+                    // Files are hashed as they are found, so there are no totals yet.
+                    Some(Stage::Walking) if scan.total_files > 0 => tr!(
+                        "scan-walking-hashed",
+                        files = locale.number(scan.files as u64), total_files = locale.number(scan.total_files as u64),
+                        bytes = locale.size(scan.done_bytes()), total_bytes = locale.size(scan.total_bytes),
+                    ),
+                    Some(Stage::Walking) | None => tr!("scan-walking"),
+                    Some(Stage::Hashing) => {
+                        tr!(
+                            "scan-hashing",
+                            files = locale.number(scan.files as u64), total_files = locale.number(scan.total_files as u64),
+                            bytes = locale.size(scan.done_bytes()), total_bytes = locale.size(scan.total_bytes),
+                        )
+                    },
+                }).size(30));
+                col = col.push(progress_bar(0.0..=1.0, scan.fraction()));
+                let left = scan.throughput.time_left(scan.total_bytes.saturating_sub(scan.done_bytes()));
+                // Until hashing starts, more files may yet be found, so there's no telling how long is left.
+                match (scan.throughput.bytes_per_sec(), left.filter(|_| scan.stage == Some(Stage::Hashing))) {
+                    (Some(rate), Some(left)) => col = col.push(text(tr!("scan-time-left", rate = locale.size(rate), left = locale.duration(left)))),
+                    (Some(rate), None) => col = col.push(text(tr!("scan-rate", rate = locale.size(rate)))),
+                    (None, _) => (),
+                }
+                if let Some(current) = &scan.current {
+                    col = col.push(text(current.to_string_lossy().into_owned()));
+                }
+                col = col.push(match (scan.cancel.is_cancelled(), scan.paused) {
+                    // The files being hashed are finished first, which can take a while for large ones.
+                    (true, true) => row![button(text(tr!("scan-pause"))), button(text(tr!("scan-cancel"))), text(tr!("scan-pausing"))].spacing(10),
+                    (true, false) => row![button(text(tr!("scan-pause"))), button(text(tr!("scan-cancel"))), text(tr!("scan-stopping"))].spacing(10),
+                    (false, _) => row![
+                        button(text(tr!("scan-pause"))).on_press(Message::PauseScan),
+                        button(text(tr!("scan-cancel"))).on_press(Message::CancelScan),
+                    ].spacing(10),
+                });
+            },
+            (None, Some(related)) => {
+                let groups = related.files.values().filter(|group| group.len() > 1).count();
+                col = col.push(text(tr!("work-found-groups", groups = self.config.locale.number(groups as u64))).size(30));
+                if let Some(usage) = &self.project.usage {
+                    col = col.push(text(format!("{:}.", self.config.locale.usage(usage))));
+                }
+                if related.partial {
+                    col = col.push(text(tr!("work-partial")));
+                }
+                if let Some(walk) = self.project.walk.as_ref().filter(|walk| !walk.hard_links.is_empty()) {
+                    col = col.push(text(tr!("work-hard-links", files = self.config.locale.number(walk.hard_links.len() as u64))));
+                }
+                let errors = self.project.walk.as_ref().map_or(0, |walk| walk.errors.len()) + related.errors.len();
+                if errors > 0 {
+                    col = col.push(row![
+                        text(tr!("work-unreadable", files = self.config.locale.number(errors as u64))),
+                        explained(
+                            button(text(if self.show_errors { tr!("work-hide-errors") } else { tr!("work-show-errors") })).on_press(Message::ShowErrors(!self.show_errors)),
+                            help::ERRORS,
+                        ),
+                    ].spacing(10));
+                    if self.show_errors {
+                        col = col.push(self.errors_panel());
+                    }
+                }
+                col = col.push(self.roots_panel());
+                col = col.push(self.filter_panel());
+                col = col.push(explained(checkbox(tr!("work-watch"), self.watching.is_some()).on_toggle(Message::ToggleWatch), help::WATCH));
+                let mut buttons = row![
+                    button(text(tr!("work-review"))).on_press(Message::ShowResults),
+                    button(text(tr!("work-scan-again"))).on_press(Message::StartScan { resume: false }),
+                ].spacing(10);
+                if related.partial {
+                    buttons = buttons.push(explained(button(text(tr!("work-resume-scan"))).on_press(Message::StartScan { resume: true }), help::RESUME));
+                }
+                let find_images = button(text(if self.finding_images { tr!("work-finding-images") } else { tr!("work-find-images") }));
+                buttons = buttons.push(explained(find_images.on_press_maybe((!self.finding_images).then_some(Message::FindImages)), help::IMAGES));
+                let find_audio = button(text(if self.finding_audio { tr!("work-finding-audio") } else { tr!("work-find-audio") }));
+                buttons = buttons.push(explained(find_audio.on_press_maybe((!self.finding_audio).then_some(Message::FindAudio)), help::AUDIO));
+                let find_archives = button(text(if self.finding_archives { tr!("work-finding-archives") } else { tr!("work-find-archives") }));
+                buttons = buttons.push(explained(find_archives.on_press_maybe((!self.finding_archives).then_some(Message::FindArchives)), help::SAME_ARCHIVES));
+                col = col.push(buttons);
+                let locale = &self.config.locale;
+                if let Some(similar) = &self.images {
+                    let heading = tr!("work-similar-images", groups = locale.number(similar.groups.len() as u64));
+                    col = col.push(self.similar_panel(heading, &similar.groups, similar.errors.len()));
+                }
+                if let Some(similar) = &self.audio {
+                    let heading = tr!("work-same-audio", groups = locale.number(similar.groups.len() as u64));
+                    col = col.push(self.similar_panel(heading, &similar.groups, similar.errors.len()));
+                }
+                if let Some(similar) = &self.archives {
+                    let heading = tr!("work-same-archives", groups = locale.number(similar.groups.len() as u64));
+                    col = col.push(self.similar_panel(heading, &similar.groups, similar.errors.len()));
+                }
+            },
+            (None, None) => {
+                col = col.push(self.roots_panel());
+                col = col.push(self.filter_panel());
+                col = col.push(button(text(tr!("work-scan"))).on_press(Message::StartScan { resume: false }));
+            },
+        }
+        col
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        if self.filters.update(&message) {
+            return Task::none();
+        }
+        match message {
+            Message::TogglePipeline(pipeline) => self.pipeline = pipeline,
+            Message::ProfileNameChanged(name) => self.profile_name = name,
+            Message::SaveProfile => self.problem = self.save_profile().err(),
+            Message::AddRoot => {
+                let Some(root) = FileDialog::new().set_title(tr!("choose-extra-root")).pick_folder() else {
+                    return Task::none();
+                };
+                match &self.project.reference {
+                    Some(reference) if guard::overlaps(&root, reference) => self.problem = Some(tr!(
+                        "roots-overlap",
+                        folder = root.to_str().unwrap_or("<directory>"), reference = reference.to_str().unwrap_or("<directory>"),
+                    )),
+                    _ => self.extra_roots.push(root),
+                }
+            },
+            Message::RemoveRoot(i) if i < self.extra_roots.len() => {
+                self.extra_roots.remove(i);
+            },
+            Message::ShowErrors(show) => self.show_errors = show,
+            Message::ResumeRun | Message::RollBackRun => {
+                let (Some(run), Some(permit)) = (self.interrupted.take(), &self.config.permit) else {
+                    return Task::none();
+                };
+                let data = &self.config.dirs.data;
+                let locale = &self.config.locale;
+                if let Message::RollBackRun = message {
+                    self.problem = match journal::roll_back(data, &run, permit) {
+                        Ok(restored) => match restored.iter().filter(|(_, result)| result.is_err()).count() {
+                            0 => None,
+                            failed => {
+                                self.interrupted = Some(run);
+                                Some(tr!("interrupted-roll-back-failed", files = locale.number(failed as u64)))
+                            },
+                        },
+                        Err(e) => {
+                            self.interrupted = Some(run);
+                            Some(tr!("interrupted-read-failed", error = e.to_string()))
+                        },
+                    };
+                    return Task::none();
+                }
+                let (outcomes, journaled) = journal::resume(data, &run, &DedupOptions::default(), permit);
+                self.project.record(&outcomes);
+                let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).count();
+                self.problem = match (journaled, self.project.save_decisions(data, &outcomes)) {
+                    (Err(e), _) => Some(tr!("journal-write-failed", error = e.to_string())),
+                    (_, Err(e)) => Some(tr!("project-save-failed", error = e.to_string())),
+                    _ if failed > 0 => Some(tr!("interrupted-resume-failed", files = locale.number(failed as u64))),
+                    _ => None,
+                };
+            },
+            Message::ToggleWatch(false) => self.watching = None,
+            Message::ToggleWatch(true) if self.scan.is_none() => {
+                let (Some(_), Some(related)) = (&self.project.walk, &self.project.related) else {
+                    self.problem = Some(tr!("watch-needs-scan"));
+                    return Task::none();
+                };
+                // Changes are judged by the filters the results were made with, not those typed since.
+                let filter = match WalkFilter::new(&self.project.walk_conf) {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        self.problem = Some(tr!("filter-invalid", error = e.to_string()));
+                        return Task::none();
+                    },
+                };
+                let roots = self.project.scanned();
+                let stop = CancellationToken::default();
+                self.watching = Some(Watching(stop.clone()));
+                return watch(roots, filter, related.algo, stop);
+            },
+            Message::Watched(updates) => {
+                let (Some(updates), Some(_), Some(walk), Some(related)) = (updates.take(), &self.watching, &mut self.project.walk, &mut self.project.related) else {
+                    return Task::none();
+                };
+                watch::apply(updates, walk, related);
+                self.images = None;
+                self.audio = None;
+                self.archives = None;
+                if let Some(reference) = &self.project.reference {
+                    related.retain_reference_duplicates(reference);
+                }
+                match Denylist::load(&self.config.dirs.config) {
+                    Ok(denylist) => {
+                        related.remove_denied(&denylist);
+                    },
+                    Err(e) => self.problem = Some(tr!("denylist-read-failed", error = e.to_string())),
+                }
+                if let Err(e) = self.project.save(&self.config.dirs.data) {
+                    self.problem = Some(tr!("project-save-failed", error = e.to_string()));
+                }
+            },
+            Message::FindImages if !self.finding_images => {
+                if let Some(related) = &self.project.related {
+                    self.finding_images = true;
+                    let related = RelatedFiles { files: related.files.clone(), errors: Vec::new(), ..*related };
+                    return find_images(related);
+                }
+            },
+            Message::ImagesFound(similar) => {
+                self.finding_images = false;
+                self.images = similar.take();
+            },
+            Message::FindAudio if !self.finding_audio => {
+                if let Some(related) = &self.project.related {
+                    self.finding_audio = true;
+                    let related = RelatedFiles { files: related.files.clone(), errors: Vec::new(), ..*related };
+                    return find_audio(related);
+                }
+            },
+            Message::AudioFound(similar) => {
+                self.finding_audio = false;
+                self.audio = similar.take();
+            },
+            Message::FindArchives if !self.finding_archives => {
+                if let Some(related) = &self.project.related {
+                    self.finding_archives = true;
+                    let related = RelatedFiles { files: related.files.clone(), errors: Vec::new(), ..*related };
+                    return find_archives(related);
+                }
+            },
+            Message::ArchivesFound(similar) => {
+                self.finding_archives = false;
+                self.archives = similar.take();
+            },
+            Message::WatchFailed(problem) => {
+                self.watching = None;
+                self.problem = Some(problem);
+            },
+            Message::StartScan { resume } if self.scan.is_none() => {
+                self.watching = None;
+                self.images = None;
+                self.audio = None;
+                self.archives = None;
+                let (walk_conf, filter) = match self.filters.walk_conf() {
+                    Ok(conf) => conf,
+                    Err(problem) => {
+                        self.problem = Some(problem);
+                        return Task::none();
+                    },
+                };
+                let denylist = match Denylist::load(&self.config.dirs.config) {
+                    Ok(denylist) => denylist,
+                    Err(e) => {
+                        self.problem = Some(tr!("denylist-read-failed", error = e.to_string()));
+                        return Task::none();
+                    },
+                };
+                // Results made over other folders don't apply, so they are dropped before being carried on from.
+                self.project.set_extra_roots(self.extra_roots.clone());
+                match guard::claim_project(&self.config.dirs.data, &self.project) {
+                    Ok(guards) => {
+                        let state = Scan::default();
+                        let conf = RelateConf { cancel: Some(state.cancel.clone()), ..self.config.settings.relate_conf() };
+                        self.scan = Some(state);
+                        self.problem = None;
+                        self.project.walk_conf = walk_conf;
+                        let previous = if resume { self.project.related.take() } else { None };
+                        // The log is there to explain crashes, not being able to keep it is no reason to stop.
+                        let log = ScanLog::start(&self.config.dirs.data, ScanConf {
+                            dir: self.project.path.clone(),
+                            extra_roots: self.project.extra_roots.clone(),
+                            reference: self.project.reference.clone(),
+                            walk: self.project.walk_conf.clone(),
+                            strategy: conf.strategy,
+                            device_threads: conf.device_threads,
+                            hash_algo: conf.hash_algo,
+                            mmap_threshold: conf.mmap_threshold,
+                            io_limit: conf.io_limit,
+                            max_duration: None,
+                            resumed: previous.is_some(),
+                            pipelined: self.pipeline,
+                        }).ok();
+                        let job = ScanJob {
+                            roots: self.project.roots(),
+                            reference: self.project.reference.clone(),
+                            filter,
+                            conf,
+                            denylist,
+                            previous,
+                            log,
+                            data_dir: self.config.dirs.data.clone(),
+                            pipelined: self.pipeline,
+                        };
+                        return scan(job, guards);
+                    },
+                    Err(conflict) => self.problem = Some(tr!("scan-conflict", error = conflict.to_string())),
+                }
+            },
+            Message::Walked { files, bytes } => {
+                if let Some(scan) = &mut self.scan {
+                    scan.total_files = files;
+                    scan.total_bytes = bytes;
+                }
+            },
+            Message::ScanProgress(progress) => {
+                if let Some(scan) = &mut self.scan {
+                    scan.update(progress);
+                }
+            },
+            Message::CancelScan => {
+                if let Some(scan) = &self.scan {
+                    scan.cancel.cancel();
+                }
+            },
+            // Pausing stops the scan just like cancelling.  What was hashed is saved as partial results,
+            // which a resumed scan carries on from.
+            Message::PauseScan => {
+                if let Some(scan) = &mut self.scan {
+                    scan.paused = true;
+                    scan.cancel.cancel();
+                }
+            },
+            Message::ScanFinished(results) => {
+                let cancelled = self.scan.take().is_some_and(|scan| scan.cancel.is_cancelled());
+                if let Some((walk, related, usage, log)) = results.take() {
+                    let outcome = ScanOutcome {
+                        groups: related.files.values().filter(|group| group.len() > 1).count(),
+                        errors: walk.errors.len() + related.errors.len(),
+                        partial: related.partial,
+                        cancelled,
+                        usage: Some(usage),
+                    };
+                    self.project.walk = Some(walk);
+                    self.project.related = Some(related);
+                    self.project.usage = Some(usage);
+                    let saved = self.project.save(&self.config.dirs.data);
+                    if let Some(log) = log {
+                        let _ = match &saved {
+                            Ok(_) => log.finished(outcome),
+                            Err(e) => log.failed(e.to_string()),
+                        };
+                    }
+                    if let Err(e) = saved {
+                        self.problem = Some(tr!("project-save-failed", error = e.to_string()));
+                    }
+                }
+            },
+            _ => (),
+        }
+        Task::none()
+    }
+}
+
+/// Poll for other instances asking us to come to the front.
+fn focus_requests(lock_path: PathBuf) -> impl Stream<Item = Message> {
+    iced::stream::channel(1, move |mut output| async move {
+        let (tx, mut rx) = mpsc::unbounded();
+        thread::spawn(move || {
+            while !tx.is_closed() {
+                if lock::take_focus_request(&lock_path) && tx.unbounded_send(()).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(500));
+            }
+        });
+        while rx.next().await.is_some() {
+            if output.send(Message::FocusRequested).await.is_err() {
+                break;
+            }
+        }
+    })
+}
+
+/// Policies offered for automatic selection.  Regex priorities need patterns, so they aren't offered here.
+const SELECTABLE_POLICIES: [KeepPolicy; 7] = [
+    KeepPolicy::OldestCreated,
+    KeepPolicy::NewestCreated,
+    KeepPolicy::ShortestPath,
+    KeepPolicy::ShallowestDirectory,
+    KeepPolicy::Alphabetical,
+    KeepPolicy::CaptureDateName,
+    KeepPolicy::DatedFolder,
+];
+
+/// What the results of a scan are listed by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tab {
+    Groups,
+    /// The folders directly in `Results::browsing', with how much is duplicated in each.
+    Folders,
+    /// Files sharing a name but not their contents.
+    Collisions,
+    /// Folders holding the same as another, and those holding mostly the same.
+    DuplicateFolders,
+    /// What became of each file acted on last, see `Results::applied'.
+    Outcomes,
+}
+
+const RULE_VERBS: [Verb; 3] = [Verb::Mark, Verb::Keep, Verb::Prefer];
+
+/// Which kind of `Target' a rule being composed has, before its value is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TargetKind {
+    Under,
+    Matching,
+    Containing,
+}
+
+impl fmt::Display for TargetKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetKind::Under => write!(f, "under"),
+            TargetKind::Matching => write!(f, "matching"),
+            TargetKind::Containing => write!(f, "containing"),
+        }
+    }
+}
+
+impl TargetKind {
+    fn target(self, value: &str) -> Target {
+        match self {
+            TargetKind::Under => Target::Under(value.into()),
+            TargetKind::Matching => Target::Matching(value.to_owned()),
+            TargetKind::Containing => Target::Containing(value.to_owned()),
+        }
+    }
+}
+
+const TARGET_KINDS: [TargetKind; 3] = [TargetKind::Under, TargetKind::Matching, TargetKind::Containing];
+
+/// How the duplicate groups are listed, the first most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum GroupSort {
+    Wasted,
+    Size,
+    Copies,
+}
+
+const GROUP_SORTS: [GroupSort; 3] = [GroupSort::Wasted, GroupSort::Size, GroupSort::Copies];
+
+impl fmt::Display for GroupSort {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GroupSort::Wasted => write!(f, "{:}", tr!("sort-wasted")),
+            GroupSort::Size => write!(f, "{:}", tr!("sort-size")),
+            GroupSort::Copies => write!(f, "{:}", tr!("sort-copies")),
+        }
+    }
+}
+
+/// Groups listed at once, as laying out thousands of rows slows down every frame.
+const PAGE_SIZE: usize = 100;
+
+/// The least similarity the folders shown as similar can be picked to share, in percent.
+const LOWEST_SIMILARITY: u8 = 50;
+
+/// Browse the duplicate groups of a scanned project and choose which copies to remove.
+/// Choices are kept in the project's decisions, so they survive between sessions.
+struct Results {
+    work : Work,
+    /// Hashes of the duplicate groups, in the order of `sort'.
+    order : Vec<String>,
+    sort : GroupSort,
+    /// Only show the groups with a copy whose path has this in it, whatever the case.
+    query : String,
+    /// Which `PAGE_SIZE' groups of those shown are listed.
+    page : usize,
+    /// The file last picked to be previewed, once looked into.
+    preview : Option<Preview>,
+    /// The preview's picture, kept so it isn't uploaded again every frame.
+    thumbnail : Option<image::Handle>,
+    expanded : HashSet<String>,
+    policy : KeepPolicy,
+    /// Another policy and how its choices differ from those of `policy', to help pick one.
+    comparison : Option<(KeepPolicy, PlanDiff)>,
+    /// What removing the selected files would do, until the selection changes.
+    dry_run : Option<Vec<Outcome>>,
+    /// What removing the selected files did, until the selection changes.
+    applied : Option<Vec<Outcome>>,
+    /// Changes waiting for the user to confirm them, shown in place of the results until they do or cancel.
+    confirming : Option<Pending>,
+    tab : Tab,
+    /// The folder whose folders are listed, the project's own when `None'.
+    browsing : Option<PathBuf>,
+    /// Only show the groups with a copy in this folder, chosen from the folder list.
+    folder : Option<PathBuf>,
+    /// The name collisions, found when first listed.
+    collisions : Option<Vec<Collision>>,
+    /// Names of collisions whose versions are shown.
+    expanded_names : HashSet<String>,
+    /// The folders holding the same as another, found when first listed.
+    duplicate_folders : Option<Vec<FolderGroup>>,
+    /// The folders sharing at least `LOWEST_SIMILARITY' of their contents, found along with `duplicate_folders'.
+    similar_folders : Option<Vec<SimilarPair>>,
+    /// How similar the folders shown as similar are, at least.
+    similarity : u8,
+    /// The selection rule being composed, added to the project's once it has a value.
+    rule_verb : Verb,
+    rule_target : TargetKind,
+    rule_value : String,
+}
+
+impl Results {
+    fn new(mut work: Work) -> Self {
+        // Contents ignored since the scan aren't shown either.
+        match (Denylist::load(&work.config.dirs.config), &mut work.project.related) {
+            (Ok(denylist), Some(related)) => {
+                related.remove_denied(&denylist);
+            },
+            (Err(e), _) => work.problem = Some(tr!("denylist-read-failed", error = e.to_string())),
+            _ => (),
+        }
+        let order = work.project.related
+            .as_ref()
+            .map(|related| related.duplicate_groups().into_iter().map(|group| group.hash.to_owned()).collect())
+            .unwrap_or_default();
+        // Regex priorities can't be picked here, so they aren't offered back either.
+        let policy = work.project.preferences.keep.clone()
+            .filter(|keep| SELECTABLE_POLICIES.contains(keep))
+            .unwrap_or(KeepPolicy::OldestCreated);
+        Results {
+            work, order, sort: GroupSort::Wasted, query: String::new(), page: 0, preview: None, thumbnail: None, expanded: HashSet::new(), policy, comparison: None, dry_run: None, applied: None, confirming: None, tab: Tab::Groups, browsing: None,
+            folder: None, collisions: None, expanded_names: HashSet::new(),
+            duplicate_folders: None, similar_folders: None, similarity: folders::MIN_SIMILARITY, rule_verb: Verb::Mark,
+            rule_target: TargetKind::Under, rule_value: String::new(),
+        }
+    }
+
+    /// `policy' compiled for this project, protecting its reference folder.
+    fn resolver(&self, policy: KeepPolicy) -> Option<Resolver> {
+        let resolver = Resolver::new(policy).ok()?;
+        Some(match &self.work.project.reference {
+            Some(reference) => resolver.with_reference(reference.clone()),
+            None => resolver,
+        })
+    }
+
+    fn compare_with(&mut self, other: KeepPolicy) {
+        let plan = |policy: &KeepPolicy| {
+            let related = self.work.project.related.as_ref()?;
+            Some(Plan::new(related, &self.resolver(policy.clone())?, Action::Delete))
+        };
+        self.comparison = plan(&self.policy)
+            .zip(plan(&other))
+            .map(|(mine, theirs)| (other, mine.diff(&theirs)));
+    }
+
+    /// Put `order' in the order of `sort', breaking ties by hash so the pages stay put.
+    fn sort_order(&mut self) {
+        let Some(related) = &self.work.project.related else { return };
+        let measure = |hash: &String| {
+            let group = related.files.get(hash);
+            let (size, copies) = group.map_or((0, 0), |group| (group.iter().next().map_or(0, |fi| fi.size), group.len() as u64));
+            match self.sort {
+                GroupSort::Wasted => size * copies.saturating_sub(1),
+                GroupSort::Size => size,
+                GroupSort::Copies => copies,
+            }
+        };
+        self.order.sort_by(|a, b| measure(b).cmp(&measure(a)).then_with(|| a.cmp(b)));
+    }
+
+    fn selected(&self, hash: &str) -> Option<&BTreeSet<PathBuf>> {
+        self.work.project.decisions.remove.get(hash)
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        let Some(related) = &self.work.project.related else {
+            return column![text(tr!("results-not-scanned"))];
+        };
+        if let Some(pending) = &self.confirming {
+            return self.confirm_view(pending);
+        }
+        let locale = &self.work.config.locale;
+        let (mut wasted, mut wasted_allocated, mut selected_bytes) = (0, 0, 0);
+        let mut reclaimable = Reclaim::default();
+        let resolver = self.resolver(self.policy.clone());
+        match self.tab {
+            Tab::Folders if self.folder.is_none() => return self.folders_view(related),
+            Tab::Collisions => return self.collisions_view(),
+            Tab::DuplicateFolders => return self.duplicate_folders_view(),
+            Tab::Outcomes if self.applied.is_some() => return self.report_view(),
+            _ => (),
+        }
+        let query = self.query.trim().to_lowercase();
+        let shown = self.order
+            .iter()
+            .filter_map(|hash| Some((hash, related.files.get(hash)?)))
+            .filter(|(_, group)| self.folder.as_ref().is_none_or(|folder| group.iter().any(|fi| fi.name.starts_with(folder))))
+            .filter(|(_, group)| query.is_empty() || group.iter().any(|fi| fi.name.to_string_lossy().to_lowercase().contains(&query)))
+            .collect::<Vec<(&String, &HashSet<FileInfo>)>>();
+        let reclaim_of = |group: &HashSet<FileInfo>| resolver.as_ref().and_then(|resolver| resolver.resolve(group)).map(|res| res.reclaimable()).unwrap_or_default();
+        for (hash, group) in &shown {
+            let size = group.iter().next().map_or(0, |fi| fi.size);
+            wasted += size * (group.len() as u64 - 1);
+            let allocated = group.iter().map(|fi| fi.allocated());
+            wasted_allocated += allocated.clone().sum::<u64>() - allocated.min().unwrap_or(0);
+            selected_bytes += size * self.selected(hash).map_or(0, |sel| sel.len() as u64);
+            reclaimable += reclaim_of(group);
+        }
+        let pages = shown.len().div_ceil(PAGE_SIZE).max(1);
+        let page = self.page.min(pages - 1);
+        let mut rows = Column::new().spacing(5);
+        for &(hash, group) in shown.iter().skip(page * PAGE_SIZE).take(PAGE_SIZE) {
+            let size = group.iter().next().map_or(0, |fi| fi.size);
+            let count = group.len();
+            let selected = self.selected(hash);
+            let reclaim = reclaim_of(group);
+            let expanded = self.expanded.contains(hash);
+            let status = match self.work.project.group_status(hash) {
+                GroupStatus::Open => String::new(),
+                GroupStatus::Partial { done, remaining } => {
+                    tr!("group-partly-resolved", done = locale.number(done as u64), remaining = locale.number(remaining as u64))
+                },
+                GroupStatus::Resolved => tr!("group-resolved"),
+            };
+            rows = rows.push(row![
+                button(if expanded { "-" } else { "+" }).on_press(Message::ToggleGroup(hash.clone())),
+                text(tr!(
+                    "group-summary",
+                    copies = locale.number(count as u64), size = locale.size(size), wasted = locale.size(size * (count as u64 - 1)),
+                    reclaimable = locale.size(reclaim.allocated), selected = locale.number(selected.map_or(0, |sel| sel.len() as u64)),
+                    status = status,
+                )),
+                text(hash[..12.min(hash.len())].to_owned()),
+                explained(button(text(tr!("group-never-flag"))).on_press(Message::Deny(hash.clone())), help::NEVER_FLAG),
+            ].spacing(10));
+            if expanded {
+                let mut files = group.iter().collect::<Vec<&FileInfo>>();
+                files.sort_by(|a, b| a.name.cmp(&b.name));
+                let mislabels = naming::mislabeled(files.iter().copied());
+                let done = self.work.project.decisions.done.get(hash);
+                for fi in files {
+                    let checked = selected.is_some_and(|sel| sel.contains(&*fi.name));
+                    let done = done.is_some_and(|done| done.contains(&*fi.name));
+                    let (hash, path) = (hash.clone(), fi.name.to_path_buf());
+                    let protected = self.work.project.in_reference(&fi.name);
+                    let mut file_box = checkbox(fi.name.to_string_lossy().into_owned(), checked);
+                    if !protected && !fi.in_archive() && !done {
+                        file_box = file_box.on_toggle(move |remove| Message::ToggleFile(hash.clone(), path.clone(), remove));
+                    }
+                    let previewing = self.preview.as_ref().is_some_and(|preview| preview.file.name == fi.name);
+                    let preview = button(text(tr!("file-preview"))).on_press_maybe((!previewing).then(|| Message::PreviewFile(fi.clone())));
+                    let on_disk = platform::on_disk(fi).to_path_buf();
+                    let open = button(text(tr!("file-open"))).on_press(Message::OpenFile(on_disk.clone()));
+                    let reveal = button(text(tr!("file-reveal"))).on_press(Message::RevealFile(on_disk));
+                    let mut file_row = row![text("").width(30), preview, open, reveal, file_box].spacing(10);
+                    if done {
+                        file_row = file_row.push(text(tr!("file-already-done", action = action_key(self.action()))));
+                    } else if protected {
+                        file_row = file_row.push(text(tr!("file-reference-copy")));
+                    } else if fi.in_archive() {
+                        file_row = file_row.push(text(tr!("file-in-archive")));
+                    }
+                    if let Some(mislabel) = mislabels.iter().find(|mislabel| mislabel.file == fi) {
+                        file_row = file_row.push(
+                            text(tr!("file-mislabeled", suggested = mislabel.suggested.clone()))
+                                .style(style::warning),
+                        );
+                    }
+                    rows = rows.push(file_row);
+                }
+            }
+        }
+        let mut actions = row![
+            button(text(tr!("back"))).on_press(Message::BackToWork),
+            match &self.folder {
+                Some(_) => button(text(tr!("results-all-folders"))).on_press(Message::ShowFolder(None)),
+                None => button(text(tr!("results-by-folder"))).on_press(Message::ShowTab(Tab::Folders)),
+            },
+            explained(button(text(tr!("results-duplicate-folders"))).on_press(Message::ShowTab(Tab::DuplicateFolders)), help::DUPLICATE_FOLDERS),
+            explained(button(text(tr!("results-name-collisions"))).on_press(Message::ShowTab(Tab::Collisions)), help::NAME_COLLISIONS),
+            explained(text(tr!("results-keep")), help::KEEP),
+            explained(pick_list(SELECTABLE_POLICIES, Some(self.policy.clone()), Message::ChoosePolicy), help::keep(&self.policy)),
+            button(text(tr!("results-select-duplicates"))).on_press(Message::AutoSelect),
+        ].spacing(10);
+        if related.files.values().any(|group| group.len() > 1 && group.iter().any(|fi| fi.in_archive())) {
+            actions = actions.push(button(text(tr!("results-select-archived"))).on_press(Message::SelectArchived));
+        }
+        column![
+            text(tr!(
+                "results-summary",
+                groups = locale.number(shown.len() as u64), wasted = locale.size(wasted), allocated = locale.size(wasted_allocated),
+                selected = locale.size(selected_bytes),
+            )).size(30),
+            explained(
+                text(tr!(
+                    "results-reclaimable",
+                    policy = self.policy.to_string(), size = locale.size(reclaimable.size), allocated = locale.size(reclaimable.allocated),
+                )),
+                help::RECLAIMABLE,
+            ),
+            actions.push(row![
+                button(text(tr!("results-clear-selection"))).on_press(Message::ClearSelection),
+                explained(button(text(tr!("results-dry-run"))).on_press(Message::DryRun), help::DRY_RUN),
+                // Only the selected files are acted on, the rest of their groups can be dealt with later.
+                explained(
+                    button(text(self.apply_label())).on_press_maybe(
+                        self.work.config.permit.filter(|_| !self.work.project.decisions.remove.is_empty()).map(|_| Message::ApplySelected),
+                    ),
+                    help::action(self.action()),
+                ),
+                button(text(tr!("results-export-json"))).on_press(Message::Export(ExportFormat::Json)),
+                button(text(tr!("results-export-csv"))).on_press(Message::Export(ExportFormat::Csv)),
+                text(tr!("results-compare-with")),
+                pick_list(SELECTABLE_POLICIES, self.comparison.as_ref().map(|(other, _)| other.clone()), Message::ComparePolicy),
+            ].spacing(10)),
+            self.comparison_view(),
+            self.rules_view(),
+            self.empty_view(related),
+            self.outcomes_view(),
+            row![
+                text_input(&tr!("results-search"), &self.query).on_input(Message::GroupQueryChanged).width(300),
+                text(tr!("results-sort")),
+                pick_list(GROUP_SORTS, Some(self.sort), Message::SortGroups),
+                button(text(tr!("results-previous-page"))).on_press_maybe((page > 0).then(|| Message::ShowPage(page - 1))),
+                text(tr!("results-page", page = locale.number(page as u64 + 1), pages = locale.number(pages as u64))),
+                button(text(tr!("results-next-page"))).on_press_maybe((page + 1 < pages).then(|| Message::ShowPage(page + 1))),
+            ].spacing(10),
+            row![scrollable(rows).width(Length::Fill), self.preview_view()].spacing(10),
+        ].spacing(10)
+    }
+
+    /// The file being previewed, to tell it apart from its copies: its path, size and times, and what is in it.
+    fn preview_view(&self) -> Column<'_, Message> {
+        let Some(preview) = &self.preview else {
+            return Column::new();
+        };
+        let locale = &self.work.config.locale;
+        let fi = &preview.file;
+        let time = |time: Option<SystemTime>| time.map_or_else(|| tr!("time-unknown"), |time| locale.time(time));
+        let mut col = column![
+            row![text(tr!("preview-title")).size(20), button(text(tr!("preview-close"))).on_press(Message::ClosePreview)].spacing(10),
+            text(fi.name.to_string_lossy().into_owned()),
+            text(tr!("preview-size", size = locale.size(fi.size), allocated = locale.size(fi.allocated()))),
+            text(tr!("preview-created", time = time(fi.created))),
+            text(tr!("preview-modified", time = time(fi.modified))),
+        ].spacing(5).width(preview::THUMBNAIL_SIZE as f32 + 20.0);
+        col = match (&preview.content, &self.thumbnail) {
+            (Content::Image(_), Some(thumbnail)) => col.push(image(thumbnail.clone())),
+            (Content::Text(lines), _) => col.push(text(lines.join("\n")).font(Font::MONOSPACE).size(12)),
+            (Content::Binary, _) => col.push(text(tr!("preview-binary"))),
+            (Content::InArchive, _) => col.push(text(tr!("preview-in-archive"))),
+            (Content::Unreadable(e), _) => col.push(text(tr!("preview-unreadable", error = e.clone())).style(style::problem)),
+            (Content::Image(_), None) => col,
+        };
+        col
+    }
+
+    /// How much is duplicated in each folder directly in the one browsed, most first, with a bar for its share of
+    /// the duplication there, to open or show the groups of.
+    fn folders_view<'a>(&'a self, related: &'a RelatedFiles) -> Column<'a, Message> {
+        let locale = &self.work.config.locale;
+        let root = &self.work.project.path;
+        let analysis = Analysis::new(related, root);
+        let browsing = self.browsing.as_deref().unwrap_or(root);
+        let name = |folder: &Path| match folder.strip_prefix(root) {
+            Ok(relative) if relative.as_os_str().is_empty() => tr!("folders-top-level"),
+            Ok(relative) => relative.to_string_lossy().into_owned(),
+            Err(_) => folder.to_string_lossy().into_owned(),
+        };
+        let here = analysis.get(browsing).cloned().unwrap_or_default();
+        let folders = analysis.children(browsing);
+        let mut rows = Column::new().spacing(5);
+        for stats in &folders {
+            let share = stats.wasted as f32 / here.wasted.max(1) as f32;
+            let open = button(text(tr!("folders-open"))).on_press_maybe(analysis.has_children(&stats.folder).then(|| Message::BrowseFolder(Some(stats.folder.clone()))));
+            rows = rows.push(row![
+                open,
+                button(text(tr!("folders-show"))).on_press(Message::ShowFolder(Some(stats.folder.clone()))),
+                progress_bar(0.0..=1.0, share).width(150).height(20),
+                text(tr!(
+                    "folders-summary",
+                    folder = name(&stats.folder), wasted = locale.size(stats.wasted), files = locale.number(stats.duplicates as u64),
+                    groups = locale.number(stats.groups as u64),
+                )),
+            ].spacing(10));
+        }
+        let up = browsing.parent().filter(|_| browsing != root).map(|parent| Message::BrowseFolder(Some(parent.to_path_buf())));
+        column![
+            text(tr!(
+                "folders-summary",
+                folder = name(browsing), wasted = locale.size(here.wasted), files = locale.number(here.duplicates as u64),
+                groups = locale.number(here.groups as u64),
+            )).size(30),
+            row![
+                button(text(tr!("back"))).on_press(Message::BackToWork),
+                button(text(tr!("results-by-group"))).on_press(Message::ShowTab(Tab::Groups)),
+                button(text(tr!("folders-up"))).on_press_maybe(up),
+                button(text(tr!("folders-show-all"))).on_press(Message::ShowFolder(Some(browsing.to_path_buf()))),
+            ].spacing(10),
+            text(tr!("folders-holding-duplicates", folders = locale.number(folders.len() as u64))),
+            scrollable(rows),
+        ].spacing(10)
+    }
+
+    /// The names files with different contents share, each with how many versions there are, to open and see the
+    /// files of each version, newest first.
+    fn collisions_view(&self) -> Column<'_, Message> {
+        let locale = &self.work.config.locale;
+        let collisions = self.collisions.as_deref().unwrap_or_default();
+        let mut rows = Column::new().spacing(5);
+        for collision in collisions {
+            let expanded = self.expanded_names.contains(&*collision.name);
+            rows = rows.push(row![
+                button(if expanded { "-" } else { "+" }).on_press(Message::ToggleName(collision.name.clone())),
+                text(tr!("collision-summary", name = collision.name.clone(), versions = locale.number(collision.versions.len() as u64))),
+            ].spacing(10));
+            if !expanded {
+                continue;
+            }
+            for version in &collision.versions {
+                let size = version.files.first().map_or(0, |fi| fi.size);
+                let modified = version.modified().map_or_else(|| tr!("time-unknown"), |time| locale.time(time));
+                rows = rows.push(row![
+                    text("").width(30),
+                    text(tr!("collision-version", hash = &version.hash[..12.min(version.hash.len())], size = locale.size(size), modified = modified)),
+                ].spacing(10));
+                for fi in &version.files {
+                    rows = rows.push(row![text("").width(60), text(fi.name.to_string_lossy().into_owned())].spacing(10));
+                }
+            }
+        }
+        column![
+            text(tr!("collisions-summary", names = locale.number(collisions.len() as u64))).size(30),
+            row![
+                button(text(tr!("back"))).on_press(Message::BackToWork),
+                button(text(tr!("results-by-group"))).on_press(Message::ShowTab(Tab::Groups)),
+            ].spacing(10),
+            scrollable(rows),
+        ].spacing(10)
+    }
+
+    /// The folders holding the same as another, most wasteful first, each with a way to remove all but the one the
+    /// keep policy keeps.
+    fn duplicate_folders_view(&self) -> Column<'_, Message> {
+        let locale = &self.work.config.locale;
+        let groups = self.duplicate_folders.as_deref().unwrap_or_default();
+        let mut rows = Column::new().spacing(5);
+        for (i, group) in groups.iter().enumerate() {
+            let remove = button(text(self.apply_label())).on_press_maybe(self.work.config.permit.map(|_| Message::RemoveFolderCopies(i)));
+            rows = rows.push(row![
+                text(tr!(
+                    "folder-group-summary",
+                    copies = locale.number(group.folders.len() as u64), files = locale.number(group.files as u64), size = locale.size(group.size),
+                    wasted = locale.size(group.wasted()),
+                )),
+                explained(remove, help::action(self.action())),
+            ].spacing(10));
+            for folder in &group.folders {
+                rows = rows.push(row![text("").width(30), text(folder.name.to_string_lossy().into_owned())].spacing(10));
+            }
+        }
+        let similar = self.similar_folders.as_deref().unwrap_or_default().iter().filter(|pair| pair.similarity() >= self.similarity);
+        let similar = similar.collect::<Vec<&SimilarPair>>();
+        rows = rows.push(row![
+            explained(text(tr!("similar-folders-summary", pairs = locale.number(similar.len() as u64), similarity = self.similarity)), help::SIMILAR_FOLDERS),
+            explained(slider(LOWEST_SIMILARITY..=100, self.similarity, Message::SimilarityChanged).width(200), help::MIN_SIMILARITY),
+        ].spacing(10));
+        for pair in similar {
+            rows = rows.push(row![
+                text("").width(30),
+                text(tr!(
+                    "similar-folders-pair",
+                    similarity = pair.similarity(), shared = locale.number(pair.shared as u64), total = locale.number(pair.total as u64),
+                    first = pair.first.display().to_string(), second = pair.second.display().to_string(),
+                )),
+            ].spacing(10));
+        }
+        let wasted = groups.iter().map(|group| group.wasted()).sum();
+        column![
+            text(tr!("folder-groups-summary", groups = locale.number(groups.len() as u64), wasted = locale.size(wasted))).size(30),
+            row![
+                button(text(tr!("back"))).on_press(Message::BackToWork),
+                button(text(tr!("results-by-group"))).on_press(Message::ShowTab(Tab::Groups)),
+                explained(text(tr!("results-keep")), help::KEEP),
+                explained(pick_list(SELECTABLE_POLICIES, Some(self.policy.clone()), Message::ChoosePolicy), help::keep(&self.policy)),
+            ].spacing(10),
+            self.outcomes_view(),
+            scrollable(rows),
+        ].spacing(10)
+    }
+
+    fn comparison_view(&self) -> Column<'_, Message> {
+        let Some((other, diff)) = &self.comparison else {
+            return Column::new();
+        };
+        let locale = &self.work.config.locale;
+        let net = diff.net_reclaimable();
+        column![
+            text(tr!(
+                "comparison-summary",
+                other = other.to_string(), policy = self.policy.to_string(), removed = locale.number(diff.only_second.len() as u64),
+                spared = locale.number(diff.only_first.len() as u64), freed = locale.size(net.unsigned_abs() as u64),
+                direction = if net < 0 { "less" } else { "more" },
+            )),
+        ]
+    }
+
+    /// The project's selection rules, a way to compose more, and to select the files they mark.
+    fn rules_view(&self) -> Column<'_, Message> {
+        let rules = &self.work.project.preferences.rules;
+        let mut col = Column::new().spacing(5);
+        for (i, rule) in rules.iter().enumerate() {
+            col = col.push(row![button(text(tr!("rules-remove"))).on_press(Message::RemoveRule(i)), text(rule.to_string())].spacing(10));
+        }
+        let placeholder = match self.rule_target {
+            TargetKind::Under => tr!("rules-under-example"),
+            TargetKind::Matching => tr!("rules-matching-example"),
+            TargetKind::Containing => tr!("rules-containing-example"),
+        };
+        col.push(row![
+            explained(text(tr!("rules-rule")), help::RULES),
+            pick_list(RULE_VERBS, Some(self.rule_verb), Message::ChooseRuleVerb),
+            pick_list(TARGET_KINDS, Some(self.rule_target), Message::ChooseRuleTarget),
+            text_input(&placeholder, &self.rule_value).on_input(Message::RuleValueChanged).on_submit(Message::AddRule).width(300),
+            button(text(tr!("rules-add"))).on_press_maybe((!self.rule_value.trim().is_empty()).then_some(Message::AddRule)),
+            button(text(tr!("rules-select"))).on_press_maybe((!rules.is_empty()).then_some(Message::SelectByRules)),
+        ].spacing(10))
+    }
+
+    /// How many empty files were found, which aren't among the groups, and a way to be rid of them.
+    fn empty_view(&self, related: &RelatedFiles) -> Column<'_, Message> {
+        let empty = related.empty_files().len();
+        if empty == 0 {
+            return Column::new();
+        }
+        let remove = button(text(tr!("empty-remove"))).on_press_maybe(self.work.config.permit.map(|_| Message::RemoveEmpty));
+        column![
+            row![
+                text(tr!("empty-summary", files = self.work.config.locale.number(empty as u64))),
+                explained(remove, help::EMPTY_FILES),
+            ].spacing(10),
+        ]
+    }
+
+    /// What `pending' is about to change, and why, for the user to go ahead with or not.
+    fn confirm_view<'a>(&'a self, pending: &'a Pending) -> Column<'a, Message> {
+        let locale = &self.work.config.locale;
+        let steps = &pending.plan.steps;
+        // Empty files are trashed whatever the action, see `Plan::empty_files'.
+        let action = action_key(steps.first().map_or(self.action(), |step| step.action));
+        let mut files = Column::new().spacing(2);
+        for step in steps {
+            let target = step.target.display().to_string();
+            files = files.push(text(match step.keep == step.target {
+                true => target,
+                false => tr!("confirm-step", file = target, keep = step.keep.display().to_string()),
+            }));
+        }
+        let go = self.work.config.permit.filter(|_| !steps.is_empty()).map(|_| Message::ConfirmCommit);
+        column![
+            text(tr!("confirm-title", action = action)).size(30),
+            text(tr!(
+                "confirm-summary",
+                files = locale.number(steps.len() as u64), action = action, size = locale.size(pending.plan.reclaimable()),
+                allocated = locale.size(pending.plan.reclaimable_allocated()),
+            )),
+            text(match pending.planned {
+                Planned::ByPolicy => tr!("confirm-policy", policy = self.policy.to_string()),
+                Planned::EmptyFiles => tr!("confirm-empty"),
+                Planned::Retry => tr!("confirm-retry"),
+            }),
+            pending.crossings.iter().fold(Column::new().spacing(2), |col, crossing| col.push(text(tr!(
+                "confirm-cross-device",
+                keep = crossing.keep.display().to_string(), copies = locale.number(crossing.copies.len() as u64),
+                decision = crossing.decision.to_string(),
+            )).style(style::warning))),
+            scrollable(files).height(Length::Fill),
+            row![
+                button(text(tr!("confirm-cancel"))).on_press(Message::CancelCommit),
+                explained(button(text(tr!("confirm-export-dry-run"))).on_press(Message::ExportDryRun), help::DRY_RUN),
+                button(text(tr!("confirm-go", action = action))).on_press_maybe(go),
+            ].spacing(10),
+        ].spacing(10)
+    }
+
+    /// What the last dry run would do, or what acting on the selection did.
+    fn outcomes_view(&self) -> Column<'_, Message> {
+        let (outcomes, dry_run) = match (&self.dry_run, &self.applied) {
+            (Some(outcomes), _) => (outcomes, true),
+            // What became of each file is listed on a tab of its own.
+            (None, Some(outcomes)) => {
+                let report = button(text(tr!("outcome-show-report"))).on_press(Message::ShowTab(Tab::Outcomes));
+                return column![row![text(self.outcomes_summary(outcomes, false)), report].spacing(10)];
+            },
+            (None, None) => return Column::new(),
+        };
+        let mut col = column![text(self.outcomes_summary(outcomes, dry_run))];
+        for outcome in outcomes {
+            if let Err(e) = &outcome.result {
+                col = col.push(text(tr!("outcome-failed", file = outcome.step.target.display().to_string(), error = e.to_string())).style(style::warning));
+            }
+        }
+        col
+    }
+
+    /// How many of `outcomes' went through, or would in a `dry_run', and what they reclaim.
+    fn outcomes_summary(&self, outcomes: &[Outcome], dry_run: bool) -> String {
+        let locale = &self.work.config.locale;
+        let done = outcomes.iter().filter(|outcome| outcome.result.is_ok()).collect::<Vec<&Outcome>>();
+        let (n, all) = (locale.number(done.len() as u64), locale.number(outcomes.len() as u64));
+        let size = locale.size(done.iter().map(|outcome| outcome.step.size).sum());
+        let allocated = locale.size(done.iter().map(|outcome| outcome.step.allocated).sum());
+        // Empty files are trashed whatever the action, see `Plan::empty_files'.
+        let action = action_key(outcomes.first().map_or(self.action(), |outcome| outcome.step.action));
+        match dry_run {
+            true => tr!("outcome-dry-run", done = n, files = all, action = action, size = size, allocated = allocated),
+            false => tr!("outcome-applied", done = n, files = all, action = action, size = size, allocated = allocated),
+        }
+    }
+
+    /// Every file acted on last and what became of it, with a way to try again those that failed.
+    fn report_view(&self) -> Column<'_, Message> {
+        let outcomes = self.applied.as_deref().unwrap_or_default();
+        let mut rows = Column::new().spacing(5);
+        for (i, outcome) in outcomes.iter().enumerate() {
+            let file = outcome.step.target.display().to_string();
+            let retry = button(text(tr!("report-retry"))).on_press_maybe(self.work.config.permit.map(|_| Message::Retry(Some(i))));
+            rows = rows.push(match &outcome.result {
+                Ok(()) => row![text(tr!("report-done", file = file, action = action_key(outcome.step.action)))],
+                Err(_) if outcome.in_use() => row![text(tr!("report-skipped", file = file)).style(style::warning), retry],
+                Err(e) => row![text(tr!("report-failed", file = file, error = e.to_string())).style(style::problem), retry],
+            }.spacing(10));
+        }
+        let failed = outcomes.iter().any(|outcome| outcome.result.is_err());
+        column![
+            text(self.outcomes_summary(outcomes, false)).size(20),
+            row![
+                button(text(tr!("report-back"))).on_press(Message::ShowTab(Tab::Groups)),
+                button(text(tr!("report-export"))).on_press(Message::ExportOutcomes),
+                button(text(tr!("report-retry-failed"))).on_press_maybe(self.work.config.permit.filter(|_| failed).map(|_| Message::Retry(None))),
+            ].spacing(10),
+            scrollable(rows),
+        ].spacing(10)
+    }
+
+    /// What removal will do with the selected files: the action last used for the project, or trashing them.
+    fn action(&self) -> Action {
+        self.work.project.preferences.action.unwrap_or_default()
+    }
+
+    fn apply_label(&self) -> String {
+        tr!("apply-selected", action = action_key(self.action()))
+    }
+
+    fn update(&mut self, message: Message) -> Task<Message> {
+        let decisions = &mut self.work.project.decisions;
+        match message {
+            Message::ToggleGroup(hash) => {
+                if !self.expanded.remove(&hash) {
+                    self.expanded.insert(hash);
+                }
+                return Task::none();
+            },
+            Message::ChoosePolicy(policy) => {
+                self.policy = policy.clone();
+                self.work.project.preferences.keep = Some(policy);
+                if let Some((other, _)) = self.comparison.take() {
+                    self.compare_with(other);
+                }
+            },
+            Message::ComparePolicy(other) => {
+                self.compare_with(other);
+                return Task::none();
+            },
+            Message::ToggleFile(hash, path, remove) => {
+                let selected = decisions.remove.entry(hash.clone()).or_default();
+                if remove {
+                    selected.insert(path);
+                } else {
+                    selected.remove(&path);
+                }
+                if selected.is_empty() {
+                    decisions.remove.remove(&hash);
+                }
+            },
+            Message::AutoSelect => {
+                let (Some(resolver), Some(related)) = (self.resolver(self.policy.clone()), &self.work.project.related) else {
+                    return Task::none();
+                };
+                let decisions = &mut self.work.project.decisions;
+                decisions.remove = resolver
+                    .resolve_all(related)
+                    .into_iter()
+                    .map(|(hash, res)| (hash.clone(), res.remove.into_iter().map(|fi| fi.name.to_path_buf()).collect()))
+                    .collect();
+            },
+            Message::SelectArchived => {
+                let (Some(resolver), Some(related)) = (self.resolver(self.policy.clone()), &self.work.project.related) else {
+                    return Task::none();
+                };
+                let decisions = &mut self.work.project.decisions;
+                decisions.remove.clear();
+                for step in Plan::against_archives(related, &resolver).steps {
+                    decisions.remove.entry(step.hash).or_default().insert(step.target);
+                }
+            },
+            Message::ShowTab(tab) => {
+                self.tab = tab;
+                self.browsing = None;
+                self.folder = None;
+                self.page = 0;
+                // The files may have been acted on since they were last listed.
+                let related = self.work.project.related.as_ref();
+                self.collisions = related.filter(|_| tab == Tab::Collisions).map(naming::collisions);
+                let roots = self.work.project.scanned();
+                let related = related.filter(|_| tab == Tab::DuplicateFolders);
+                self.duplicate_folders = related.map(|related| folders::duplicate_folders(related, &roots));
+                self.similar_folders = related.map(|related| folders::similar_folders(related, &roots, LOWEST_SIMILARITY));
+                return Task::none();
+            },
+            Message::SimilarityChanged(similarity) => {
+                self.similarity = similarity;
+                return Task::none();
+            },
+            Message::ToggleName(name) => {
+                if !self.expanded_names.remove(&name) {
+                    self.expanded_names.insert(name);
+                }
+                return Task::none();
+            },
+            Message::BrowseFolder(folder) => {
+                self.browsing = folder;
+                return Task::none();
+            },
+            Message::ShowFolder(folder) => {
+                self.folder = folder;
+                self.page = 0;
+                return Task::none();
+            },
+            Message::PreviewFile(fi) => return load_preview(fi),
+            Message::Previewed(preview) => {
+                let Some(preview) = preview.take() else { return Task::none() };
+                self.thumbnail = match &preview.content {
+                    Content::Image(thumbnail) => Some(image::Handle::from_rgba(thumbnail.width, thumbnail.height, thumbnail.rgba.clone())),
+                    _ => None,
+                };
+                self.preview = Some(preview);
+                return Task::none();
+            },
+            Message::ClosePreview => {
+                (self.preview, self.thumbnail) = (None, None);
+                return Task::none();
+            },
+            Message::OpenFile(path) => {
+                if let Err(e) = platform::open_path(&path) {
+                    self.work.problem = Some(tr!("file-open-failed", file = path.display().to_string(), error = e.to_string()));
+                }
+                return Task::none();
+            },
+            Message::RevealFile(path) => {
+                if let Err(e) = platform::reveal(&path) {
+                    self.work.problem = Some(tr!("file-reveal-failed", file = path.display().to_string(), error = e.to_string()));
+                }
+                return Task::none();
+            },
+            Message::GroupQueryChanged(query) => {
+                self.query = query;
+                self.page = 0;
+                return Task::none();
+            },
+            Message::SortGroups(sort) => {
+                self.sort = sort;
+                self.sort_order();
+                self.page = 0;
+                return Task::none();
+            },
+            Message::ShowPage(page) => {
+                self.page = page;
+                return Task::none();
+            },
+            Message::ClearSelection => decisions.remove.clear(),
+            Message::ChooseRuleVerb(verb) => {
+                self.rule_verb = verb;
+                return Task::none();
+            },
+            Message::ChooseRuleTarget(target) => {
+                self.rule_target = target;
+                return Task::none();
+            },
+            Message::RuleValueChanged(value) => {
+                self.rule_value = value;
+                return Task::none();
+            },
+            Message::AddRule => {
+                let value = self.rule_value.trim();
+                if value.is_empty() {
+                    return Task::none();
+                }
+                let rule = Rule { verb: self.rule_verb, target: self.rule_target.target(value) };
+                self.work.project.preferences.rules.push(rule);
+                self.rule_value.clear();
+            },
+            Message::RemoveRule(i) => {
+                self.work.project.preferences.rules.remove(i);
+            },
+            Message::SelectByRules => {
+                let (Some(resolver), Some(related)) = (self.resolver(self.policy.clone()), &self.work.project.related) else {
+                    return Task::none();
+                };
+                match rules::select(related, &self.work.project.preferences.rules, &resolver) {
+                    Ok(selected) => self.work.project.decisions.remove = selected,
+                    Err(e) => {
+                        self.work.problem = Some(tr!("rules-invalid", error = e.to_string()));
+                        return Task::none();
+                    },
+                }
+            },
+            Message::DryRun => {
+                let (Some(resolver), Some(related)) = (self.resolver(self.policy.clone()), &self.work.project.related) else {
+                    return Task::none();
+                };
+                let mut plan = Plan::selected(related, &self.work.project.decisions.remove, &resolver, self.action());
+                plan.across_devices(related, self.work.config.settings.cross_device);
+                self.dry_run = Some(plan.preview(&DedupOptions { dry_run: true, ..Default::default() }));
+                return Task::none();
+            },
+            // Nothing is changed before the user has seen what will be and confirmed it.
+            Message::ApplySelected | Message::RemoveEmpty | Message::RemoveFolderCopies(_) => {
+                let (Some(resolver), Some(related)) = (self.resolver(self.policy.clone()), &self.work.project.related) else {
+                    return Task::none();
+                };
+                let copies = match message {
+                    Message::RemoveFolderCopies(i) => self.duplicate_folders.as_deref().unwrap_or_default().get(i).cloned(),
+                    _ => None,
+                };
+                let (mut plan, planned) = match (message, &copies) {
+                    (Message::RemoveEmpty, _) => (Plan::empty_files(related, &resolver, self.action()), Planned::EmptyFiles),
+                    (_, Some(copies)) => (Plan::folders(related, std::slice::from_ref(copies), &resolver, self.action()), Planned::ByPolicy),
+                    _ => (Plan::selected(related, &self.work.project.decisions.remove, &resolver, self.action()), Planned::ByPolicy),
+                };
+                let crossings = plan.across_devices(related, self.work.config.settings.cross_device);
+                self.confirming = Some(Pending { plan, copies, planned, crossings });
+                return Task::none();
+            },
+            Message::CancelCommit => {
+                self.confirming = None;
+                return Task::none();
+            },
+            Message::ExportDryRun => {
+                let Some(pending) = &self.confirming else {
+                    return Task::none();
+                };
+                if let Some(file) = get_outcomes_file_from_user(tr!("choose-dry-run-file"), "dry-run.csv") {
+                    let outcomes = pending.plan.preview(&DedupOptions { dry_run: true, ..Default::default() });
+                    let result = fs::File::create(&file).and_then(|out| report::outcomes_to_csv(BufWriter::new(out), &outcomes));
+                    self.work.problem = result.err().map(|e| tr!("export-failed", file = file.display().to_string(), error = e.to_string()));
+                }
+                return Task::none();
+            },
+            Message::ExportOutcomes => {
+                let Some(outcomes) = &self.applied else {
+                    return Task::none();
+                };
+                if let Some(file) = get_outcomes_file_from_user(tr!("choose-outcomes-file"), "outcomes.csv") {
+                    let result = fs::File::create(&file).and_then(|out| report::outcomes_to_csv(BufWriter::new(out), outcomes));
+                    self.work.problem = result.err().map(|e| tr!("export-failed", file = file.display().to_string(), error = e.to_string()));
+                }
+                return Task::none();
+            },
+            Message::Retry(which) => {
+                let Some(outcomes) = &self.applied else {
+                    return Task::none();
+                };
+                let steps = outcomes
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, outcome)| outcome.result.is_err() && which.is_none_or(|which| which == *i))
+                    .map(|(_, outcome)| outcome.step.clone())
+                    .collect();
+                self.confirming = Some(Pending { plan: Plan { steps }, copies: None, planned: Planned::Retry, crossings: Vec::new() });
+                return Task::none();
+            },
+            Message::ConfirmCommit => {
+                let (Some(resolver), Some(permit)) = (self.resolver(self.policy.clone()), &self.work.config.permit) else {
+                    return Task::none();
+                };
+                let Some(Pending { plan, copies, planned, .. }) = self.confirming.take() else {
+                    return Task::none();
+                };
+                let (outcomes, journaled) = journal::execute(&self.work.config.dirs.data, &self.work.project.path, &plan, &DedupOptions::default(), permit);
+                self.work.project.record(&outcomes);
+                if let (Some(copies), Action::Trash | Action::Delete) = (&copies, self.action()) {
+                    for folder in copies.resolve(&resolver).map(|res| res.remove).unwrap_or_default() {
+                        if let Err(e) = actions::remove_emptied(&folder.name, permit) {
+                            self.work.problem = Some(tr!("folder-remove-failed", folder = folder.name.display().to_string(), error = e.to_string()));
+                        }
+                    }
+                }
+                if let Some(groups) = &mut self.duplicate_folders {
+                    groups.retain(|group| Some(group) != copies.as_ref());
+                }
+                if let Err(e) = journaled {
+                    self.work.problem = Some(tr!("journal-write-failed", error = e.to_string()));
+                }
+                // Removed copies leave their groups, and groups down to one copy are no longer duplicates.
+                let related = self.work.project.related.as_ref().expect("Acted on a project without results");
+                self.order.retain(|hash| related.files.get(hash).is_some_and(|group| group.len() > 1));
+                self.comparison = None;
+                self.dry_run = None;
+                // A retry only changes what became of the files it tried again.
+                self.applied = Some(match (planned, self.applied.take()) {
+                    (Planned::Retry, Some(mut applied)) => {
+                        for outcome in outcomes {
+                            if let Some(old) = applied.iter_mut().find(|old| old.step.target == outcome.step.target) {
+                                *old = outcome;
+                            }
+                        }
+                        applied
+                    },
+                    _ => outcomes,
+                });
+                self.tab = Tab::Outcomes;
+                let applied = self.applied.as_deref().unwrap_or_default();
+                if let Err(e) = self.work.project.save_decisions(&self.work.config.dirs.data, applied) {
+                    self.work.problem = Some(tr!("project-save-failed", error = e.to_string()));
+                }
+                return Task::none();
+            },
+            Message::Deny(hash) => {
+                let Some(related) = &mut self.work.project.related else {
+                    return Task::none();
+                };
+                let note = related.files.get(&hash)
+                    .and_then(|group| group.iter().map(|fi| &fi.name).min())
+                    .and_then(|name| name.file_name())
+                    .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+                let conf_dir = &self.work.config.dirs.config;
+                let result = Denylist::load(conf_dir).and_then(|mut denylist| {
+                    denylist.add(related.algo, &hash, note);
+                    denylist.save(conf_dir)
+                });
+                if let Err(e) = result {
+                    self.work.problem = Some(tr!("denylist-save-failed", error = e.to_string()));
+                    return Task::none();
+                }
+                related.files.remove(&hash);
+                self.work.project.decisions.remove.remove(&hash);
+                self.order.retain(|other| *other != hash);
+                self.expanded.remove(&hash);
+                self.comparison = None;
+            },
+            Message::Export(format) => {
+                let Some(report) = Report::new(&self.work.project) else {
+                    return Task::none();
+                };
+                let (name, extension) = match format {
+                    ExportFormat::Json => ("JSON", "json"),
+                    ExportFormat::Csv => ("CSV", "csv"),
+                };
+                if let Some(file) = get_export_file_from_user(name, extension) {
+                    let result = fs::File::create(&file).map(BufWriter::new).and_then(|out| match format {
+                        ExportFormat::Json => report::to_json(out, &report),
+                        ExportFormat::Csv => report::to_csv(out, &report),
+                    });
+                    self.work.problem = result.err().map(|e| tr!("export-failed", file = file.display().to_string(), error = e.to_string()));
+                }
+                return Task::none();
+            },
+            _ => return Task::none(),
+        }
+        self.dry_run = None;
+        self.applied = None;
+        if let Err(e) = self.work.project.save_decisions(&self.work.config.dirs.data, &[]) {
+            self.work.problem = Some(tr!("project-save-failed", error = e.to_string()));
+        }
+        Task::none()
+    }
+}
+
+/// The contents that are never flagged, shared by every project.
+struct DenylistSettings {
+    config : Config,
+    denylist : Denylist,
+    problem : Option<String>,
+}
+
+impl DenylistSettings {
+    fn new(config: Config) -> Self {
+        let (denylist, problem) = match Denylist::load(&config.dirs.config) {
+            Ok(denylist) => (denylist, None),
+            Err(e) => (Denylist::default(), Some(tr!("denylist-read-failed", error = e.to_string()))),
+        };
+        DenylistSettings { config, denylist, problem }
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        let mut col = column![
+            text(tr!("denylist-title")).size(30),
+            text(tr!("denylist-intro")),
+            button(text(tr!("back"))).on_press(Message::BackToStart),
+        ].spacing(10);
+        if let Some(problem) = &self.problem {
+            col = col.push(text(problem.clone()).size(30).style(style::problem));
+        }
+        if self.denylist.entries.is_empty() {
+            col = col.push(text(tr!("denylist-empty")));
+        }
+        let mut rows = Column::new().spacing(5);
+        for (i, entry) in self.denylist.entries.iter().enumerate() {
+            rows = rows.push(row![
+                button(text(tr!("denylist-allow"))).on_press(Message::Allow(i)),
+                text(entry.note.clone()),
+                text(format!("{:} {:}", entry.algo, &entry.hash[..12.min(entry.hash.len())])),
+            ].spacing(10));
+        }
+        col.push(scrollable(rows))
+    }
+
+    fn update(&mut self, message: Message) {
+        if let Message::Allow(i) = message {
+            let Some(entry) = self.denylist.entries.get(i).cloned() else {
+                return;
+            };
+            // Reread first, so entries added elsewhere since this was opened aren't lost.
+            let conf_dir = &self.config.dirs.config;
+            let result = Denylist::load(conf_dir).and_then(|mut denylist| {
+                denylist.remove(entry.algo, &entry.hash);
+                denylist.save(conf_dir).map(|_| denylist)
+            });
+            match result {
+                Ok(denylist) => {
+                    self.denylist = denylist;
+                    self.problem = None;
+                },
+                Err(e) => self.problem = Some(tr!("denylist-save-failed", error = e.to_string())),
+            }
+        }
+    }
+}
+
+/// The defaults shared by every project, as typed until saved.
+struct SettingsScreen {
+    config : Config,
+    threads : String,
+    hash_algo : HashAlgo,
+    /// Megabytes a second, or empty for no limit.
+    io_limit : String,
+    low_priority : bool,
+    cross_device : CrossDevice,
+    /// The filters new projects start with.
+    filters : FilterForm,
+    theme : Theme,
+    /// `#rrggbb', or empty for the theme's own.
+    accent : String,
+    language : Language,
+    /// Saved from the work screen, and only removed here.
+    profiles : Vec<Profile>,
+    problem : Option<String>,
+}
+
+impl SettingsScreen {
+    fn new(config: Config) -> Self {
+        // Reread, in case the file was edited by hand since the app started.
+        let (settings, problem) = match Settings::load(&config.dirs.config) {
+            Ok(settings) => (settings, None),
+            Err(e) => (config.settings.clone(), Some(tr!("settings-read-failed", error = e.to_string()))),
+        };
+        SettingsScreen {
+            threads: settings.threads.map(|n| n.to_string()).unwrap_or_default(),
+            hash_algo: settings.hash_algo,
+            io_limit: settings.io_limit.map(|limit| (limit as f64 / MEGABYTE).to_string()).unwrap_or_default(),
+            low_priority: settings.low_priority,
+            cross_device: settings.cross_device,
+            filters: FilterForm::new(&settings.filters),
+            theme: settings.theme,
+            accent: settings.accent.map(|accent| accent.to_string()).unwrap_or_default(),
+            language: settings.language.as_deref().map_or(Language::System, |language| Language::Named(i18n::Catalog::new(language).language())),
+            profiles: settings.profiles,
+            config, problem,
+        }
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        let mut col = column![
+            text(tr!("settings-title")).size(30),
+            text(tr!("settings-file", file = Settings::file(&self.config.dirs.config).display().to_string())),
+            row![button(text(tr!("back"))).on_press(Message::BackToStart), button(text(tr!("settings-save"))).on_press(Message::SaveSettings)].spacing(10),
+        ].spacing(10);
+        if let Some(problem) = &self.problem {
+            col = col.push(text(problem.clone()).size(30).style(style::problem));
+        }
+        col.push(row![
+            explained(text(tr!("settings-threads")).width(150), help::THREADS),
+            text_input(&tr!("settings-threads-all"), &self.threads).on_input(Message::ThreadsChanged),
+        ].spacing(10))
+        .push(row![
+            explained(text(tr!("settings-hash")).width(150), help::HASH_ALGO),
+            pick_list(HASH_ALGOS, Some(self.hash_algo), Message::ChooseHashAlgo),
+        ].spacing(10))
+        .push(row![
+            explained(text(tr!("settings-io-limit")).width(150), help::IO_LIMIT),
+            text_input(&tr!("settings-io-limit-none"), &self.io_limit).on_input(Message::IoLimitChanged),
+        ].spacing(10))
+        .push(explained(checkbox(tr!("settings-low-priority"), self.low_priority).on_toggle(Message::ToggleLowPriority), help::LOW_PRIORITY))
+        .push(row![
+            explained(text(tr!("settings-cross-device")).width(150), help::CROSS_DEVICE),
+            pick_list(CROSS_DEVICES, Some(self.cross_device), Message::ChooseCrossDevice),
+        ].spacing(10))
+        .push(row![text(tr!("settings-language")).width(150), pick_list(Language::all(), Some(self.language), Message::ChooseLanguage)].spacing(10))
+        .push(row![text(tr!("settings-theme")).width(150), pick_list(THEMES, Some(self.theme), Message::ChooseTheme)].spacing(10))
+        .push(row![text(tr!("settings-accent")).width(150), text_input(&tr!("settings-accent-example"), &self.accent).on_input(Message::AccentChanged)].spacing(10))
+        .push(text(tr!("settings-filters")).size(20))
+        .push(self.filters.view())
+        .push(self.profiles_panel())
+    }
+
+    fn profiles_panel(&self) -> Column<'_, Message> {
+        let mut col = column![explained(text(tr!("settings-profiles")).size(20), help::PROFILE)].spacing(5);
+        if self.profiles.is_empty() {
+            col = col.push(text(tr!("settings-no-profiles")));
+        }
+        for (i, profile) in self.profiles.iter().enumerate() {
+            col = col.push(row![text(profile.name.clone()).width(300), button(text(tr!("settings-remove-profile"))).on_press(Message::RemoveProfile(i))].spacing(10));
+        }
+        col
+    }
+
+    fn update(&mut self, message: Message) {
+        if self.filters.update(&message) {
+            return;
+        }
+        match message {
+            Message::ThreadsChanged(threads) => self.threads = threads,
+            Message::ChooseHashAlgo(hash_algo) => self.hash_algo = hash_algo,
+            Message::IoLimitChanged(io_limit) => self.io_limit = io_limit,
+            Message::ToggleLowPriority(low_priority) => self.low_priority = low_priority,
+            Message::ChooseCrossDevice(cross_device) => self.cross_device = cross_device,
+            Message::ChooseTheme(theme) => self.theme = theme,
+            Message::AccentChanged(accent) => self.accent = accent,
+            Message::ChooseLanguage(language) => self.language = language,
+            Message::RemoveProfile(i) if i < self.profiles.len() => {
+                self.profiles.remove(i);
+            },
+            Message::SaveSettings => {
+                let threads = match self.threads.trim() {
+                    "" => Ok(None),
+                    threads => threads.parse().map(Some).map_err(|_| tr!("settings-threads-invalid")),
+                };
+                let io_limit = match self.io_limit.trim() {
+                    "" => Ok(None),
+                    limit => match limit.parse::<f64>() {
+                        Ok(megabytes) if megabytes > 0.0 => Ok(Some((megabytes * MEGABYTE) as u64)),
+                        _ => Err(tr!("settings-io-limit-invalid")),
+                    },
+                };
+                let accent = match self.accent.trim() {
+                    "" => Ok(None),
+                    accent => accent.parse::<Accent>().map(Some).map_err(|_| tr!("settings-accent-invalid", accent = accent)),
+                };
+                let language = match self.language {
+                    Language::System => None,
+                    Language::Named(language) => Some(language.to_owned()),
+                };
+                let settings = threads.and_then(|threads| {
+                    let (filters, _) = self.filters.walk_conf()?;
+                    let profiles = self.profiles.clone();
+                    Ok(Settings {
+                        threads,
+                        hash_algo: self.hash_algo,
+                        io_limit: io_limit?,
+                        low_priority: self.low_priority,
+                        cross_device: self.cross_device,
+                        filters,
+                        theme: self.theme,
+                        accent: accent?,
+                        language,
+                        profiles,
+                    })
+                });
+                let saved = settings.and_then(|settings| {
+                    settings.save(&self.config.dirs.config).map_err(|e| tr!("settings-save-failed", error = e.to_string()))?;
+                    Ok(settings)
+                });
+                match saved {
+                    Ok(settings) => {
+                        i18n::set_language(settings.language.as_deref());
+                        self.config.settings = settings;
+                        self.problem = None;
+                    },
+                    Err(problem) => self.problem = Some(problem),
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Files removed or replaced while deduplicating, which can be restored from the copies that were kept.
+struct History {
+    config : Config,
+    entries : Vec<journal::Entry>,
+    problem : Option<String>,
+}
+
+impl History {
+    fn new(config: Config) -> Self {
+        let mut history = History { config, entries: Vec::new(), problem: None };
+        history.reload();
+        history
+    }
+
+    fn reload(&mut self) {
+        match journal::entries(&self.config.dirs.data) {
+            Ok(entries) => self.entries = entries,
+            Err(e) => self.problem = Some(tr!("history-read-failed", error = e.to_string())),
+        }
+    }
+
+    fn view(&self) -> Column<'_, Message> {
+        let mut col = column![
+            text(tr!("history-title")).size(30),
+            text(tr!("history-intro")),
+            button(text(tr!("back"))).on_press(Message::BackToStart),
+        ].spacing(10);
+        if self.config.permit.is_none() {
+            col = col.push(text(tr!("history-audit-mode")));
+        }
+        if let Some(problem) = &self.problem {
+            col = col.push(text(problem.clone()).size(30).style(style::problem));
+        }
+        if self.entries.is_empty() {
+            col = col.push(text(tr!("history-empty")));
+        }
+        let mut rows = Column::new().spacing(5);
+        // Most recent first.
+        for (i, entry) in self.entries.iter().enumerate().rev() {
+            rows = rows.push(row![
+                button(text(tr!("history-restore"))).on_press_maybe(self.config.permit.map(|_| Message::Restore(i))),
+                text(self.config.locale.time(entry.time)),
+                text(tr!("history-action", action = action_key(entry.action))),
+                text(entry.original.to_string_lossy().into_owned()),
+            ].spacing(10));
+        }
+        col.push(scrollable(rows))
+    }
+
+    fn update(&mut self, message: Message) {
+        if let Message::Restore(i) = message {
+            let (Some(entry), Some(permit)) = (self.entries.get(i), &self.config.permit) else {
+                return;
+            };
+            self.problem = journal::restore(&self.config.dirs.data, entry, permit)
+                .err()
+                .map(|e| tr!("history-restore-failed", error = e.to_string()));
+            self.reload();
+        }
+    }
+}
+
+/// How messages name `action', to pick the words for it.
+fn action_key(action: Action) -> &'static str {
+    match action {
+        Action::Trash => "trash",
+        Action::Delete => "delete",
+        Action::HardLink => "link",
+        Action::Reflink => "clone",
+        Action::Symlink => "symlink",
+    }
+}
+
+/// `content', explained by the help text `explanation' while hovered.
+fn explained<'a>(content: impl Into<Element<'a, Message>>, explanation: &'static str) -> Element<'a, Message> {
+    tooltip(content, container(text(help::text(explanation))).padding(5).max_width(400), tooltip::Position::Bottom)
+        .style(container::rounded_box)
+        .into()
+}
+
+/// Search the help topics.  The screen it was opened from carries on underneath, so a scan isn't interrupted.
+struct HelpBrowser {
+    previous: State,
+    query: String,
+}
+
+impl HelpBrowser {
+    fn view(&self) -> Column<'_, Message> {
+        let mut topics = Column::new().spacing(10);
+        let found = help::search(&self.query);
+        if found.is_empty() {
+            topics = topics.push(text(tr!("help-nothing-found")));
+        }
+        for topic in found {
+            topics = topics.push(text(help::text(topic.title)).size(24)).push(text(help::text(topic.intro)));
+            for term in topic.terms {
+                topics = topics.push(row![text(help::text(term.name)).width(150), text(help::text(term.text))].spacing(10));
+            }
+        }
+        column![
+            text(tr!("help-title")).size(30),
+            row![
+                text_input(&tr!("help-search"), &self.query).on_input(Message::HelpQueryChanged),
+                button(text(tr!("back"))).on_press(Message::CloseHelp),
+            ].spacing(10),
+            scrollable(topics),
+        ].spacing(10)
+    }
+}
+
+enum State {
+    Init(Init),
+    Work(Box<Work>),
+    Results(Box<Results>),
+    Denylist(DenylistSettings),
+    Settings(SettingsScreen),
+    History(History),
+    Help(Box<HelpBrowser>),
+}
+
+#[derive(Debug, Clone)]
+enum Message {
+    GetWorkDir,
+    GetReferenceDirs,
+    InstallIntegration,
+    Resume(usize),
+    FocusRequested,
+    IncludeChanged(String),
+    ExcludeChanged(String),
+    MinSizeChanged(String),
+    MaxSizeChanged(String),
+    ToggleSkipUniqueSizes(bool),
+    ToggleArchives(bool),
+    ChooseSymlinks(SymlinkPolicy),
+    TogglePipeline(bool),
+    ProfileNameChanged(String),
+    SaveProfile,
+    ChooseProfile(ProfileChoice),
+    RemoveProfile(usize),
+    AddRoot,
+    RemoveRoot(usize),
+    StartScan { resume: bool },
+    Walked { files: usize, bytes: u64 },
+    ScanProgress(Progress),
+    CancelScan,
+    PauseScan,
+    ScanFinished(Handoff<(WalkInfo, RelatedFiles, ScanUsage, Option<ScanLog>)>),
+    ShowErrors(bool),
+    ResumeRun,
+    RollBackRun,
+    ToggleWatch(bool),
+    Watched(Handoff<Vec<Update>>),
+    WatchFailed(String),
+    FindImages,
+    ImagesFound(Handoff<SimilarImages>),
+    FindAudio,
+    AudioFound(Handoff<SimilarFiles>),
+    FindArchives,
+    ArchivesFound(Handoff<SimilarFiles>),
+    ShowResults,
+    BackToWork,
+    ToggleGroup(String),
+    ToggleFile(String, PathBuf, bool),
+    PreviewFile(FileInfo),
+    Previewed(Handoff<Preview>),
+    ClosePreview,
+    OpenFile(PathBuf),
+    RevealFile(PathBuf),
+    ChoosePolicy(KeepPolicy),
+    ComparePolicy(KeepPolicy),
+    AutoSelect,
+    SelectArchived,
+    ClearSelection,
+    DryRun,
+    ApplySelected,
+    RemoveEmpty,
+    ShowTab(Tab),
+    RemoveFolderCopies(usize),
+    ConfirmCommit,
+    CancelCommit,
+    ExportDryRun,
+    ExportOutcomes,
+    Retry(Option<usize>),
+    SimilarityChanged(u8),
+    ChooseRuleVerb(Verb),
+    ChooseRuleTarget(TargetKind),
+    RuleValueChanged(String),
+    AddRule,
+    RemoveRule(usize),
+    SelectByRules,
+    ToggleName(String),
+    ShowFolder(Option<PathBuf>),
+    GroupQueryChanged(String),
+    SortGroups(GroupSort),
+    ShowPage(usize),
+    BrowseFolder(Option<PathBuf>),
+    Export(ExportFormat),
+    Deny(String),
+    ShowDenylist,
+    ShowSettings,
+    ThreadsChanged(String),
+    ChooseHashAlgo(HashAlgo),
+    ChooseCrossDevice(CrossDevice),
+    IoLimitChanged(String),
+    ToggleLowPriority(bool),
+    ChooseTheme(Theme),
+    AccentChanged(String),
+    ChooseLanguage(Language),
+    SaveSettings,
+    Allow(usize),
+    ShowHistory,
+    Restore(usize),
+    ToggleAudit(bool),
+    BackToStart,
+    ShowHelp,
+    HelpQueryChanged(String),
+    CloseHelp,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExportFormat {
+    Json,
+    Csv,
+}
+
+/// Changes to files planned in the results, for the user to confirm first.
+#[derive(Debug)]
+struct Pending {
+    plan: Plan,
+    /// The duplicate folders whose copies the plan removes, which are removed too once emptied.
+    copies: Option<FolderGroup>,
+    planned: Planned,
+    /// The groups with copies on another filesystem than the file kept, and what became of them, see `Plan::across_devices'.
+    crossings: Vec<Crossing>,
+}
+
+/// How the copies a `Pending' plan keeps were picked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Planned {
+    /// By the keep policy, from the files not selected.
+    ByPolicy,
+    /// Not at all, empty files are all trashed.
+    EmptyFiles,
+    /// When the steps were first planned, which are tried again after failing.
+    Retry,
+}
+
+impl State {
+    pub fn view(&self) -> Column<'_, Message> {
+        let file_menu = |items| Menu::new(items).max_width(450.0).offset(15.0).spacing(5.0);
+        let top_menu = menu_bar!(
+            (text(tr!("menu-file")), file_menu(menu_items!(
+                (button(text(tr!("menu-deduplicate"))).on_press(Message::GetWorkDir))
+                (button(text(tr!("menu-compare"))).on_press(Message::GetReferenceDirs))
+                (button(text(tr!("menu-integration"))).on_press(Message::InstallIntegration))))
+            )
+            (text(tr!("menu-help")), file_menu(menu_items!(
+                (button(text(tr!("menu-help-topics"))).on_press(Message::ShowHelp))))
+            ))
+            .draw_path(menu::DrawPath::Backdrop);
+        match self {
+            State::Init(init) => {
+                let mut col = column![top_menu];
+                if let Err(problem) = &init.problem {
+                    col = col.push(text(match problem {
+                        Problem::NoFolder => tr!("problem-no-folder"),
+                        Problem::Missing(path) => tr!("problem-missing", folder = path.to_str().unwrap_or("<directory>")),
+                        Problem::Project(e) => tr!("problem-project", error = e.to_string()),
+                        Problem::Locked(e) => tr!("problem-locked", error = e.to_string()),
+                        Problem::Overlapping { path, reference } => tr!(
+                            "problem-overlapping",
+                            folder = path.to_str().unwrap_or("<directory>"), reference = reference.to_str().unwrap_or("<directory>"),
+                        ),
+                    }).size(50).style(style::problem));
+                }
+                col = col.push(text(tr!("data-folder", folder = init.config.dirs.data.to_str().unwrap_or("<directory>"))).size(50));
+                col = col.push(row![
+                    button(text(tr!("init-choose-folder"))).on_press(Message::GetWorkDir),
+                    button(text(tr!("denylist-title"))).on_press(Message::ShowDenylist),
+                    button(text(tr!("settings-title"))).on_press(Message::ShowSettings),
+                    button(text(tr!("history-title"))).on_press(Message::ShowHistory),
+                    explained(checkbox(tr!("init-audit-mode"), init.config.permit.is_none()).on_toggle(Message::ToggleAudit), help::AUDIT),
+                ].spacing(10));
+                if !init.config.settings.profiles.is_empty() {
+                    let chosen = init.config.profile.clone().map_or(ProfileChoice::None, ProfileChoice::Named);
+                    col = col.push(row![
+                        explained(text(tr!("init-profile")), help::PROFILE),
+                        pick_list(ProfileChoice::all(&init.config.settings), Some(chosen), Message::ChooseProfile),
+                    ].spacing(10));
+                }
+                if !init.projects.is_empty() {
+                    col = col.push(text(tr!("init-previous-projects")).size(30));
+                }
+                for (i, summary) in init.projects.iter().enumerate() {
+                    col = col.push(row![
+                        button(text(tr!("init-resume"))).on_press(Message::Resume(i)),
+                        text(summary.path.to_str().unwrap_or("<directory>").to_owned()),
+                        text(if summary.related { tr!("init-scanned") } else { tr!("init-not-scanned") }),
+                        text(tr!("init-last-changed", time = init.config.locale.time(summary.updated))),
+                    ].spacing(10));
+                }
+                col
+            },
+            State::Work(work) => column![top_menu, work.view()],
+            State::Results(results) => {
+                let mut col = column![top_menu];
+                if let Some(problem) = &results.work.problem {
+                    col = col.push(text(problem.clone()).size(30).style(style::problem));
+                }
+                col.push(results.view())
+            },
+            State::Denylist(settings) => column![top_menu, settings.view()],
+            State::Settings(settings) => column![top_menu, settings.view()],
+            State::History(history) => column![top_menu, history.view()],
+            State::Help(help) => column![top_menu, help.view()],
+        }
+    }
+
+    fn config(&self) -> &Config {
+        match self {
+            State::Init(init) => &init.config,
+            State::Work(work) => &work.config,
+            State::Results(results) => &results.work.config,
+            State::Denylist(settings) => &settings.config,
+            State::Settings(settings) => &settings.config,
+            State::History(history) => &history.config,
+            State::Help(help) => help.previous.config(),
+        }
+    }
+
+    pub fn theme(&self) -> iced::Theme {
+        let settings = &self.config().settings;
+        style::theme(settings.theme, settings.accent)
+    }
+
+    pub fn subscription(&self) -> Subscription<Message> {
+        let lock_path = lock::instance_lock_path(&self.config().dirs.data);
+        Subscription::run_with_id("focus-requests", focus_requests(lock_path))
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::InstallIntegration => {
+                install_integration();
+                return Task::none();
+            },
+            Message::FocusRequested => {
+                return window::get_latest().and_then(window::gain_focus);
+            },
+            Message::ShowHelp if !matches!(self, State::Help(_)) => {
+                let previous = std::mem::replace(self, State::Init(Init::empty()));
+                *self = State::Help(Box::new(HelpBrowser { previous, query: String::new() }));
+                return Task::none();
+            },
+            _ => (),
+        }
+        match self {
+            State::Init(init) => {
+                match message {
+                    Message::GetWorkDir => {
+                        match get_target_dir_from_user().ok_or(Problem::NoFolder).and_then(|path| start_work(&init.config, path)) {
+                            Ok(state) => *self = state,
+                            Err(problem) => init.problem = Err(problem),
+                        }
+                    },
+                    Message::GetReferenceDirs => {
+                        let dirs = get_reference_dirs_from_user().ok_or(Problem::NoFolder);
+                        match dirs.and_then(|(path, reference)| start_reference_work(&init.config, path, reference)) {
+                            Ok(state) => *self = state,
+                            Err(problem) => init.problem = Err(problem),
+                        }
+                    },
+                    Message::Resume(i) => {
+                        if let Some(summary) = init.projects.get(i) {
+                            match resume_work(&init.config, &summary.path) {
+                                Ok(state) => *self = state,
+                                Err(problem) => init.problem = Err(problem),
+                            }
+                        }
+                    },
+                    Message::ShowDenylist => *self = State::Denylist(DenylistSettings::new(init.config.clone())),
+                    Message::ShowSettings => *self = State::Settings(SettingsScreen::new(init.config.clone())),
+                    Message::ShowHistory => *self = State::History(History::new(init.config.clone())),
+                    Message::ToggleAudit(audit) => init.config.permit = Permit::unless_auditing(audit),
+                    Message::ChooseProfile(ProfileChoice::None) => init.config.profile = None,
+                    Message::ChooseProfile(ProfileChoice::Named(name)) => init.config.profile = Some(name),
+                    _ => (),
+                }
+                Task::none()
+            },
+            State::Work(work) => {
+                match message {
+                    // Choosing another folder is refused mid-scan, since it would abandon the scan's results.
+                    Message::GetWorkDir if work.scan.is_none() => {
+                        let path = get_target_dir_from_user();
+                        if path.as_ref() == Some(&work.project.path) {
+                            return Task::none();
+                        }
+                        match path.ok_or(Problem::NoFolder).and_then(|path| start_work(&work.config, path)) {
+                            Ok(state) => *self = state,
+                            Err(Problem::NoFolder) => (),
+                            Err(problem) => *self = State::Init(Init::new(work.config.clone(), Err(problem))),
+                        }
+                        Task::none()
+                    },
+                    Message::GetReferenceDirs if work.scan.is_none() => {
+                        let Some((path, reference)) = get_reference_dirs_from_user() else {
+                            return Task::none();
+                        };
+                        if path == work.project.path {
+                            // Our own lock already covers this project, so just take the new reference in place.
+                            if guard::overlaps(&path, &reference) {
+                                work.problem = Some(tr!("problem-reference-overlaps"));
+                            } else if work.project.set_reference(Some(reference)) {
+                                if let Err(e) = work.project.save(&work.config.dirs.data) {
+                                    work.problem = Some(tr!("project-save-failed", error = e.to_string()));
+                                }
+                            }
+                            return Task::none();
+                        }
+                        match start_reference_work(&work.config, path, reference) {
+                            Ok(state) => *self = state,
+                            Err(problem) => *self = State::Init(Init::new(work.config.clone(), Err(problem))),
+                        }
+                        Task::none()
+                    },
+                    Message::ShowResults if work.scan.is_none() => {
+                        if let State::Work(mut work) = std::mem::replace(self, State::Init(Init::empty())) {
+                            // Changes made while reviewing would go unseen, so watching stops.
+                            work.watching = None;
+                            *self = State::Results(Box::new(Results::new(*work)));
+                        }
+                        Task::none()
+                    },
+                    message => work.update(message),
+                }
+            },
+            State::Results(results) => {
+                match message {
+                    Message::BackToWork => {
+                        if let State::Results(results) = std::mem::replace(self, State::Init(Init::empty())) {
+                            *self = State::Work(Box::new(results.work));
+                        }
+                        Task::none()
+                    },
+                    message => results.update(message),
+                }
+            },
+            State::Denylist(settings) => {
+                match message {
+                    Message::BackToStart => *self = State::Init(Init::new(settings.config.clone(), Ok(()))),
+                    Message::GetWorkDir => {
+                        match get_target_dir_from_user().ok_or(Problem::NoFolder).and_then(|path| start_work(&settings.config, path)) {
+                            Ok(state) => *self = state,
+                            Err(Problem::NoFolder) => (),
+                            Err(problem) => *self = State::Init(Init::new(settings.config.clone(), Err(problem))),
+                        }
+                    },
+                    message => settings.update(message),
+                }
+                Task::none()
+            },
+            State::Settings(settings) => {
+                match message {
+                    Message::BackToStart => *self = State::Init(Init::new(settings.config.clone(), Ok(()))),
+                    message => settings.update(message),
+                }
+                Task::none()
+            },
+            State::History(history) => {
+                match message {
+                    Message::BackToStart => *self = State::Init(Init::new(history.config.clone(), Ok(()))),
+                    message => history.update(message),
+                }
+                Task::none()
+            },
+            State::Help(help) => {
+                match message {
+                    Message::HelpQueryChanged(query) => {
+                        help.query = query;
+                        Task::none()
+                    },
+                    Message::Walked { .. } | Message::ScanProgress(_) | Message::ScanFinished(_) | Message::Watched(_) | Message::WatchFailed(_)
+                    | Message::ImagesFound(_) | Message::AudioFound(_) | Message::ArchivesFound(_) => {
+                        help.previous.update(message)
+                    },
+                    // Anything else, e.g. from the menu, is meant for the screen underneath.
+                    message => {
+                        if let State::Help(help) = std::mem::replace(self, State::Init(Init::empty())) {
+                            *self = help.previous;
+                        }
+                        match message {
+                            Message::CloseHelp | Message::ShowHelp => Task::none(),
+                            message => self.update(message),
+                        }
+                    },
+                }
+            },
+        }
+    }
+}
+
+fn main() -> iced::Result {
+    let args = Args::parse();
+    let dirs = Dirs::locate(args.config.clone()).unwrap_or_else(|e| panic!("Failed to set up the configuration directory: {:}", e));
+    let settings = Settings::load(&dirs.config).unwrap_or_else(|e| {
+        eprintln!("{:}", tr!("settings-defaults", error = e.to_string()));
+        Settings::default()
+    });
+    i18n::set_language(settings.language.as_deref());
+    // Only one instance may use the data directory.  Rather than starting a second one,
+    // offer to bring the running instance to the front.
+    let instance_lock_path = lock::instance_lock_path(&dirs.data);
+    let _instance_lock = match Lock::acquire(&instance_lock_path) {
+        Ok(lock) => Some(lock),
+        Err(lock::Error { error_type: lock::ErrorType::Held(pid), .. }) => {
+            let focus = MessageDialog::new()
+                .set_level(MessageLevel::Warning)
+                .set_title(tr!("app-title"))
+                .set_description(tr!("instance-running", pid = pid))
+                .set_buttons(MessageButtons::YesNo)
+                .show();
+            if focus == MessageDialogResult::Yes {
+                if let Err(e) = lock::request_focus(&instance_lock_path) {
+                    eprintln!("{:}", tr!("instance-unreachable", error = e.to_string()));
+                }
+            }
+            return Ok(());
+        },
+        Err(e) => {
+            eprintln!("{:}", tr!("instance-lock-failed", error = e.to_string()));
+            None
+        },
+    };
+    // Data directory is found.  Now we can create our initial state, offering any previous projects
+    // for resumption unless we were asked to work on a particular folder.
+    let config = Config { dirs, settings, locale: Localized::system(), permit: Permit::unless_auditing(args.audit), profile: None };
+    let (state, task) = match args.target() {
+        Some((path, autostart)) => match start_work(&config, path) {
+            Ok(state) => (state, if autostart { Task::done(Message::StartScan { resume: false }) } else { Task::none() }),
+            Err(problem) => (State::Init(Init::new(config, Err(problem))), Task::none()),
+        },
+        None => (State::Init(Init::new(config, Ok(()))), Task::none()),
+    };
+    iced::application(|_: &State| tr!("app-title"), State::update, State::view)
+        .subscription(State::subscription)
+        .theme(State::theme)
+        .run_with(|| (state, task))
+}
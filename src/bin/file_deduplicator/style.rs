@@ -0,0 +1,45 @@
+//! The app's colors, named for what they say rather than what they are, so views never pick colors themselves and
+//! every theme, accent included, reads the same.
+
+use std::sync::OnceLock;
+use iced::{
+    Color, Theme,
+    theme::Palette,
+    widget::text,
+};
+use file_deduplicator::settings::{self, Accent};
+
+/// The iced theme for `theme', with `accent' in place of its primary color.
+pub fn theme(theme: settings::Theme, accent: Option<Accent>) -> Theme {
+    let base = match theme {
+        settings::Theme::System if desktop_is_dark() => Theme::Dark,
+        settings::Theme::System => Theme::Light,
+        settings::Theme::Light => Theme::Light,
+        settings::Theme::Dark => Theme::Dark,
+    };
+    match accent {
+        Some(accent) => {
+            let primary = Color::from_rgb8(accent.red, accent.green, accent.blue);
+            Theme::custom(format!("{:} with {:}", base, accent), Palette { primary, ..base.palette() })
+        },
+        None => base,
+    }
+}
+
+/// Whether the desktop is set to a dark theme.  Asking may mean reading the desktop's settings, so it's done once.
+fn desktop_is_dark() -> bool {
+    static DARK: OnceLock<bool> = OnceLock::new();
+    *DARK.get_or_init(|| dark_light::detect() == dark_light::Mode::Dark)
+}
+
+/// Something went wrong, and nothing was done about it.
+pub fn problem(theme: &Theme) -> text::Style {
+    text::Style { color: Some(theme.palette().danger) }
+}
+
+/// Worth a second look before acting, such as a file that may be mislabeled or one that couldn't be acted on.
+pub fn warning(theme: &Theme) -> text::Style {
+    // Palettes have no warning color, so amber is lightened on dark backgrounds to stay readable.
+    let color = if theme.extended_palette().is_dark { Color::from_rgb(1.0, 0.7, 0.3) } else { Color::from_rgb(0.8, 0.5, 0.0) };
+    text::Style { color: Some(color) }
+}
@@ -0,0 +1,142 @@
+//! Remember the hash of every file scanned, so a rescan only reads the files that changed since.
+//! A file is taken to be unchanged while its size and modification time are, which is what backup tools go by too.
+//! The cache is shared by every project and kept as a binary file in the data directory, since a large
+//! library has millions of entries.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use serde::{Serialize, Deserialize};
+use crate::{
+    persist,
+    relate::{FileInfo, HashAlgo, RelatedFiles, WalkInfo},
+};
+
+const CACHE_FILE: &str = "hashes.bin";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Format(bincode::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(bincode::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed hash cache: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// The hash of a file as it was when last scanned.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    modified: SystemTime,
+    algo: HashAlgo,
+    hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashCache {
+    /// By path.  Paths that aren't valid Unicode are left out and simply hashed every time.
+    entries: HashMap<String, Entry>,
+}
+
+impl HashCache {
+    /// Where the cache lives under the data directory `data_dir'.
+    pub fn file(data_dir: &Path) -> PathBuf {
+        data_dir.join(CACHE_FILE)
+    }
+
+    /// Read the cache under `data_dir'.  It is empty until something is scanned.
+    pub fn load(data_dir: &Path) -> Result<Self, Error> {
+        let file = Self::file(data_dir);
+        if !file.exists() && !persist::backup_path(&file).exists() {
+            return Ok(Self::default());
+        }
+        persist::read_recovering(
+            &file,
+            |contents| bincode::deserialize(contents).map_err(format_error(&file)),
+            |e| io_error(&file)(e),
+        )
+    }
+
+    pub fn save(&self, data_dir: &Path) -> Result<(), Error> {
+        let file = Self::file(data_dir);
+        let contents = bincode::serialize(self).map_err(format_error(&file))?;
+        persist::write_atomic(&file, &contents).map_err(io_error(&file))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The `algo' hash of `fi', if it hasn't changed since it was cached.
+    pub fn get(&self, fi: &FileInfo, algo: HashAlgo) -> Option<&str> {
+        let entry = self.entries.get(fi.name.to_str()?)?;
+        let unchanged = entry.size == fi.size && Some(entry.modified) == fi.modified && entry.algo == algo;
+        unchanged.then_some(entry.hash.as_str())
+    }
+
+    /// The files of `walk' whose `algo' hash is known, grouped by it, to carry on from with `RelatedFiles::resume'.
+    pub fn known(&self, walk: &WalkInfo, algo: HashAlgo) -> RelatedFiles {
+        let mut files = HashMap::<String, HashSet<FileInfo>>::new();
+        for fi in &walk.files {
+            if let Some(hash) = self.get(fi, algo) {
+                files.entry(hash.to_owned()).or_default().insert(fi.clone());
+            }
+        }
+        RelatedFiles { algo, files, partial: false, errors: Vec::new() }
+    }
+
+    /// Remember the hashes in `related', made from `walk' of the folders `roots'.  When the walk and hashing both
+    /// finished, files under `roots' that weren't hashed this time are forgotten, as they are gone or unreadable.
+    pub fn update(&mut self, roots: &[&Path], walk: &WalkInfo, related: &RelatedFiles) {
+        let hashed = related.files.iter().flat_map(|(hash, group)| group.iter().map(move |fi| (hash, fi)));
+        if !walk.partial && !related.partial {
+            let names = related.files.values().flatten().filter_map(|fi| fi.name.to_str()).collect::<HashSet<&str>>();
+            self.entries.retain(|name, _| names.contains(name.as_str()) || !roots.iter().any(|root| Path::new(name).starts_with(root)));
+        }
+        for (hash, fi) in hashed {
+            let (Some(name), Some(modified)) = (fi.name.to_str(), fi.modified) else {
+                continue;
+            };
+            self.entries.insert(name.to_owned(), Entry { size: fi.size, modified, algo: related.algo, hash: hash.clone() });
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Where the configuration and the data are kept.  Configuration, such as the denylist, goes in the platform's
+//! configuration directory, e.g. `$XDG_CONFIG_HOME/file-deduplicator' on Linux, and everything made by scanning and
+//! deduplicating, such as projects, the hash cache and the journal, in its data directory, e.g.
+//! `$XDG_DATA_HOME/file-deduplicator'.  Earlier versions kept both in `~/.file-deduplicator', which is still used
+//! while it exists, so nothing is lost.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+use directories::ProjectDirs;
+use xdg_home::home_dir;
+
+/// Where earlier versions kept everything, under the home directory.
+pub const LEGACY_DIR: &str = ".file-deduplicator";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    /// Neither the platform's directories nor the home directory could be found.
+    NoHome,
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::NoHome => write!(f, "Couldn't find the user's home directory, choose a configuration directory instead"),
+        }
+    }
+}
+
+/// The directories the application keeps its files in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dirs {
+    /// What the user chose, shared by every project.
+    pub config: PathBuf,
+    /// What scans and deduplication produce.
+    pub data: PathBuf,
+}
+
+impl Dirs {
+    /// Keep both configuration and data in `dir', as asked for with `--config'.
+    pub fn in_one(dir: PathBuf) -> Self {
+        Self { config: dir.clone(), data: dir }
+    }
+
+    /// The platform's directories, or the directory of earlier versions where it exists.
+    pub fn platform() -> Result<Self, Error> {
+        let legacy = home_dir().map(|home| home.join(LEGACY_DIR));
+        if let Some(legacy) = legacy.filter(|legacy| legacy.is_dir()) {
+            return Ok(Self::in_one(legacy));
+        }
+        let dirs = ProjectDirs::from("", "", "file-deduplicator").ok_or(Error { path: "~".into(), error_type: ErrorType::NoHome })?;
+        Ok(Self { config: dirs.config_dir().to_path_buf(), data: dirs.data_dir().to_path_buf() })
+    }
+
+    /// `dir' if given, else the platform's directories.  Either is created if missing.
+    pub fn locate(dir: Option<PathBuf>) -> Result<Self, Error> {
+        let dirs = match dir {
+            Some(dir) => Self::in_one(dir),
+            None => Self::platform()?,
+        };
+        for dir in [&dirs.config, &dirs.data] {
+            fs::create_dir_all(dir).map_err(io_error(dir))?;
+        }
+        Ok(dirs)
+    }
+}
@@ -0,0 +1,116 @@
+//! Find which files of one saved scan another saved scan already holds, e.g. which camera imports are in the archive
+//! already, from the hashes both scans kept, so neither folder is walked or hashed again.  The scans may be kept in
+//! different data directories, such as one copied from another computer.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+use crate::{
+    paths,
+    project::{self, Project},
+    relate::{FileInfo, HashAlgo, RelatedFiles},
+};
+
+#[derive(Debug)]
+pub enum ErrorType {
+    Project(Box<project::Error>),
+    /// No project for the folder was saved.
+    NotScanned,
+    /// The folder's last scan didn't finish, so there are no hashes to go by.
+    Unfinished,
+    /// The scans hashed with different algorithms, so their hashes can't be compared.
+    DifferentAlgo { archive: HashAlgo, scan: HashAlgo },
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            // Says where itself.
+            ErrorType::Project(e) => write!(f, "{:}", e),
+            ErrorType::NotScanned => write!(f, "{:}: not scanned yet", self.path.display()),
+            ErrorType::Unfinished => write!(f, "{:}: the last scan did not finish", self.path.display()),
+            ErrorType::DifferentAlgo { archive, scan } => write!(
+                f, "{:}: hashed with {:} but the archive with {:}, scan one of them again with the other's algorithm",
+                self.path.display(), scan, archive,
+            ),
+        }
+    }
+}
+
+/// A file of the scan looked at, and where the archive holds the same contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Known {
+    pub file: FileInfo,
+    pub hash: String,
+    /// Sorted.
+    pub copies: Vec<PathBuf>,
+}
+
+/// The files of a scan, by whether an archive already holds them.
+#[derive(Debug, Clone, Default)]
+pub struct Crosscheck {
+    /// Sorted by path.
+    pub known: Vec<Known>,
+    /// Sorted by path.
+    pub unknown: Vec<FileInfo>,
+    /// Either scan stopped early, so some files in `unknown' may be in the archive after all.
+    pub partial: bool,
+}
+
+impl Crosscheck {
+    /// Sort the files of `scan' by whether `archive' holds a copy of each.  Where the two scans found the same
+    /// file, as when one folder is inside the other, it isn't taken for a copy of itself.
+    pub fn new(archive: &RelatedFiles, scan: &RelatedFiles) -> Self {
+        let mut check = Self { partial: archive.partial || scan.partial, ..Default::default() };
+        for (hash, group) in &scan.files {
+            for fi in group {
+                let copies = archive.files
+                    .get(hash)
+                    .into_iter()
+                    .flatten()
+                    .filter(|copy| !paths::same(&copy.name, &fi.name))
+                    .map(|copy| copy.name.to_path_buf());
+                let mut copies = copies.collect::<Vec<PathBuf>>();
+                if copies.is_empty() {
+                    check.unknown.push(fi.clone());
+                } else {
+                    copies.sort();
+                    check.known.push(Known { file: fi.clone(), hash: hash.clone(), copies });
+                }
+            }
+        }
+        check.known.sort_by(|a, b| a.file.name.cmp(&b.file.name));
+        check.unknown.sort_by(|a, b| a.name.cmp(&b.name));
+        check
+    }
+
+    /// Like `new', with the last scans of `archive' saved under `archive_data_dir' and of `path' under `data_dir'.
+    pub fn load(archive_data_dir: &Path, archive: &Path, data_dir: &Path, path: &Path) -> Result<Self, Error> {
+        let archive_scan = load_scan(archive_data_dir, archive)?;
+        let scan = load_scan(data_dir, path)?;
+        if archive_scan.algo != scan.algo {
+            let error_type = ErrorType::DifferentAlgo { archive: archive_scan.algo, scan: scan.algo };
+            return Err(Error { path: path.to_path_buf(), error_type });
+        }
+        Ok(Self::new(&archive_scan, &scan))
+    }
+
+    /// The bytes the files in `known' take, which the archive holds already.
+    pub fn known_size(&self) -> u64 {
+        self.known.iter().map(|known| known.file.size).sum()
+    }
+}
+
+/// The results of the last scan of `path' saved under `data_dir'.
+fn load_scan(data_dir: &Path, path: &Path) -> Result<RelatedFiles, Error> {
+    let error = |error_type| Error { path: path.to_path_buf(), error_type };
+    let project = Project::load(data_dir, path).map_err(|e| error(ErrorType::Project(Box::new(e))))?;
+    project.ok_or_else(|| error(ErrorType::NotScanned))?.related.ok_or_else(|| error(ErrorType::Unfinished))
+}
@@ -0,0 +1,14 @@
+//! Act on what a `scan' found: pick the copy of each group to keep with a `KeepPolicy', plan what to do with the
+//! others, then carry the plan out.
+
+pub use crate::{
+    actions::{Action, DedupOptions, Outcome, Permit, Plan, PlanDiff, Step},
+    policy::{KeepPolicy, Resolver},
+};
+use crate::relate::RelatedFiles;
+
+/// Plan `action' for every copy in `related' but the one `keep' keeps in each group.  Only fails for a
+/// `KeepPolicy::RegexPriority' with a pattern that isn't valid.
+pub fn plan(related: &RelatedFiles, keep: KeepPolicy, action: Action) -> Result<Plan, regex::Error> {
+    Ok(Plan::new(related, &Resolver::new(keep)?, action))
+}
@@ -0,0 +1,117 @@
+//! Contents the user never wants flagged as duplicates, such as license files or fonts shipped with many programs.
+//! The list is shared by every project and kept as a JSON file in the configuration directory.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+use serde::{Serialize, Deserialize};
+use crate::{
+    persist,
+    relate::HashAlgo,
+};
+
+const DENYLIST_FILE: &str = "denylist.json";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Format(serde_json::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(serde_json::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed denylist: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// Contents that are never flagged.  A hash only means something together with the algorithm that made it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub algo: HashAlgo,
+    pub hash: String,
+    /// A reminder of what the contents are, such as the name of one of the files.
+    #[serde(default)]
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Denylist {
+    /// In the order they were added.
+    pub entries: Vec<Entry>,
+}
+
+impl Denylist {
+    /// Where the denylist lives under the configuration directory `conf_dir'.
+    pub fn file(conf_dir: &Path) -> PathBuf {
+        conf_dir.join(DENYLIST_FILE)
+    }
+
+    /// Read the denylist under `conf_dir'.  It is empty until something is added.
+    pub fn load(conf_dir: &Path) -> Result<Self, Error> {
+        let file = Self::file(conf_dir);
+        if !file.exists() && !persist::backup_path(&file).exists() {
+            return Ok(Self::default());
+        }
+        persist::read_recovering(
+            &file,
+            |contents| serde_json::from_slice(contents).map_err(format_error(&file)),
+            |e| io_error(&file)(e),
+        )
+    }
+
+    pub fn save(&self, conf_dir: &Path) -> Result<(), Error> {
+        let file = Self::file(conf_dir);
+        let contents = serde_json::to_vec_pretty(self).map_err(format_error(&file))?;
+        persist::write_atomic(&file, &contents).map_err(io_error(&file))
+    }
+
+    pub fn contains(&self, algo: HashAlgo, hash: &str) -> bool {
+        self.entries.iter().any(|entry| entry.algo == algo && entry.hash.eq_ignore_ascii_case(hash))
+    }
+
+    /// Never flag contents with `hash' again.  Returns whether it wasn't already on the list.
+    pub fn add(&mut self, algo: HashAlgo, hash: &str, note: String) -> bool {
+        if self.contains(algo, hash) {
+            return false;
+        }
+        self.entries.push(Entry { algo, hash: hash.to_ascii_lowercase(), note });
+        true
+    }
+
+    /// Flag contents with `hash' again.  Returns whether it was on the list.
+    pub fn remove(&mut self, algo: HashAlgo, hash: &str) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.algo != algo || !entry.hash.eq_ignore_ascii_case(hash));
+        self.entries.len() != len
+    }
+}
@@ -0,0 +1,160 @@
+//! Estimate how much a full scan would reclaim by hashing a random sample of the files that could be duplicates.
+//! Only files of the same size can share contents, so the walk is split into groups by size, and a random
+//! selection of those groups is hashed completely.  The share of their potential waste that turns out to be real
+//! is then extended to every group.
+
+use std::{
+    collections::HashMap,
+    sync::{atomic::{self, AtomicUsize}, Mutex, PoisonError},
+    thread, time,
+};
+use itertools::Itertools;
+use crate::relate::{self, FileInfo, HashAlgo, WalkInfo};
+
+/// Configure the sampling.
+#[derive(Debug, Clone)]
+pub struct EstimateConf {
+    /// How many groups of same-sized files to hash at most.  Every group is hashed when there are fewer,
+    /// and the estimate is then exact.
+    pub groups: usize,
+    /// Max number of threads hashing at once.  `0` will be changed to 1.
+    pub max_threads: u16,
+    pub hash_algo: HashAlgo,
+    /// Stop starting new groups after this.  The groups hashed so far still make an estimate, only a rougher one.
+    pub deadline: Option<time::Instant>,
+}
+
+impl Default for EstimateConf {
+    /// Sample two hundred groups on every available core with XXH128, since the hashes are thrown away.
+    fn default() -> Self {
+        Self {
+            groups: 200,
+            max_threads: relate::available_threads(),
+            hash_algo: HashAlgo::Xxh128,
+            deadline: None,
+        }
+    }
+}
+
+/// What a sample suggests a full scan would find.
+#[derive(Debug, Default)]
+pub struct Estimate {
+    /// Groups of two or more non-empty files sharing a size, the only files that could be duplicates.
+    pub candidate_groups: usize,
+    pub candidate_files: usize,
+    /// Bytes freed if every candidate turned out to be a copy, keeping one file of each size.
+    pub upper_bound: u64,
+    /// Groups actually hashed.
+    pub sampled_groups: usize,
+    /// Bytes read to hash them.
+    pub sampled_bytes: u64,
+    /// Bytes found to be reclaimable in the sampled groups.
+    pub found: u64,
+    /// The estimated bytes a full scan would free, judging by file sizes.
+    pub reclaimable: u64,
+    /// Roughly how far off `reclaimable' may be, as the half width of a 95% confidence interval.
+    /// Zero once every group was sampled.
+    pub margin: u64,
+    pub errors: Vec<relate::Error>,
+}
+
+impl Estimate {
+    /// Whether every candidate was hashed, so `reclaimable' is what a full scan would find.
+    pub fn exact(&self) -> bool {
+        self.sampled_groups == self.candidate_groups
+    }
+}
+
+/// Bytes that could be freed in a group of same-sized files, if they were all copies.
+fn potential(group: &[&FileInfo]) -> u64 {
+    group[0].size * (group.len() as u64 - 1)
+}
+
+/// Bytes that are actually freed by keeping a single copy of each of `group''s contents.
+/// Files that can't be read are left out, and their errors added to `errors'.
+fn wasted(group: &[&FileInfo], algo: HashAlgo, errors: &Mutex<Vec<relate::Error>>) -> u64 {
+    let mut by_hash = HashMap::<String, u64>::new();
+    for info in group {
+        match relate::hash_from_file_info(info, algo) {
+            Ok(file) => *by_hash.entry(file.hash).or_default() += 1,
+            Err(e) => errors.lock().unwrap_or_else(PoisonError::into_inner).push(e),
+        }
+    }
+    by_hash.values().map(|n| group[0].size * (n - 1)).sum()
+}
+
+/// Estimate what relating `walk' would reclaim, by hashing at most `conf.groups' of its groups of same-sized files.
+pub fn estimate(walk: &WalkInfo, conf: &EstimateConf) -> Estimate {
+    let candidates = walk.files
+        .iter()
+        .filter(|info| info.size > 0)
+        .into_group_map_by(|info| info.size)
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect::<Vec<Vec<&FileInfo>>>();
+    let mut estimate = Estimate {
+        candidate_groups: candidates.len(),
+        candidate_files: candidates.iter().map(Vec::len).sum(),
+        upper_bound: candidates.iter().map(|group| potential(group)).sum(),
+        ..Default::default()
+    };
+    // Hashed in random order, so stopping at the deadline still leaves a random sample.
+    let sample = rand::seq::index::sample(&mut rand::rng(), candidates.len(), conf.groups.min(candidates.len()))
+        .into_iter()
+        .map(|i| &candidates[i])
+        .collect::<Vec<&Vec<&FileInfo>>>();
+    let next = AtomicUsize::new(0);
+    let (results, errors) = (Mutex::new(Vec::new()), Mutex::new(Vec::new()));
+    thread::scope(|s| {
+        for _ in 0..conf.max_threads.max(1) {
+            s.spawn(|| loop {
+                let i = next.fetch_add(1, atomic::Ordering::Relaxed);
+                if i >= sample.len() || conf.deadline.is_some_and(|deadline| time::Instant::now() >= deadline) {
+                    break;
+                }
+                let group = sample[i];
+                let found = wasted(group, conf.hash_algo, &errors);
+                let bytes = group.iter().map(|info| info.size).sum::<u64>();
+                results.lock().unwrap_or_else(PoisonError::into_inner).push((potential(group), found, bytes));
+            });
+        }
+    });
+    let results = results.into_inner().unwrap_or_else(PoisonError::into_inner);
+    estimate.errors = errors.into_inner().unwrap_or_else(PoisonError::into_inner);
+    estimate.sampled_groups = results.len();
+    estimate.sampled_bytes = results.iter().map(|(_, _, bytes)| bytes).sum();
+    estimate.found = results.iter().map(|(_, found, _)| found).sum();
+    let results = results.into_iter().map(|(potential, found, _)| (potential, found)).collect::<Vec<(u64, u64)>>();
+    let (reclaimable, margin) = extrapolate(&results, estimate.candidate_groups, estimate.upper_bound);
+    // However rough the estimate, it can't be below what was found nor above what is possible.
+    estimate.reclaimable = (reclaimable.round() as u64).clamp(estimate.found, estimate.upper_bound);
+    estimate.margin = margin.round() as u64;
+    estimate
+}
+
+/// Extend the `(potential, found)' bytes of a sample of `groups' groups to all of them, whose potential adds up to
+/// `upper_bound'.  Returns the estimated total and its margin, using a ratio estimator: large groups weigh more.
+fn extrapolate(sample: &[(u64, u64)], groups: usize, upper_bound: u64) -> (f64, f64) {
+    if sample.is_empty() {
+        // Nothing to go on: anything up to everything could be reclaimable.
+        return (0.0, upper_bound as f64);
+    }
+    let n = sample.len() as f64;
+    // Every candidate group has some potential, so this is never a division by zero.
+    let potential = sample.iter().map(|(potential, _)| *potential as f64).sum::<f64>();
+    let ratio = sample.iter().map(|(_, found)| *found as f64).sum::<f64>() / potential;
+    let reclaimable = ratio * upper_bound as f64;
+    if sample.len() >= groups {
+        return (reclaimable, 0.0);
+    }
+    if sample.len() == 1 {
+        return (reclaimable, upper_bound as f64);
+    }
+    let residuals = sample
+        .iter()
+        .map(|(potential, found)| (*found as f64 - ratio * *potential as f64).powi(2))
+        .sum::<f64>() / (n - 1.0);
+    let groups = groups as f64;
+    let standard_error = groups * ((1.0 - n / groups) * residuals / n).sqrt();
+    (reclaimable, 1.96 * standard_error)
+}
@@ -0,0 +1,243 @@
+//! Whole folders that are copies of each other, so a copied folder can be removed at once rather than file by file.
+//! Each folder is hashed from the names and hashes of what it holds, its folders included, so two folders hash the
+//! same when they hold the same names with the same contents all the way down.  Folders holding mostly the same,
+//! such as two snapshots of a backup, are found apart by how many of their contents they share.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+use crate::{
+    paths,
+    policy::{Resolution, Resolver},
+    relate::{FileInfo, HashAlgo, Hasher, RelatedFiles},
+};
+
+/// Folders holding the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FolderGroup {
+    /// The folders' own hash, from their names and contents, unlike that of any file.
+    pub hash: String,
+    /// Bytes in each folder.
+    pub size: u64,
+    /// Files in each folder, counting those in its folders.
+    pub files: usize,
+    /// Sorted by path.  `FileInfo::size' is that of the folder itself, not of what it holds.
+    pub folders: Vec<FileInfo>,
+}
+
+impl FolderGroup {
+    /// Bytes freed by removing every folder but one.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.folders.len() as u64 - 1)
+    }
+
+    /// Split the folders into the one `resolver' keeps and the copies it makes redundant.  Folders in the reference
+    /// folder are neither.
+    pub fn resolve(&self, resolver: &Resolver) -> Option<Resolution<'_>> {
+        let keep = resolver.survivor(&self.folders)?;
+        let remove = self.folders.iter().filter(|folder| *folder != keep && !resolver.is_protected(folder)).collect();
+        Some(Resolution { keep, remove })
+    }
+}
+
+/// How much of their contents two folders share, in percent, for `similar_folders' to find them.
+pub const MIN_SIMILARITY: u8 = 80;
+
+/// Two folders holding mostly the same.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimilarPair {
+    /// The first of the two by path.
+    pub first: PathBuf,
+    pub second: PathBuf,
+    /// Contents found in both.
+    pub shared: usize,
+    /// Contents found in either.
+    pub total: usize,
+}
+
+impl SimilarPair {
+    /// The share of the contents in either folder found in both, in percent, rounded down.
+    pub fn similarity(&self) -> u8 {
+        (self.shared * 100 / self.total.max(1)) as u8
+    }
+}
+
+/// What a folder holds, once every file in it is known.
+struct Summary {
+    hash: String,
+    size: u64,
+    files: usize,
+}
+
+/// The folders below `roots', and `roots' themselves, holding the same as another, most wasteful first.
+///
+/// Only folders whose every file `related' knows are hashed, so a folder holding anything the scan left out, such
+/// as files filtered out or that couldn't be read, never passes for a copy.  The folders are read again for that.
+/// Where folders below copies are copies too, only the copies holding them are listed, and empty folders aren't.
+pub fn duplicate_folders(related: &RelatedFiles, roots: &[PathBuf]) -> Vec<FolderGroup> {
+    let known = related.files
+        .iter()
+        .flat_map(|(hash, group)| group.iter().filter(|fi| !fi.in_archive()).map(move |fi| (&*fi.name, (hash.as_str(), fi.size))))
+        .collect::<HashMap<&Path, (&str, u64)>>();
+    let holding = known.keys().flat_map(|path| path.ancestors().skip(1)).collect::<HashSet<&Path>>();
+    let mut summarizer = Summarizer { algo: related.algo, known, holding, summaries: HashMap::new() };
+    for root in paths::distinct_roots(roots) {
+        summarizer.summarize(&root);
+    }
+
+    let mut by_hash = HashMap::<&str, Vec<&Path>>::new();
+    for (folder, summary) in summarizer.summaries.iter().filter(|(_, summary)| summary.size > 0) {
+        by_hash.entry(&summary.hash).or_default().push(folder);
+    }
+    by_hash.retain(|_, folders| folders.len() > 1);
+    // Copies inside copies go with them.  Two copies in one folder don't, since removing that folder's copies
+    // would leave both.
+    let within_copies = |folders: &Vec<&Path>| {
+        let Some(parents) = folders.iter().map(|folder| folder.parent()).collect::<Option<Vec<&Path>>>() else { return false };
+        let distinct = parents.iter().collect::<HashSet<_>>().len() == parents.len();
+        let hashes = parents.iter().map(|parent| summarizer.summaries.get(*parent).map(|summary| summary.hash.as_str())).collect::<HashSet<_>>();
+        distinct && hashes.len() == 1 && hashes.into_iter().next().flatten().is_some_and(|hash| by_hash.contains_key(hash))
+    };
+    let mut groups = by_hash
+        .iter()
+        .filter(|(_, folders)| !within_copies(folders))
+        .map(|(hash, folders)| {
+            let summary = &summarizer.summaries[folders[0]];
+            let mut infos = folders.iter().filter_map(|folder| FileInfo::from_path(folder, false).ok()).collect::<Vec<FileInfo>>();
+            infos.sort_by(|a, b| a.name.cmp(&b.name));
+            FolderGroup { hash: hash.to_string(), size: summary.size, files: summary.files, folders: infos }
+        })
+        .filter(|group| group.folders.len() > 1)
+        .collect::<Vec<FolderGroup>>();
+    groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.folders[0].name.cmp(&b.folders[0].name)));
+    groups
+}
+
+struct Summarizer<'a> {
+    algo: HashAlgo,
+    /// The hash and size of every file scanned.
+    known: HashMap<&'a Path, (&'a str, u64)>,
+    /// The folders holding a file scanned, at any depth.
+    holding: HashSet<&'a Path>,
+    summaries: HashMap<PathBuf, Summary>,
+}
+
+impl Summarizer<'_> {
+    /// Hash `folder', after every folder in it, and keep the summaries of those whose files are all known.
+    fn summarize(&mut self, folder: &Path) -> Option<String> {
+        // Saves reading the whole of a folder the scan left out, such as one excluded by a filter.
+        if !self.holding.contains(folder) && holds_files(folder) {
+            return None;
+        }
+        let entries = fs::read_dir(folder).ok()?;
+        let (mut complete, mut size, mut files) = (true, 0, 0);
+        let mut children = Vec::new();
+        for entry in entries {
+            let Ok(entry) = entry else {
+                complete = false;
+                continue;
+            };
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|kind| kind.is_dir()) {
+                match self.summarize(&path) {
+                    Some(hash) => {
+                        let summary = &self.summaries[&path];
+                        size += summary.size;
+                        files += summary.files;
+                        children.push((entry.file_name(), b'd', hash));
+                    },
+                    None => complete = false,
+                }
+            } else if let Some((hash, file_size)) = self.known.get(path.as_path()) {
+                size += file_size;
+                files += 1;
+                children.push((entry.file_name(), b'f', hash.to_string()));
+            } else {
+                complete = false;
+            }
+        }
+        if !complete {
+            return None;
+        }
+        children.sort();
+        let mut hasher = Hasher::new(self.algo);
+        for (name, kind, hash) in &children {
+            hasher.update(&[*kind]);
+            hasher.update(name.as_encoded_bytes());
+            hasher.update(b"\0");
+            hasher.update(hash.as_bytes());
+            hasher.update(b"\n");
+        }
+        let hash = hasher.finish();
+        self.summaries.insert(folder.to_path_buf(), Summary { hash: hash.clone(), size, files });
+        Some(hash)
+    }
+}
+
+/// Whether anything but folders is below `folder'.
+fn holds_files(folder: &Path) -> bool {
+    walkdir::WalkDir::new(folder).into_iter().any(|entry| entry.map_or(true, |entry| !entry.file_type().is_dir()))
+}
+
+/// The pairs of folders below `roots' sharing at least `min_similarity' percent of the contents either holds, counting
+/// everything below them, most similar first.  A folder needs two different contents to be compared at all, and isn't
+/// compared with those holding it or held by it.  Where the folders of a pair's folders are similar too, only the pair
+/// holding them is listed.  Unlike `duplicate_folders', names don't matter, and neither do files the scan left out.
+pub fn similar_folders(related: &RelatedFiles, roots: &[PathBuf], min_similarity: u8) -> Vec<SimilarPair> {
+    let roots = paths::distinct_roots(roots);
+    let mut contents = HashMap::<&Path, HashSet<&str>>::new();
+    for (hash, group) in &related.files {
+        for fi in group.iter().filter(|fi| !fi.in_archive()) {
+            let Some(root) = roots.iter().find(|root| fi.name.starts_with(root)) else { continue };
+            for folder in fi.name.ancestors().skip(1).take_while(|folder| folder.starts_with(root)) {
+                contents.entry(folder).or_default().insert(hash);
+            }
+        }
+    }
+    contents.retain(|_, hashes| hashes.len() > 1);
+
+    let mut holders = HashMap::<&str, Vec<&Path>>::new();
+    for (folder, hashes) in &contents {
+        for hash in hashes {
+            holders.entry(hash).or_default().push(folder);
+        }
+    }
+    let mut shared = HashMap::<(&Path, &Path), usize>::new();
+    for folders in holders.values_mut() {
+        folders.sort();
+        for (i, first) in folders.iter().enumerate() {
+            for second in &folders[i + 1..] {
+                if !second.starts_with(first) {
+                    *shared.entry((*first, *second)).or_default() += 1;
+                }
+            }
+        }
+    }
+    let pairs = shared
+        .into_iter()
+        .map(|((first, second), shared)| {
+            let total = contents[first].len() + contents[second].len() - shared;
+            SimilarPair { first: first.to_path_buf(), second: second.to_path_buf(), shared, total }
+        })
+        .filter(|pair| pair.similarity() >= min_similarity)
+        .collect::<Vec<SimilarPair>>();
+    // Folders in a pair's folders are listed with it.  So is a folder paired with one in the other's counterpart, as
+    // `2023' with `2024/photos' when `2023/photos' is there too: the pair lined up better is listed instead.
+    let related = |a: &Path, b: &Path| a.starts_with(b) || b.starts_with(a);
+    let skew = |pair: &SimilarPair| pair.first.components().count().abs_diff(pair.second.components().count());
+    let overshadowed = |pair: &SimilarPair| {
+        pairs.iter().filter(|other| *other != pair).any(|other| {
+            let inside = pair.first.starts_with(&other.first) && pair.second.starts_with(&other.second)
+                && pair.first != other.first && pair.second != other.second;
+            let lined_up = related(&pair.first, &other.first) && related(&pair.second, &other.second) && skew(other) < skew(pair);
+            inside || lined_up
+        })
+    };
+    let mut pairs = pairs.iter().filter(|pair| !overshadowed(pair)).cloned().collect::<Vec<SimilarPair>>();
+    pairs.sort_by(|a, b| {
+        b.similarity().cmp(&a.similarity()).then_with(|| b.shared.cmp(&a.shared)).then_with(|| a.first.cmp(&b.first)).then_with(|| a.second.cmp(&b.second))
+    });
+    pairs
+}
@@ -0,0 +1,99 @@
+//! Present numbers, byte counts and times to the user the way their locale writes them.
+//! Everything shown to people goes through here, while machine readable output keeps plain numbers.
+
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Local};
+use pure_rust_locales::{locale_match, Locale};
+use crate::usage::ScanUsage;
+
+const UNITS: [&str; 7] = ["bytes", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// Formatting conventions for one locale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Localized {
+    locale: Locale,
+}
+
+impl Default for Localized {
+    /// The user's locale.
+    fn default() -> Self {
+        Self::system()
+    }
+}
+
+impl Localized {
+    /// Use `locale', e.g. `de_DE' or `de-DE'.  Unknown locales fall back to POSIX conventions.
+    pub fn new(locale: &str) -> Self {
+        // BCP 47 tags like `de-DE' name the same locales as POSIX's `de_DE.UTF-8'.
+        let name = locale.split(['.', '@']).next().unwrap_or_default().replace('-', "_");
+        Self { locale: Locale::try_from(name.as_str()).unwrap_or(Locale::POSIX) }
+    }
+
+    /// The locale the operating system reports for the user.
+    pub fn system() -> Self {
+        Self::new(&sys_locale::get_locale().unwrap_or_default())
+    }
+
+    fn thousands_separator(&self) -> &'static str {
+        locale_match!(self.locale => LC_NUMERIC::THOUSANDS_SEP)
+    }
+
+    fn decimal_point(&self) -> &'static str {
+        locale_match!(self.locale => LC_NUMERIC::DECIMAL_POINT)
+    }
+
+    /// `n' with its digits grouped in threes.
+    pub fn number(&self, n: u64) -> String {
+        let digits = n.to_string();
+        let mut out = String::new();
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                out.push_str(self.thousands_separator());
+            }
+            out.push(c);
+        }
+        out
+    }
+
+    /// `bytes' in the largest binary unit that keeps the figure at one or more, e.g. `1.5 MiB'.
+    pub fn size(&self, bytes: u64) -> String {
+        let unit = (0..UNITS.len()).rev().find(|unit| bytes >> (10 * unit) > 0).unwrap_or(0);
+        if unit == 0 {
+            return format!("{:} {:}", self.number(bytes), UNITS[0]);
+        }
+        let value = bytes as f64 / (1u64 << (10 * unit)) as f64;
+        let tenths = (value * 10.0).round() as u64;
+        format!("{:}{:}{:} {:}", self.number(tenths / 10), self.decimal_point(), tenths % 10, UNITS[unit])
+    }
+
+    /// `duration' in hours, minutes and seconds, e.g. `1 h 2 min' or `3.5 s', to the precision that matters.
+    pub fn duration(&self, duration: Duration) -> String {
+        let secs = duration.as_secs();
+        match secs {
+            0..60 => {
+                let tenths = duration.as_millis().div_ceil(100) as u64;
+                format!("{:}{:}{:} s", tenths / 10, self.decimal_point(), tenths % 10)
+            }
+            60..3600 => format!("{:} min {:} s", secs / 60, secs % 60),
+            _ => format!("{:} h {:} min", self.number(secs / 3600), secs % 3600 / 60),
+        }
+    }
+
+    /// What a scan cost, in a sentence, e.g. `Took 2 min 5 s (CPU 7 min 1 s), read 3.2 GiB, peak memory 80.0 MiB'.
+    pub fn usage(&self, usage: &ScanUsage) -> String {
+        let mut out = format!("Took {:}", self.duration(usage.wall_time));
+        if let Some(cpu_time) = usage.cpu_time {
+            out.push_str(&format!(" (CPU {:})", self.duration(cpu_time)));
+        }
+        out.push_str(&format!(", read {:}", self.size(usage.bytes_read)));
+        if let Some(peak_memory) = usage.peak_memory {
+            out.push_str(&format!(", peak memory {:}", self.size(peak_memory)));
+        }
+        out
+    }
+
+    /// `time' as a local date and time.
+    pub fn time(&self, time: SystemTime) -> String {
+        DateTime::<Local>::from(time).format_localized("%x %X", self.locale).to_string()
+    }
+}
@@ -0,0 +1,107 @@
+//! Refuse to start a scan whose scope overlaps one already in progress, so no file is hashed twice at once.
+//! Scans in this process are tracked in memory, scans in other processes are found through project locks.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use crate::{lock, paths, project::{self, Project}};
+
+/// Roots of the scans currently running in this process.
+static RUNNING: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Why a scan may not start.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Conflict {
+    /// A scan of `running' is in progress in this process.
+    Running { requested: PathBuf, running: PathBuf },
+    /// The project for `locked' is open in process `pid'.
+    Locked { requested: PathBuf, locked: PathBuf, pid: u32 },
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (requested, other) = match self {
+            Conflict::Running { requested, running } => (requested, running),
+            Conflict::Locked { requested, locked, .. } => (requested, locked),
+        };
+        let relation = if requested.starts_with(other) {
+            "is inside"
+        } else if other.starts_with(requested) {
+            "contains"
+        } else {
+            "overlaps"
+        };
+        match self {
+            Conflict::Running { .. } => write!(f, "'{:}' {:} '{:}', which is already being scanned", requested.display(), relation, other.display()),
+            Conflict::Locked { pid, .. } => write!(f, "'{:}' {:} '{:}', whose project is in use by process {:}", requested.display(), relation, other.display(), pid),
+        }
+    }
+}
+
+/// Whether scanning `a' and `b' would visit some of the same files.
+pub fn overlaps(a: &Path, b: &Path) -> bool {
+    paths::starts_with(a, b) || paths::starts_with(b, a)
+}
+
+/// Registration of a running scan.  The scope is released when this is dropped.
+#[derive(Debug)]
+pub struct ScanGuard {
+    root: PathBuf,
+}
+
+impl ScanGuard {
+    /// Register a scan of `root', unless it overlaps a scan already running in this process.
+    pub fn claim(root: &Path) -> Result<Self, Conflict> {
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(other) = running.iter().find(|other| overlaps(root, other)) {
+            return Err(Conflict::Running { requested: root.to_path_buf(), running: other.clone() });
+        }
+        running.push(root.to_path_buf());
+        Ok(Self { root: root.to_path_buf() })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for ScanGuard {
+    fn drop(&mut self) {
+        let mut running = RUNNING.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(i) = running.iter().position(|other| *other == self.root) {
+            running.swap_remove(i);
+        }
+    }
+}
+
+/// Refuse a scan of `root' if another process holds the lock of an overlapping project under `data_dir'.
+/// The project for `root' itself is not considered, its own lock already keeps it to one process.
+pub fn check_projects(data_dir: &Path, root: &Path) -> Result<(), Conflict> {
+    check_others(data_dir, &Project::new(root.to_path_buf()).id(), root)
+}
+
+/// Register a scan of every root of `project', as `claim' does, once `check_projects' finds none of them in use.
+/// Nothing is registered unless all of them are free.
+pub fn claim_project(data_dir: &Path, project: &Project) -> Result<Vec<ScanGuard>, Conflict> {
+    let roots = project.roots();
+    for root in &roots {
+        check_others(data_dir, &project.id(), root)?;
+    }
+    roots.iter().map(|root| ScanGuard::claim(root)).collect()
+}
+
+fn check_others(data_dir: &Path, own: &str, root: &Path) -> Result<(), Conflict> {
+    let (projects, _) = project::list(data_dir).unwrap_or_default();
+    for summary in projects {
+        let other = Project::new(summary.path.clone());
+        if other.id() == own || !overlaps(root, &summary.path) {
+            continue;
+        }
+        if let Some(pid) = lock::live_holder(&other.lock_path(data_dir)) {
+            return Err(Conflict::Locked { requested: root.to_path_buf(), locked: summary.path, pid });
+        }
+    }
+    Ok(())
+}
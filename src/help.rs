@@ -0,0 +1,244 @@
+//! Explanations of the settings and choices, written once and shown everywhere they're needed: by
+//! `fdedup_cli --help', as tooltips in the app, and as help topics that both can search.  The texts are messages of
+//! the Fluent catalogs, so the constants here are their ids, and `text' renders one in the user's language.
+
+use crate::{
+    actions::Action,
+    i18n,
+    policy::KeepPolicy,
+    relate::SymlinkPolicy,
+};
+
+/// One setting or choice explained within a topic, by the ids of its name and text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Term {
+    pub name: &'static str,
+    pub text: &'static str,
+}
+
+/// A page of help, by the ids of its title and introduction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Topic {
+    pub title: &'static str,
+    pub intro: &'static str,
+    pub terms: &'static [Term],
+}
+
+impl Topic {
+    /// Whether `word', in lower case, appears anywhere in the topic.
+    fn mentions(&self, word: &str) -> bool {
+        let ids = [self.title, self.intro].into_iter().chain(self.terms.iter().flat_map(|term| [term.name, term.text]));
+        ids.map(text).any(|text| text.to_lowercase().contains(word))
+    }
+}
+
+/// Help text `id', e.g. `INCLUDE' or a topic's title, in the user's language.
+pub fn text(id: &str) -> String {
+    i18n::format(id, &[])
+}
+
+pub const INCLUDE: &str = "help-include";
+pub const EXCLUDE: &str = "help-exclude";
+pub const MIN_SIZE: &str = "help-min-size";
+pub const SKIP_EMPTY: &str = "help-skip-empty";
+pub const MAX_SIZE: &str = "help-max-size";
+pub const SKIP_UNIQUE_SIZES: &str = "help-skip-unique-sizes";
+pub const ARCHIVES: &str = "help-archives";
+pub const SYMLINKS: &str = "help-symlinks";
+pub const SYMLINKS_SKIP: &str = "help-symlinks-skip";
+pub const SYMLINKS_FOLLOW: &str = "help-symlinks-follow";
+pub const SYMLINKS_REPORT: &str = "help-symlinks-report";
+pub const PROFILE: &str = "help-profile";
+
+pub const HASH_ALGO: &str = "help-hash-algo";
+pub const THREADS: &str = "help-threads";
+pub const PIPELINE: &str = "help-pipeline";
+pub const MMAP_THRESHOLD: &str = "help-mmap-threshold";
+pub const NO_MMAP: &str = "help-no-mmap";
+pub const CHANGE_RETRIES: &str = "help-change-retries";
+pub const RETRY_LOCKED: &str = "help-retry-locked";
+pub const IO_LIMIT: &str = "help-io-limit";
+pub const LOW_PRIORITY: &str = "help-low-priority";
+pub const ROOTS: &str = "help-roots";
+pub const REFERENCE: &str = "help-reference";
+pub const RESUME: &str = "help-resume";
+pub const NO_CACHE: &str = "help-no-cache";
+pub const WATCH: &str = "help-watch";
+pub const ERRORS: &str = "help-errors";
+pub const MAX_DURATION: &str = "help-max-duration";
+
+pub const KEEP: &str = "help-keep";
+pub const KEEP_OLDEST: &str = "help-keep-oldest";
+pub const KEEP_NEWEST: &str = "help-keep-newest";
+pub const KEEP_SHORTEST: &str = "help-keep-shortest";
+pub const KEEP_SHALLOWEST: &str = "help-keep-shallowest";
+pub const KEEP_ALPHABETICAL: &str = "help-keep-alphabetical";
+pub const KEEP_CAPTURE_NAME: &str = "help-keep-capture-name";
+pub const KEEP_DATED_FOLDER: &str = "help-keep-dated-folder";
+pub const KEEP_REGEX: &str = "help-keep-regex";
+
+pub const ACTION: &str = "help-action";
+pub const TRASH: &str = "help-trash";
+pub const LINK: &str = "help-link";
+pub const REFLINK: &str = "help-reflink";
+pub const DELETE: &str = "help-delete";
+pub const SYMLINK: &str = "help-symlink";
+pub const CROSS_DEVICE: &str = "help-cross-device";
+pub const VERIFY: &str = "help-verify";
+pub const AGAINST_ARCHIVES: &str = "help-against-archives";
+pub const SELECTED: &str = "help-selected";
+pub const EMPTY_FILES: &str = "help-empty-files";
+pub const DUPLICATE_FOLDERS: &str = "help-duplicate-folders";
+pub const RULES: &str = "help-rules";
+pub const DRY_RUN: &str = "help-dry-run";
+pub const RECLAIMABLE: &str = "help-reclaimable";
+
+pub const IMAGES: &str = "help-images";
+pub const AUDIO: &str = "help-audio";
+pub const SAME_ARCHIVES: &str = "help-same-archives";
+pub const SIMILAR_FOLDERS: &str = "help-similar-folders";
+pub const MIN_SIMILARITY: &str = "help-min-similarity";
+pub const NAME_COLLISIONS: &str = "help-name-collisions";
+pub const MAX_DISTANCE: &str = "help-max-distance";
+
+pub const CONFIG: &str = "help-config";
+pub const AUDIT: &str = "help-audit";
+pub const NEVER_FLAG: &str = "help-never-flag";
+pub const UNDO: &str = "help-undo";
+
+pub const TOPICS: &[Topic] = &[
+    Topic {
+        title: "help-scanning",
+        intro: "help-scanning-intro",
+        terms: &[
+            Term { name: "help-hash-algo-name", text: HASH_ALGO },
+            Term { name: "help-threads-name", text: THREADS },
+            Term { name: "help-pipeline-name", text: PIPELINE },
+            Term { name: "help-mmap-threshold-name", text: MMAP_THRESHOLD },
+            Term { name: "help-no-mmap-name", text: NO_MMAP },
+            Term { name: "help-change-retries-name", text: CHANGE_RETRIES },
+            Term { name: "help-retry-locked-name", text: RETRY_LOCKED },
+            Term { name: "help-io-limit-name", text: IO_LIMIT },
+            Term { name: "help-low-priority-name", text: LOW_PRIORITY },
+            Term { name: "help-roots-name", text: ROOTS },
+            Term { name: "help-reference-name", text: REFERENCE },
+            Term { name: "help-resume-name", text: RESUME },
+            Term { name: "help-no-cache-name", text: NO_CACHE },
+            Term { name: "help-max-duration-name", text: MAX_DURATION },
+            Term { name: "help-watch-name", text: WATCH },
+            Term { name: "help-errors-name", text: ERRORS },
+        ],
+    },
+    Topic {
+        title: "help-filters",
+        intro: "help-filters-intro",
+        terms: &[
+            Term { name: "help-include-name", text: INCLUDE },
+            Term { name: "help-exclude-name", text: EXCLUDE },
+            Term { name: "help-min-size-name", text: MIN_SIZE },
+            Term { name: "help-max-size-name", text: MAX_SIZE },
+            Term { name: "help-skip-unique-sizes-name", text: SKIP_UNIQUE_SIZES },
+            Term { name: "help-archives-name", text: ARCHIVES },
+            Term { name: "help-symlinks-name", text: SYMLINKS },
+            Term { name: "help-symlinks-skip-name", text: SYMLINKS_SKIP },
+            Term { name: "help-symlinks-follow-name", text: SYMLINKS_FOLLOW },
+            Term { name: "help-symlinks-report-name", text: SYMLINKS_REPORT },
+            Term { name: "help-profile-name", text: PROFILE },
+        ],
+    },
+    Topic {
+        title: "help-keep-policies",
+        intro: "help-keep-policies-intro",
+        terms: &[
+            Term { name: "help-keep-oldest-name", text: KEEP_OLDEST },
+            Term { name: "help-keep-newest-name", text: KEEP_NEWEST },
+            Term { name: "help-keep-shortest-name", text: KEEP_SHORTEST },
+            Term { name: "help-keep-shallowest-name", text: KEEP_SHALLOWEST },
+            Term { name: "help-keep-alphabetical-name", text: KEEP_ALPHABETICAL },
+            Term { name: "help-keep-capture-name-name", text: KEEP_CAPTURE_NAME },
+            Term { name: "help-keep-dated-folder-name", text: KEEP_DATED_FOLDER },
+            Term { name: "help-keep-regex-name", text: KEEP_REGEX },
+        ],
+    },
+    Topic {
+        title: "help-actions",
+        intro: "help-actions-intro",
+        terms: &[
+            Term { name: "help-trash-name", text: TRASH },
+            Term { name: "help-link-name", text: LINK },
+            Term { name: "help-reflink-name", text: REFLINK },
+            Term { name: "help-delete-name", text: DELETE },
+            Term { name: "help-symlink-name", text: SYMLINK },
+            Term { name: "help-cross-device-name", text: CROSS_DEVICE },
+            Term { name: "help-verify-name", text: VERIFY },
+            Term { name: "help-against-archives-name", text: AGAINST_ARCHIVES },
+            Term { name: "help-empty-files-name", text: EMPTY_FILES },
+            Term { name: "help-duplicate-folders-name", text: DUPLICATE_FOLDERS },
+            Term { name: "help-rules-name", text: RULES },
+            Term { name: "help-selected-name", text: SELECTED },
+            Term { name: "help-dry-run-name", text: DRY_RUN },
+            Term { name: "help-reclaimable-name", text: RECLAIMABLE },
+        ],
+    },
+    Topic {
+        title: "help-similar-files",
+        intro: "help-similar-files-intro",
+        terms: &[
+            Term { name: "help-images-name", text: IMAGES },
+            Term { name: "help-max-distance-name", text: MAX_DISTANCE },
+            Term { name: "help-audio-name", text: AUDIO },
+            Term { name: "help-same-archives-name", text: SAME_ARCHIVES },
+            Term { name: "help-similar-folders-name", text: SIMILAR_FOLDERS },
+            Term { name: "help-min-similarity-name", text: MIN_SIMILARITY },
+            Term { name: "help-name-collisions-name", text: NAME_COLLISIONS },
+        ],
+    },
+    Topic {
+        title: "help-safety",
+        intro: "help-safety-intro",
+        terms: &[
+            Term { name: "help-audit-name", text: AUDIT },
+            Term { name: "help-never-flag-name", text: NEVER_FLAG },
+            Term { name: "help-undo-name", text: UNDO },
+            Term { name: "help-reference-name", text: REFERENCE },
+        ],
+    },
+];
+
+/// The topics mentioning every word of `query', ignoring case, in the order of `TOPICS'.  All of them for an empty
+/// query.
+pub fn search(query: &str) -> Vec<&'static Topic> {
+    let words = query.split_whitespace().map(str::to_lowercase).collect::<Vec<String>>();
+    TOPICS.iter().filter(|topic| words.iter().all(|word| topic.mentions(word))).collect()
+}
+
+pub fn keep(policy: &KeepPolicy) -> &'static str {
+    match policy {
+        KeepPolicy::OldestCreated => KEEP_OLDEST,
+        KeepPolicy::NewestCreated => KEEP_NEWEST,
+        KeepPolicy::ShortestPath => KEEP_SHORTEST,
+        KeepPolicy::ShallowestDirectory => KEEP_SHALLOWEST,
+        KeepPolicy::Alphabetical => KEEP_ALPHABETICAL,
+        KeepPolicy::RegexPriority(_) => KEEP_REGEX,
+        KeepPolicy::CaptureDateName => KEEP_CAPTURE_NAME,
+        KeepPolicy::DatedFolder => KEEP_DATED_FOLDER,
+    }
+}
+
+pub fn action(action: Action) -> &'static str {
+    match action {
+        Action::Trash => TRASH,
+        Action::HardLink => LINK,
+        Action::Reflink => REFLINK,
+        Action::Symlink => SYMLINK,
+        Action::Delete => DELETE,
+    }
+}
+
+pub fn symlinks(policy: SymlinkPolicy) -> &'static str {
+    match policy {
+        SymlinkPolicy::Skip => SYMLINKS_SKIP,
+        SymlinkPolicy::Follow => SYMLINKS_FOLLOW,
+        SymlinkPolicy::Report => SYMLINKS_REPORT,
+    }
+}
@@ -0,0 +1,121 @@
+//! Translate what the applications say to the user.  Messages live in Fluent catalogs under `locales/', one per
+//! language, and are looked up by id, e.g. `tr!("scan-stopped")' or `tr!("files-removed", count = 3)'.  The
+//! language is the user's unless the settings name another, and can be changed while running; messages missing from
+//! a catalog come from the English one.
+//!
+//! Numbers, sizes and times are still formatted by `format::Localized' and handed to messages as text.  The help
+//! texts are messages too, named by the constants of `help'.
+
+use std::sync::{Arc, RwLock};
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+pub use fluent_bundle::FluentValue as Value;
+
+/// The language every message is written in, and that others fall back to.
+pub const FALLBACK: &str = "en";
+
+/// The catalogs built into the applications, by language.
+const CATALOGS: [(&str, &str); 1] = [
+    ("en", include_str!("../locales/en.ftl")),
+];
+
+/// The messages of one language.
+pub struct Catalog {
+    language: &'static str,
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+impl Catalog {
+    /// The catalog best matching `language', e.g. `de-AT' or `de_AT.UTF-8', which is English when there is none.
+    pub fn new(language: &str) -> Self {
+        let language = best_match(language);
+        let bundles = [language, FALLBACK]
+            .into_iter()
+            .take(if language == FALLBACK { 1 } else { 2 })
+            .map(|language| {
+                let source = CATALOGS.iter().find(|(name, _)| *name == language).map(|(_, source)| *source).unwrap_or_default();
+                let resource = FluentResource::try_new(source.to_owned()).unwrap_or_else(|(resource, _)| resource);
+                let mut bundle = FluentBundle::new_concurrent(vec![language.parse().expect("Catalogs are named by language")]);
+                // Isolation marks keep right-to-left text apart, but show up as junk on terminals.
+                bundle.set_use_isolating(false);
+                bundle.add_resource(resource).expect("Messages are only defined once");
+                bundle
+            })
+            .collect();
+        Catalog { language, bundles }
+    }
+
+    /// The language the messages are in.
+    pub fn language(&self) -> &'static str {
+        self.language
+    }
+
+    /// Whether there is a message `id'.
+    pub fn has(&self, id: &str) -> bool {
+        self.bundles.iter().any(|bundle| bundle.has_message(id))
+    }
+
+    /// Message `id' with `args' in place, or `id' itself if there is no such message.
+    pub fn format(&self, id: &str, args: &[(&str, Value)]) -> String {
+        let mut fluent_args = FluentArgs::new();
+        for (name, value) in args {
+            fluent_args.set(*name, value.clone());
+        }
+        for bundle in &self.bundles {
+            let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else { continue };
+            let mut errors = Vec::new();
+            return bundle.format_pattern(pattern, Some(&fluent_args), &mut errors).into_owned();
+        }
+        id.to_owned()
+    }
+}
+
+/// The languages with a catalog.
+pub fn languages() -> Vec<&'static str> {
+    CATALOGS.iter().map(|(language, _)| *language).collect()
+}
+
+/// The language of `CATALOGS' closest to `language', matching the language alone when there is no catalog for its
+/// region.
+fn best_match(language: &str) -> &'static str {
+    let name = language.split(['.', '@']).next().unwrap_or_default().replace('_', "-");
+    let Ok(wanted) = name.parse::<LanguageIdentifier>() else { return FALLBACK };
+    let exact = CATALOGS.iter().find(|(name, _)| name.parse::<LanguageIdentifier>().is_ok_and(|have| have == wanted));
+    let same_language = || CATALOGS.iter().find(|(name, _)| name.parse::<LanguageIdentifier>().is_ok_and(|have| have.language == wanted.language));
+    exact.or_else(same_language).map_or(FALLBACK, |(name, _)| *name)
+}
+
+/// The catalog messages come from, picked on first use unless `set_language' was called.
+static CURRENT: RwLock<Option<Arc<Catalog>>> = RwLock::new(None);
+
+/// Translate from now on into `language', or the user's when `None'.
+pub fn set_language(language: Option<&str>) {
+    let language = language.map(str::to_owned).unwrap_or_else(|| sys_locale::get_locale().unwrap_or_default());
+    *CURRENT.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(Catalog::new(&language)));
+}
+
+/// The catalog in use.
+pub fn current() -> Arc<Catalog> {
+    if let Some(catalog) = CURRENT.read().unwrap_or_else(|e| e.into_inner()).as_ref() {
+        return catalog.clone();
+    }
+    set_language(None);
+    current()
+}
+
+/// Message `id' of the catalog in use with `args' in place, see `tr!'.
+pub fn format(id: &str, args: &[(&str, Value)]) -> String {
+    current().format(id, args)
+}
+
+/// Message `$id' in the user's language, with each `name = value' argument in place.
+#[macro_export]
+macro_rules! tr {
+    ($id:literal) => {
+        $crate::i18n::format($id, &[])
+    };
+    ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format($id, &[$((stringify!($name), $crate::i18n::Value::from($value))),+])
+    };
+}
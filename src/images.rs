@@ -0,0 +1,191 @@
+//! Find pictures that look the same although their files differ, e.g. because one was recompressed or had its
+//! metadata edited.  Each image is reduced to a difference hash (dHash): shrunk to 9 by 8 grey pixels, with a bit
+//! for whether each pixel is brighter than the one to its right.  Pictures whose hashes differ in only a few bits
+//! look alike.  Nothing guarantees they are the same, so they are reported apart from exact duplicates.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, io,
+    path::{Path, PathBuf},
+};
+use image::ImageReader;
+use crate::{
+    relate::{FileInfo, RelatedFiles},
+    similar,
+};
+
+/// The file name extensions of the images looked at, in lower case.
+pub const EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "webp"];
+/// Hashes this many bits apart or fewer are taken for the same picture.
+pub const MAX_DISTANCE: u32 = 4;
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Image(image::ImageError),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn image_error(path: &Path) -> impl FnOnce(image::ImageError) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Image(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Image(e) => write!(f, "{:}: unreadable image: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// Whether `path' is named like an image we can read.
+pub fn is_image(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// The difference hash of the image at `path'.  The format is guessed from the contents, not the name.
+pub fn dhash(path: &Path) -> Result<u64, Error> {
+    let reader = ImageReader::open(path).map_err(io_error(path))?.with_guessed_format().map_err(io_error(path))?;
+    let small = reader.decode().map_err(image_error(path))?.thumbnail_exact(9, 8).to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash = hash << 1 | u64::from(small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0]);
+        }
+    }
+    Ok(hash)
+}
+
+/// The image at `path' shrunk to fit in `max' by `max' pixels, keeping its proportions, as RGBA rows.
+pub fn thumbnail(path: &Path, max: u32) -> Result<Thumbnail, Error> {
+    let reader = ImageReader::open(path).map_err(io_error(path))?.with_guessed_format().map_err(io_error(path))?;
+    let small = reader.decode().map_err(image_error(path))?.thumbnail(max, max).to_rgba8();
+    Ok(Thumbnail { width: small.width(), height: small.height(), rgba: small.into_raw() })
+}
+
+/// A picture small enough to show next to others, see `thumbnail'.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Thumbnail {
+    pub width: u32,
+    pub height: u32,
+    /// Four bytes a pixel, row after row.
+    pub rgba: Vec<u8>,
+}
+
+/// How many bits `a' and `b' differ in.
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageConf {
+    /// See `MAX_DISTANCE'.
+    pub max_distance: u32,
+    /// Decode with this many threads.  `0' will be changed to 1.
+    pub threads: u16,
+}
+
+impl Default for ImageConf {
+    fn default() -> Self {
+        Self { max_distance: MAX_DISTANCE, threads: crate::relate::available_threads() }
+    }
+}
+
+/// Pictures that look alike, among the files of a scan.
+#[derive(Debug, Default)]
+pub struct SimilarImages {
+    /// As in `similar::SimilarFiles'.
+    pub groups: Vec<Vec<FileInfo>>,
+    pub errors: Vec<Error>,
+}
+
+/// Which of `n' things are joined together, by following each to the first of its set.
+struct Sets(Vec<usize>);
+
+impl Sets {
+    fn new(n: usize) -> Self {
+        Self((0..n).collect())
+    }
+
+    fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.0[root] != root {
+            root = self.0[root];
+        }
+        // Point everything on the way straight at the root, so the next find is quick.
+        let mut i = i;
+        while self.0[i] != root {
+            i = std::mem::replace(&mut self.0[i], root);
+        }
+        root
+    }
+
+    fn join(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+/// The pairs of `hashes' no more than `max_distance' bits apart, each once.  Splitting the bits into
+/// `max_distance + 1' runs, two hashes that close must agree on at least one run entirely, so only hashes sharing a
+/// run are compared rather than every pair.
+fn close_pairs(hashes: &[u64], max_distance: u32) -> Vec<(usize, usize)> {
+    let runs = (max_distance + 1).min(64);
+    let mut pairs = Vec::new();
+    for run in 0..runs {
+        let (start, end) = (run * 64 / runs, (run + 1) * 64 / runs);
+        let mask = (u64::MAX >> (64 - (end - start))) << start;
+        let mut buckets = HashMap::<u64, Vec<usize>>::new();
+        for (i, hash) in hashes.iter().enumerate() {
+            buckets.entry(hash & mask).or_default().push(i);
+        }
+        for bucket in buckets.values() {
+            for (n, &a) in bucket.iter().enumerate() {
+                pairs.extend(bucket[n + 1..].iter().filter(|&&b| distance(hashes[a], hashes[b]) <= max_distance).map(|&b| (a, b)));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs.dedup();
+    pairs
+}
+
+impl SimilarImages {
+    /// Look for pictures that look alike among the files of `related', see `similar::key_contents'.
+    pub fn find(related: &RelatedFiles, conf: &ImageConf) -> Self {
+        let (hashed, mut errors) = similar::key_contents(related, is_image, conf.threads, dhash);
+        let hashes = hashed.iter().map(|(_, hash)| *hash).collect::<Vec<u64>>();
+        let mut sets = Sets::new(hashes.len());
+        for (a, b) in close_pairs(&hashes, conf.max_distance) {
+            sets.join(a, b);
+        }
+        let mut members = HashMap::<usize, Vec<&HashSet<FileInfo>>>::new();
+        for (i, (copies, _)) in hashed.iter().enumerate() {
+            members.entry(sets.find(i)).or_default().push(copies);
+        }
+        errors.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { groups: similar::groups(members.into_values()), errors }
+    }
+}
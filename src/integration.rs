@@ -0,0 +1,234 @@
+//! Install "Find duplicates here" entries into the desktop file manager.
+//! Each entry launches an executable with `--scan <dir>' for the folder it was invoked on.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+use xdg_home::home_dir;
+
+/// The label shown in the file manager's context menu.
+pub const MENU_LABEL: &str = "Find duplicates here";
+
+/// File managers we know how to extend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileManager {
+    /// GNOME Files, through `~/.local/share/nautilus/scripts'.
+    Nautilus,
+    /// Windows Explorer, through per-user registry keys.
+    Explorer,
+    /// macOS Finder, through a service in `~/Library/Services'.
+    Finder,
+}
+
+impl FileManager {
+    /// The file manager native to the platform we were built for.
+    pub fn native() -> Self {
+        if cfg!(target_os = "windows") {
+            FileManager::Explorer
+        } else if cfg!(target_os = "macos") {
+            FileManager::Finder
+        } else {
+            FileManager::Nautilus
+        }
+    }
+
+    /// Where the integration lives once installed.
+    pub fn location(&self) -> io::Result<PathBuf> {
+        match self {
+            FileManager::Nautilus => Ok(home()?.join(".local/share/nautilus/scripts").join(MENU_LABEL)),
+            FileManager::Explorer => Ok(PathBuf::from(format!(r"HKCU\{:}", REG_DIR_KEY))),
+            FileManager::Finder => Ok(home()?.join("Library/Services").join(format!("{:}.workflow", MENU_LABEL))),
+        }
+    }
+
+    /// Install the context-menu entry so that it runs `exe --scan <dir>'.
+    pub fn install(&self, exe: &Path) -> io::Result<PathBuf> {
+        let location = self.location()?;
+        match self {
+            FileManager::Nautilus => install_nautilus(&location, exe)?,
+            FileManager::Explorer => install_explorer(exe)?,
+            FileManager::Finder => install_finder(&location, exe)?,
+        }
+        Ok(location)
+    }
+
+    /// Remove a previously installed entry.  Removing a missing entry is not an error.
+    pub fn uninstall(&self) -> io::Result<()> {
+        let location = self.location()?;
+        let result = match self {
+            FileManager::Nautilus => fs::remove_file(&location),
+            FileManager::Explorer => {
+                reg(&["delete", &format!(r"HKCU\{:}", REG_DIR_KEY), "/f"])
+                    .and_then(|_| reg(&["delete", &format!(r"HKCU\{:}", REG_BACKGROUND_KEY), "/f"]))
+            },
+            FileManager::Finder => fs::remove_dir_all(&location),
+        };
+        match result {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+}
+
+fn home() -> io::Result<PathBuf> {
+    home_dir().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "Couldn't find user's home directory"))
+}
+
+/// Quote `s' for inclusion in a POSIX shell script.
+fn sh_quote(s: &str) -> String {
+    format!("'{:}'", s.replace('\'', r"'\''"))
+}
+
+fn launcher_script(exe: &Path) -> String {
+    format!(
+        "#!/bin/sh\n\
+         # Installed by file-deduplicator.\n\
+         dir=\"${{1:-.}}\"\n\
+         [ -d \"$dir\" ] || dir=\"$(dirname \"$dir\")\"\n\
+         exec {:} --scan \"$(cd \"$dir\" && pwd)\"\n",
+        sh_quote(&exe.to_string_lossy()),
+    )
+}
+
+fn install_nautilus(location: &Path, exe: &Path) -> io::Result<()> {
+    if let Some(dir) = location.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(location, launcher_script(exe))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(location, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+const REG_DIR_KEY: &str = r"Software\Classes\Directory\shell\FileDeduplicator";
+const REG_BACKGROUND_KEY: &str = r"Software\Classes\Directory\Background\shell\FileDeduplicator";
+
+fn reg(args: &[&str]) -> io::Result<()> {
+    let status = Command::new("reg").args(args).status()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(format!("reg {:} failed with {:}", args.join(" "), status)))
+    }
+}
+
+fn install_explorer(exe: &Path) -> io::Result<()> {
+    let exe = exe.to_string_lossy();
+    // `%1' is the folder that was clicked, `%V' is the folder whose background was clicked.
+    for (key, arg) in [(REG_DIR_KEY, "%1"), (REG_BACKGROUND_KEY, "%V")] {
+        let key = format!(r"HKCU\{:}", key);
+        reg(&["add", &key, "/ve", "/d", MENU_LABEL, "/f"])?;
+        reg(&["add", &key, "/v", "Icon", "/d", &exe, "/f"])?;
+        let command = format!("\"{:}\" --scan \"{:}\"", exe, arg);
+        reg(&["add", &format!(r"{:}\command", key), "/ve", "/d", &command, "/f"])?;
+    }
+    Ok(())
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn finder_info_plist() -> String {
+    format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>NSServices</key>
+	<array>
+		<dict>
+			<key>NSMenuItem</key>
+			<dict>
+				<key>default</key>
+				<string>{}</string>
+			</dict>
+			<key>NSMessage</key>
+			<string>runWorkflowAsService</string>
+			<key>NSRequiredContext</key>
+			<dict>
+				<key>NSApplicationIdentifier</key>
+				<string>com.apple.finder</string>
+			</dict>
+			<key>NSSendFileTypes</key>
+			<array>
+				<string>public.folder</string>
+			</array>
+		</dict>
+	</array>
+</dict>
+</plist>
+"#, MENU_LABEL)
+}
+
+fn finder_document(exe: &Path) -> String {
+    let command = format!("for dir in \"$@\"; do {:} --scan \"$dir\" & done", sh_quote(&exe.to_string_lossy()));
+    format!(r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>AMApplicationBuild</key>
+	<string>523</string>
+	<key>AMApplicationVersion</key>
+	<string>2.10</string>
+	<key>AMDocumentVersion</key>
+	<string>2</string>
+	<key>actions</key>
+	<array>
+		<dict>
+			<key>action</key>
+			<dict>
+				<key>AMAccepts</key>
+				<dict>
+					<key>Container</key>
+					<string>List</string>
+					<key>Optional</key>
+					<true/>
+					<key>Types</key>
+					<array>
+						<string>com.apple.cocoa.string</string>
+					</array>
+				</dict>
+				<key>ActionBundlePath</key>
+				<string>/System/Library/Automator/Run Shell Script.action</string>
+				<key>ActionName</key>
+				<string>Run Shell Script</string>
+				<key>ActionParameters</key>
+				<dict>
+					<key>COMMAND_STRING</key>
+					<string>{}</string>
+					<key>inputMethod</key>
+					<integer>1</integer>
+					<key>shell</key>
+					<string>/bin/sh</string>
+				</dict>
+				<key>BundleIdentifier</key>
+				<string>com.apple.RunShellScript</string>
+			</dict>
+		</dict>
+	</array>
+	<key>workflowMetaData</key>
+	<dict>
+		<key>serviceInputTypeIdentifier</key>
+		<string>com.apple.Automator.fileSystemObject.folder</string>
+		<key>serviceProcessesInput</key>
+		<integer>0</integer>
+		<key>workflowTypeIdentifier</key>
+		<string>com.apple.Automator.servicesMenu</string>
+	</dict>
+</dict>
+</plist>
+"#, xml_escape(&command))
+}
+
+fn install_finder(location: &Path, exe: &Path) -> io::Result<()> {
+    let contents = location.join("Contents");
+    fs::create_dir_all(&contents)?;
+    fs::write(contents.join("Info.plist"), finder_info_plist())?;
+    fs::write(contents.join("document.wflow"), finder_document(exe))?;
+    Ok(())
+}
@@ -0,0 +1,305 @@
+//! An undo journal of the changes made while deduplicating, so a removed copy can be brought back.
+//! Every completed step is appended to a JSON Lines file in the data directory.  A removed file is
+//! restored by copying the surviving file back to where it was.
+//!
+//! The whole plan is saved beside the journal before its first step, and removed once every step has been tried.
+//! Should the app be killed or the power fail in between, it is still there on the next start, and with the
+//! journal tells which steps are left, to finish them or take back the ones done, see `Run'.
+
+use std::{
+    fmt, fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use serde::{Serialize, Deserialize};
+use crate::{
+    actions::{Action, DedupOptions, Outcome, Permit, Plan, Step},
+    archive, persist,
+    platform::OpenFiles,
+    relate::{self, FileInfo, HashAlgo},
+};
+
+const JOURNAL_FILE: &str = "journal.jsonl";
+const RUN_FILE: &str = "run.json";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Format(serde_json::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(serde_json::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed journal: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// A change that was made, and how to take it back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub time: SystemTime,
+    pub action: Action,
+    pub hash: String,
+    /// The copy that was removed or replaced.
+    pub original: PathBuf,
+    /// The copy that was kept, whose contents are restored to `original'.
+    pub surviving: PathBuf,
+}
+
+impl Entry {
+    fn new(step: &Step) -> Self {
+        Self {
+            time: SystemTime::now(),
+            action: step.action,
+            hash: step.hash.clone(),
+            original: step.target.clone(),
+            surviving: step.keep.clone(),
+        }
+    }
+}
+
+/// A plan being carried out, kept until every step has been tried.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    /// The folder of the project the plan was made for.
+    pub project: PathBuf,
+    pub started: SystemTime,
+    pub steps: Vec<Step>,
+}
+
+impl Run {
+    /// Whether `entry' records one of the steps, done since the run started.
+    fn recorded(&self, entry: &Entry) -> bool {
+        entry.time >= self.started && self.steps.iter().any(|step| step.target == entry.original && step.hash == entry.hash)
+    }
+
+    /// The entries among `entries' recording the steps done, oldest first.
+    pub fn done<'a>(&self, entries: &'a [Entry]) -> Vec<&'a Entry> {
+        entries.iter().filter(|entry| self.recorded(entry)).collect()
+    }
+
+    /// The steps no entry among `entries' records, those that failed or were never reached.
+    pub fn remaining(&self, entries: &[Entry]) -> Vec<Step> {
+        let done = self.done(entries);
+        self.steps
+            .iter()
+            .filter(|step| !done.iter().any(|entry| entry.original == step.target && entry.hash == step.hash))
+            .cloned()
+            .collect()
+    }
+}
+
+/// The journal under the data directory `data_dir'.
+pub fn journal_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(JOURNAL_FILE)
+}
+
+/// Where the run being carried out is kept under the data directory `data_dir'.
+pub fn run_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(RUN_FILE)
+}
+
+/// The run under `data_dir' that was cut short, if one was.
+pub fn interrupted(data_dir: &Path) -> Result<Option<Run>, Error> {
+    let path = run_path(data_dir);
+    match fs::read(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(io_error(&path)(e)),
+        Ok(contents) => serde_json::from_slice(&contents).map(Some).map_err(format_error(&path)),
+    }
+}
+
+/// Refuses while another run is left over, which would be forgotten.
+fn begin(data_dir: &Path, run: &Run) -> Result<(), Error> {
+    let path = run_path(data_dir);
+    if path.exists() {
+        return Err(io_error(&path)(io::Error::new(io::ErrorKind::AlreadyExists, "an interrupted run has to be resumed or rolled back first")));
+    }
+    let contents = serde_json::to_vec(run).map_err(format_error(&path))?;
+    fs::create_dir_all(data_dir).map_err(io_error(data_dir))?;
+    persist::write_atomic(&path, &contents).map_err(io_error(&path))
+}
+
+fn finish(data_dir: &Path) -> Result<(), Error> {
+    let path = run_path(data_dir);
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(io_error(&path)(e)),
+        _ => Ok(()),
+    }
+}
+
+fn append(data_dir: &Path, entry: &Entry) -> Result<(), Error> {
+    let path = journal_path(data_dir);
+    let mut line = serde_json::to_vec(entry).map_err(format_error(&path))?;
+    line.push(b'\n');
+    fs::create_dir_all(data_dir).map_err(io_error(data_dir))?;
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(&path).map_err(io_error(&path))?;
+    file.write_all(&line).and_then(|_| file.sync_data()).map_err(io_error(&path))
+}
+
+/// Carry out `plan' for the project of folder `project' like `Plan::execute', recording each step that succeeds
+/// in the journal under `data_dir'.  Should the journal fail, nothing more is changed: the outcomes so far are
+/// returned along with the error, and the run is left to be resumed or rolled back.  A dry run changes nothing,
+/// so there is nothing to record.
+pub fn execute(data_dir: &Path, project: &Path, plan: &Plan, options: &DedupOptions, permit: &Permit) -> (Vec<Outcome>, Result<(), Error>) {
+    if options.dry_run {
+        return (plan.preview(options), Ok(()));
+    }
+    let run = Run { project: project.to_path_buf(), started: SystemTime::now(), steps: plan.steps.clone() };
+    if let Err(e) = begin(data_dir, &run) {
+        return (Vec::new(), Err(e));
+    }
+    carry_out(data_dir, &run.steps, options, permit)
+}
+
+/// Finish `run', which was cut short, by carrying out the steps the journal under `data_dir' doesn't record.
+/// Those that failed before are tried again.
+pub fn resume(data_dir: &Path, run: &Run, options: &DedupOptions, permit: &Permit) -> (Vec<Outcome>, Result<(), Error>) {
+    match entries(data_dir) {
+        Ok(entries) => carry_out(data_dir, &run.remaining(&entries), options, permit),
+        Err(e) => (Vec::new(), Err(e)),
+    }
+}
+
+/// The entries `roll_back' took back, each with how restoring it went.
+pub type RolledBack = Result<Vec<(Entry, Result<(), Error>)>, Error>;
+
+/// Take back the steps of `run', which was cut short, that the journal under `data_dir' records, newest first,
+/// returning each with how restoring it went.  The run is only over once every step is taken back: until then it is
+/// left, with what couldn't be restored still in the journal, to roll back again.
+pub fn roll_back(data_dir: &Path, run: &Run, permit: &Permit) -> RolledBack {
+    let entries = entries(data_dir)?;
+    let restored = run
+        .done(&entries)
+        .into_iter()
+        .rev()
+        .map(|entry| (entry.clone(), restore(data_dir, entry, permit)))
+        .collect::<Vec<_>>();
+    if restored.iter().all(|(_, result)| result.is_ok()) {
+        finish(data_dir)?;
+    }
+    Ok(restored)
+}
+
+fn carry_out(data_dir: &Path, steps: &[Step], options: &DedupOptions, permit: &Permit) -> (Vec<Outcome>, Result<(), Error>) {
+    let mut outcomes = Vec::new();
+    let open = OpenFiles::read();
+    for step in steps {
+        let result = step.execute(options, &open, permit);
+        let recorded = match result {
+            Ok(()) => append(data_dir, &Entry::new(step)),
+            Err(_) => Ok(()),
+        };
+        outcomes.push(Outcome { step: step.clone(), result });
+        if recorded.is_err() {
+            return (outcomes, recorded);
+        }
+    }
+    let finished = finish(data_dir);
+    (outcomes, finished)
+}
+
+/// Every change recorded under `data_dir', oldest first.
+pub fn entries(data_dir: &Path) -> Result<Vec<Entry>, Error> {
+    let path = journal_path(data_dir);
+    let contents = match fs::read_to_string(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        contents => contents.map_err(io_error(&path))?,
+    };
+    let lines = contents.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<&str>>();
+    let mut entries = Vec::with_capacity(lines.len());
+    for (i, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            // A crash while appending leaves the last line cut short.  The step it describes may never have
+            // happened, and there is nothing to restore from a partial record anyway.
+            Err(_) if i + 1 == lines.len() => (),
+            Err(e) => return Err(format_error(&path)(e)),
+        }
+    }
+    Ok(entries)
+}
+
+/// Whether `path', which may be a file in an archive, still has the contents hashed to `hash'.  The journal doesn't
+/// record which algorithm made the hash, so each is tried in turn.
+fn holds(path: &Path, hash: &str) -> io::Result<bool> {
+    let info = match archive::find_member(path)? {
+        Some(member) => member,
+        None => FileInfo::from_path(path, true)?,
+    };
+    for algo in [HashAlgo::Sha256, HashAlgo::Blake3, HashAlgo::Xxh128] {
+        if relate::hash_from_file_info(&info, algo)?.hash == hash {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+/// Put `entry.original' back by copying `entry.surviving' over it, out of its archive if it is in one, then drop
+/// `entry' from the journal under `data_dir'.  Refuses when something else has taken the original's place since, or
+/// when the surviving copy no longer holds what was removed.
+pub fn restore(data_dir: &Path, entry: &Entry, permit: &Permit) -> Result<(), Error> {
+    let original = &entry.original;
+    let occupied = match entry.action {
+        Action::Trash | Action::Delete => original.exists(),
+        // The link or clone is replaced by a full copy, but only while it still holds the same contents.
+        Action::HardLink | Action::Reflink | Action::Symlink => original.exists() && !archive::contents_identical(original, &entry.surviving).map_err(io_error(original))?,
+    };
+    if occupied {
+        return Err(io_error(original)(io::Error::new(io::ErrorKind::AlreadyExists, "another file has taken its place")));
+    }
+    if entry.surviving != *original && !holds(&entry.surviving, &entry.hash).map_err(io_error(&entry.surviving))? {
+        return Err(io_error(&entry.surviving)(io::Error::new(io::ErrorKind::InvalidData, "the surviving copy has changed since")));
+    }
+    if let Some(dir) = original.parent() {
+        fs::create_dir_all(dir).map_err(io_error(dir))?;
+    }
+    // A removed empty file had no surviving copy, see `Plan::empty_files', and is simply made again.
+    let copy = |temp: &Path| match entry.surviving == *original {
+        true => fs::File::create(temp).map(|_| ()),
+        false => archive::copy(&entry.surviving, temp).map(|_| ()),
+    };
+    persist::replace_with(original, copy, permit).map_err(io_error(original))?;
+    forget(data_dir, entry)
+}
+
+/// Drop `entry' from the journal under `data_dir' without restoring it.
+pub fn forget(data_dir: &Path, entry: &Entry) -> Result<(), Error> {
+    let path = journal_path(data_dir);
+    let mut contents = Vec::new();
+    for kept in entries(data_dir)?.iter().filter(|other| *other != entry) {
+        contents.extend(serde_json::to_vec(kept).map_err(format_error(&path))?);
+        contents.push(b'\n');
+    }
+    persist::write_atomic(&path, &contents).map_err(io_error(&path))
+}
@@ -1 +1,58 @@
+//! Find duplicate files and remove all but one copy of each, safely.  The engine needs no windowing crates, so other
+//! programs can embed it:
+//!
+//! ```no_run
+//! use file_deduplicator::{dedupe::{self, Action, DedupOptions, KeepPolicy, Permit}, scan};
+//!
+//! let related = scan::duplicates(&["/home/me/Pictures".into()]);
+//! let plan = dedupe::plan(&related, KeepPolicy::OldestCreated, Action::Trash).expect("Only regexes can be invalid");
+//! let permit = Permit::unless_auditing(false).expect("Not auditing");
+//! for outcome in plan.execute(&DedupOptions { verify: true, dry_run: false }, &permit) {
+//!     if let Err(e) = outcome.result {
+//!         eprintln!("{:}: {:}", outcome.step.target.display(), e);
+//!     }
+//! }
+//! ```
+//!
+//! `scan', `relate' and `dedupe' make up the API meant for that, and change with care.  The other modules serve the
+//! applications in this package and may change with them.
+
+pub mod scan;
+pub mod dedupe;
 pub mod relate;
+pub mod policy;
+pub mod rules;
+pub mod platform;
+pub mod integration;
+pub mod project;
+pub mod actions;
+pub mod lock;
+pub mod guard;
+pub mod persist;
+pub mod naming;
+pub mod format;
+pub mod report;
+pub mod denylist;
+pub mod journal;
+pub mod estimate;
+pub mod telemetry;
+pub mod usage;
+pub mod throttle;
+pub mod paths;
+pub mod cache;
+pub mod store;
+pub mod help;
+pub mod i18n;
+pub mod watch;
+pub mod images;
+pub mod preview;
+pub mod similar;
+pub mod audio;
+pub mod archive;
+pub mod photo;
+pub mod analysis;
+pub mod folders;
+pub mod config;
+pub mod settings;
+pub mod crosscheck;
+pub mod manifest;
@@ -0,0 +1,148 @@
+//! Lock files that keep two processes from mutating the same project or configuration at once.
+//! A lock records the holder's process id, so a lock left behind by a crashed process can be recovered.
+
+use std::{
+    fmt, fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+};
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    /// Another live process holds the lock.
+    Held(u32),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Held(pid) => write!(f, "{:} is held by another running instance (process {:})", self.path.display(), pid),
+        }
+    }
+}
+
+const INSTANCE_LOCK: &str = "instance.lock";
+
+/// The lock held by the interactive application for the data directory `data_dir'.
+pub fn instance_lock_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(INSTANCE_LOCK)
+}
+
+/// Whether a process with id `pid' is still running.
+#[cfg(target_os = "linux")]
+fn process_alive(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn process_alive(pid: u32) -> bool {
+    process::Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stderr(process::Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(windows)]
+fn process_alive(pid: u32) -> bool {
+    process::Command::new("tasklist")
+        .args(["/NH", "/FI", &format!("PID eq {:}", pid)])
+        .stderr(process::Stdio::null())
+        .output()
+        .is_ok_and(|out| String::from_utf8_lossy(&out.stdout).contains(&pid.to_string()))
+}
+
+/// A held lock.  The lock file is removed when this is dropped.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Take the lock at `path'.  A lock whose holder is no longer running is considered stale and taken over.
+    pub fn acquire(path: &Path) -> Result<Self, Error> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_error(dir))?;
+        }
+        // A single retry is enough: the only reason to retry is that we just removed a stale lock.
+        for _ in 0..2 {
+            match fs::OpenOptions::new().write(true).create_new(true).open(path) {
+                Ok(mut file) => {
+                    write!(file, "{:}", process::id()).map_err(io_error(path))?;
+                    return Ok(Self { path: path.to_path_buf() });
+                },
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                    match live_holder(path) {
+                        Some(pid) => {
+                            return Err(Error { path: path.to_path_buf(), error_type: ErrorType::Held(pid) });
+                        },
+                        // Stale, or so freshly created that the holder hasn't written its id yet.
+                        // The latter is rare enough that treating it as stale is acceptable.  A lock
+                        // carrying our own id was left by an earlier process that was given the same id.
+                        None => match fs::remove_file(path) {
+                            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(io_error(path)(e)),
+                            _ => continue,
+                        },
+                    }
+                },
+                Err(e) => return Err(io_error(path)(e)),
+            }
+        }
+        Err(Error { path: path.to_path_buf(), error_type: ErrorType::IO(io::Error::other("lock was retaken while recovering it")) })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// The id of the process holding the lock at `path', if it is held.
+pub fn holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// The id of another, still running, process holding the lock at `path'.
+pub fn live_holder(path: &Path) -> Option<u32> {
+    holder(path).filter(|pid| *pid != process::id() && process_alive(*pid))
+}
+
+fn focus_file(path: &Path) -> PathBuf {
+    path.with_extension("focus")
+}
+
+/// Ask the process holding the lock at `path' to bring itself to the front.
+pub fn request_focus(path: &Path) -> io::Result<()> {
+    fs::write(focus_file(path), process::id().to_string())
+}
+
+/// Whether another process has asked the holder of the lock at `path' to come to the front.
+/// The request is consumed, so it is only reported once.
+pub fn take_focus_request(path: &Path) -> bool {
+    fs::remove_file(focus_file(path)).is_ok()
+}
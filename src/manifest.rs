@@ -0,0 +1,180 @@
+//! Read and write hash indexes in the formats other tools use, so results can be checked, shared and carried over
+//! without scanning again: a checksum manifest as written by `sha256sum', `b3sum' and `xxh128sum', one line per file,
+//! and the duplicate sets printed by `fdupes' and `jdupes', one path per line with a blank line after each set.
+
+use std::{
+    collections::HashMap,
+    fmt, io::{self, BufRead, Write},
+    path::{Path, PathBuf},
+};
+use crate::{
+    paths,
+    relate::{ContentComparator, ExactHash, FileInfo, HashAlgo, RelatedFiles},
+};
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    /// A line of a checksum manifest, counting from 1, that isn't a hash of the algorithm asked for and a path.
+    Malformed(usize),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Malformed(line) => write!(f, "{:}: line {:} is not a checksum of the chosen hash and a file name", self.path.display(), line),
+        }
+    }
+}
+
+/// How a hash index is laid out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `<hash>  <path>' for every file, as the `sha256sum' family writes and checks with `-c'.
+    Checksums,
+    /// The paths of each set of duplicates, followed by a blank line, as `fdupes' prints.  Files without a copy
+    /// are left out, and there are no hashes.
+    Fdupes,
+}
+
+/// Write the files of `related' to `out' in `format', sorted by path within each hash, and the hashes by path too.
+pub fn write<W: Write>(mut out: W, related: &RelatedFiles, format: Format) -> io::Result<()> {
+    let mut groups = related.files
+        .iter()
+        .filter(|(_, group)| format == Format::Checksums || group.len() > 1)
+        .map(|(hash, group)| {
+            let mut paths = group.iter().map(|fi| &*fi.name).collect::<Vec<&Path>>();
+            paths.sort();
+            (hash, paths)
+        })
+        .collect::<Vec<(&String, Vec<&Path>)>>();
+    groups.sort_by(|a, b| a.1.cmp(&b.1));
+    for (hash, paths) in groups {
+        for path in &paths {
+            match format {
+                Format::Checksums => write_checksum(&mut out, hash, path)?,
+                Format::Fdupes => writeln!(out, "{:}", path.display())?,
+            }
+        }
+        if format == Format::Fdupes {
+            writeln!(out)?;
+        }
+    }
+    Ok(())
+}
+
+/// One line of a checksum manifest.  Like `sha256sum', a name holding a backslash or a line break is escaped,
+/// and the line then starts with a backslash.
+fn write_checksum(out: &mut impl Write, hash: &str, path: &Path) -> io::Result<()> {
+    let name = path.to_string_lossy();
+    if name.contains(['\\', '\n', '\r']) {
+        let name = name.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r");
+        writeln!(out, "\\{:}  {:}", hash, name)
+    } else {
+        writeln!(out, "{:}  {:}", hash, name)
+    }
+}
+
+/// How many hex digits a hash made with `algo' has, so a manifest of another algorithm isn't taken for one of `algo'.
+fn hex_digits(algo: HashAlgo) -> usize {
+    match algo {
+        HashAlgo::Sha256 | HashAlgo::Blake3 => 64,
+        HashAlgo::Xxh128 => 32,
+    }
+}
+
+/// The hash and path on line `n' of a checksum manifest, either in text mode, `<hash>  <path>', or binary mode,
+/// `<hash> *<path>'.  The hash has to be as long as one made with `algo'.
+fn parse_checksum(line: &str, n: usize, file: &Path, algo: HashAlgo) -> Result<(String, PathBuf), Error> {
+    let malformed = || Error { path: file.to_path_buf(), error_type: ErrorType::Malformed(n) };
+    let (escaped, line) = match line.strip_prefix('\\') {
+        Some(line) => (true, line),
+        None => (false, line),
+    };
+    let (hash, name) = line.split_once(' ').ok_or_else(malformed)?;
+    let name = name.strip_prefix([' ', '*']).ok_or_else(malformed)?;
+    if hash.len() != hex_digits(algo) || !hash.chars().all(|c| c.is_ascii_hexdigit()) || name.is_empty() {
+        return Err(malformed());
+    }
+    let name = match escaped {
+        false => name.to_owned(),
+        true => {
+            let mut unescaped = String::new();
+            let mut chars = name.chars();
+            while let Some(c) = chars.next() {
+                if c != '\\' {
+                    unescaped.push(c);
+                    continue;
+                }
+                unescaped.push(match chars.next() {
+                    Some('n') => '\n',
+                    Some('r') => '\r',
+                    Some('\\') => '\\',
+                    _ => return Err(malformed()),
+                });
+            }
+            unescaped
+        },
+    };
+    Ok((hash.to_lowercase(), name.into()))
+}
+
+/// Read the index in `format' from `input', which was read from `file', as the results of a scan hashed with `algo'.
+/// Relative paths are taken to be below `base', as the tools write them relative to where they ran.  Every file is
+/// looked up for its size and times, and those that can't be are left out, as errors.  An `fdupes' index has no
+/// hashes, so every file of each set is hashed, and grouped by what it holds now rather than by the set it was in.
+pub fn read(input: impl BufRead, file: &Path, format: Format, algo: HashAlgo, base: &Path) -> Result<RelatedFiles, Error> {
+    let mut lines = Vec::new();
+    for line in input.lines() {
+        lines.push(line.map_err(io_error(file))?);
+    }
+    let mut related = RelatedFiles { algo, files: HashMap::new(), partial: false, errors: Vec::new() };
+    match format {
+        Format::Checksums => {
+            for (i, line) in lines.iter().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+                let (hash, path) = parse_checksum(line, i + 1, file, algo)?;
+                add(&mut related, &hash, &paths::normalize(&base.join(path)));
+            }
+        },
+        Format::Fdupes => {
+            // `fdupes -S' heads each set with its size.
+            let sets = lines.split(|line| line.is_empty()).filter(|set| !set.is_empty());
+            let exact = ExactHash { algo };
+            let members = sets.flatten().filter(|line| !line.ends_with(" bytes each:")).map(|line| paths::normalize(&base.join(line)));
+            for path in members {
+                match exact.key(&path) {
+                    Ok(hash) => add(&mut related, &hash, &path),
+                    Err(e) => related.errors.push(e),
+                }
+            }
+        },
+    }
+    Ok(related)
+}
+
+/// Put `path' in the group of `hash', unless it can't be looked up.
+fn add(related: &mut RelatedFiles, hash: &str, path: &Path) {
+    match FileInfo::from_path(path, false) {
+        Ok(fi) => {
+            related.files.entry(hash.to_owned()).or_default().insert(fi);
+        },
+        Err(e) => related.errors.push(e),
+    }
+}
@@ -0,0 +1,155 @@
+//! Spot copies whose names have little to do with the rest of their group, as happens when photos or songs are
+//! saved under a camera's or a player's own naming scheme, and suggest the name the other copies agree on.  Also
+//! spot the opposite: files sharing a name but not their contents, as backup copies that went their own ways do.
+
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    path::Path,
+};
+use crate::relate::{FileInfo, RelatedFiles};
+
+/// Below this similarity, between 0 and 1, two names are considered unrelated.
+const SIMILARITY_THRESHOLD: f64 = 0.5;
+
+/// Markers that file managers and browsers add to copies, which say nothing about the contents.
+const COPY_MARKERS: [&str; 4] = [" - copy", "_copy", " copy", "copy of "];
+
+/// A copy named unlike the rest of its group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mislabel<'a> {
+    pub file: &'a FileInfo,
+    /// The name the group agrees on, with this copy's own extension.
+    pub suggested: String,
+}
+
+/// The stem of `path', lowercased and without copy markers or a trailing ` (2)'.
+fn normalized_stem(path: &Path) -> String {
+    let mut stem = path.file_stem().map(|stem| stem.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if let Some(open) = stem.rfind(" (") {
+        if stem.ends_with(')') && stem[open + 2..stem.len() - 1].chars().all(|c| c.is_ascii_digit()) {
+            stem.truncate(open);
+        }
+    }
+    for marker in COPY_MARKERS {
+        stem = stem.replace(marker, "");
+    }
+    stem.trim().to_owned()
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<char>>();
+    let mut row = (0..=b.len()).collect::<Vec<usize>>();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// How alike two names are, from 0 for nothing in common to 1 for equal.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / longest as f64
+}
+
+/// The copies in `group' whose names are unlike the name most of the group shares.
+/// Nothing is reported when no name is shared by more than one copy, as there is then no agreement to go by.
+pub fn mislabeled<'a>(group: impl IntoIterator<Item = &'a FileInfo>) -> Vec<Mislabel<'a>> {
+    let mut files = group.into_iter().map(|fi| (fi, normalized_stem(&fi.name))).collect::<Vec<_>>();
+    files.sort_by(|(a, _), (b, _)| a.name.cmp(&b.name));
+    let mut counts = HashMap::<&str, usize>::new();
+    for (_, stem) in &files {
+        *counts.entry(stem).or_default() += 1;
+    }
+    // Most shared, then shortest, then first alphabetically, so the choice doesn't depend on hash order.
+    let Some((canonical, count)) = counts.into_iter().min_by(|(a, n), (b, m)| m.cmp(n).then(a.len().cmp(&b.len())).then(a.cmp(b))) else {
+        return Vec::new();
+    };
+    if count < 2 {
+        return Vec::new();
+    }
+    // The shortest spelling is the least likely to carry a marker we don't know about.
+    let Some(representative) = files
+        .iter()
+        .filter(|(_, stem)| stem == canonical)
+        .map(|(fi, _)| *fi)
+        .min_by_key(|fi| fi.name.file_name().map_or(0, |name| name.len()))
+    else {
+        return Vec::new();
+    };
+    let canonical_stem = representative.name.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    files
+        .iter()
+        .filter(|(_, stem)| similarity(stem, canonical) < SIMILARITY_THRESHOLD)
+        .map(|(fi, _)| Mislabel {
+            file: fi,
+            suggested: match fi.name.extension() {
+                Some(ext) => format!("{:}.{:}", canonical_stem, ext.to_string_lossy()),
+                None => canonical_stem.clone(),
+            },
+        })
+        .collect()
+}
+
+/// The files of one contents among those sharing a name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Version {
+    pub hash: String,
+    /// Sorted by path.
+    pub files: Vec<FileInfo>,
+}
+
+impl Version {
+    /// When the newest of the files was last changed, to tell which version is the latest.
+    pub fn modified(&self) -> Option<std::time::SystemTime> {
+        self.files.iter().filter_map(|fi| fi.modified).max()
+    }
+}
+
+/// A name shared by files with different contents.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Collision {
+    pub name: String,
+    /// At least two, newest first, and by path where that can't tell them apart.
+    pub versions: Vec<Version>,
+}
+
+/// The names in `related' that files with different contents share, sorted by name.  Names must match exactly,
+/// as the copies of a backup keep theirs, and members of archives count by their own names.
+pub fn collisions(related: &RelatedFiles) -> Vec<Collision> {
+    let mut by_name = HashMap::<&OsStr, HashMap<&str, Vec<&FileInfo>>>::new();
+    for (hash, group) in &related.files {
+        for fi in group {
+            if let Some(name) = fi.name.file_name() {
+                by_name.entry(name).or_default().entry(hash).or_default().push(fi);
+            }
+        }
+    }
+    let mut collisions = by_name
+        .into_iter()
+        .filter(|(_, versions)| versions.len() > 1)
+        .map(|(name, versions)| {
+            let mut versions = versions
+                .into_iter()
+                .map(|(hash, files)| {
+                    let mut files = files.into_iter().cloned().collect::<Vec<FileInfo>>();
+                    files.sort_by(|a, b| a.name.cmp(&b.name));
+                    Version { hash: hash.to_owned(), files }
+                })
+                .collect::<Vec<Version>>();
+            versions.sort_by(|a, b| b.modified().cmp(&a.modified()).then_with(|| a.files[0].name.cmp(&b.files[0].name)));
+            Collision { name: name.to_string_lossy().into_owned(), versions }
+        })
+        .collect::<Vec<Collision>>();
+    collisions.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.versions[0].files[0].name.cmp(&b.versions[0].files[0].name)));
+    collisions
+}
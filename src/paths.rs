@@ -0,0 +1,70 @@
+//! Spell paths one way, so the same file reached through two spellings of a path is recognised as one file
+//! rather than counted twice or taken for a copy of itself.
+
+use std::{
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+/// Whether the filesystems usually found here ignore case in names, as on Windows and macOS.
+pub const CASE_INSENSITIVE: bool = cfg!(any(windows, target_os = "macos"));
+
+/// `path' without `.' components, with each `..' taking away the component before it, and with repeated separators
+/// reduced to one.  Unlike `fs::canonicalize', nothing is looked up on disk: the path needn't exist, a relative path
+/// stays relative and links are left alone, so a file is still reported where it was found.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            // Above the root is the root, and above the start of a relative path is kept as is.
+            Component::ParentDir => match out.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    out.pop();
+                },
+                Some(Component::RootDir | Component::Prefix(_)) => (),
+                Some(Component::ParentDir | Component::CurDir) | None => out.push(component),
+            },
+            component => out.push(component),
+        }
+    }
+    if out.as_os_str().is_empty() && !path.as_os_str().is_empty() {
+        out.push(Component::CurDir);
+    }
+    out
+}
+
+/// What every spelling of `path' has in common: the normalized path, in lower case where names ignore case.
+/// Only for comparing paths, the result may not name the file on a case-sensitive filesystem.
+pub fn key(path: &Path) -> PathBuf {
+    let normal = normalize(path);
+    if CASE_INSENSITIVE {
+        OsString::from(normal.to_string_lossy().to_lowercase()).into()
+    } else {
+        normal
+    }
+}
+
+/// Whether `a' and `b' are spellings of the same path.
+pub fn same(a: &Path, b: &Path) -> bool {
+    key(a) == key(b)
+}
+
+/// Whether `path' is `base' or below it, however either is spelled.
+pub fn starts_with(path: &Path, base: &Path) -> bool {
+    key(path).starts_with(key(base))
+}
+
+/// `roots' normalized, leaving out any inside another or spelled like an earlier one, so walking what is left visits
+/// every file below them once.  Otherwise in the order given.
+pub fn distinct_roots(roots: &[PathBuf]) -> Vec<PathBuf> {
+    let roots = roots.iter().map(|root| normalize(root)).collect::<Vec<PathBuf>>();
+    roots
+        .iter()
+        .enumerate()
+        .filter(|(i, root)| {
+            !roots.iter().enumerate().any(|(j, other)| j != *i && starts_with(root, other) && (j < *i || !same(root, other)))
+        })
+        .map(|(_, root)| root.clone())
+        .collect()
+}
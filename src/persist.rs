@@ -0,0 +1,111 @@
+//! Write state files so that a crash or power loss leaves either the old or the new contents, never a mix.
+//! Contents go to a temporary file beside the target, are flushed to disk, and then renamed over it.
+//! The previous version is kept as a backup, so a file damaged some other way can still be recovered.
+
+use std::{
+    ffi::OsString,
+    fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+};
+use crate::actions::Permit;
+
+const TEMP_SUFFIX: &str = ".tmp";
+const BACKUP_SUFFIX: &str = ".bak";
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Where `write_atomic' stages the new contents of `path'.
+pub fn temp_path(path: &Path) -> PathBuf {
+    with_suffix(path, TEMP_SUFFIX)
+}
+
+/// Where `write_atomic' keeps the previous contents of `path'.
+pub fn backup_path(path: &Path) -> PathBuf {
+    with_suffix(path, BACKUP_SUFFIX)
+}
+
+/// Flush the directory holding `path', so a rename inside it survives a power loss.
+#[cfg(unix)]
+fn sync_parent(path: &Path) -> io::Result<()> {
+    match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => fs::File::open(dir)?.sync_all(),
+        _ => fs::File::open(".")?.sync_all(),
+    }
+}
+
+/// Directories can't be opened as files here, and renames are durable once they return.
+#[cfg(not(unix))]
+fn sync_parent(_path: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn replace(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temp = temp_path(path);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp, path)?;
+    sync_parent(path)
+}
+
+/// Replace the contents of `path' with `contents' in a single step, keeping the old contents as a backup.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if path.exists() {
+        // The backup is a convenience, a filesystem without hard links just doesn't get one.
+        let backup = backup_path(path);
+        let _ = fs::remove_file(&backup);
+        let _ = fs::hard_link(path, &backup);
+    }
+    replace(path, contents)
+}
+
+/// Put the file `make' creates in place of `path' with a single rename, so whatever fails, or however the app is
+/// stopped, `path' holds either the old file or the new one, never neither.  `make' is given a path beside `path'
+/// to create, which is removed again should anything after fail.  Refuses when something is already there, as
+/// that may be the user's, or left by an attempt cut short.
+pub fn replace_with(path: &Path, make: impl FnOnce(&Path) -> io::Result<()>, _permit: &Permit) -> io::Result<()> {
+    let temp = temp_path(path);
+    if fs::symlink_metadata(&temp).is_ok() {
+        return Err(io::Error::new(io::ErrorKind::AlreadyExists, format!("'{:}' is in the way", temp.display())));
+    }
+    make(&temp)
+        .and_then(|_| fs::rename(&temp, path))
+        .inspect_err(|_| {
+            let _ = fs::remove_file(&temp);
+        })?;
+    sync_parent(path)
+}
+
+/// Remove what an interrupted `write_atomic' of `path' left behind.
+/// The target itself was never touched, so there is nothing else to undo.
+pub fn discard_partial(path: &Path) {
+    let _ = fs::remove_file(temp_path(path));
+}
+
+/// Read `path' with `parse'.  Should that fail, fall back to the backup and restore it over `path'.
+/// The original error is returned when there is no usable backup.
+pub fn read_recovering<T, E>(path: &Path, parse: impl Fn(&[u8]) -> Result<T, E>, io_error: impl Fn(io::Error) -> E) -> Result<T, E> {
+    discard_partial(path);
+    let error = match fs::read(path).map_err(&io_error).and_then(|contents| parse(&contents)) {
+        Ok(value) => return Ok(value),
+        Err(e) => e,
+    };
+    let backup = backup_path(path);
+    let Ok(contents) = fs::read(&backup) else {
+        return Err(error);
+    };
+    match parse(&contents) {
+        Ok(value) => {
+            // Not `write_atomic', which would swap the damaged file in as the backup.
+            let _ = replace(path, &contents);
+            Ok(value)
+        },
+        Err(_) => Err(error),
+    }
+}
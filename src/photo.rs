@@ -0,0 +1,121 @@
+//! Read when a photo was taken, and with which camera, from its EXIF metadata, so copies of it can be kept where
+//! that date says they belong, see `policy::KeepPolicy::CaptureDateName' and `policy::KeepPolicy::DatedFolder'.
+
+use std::{
+    fmt, fs, io,
+    path::{Component, Path, PathBuf},
+};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use exif::{In, Tag, Value};
+
+/// The file name extensions of the photos whose metadata is read, in lower case.
+pub const EXTENSIONS: &[&str] = &["jpg", "jpeg", "tif", "tiff", "heic", "heif", "png", "webp"];
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Exif(exif::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn exif_error(path: &Path) -> impl FnOnce(exif::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Exif(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Exif(e) => write!(f, "{:}: no usable EXIF metadata: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// What the EXIF metadata of a photo tells about it.  Either may be missing, e.g. from a scan.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PhotoInfo {
+    /// When the photo was taken, in the camera's local time.
+    pub captured: Option<NaiveDateTime>,
+    /// The make and model of the camera, e.g. `Canon EOS 5D'.
+    pub camera: Option<String>,
+}
+
+/// Whether `path' is named like a photo that may have EXIF metadata.
+pub fn is_photo(path: &Path) -> bool {
+    path.extension().is_some_and(|extension| EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str()))
+}
+
+/// Read the EXIF metadata of the photo at `path'.  Goes by when it was taken, else when it was last edited.
+pub fn read(path: &Path) -> Result<PhotoInfo, Error> {
+    let mut file = io::BufReader::new(fs::File::open(path).map_err(io_error(path))?);
+    let exif = exif::Reader::new().read_from_container(&mut file).map_err(exif_error(path))?;
+    let text = |tag| match exif.get_field(tag, In::PRIMARY).map(|field| &field.value) {
+        Some(Value::Ascii(values)) => values.first().map(|value| {
+            String::from_utf8_lossy(value).trim_matches(|c: char| c == '\0' || c.is_whitespace()).to_owned()
+        }),
+        _ => None,
+    }.filter(|value| !value.is_empty());
+    let captured = [Tag::DateTimeOriginal, Tag::DateTimeDigitized, Tag::DateTime].into_iter().find_map(|tag| {
+        let time = exif::DateTime::from_ascii(text(tag)?.as_bytes()).ok()?;
+        NaiveDate::from_ymd_opt(time.year.into(), time.month.into(), time.day.into())?
+            .and_hms_opt(time.hour.into(), time.minute.into(), time.second.into())
+    });
+    let camera = match (text(Tag::Make), text(Tag::Model)) {
+        // Models often repeat the make, e.g. `Canon' and `Canon EOS 5D'.
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{:} {:}", make, model)),
+        (make, model) => make.or(model),
+    };
+    Ok(PhotoInfo { captured, camera })
+}
+
+/// The capture date of the first of `paths' that is a photo with one.
+pub fn capture_date<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Option<NaiveDate> {
+    paths.into_iter().filter(|path| is_photo(path)).find_map(|path| read(path).ok()?.captured).map(|time| time.date())
+}
+
+/// Whether the file name of `path' holds `date', as in `IMG_20240131_101500.jpg' or `2024-01-31 party.jpg'.
+pub fn name_has_date(path: &Path, date: NaiveDate) -> bool {
+    let name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    ["%Y%m%d", "%Y-%m-%d", "%Y_%m_%d", "%Y.%m.%d"].iter().any(|format| name.contains(&date.format(format).to_string()))
+}
+
+/// Whether `path' is inside folders named after `date', either a folder for its year and within it one for its
+/// month, as in `2024/01' or `2024/01 January', or a single folder such as `2024-01' or `2024-01-31'.
+pub fn in_dated_folder(path: &Path, date: NaiveDate) -> bool {
+    let folders = path
+        .parent()
+        .into_iter()
+        .flat_map(Path::components)
+        .filter_map(|component| match component {
+            Component::Normal(name) => Some(name.to_string_lossy()),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    let year = date.format("%Y").to_string();
+    let month = |name: &str| name.split(|c: char| !c.is_ascii_digit()).next().and_then(|n| n.parse().ok()) == Some(date.month());
+    let single = ["%Y-%m", "%Y_%m", "%Y%m"].map(|format| date.format(format).to_string());
+    folders.windows(2).any(|pair| pair[0] == year && month(&pair[1]))
+        || folders.iter().any(|name| single.iter().any(|prefix| name.starts_with(prefix.as_str())))
+}
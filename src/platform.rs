@@ -0,0 +1,498 @@
+//! Thin wrappers around operating system facilities like the desktop file manager.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+use crate::{
+    actions::Permit,
+    relate::{self, FileInfo},
+};
+
+#[cfg(target_os = "windows")]
+const OPENER: &str = "explorer";
+#[cfg(target_os = "macos")]
+const OPENER: &str = "open";
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const OPENER: &str = "xdg-open";
+
+/// Open `path' with the desktop's default handler.  For directories this is the file manager.
+/// The handler is detached, so this returns as soon as it has been launched.
+pub fn open_path(path: &Path) -> io::Result<()> {
+    Command::new(OPENER)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Show `path' selected in its folder in the desktop's file manager.  Where the file manager can't be asked to
+/// select it, its folder is opened instead.
+#[cfg(target_os = "windows")]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    // Explorer parses its own command line and wants the quotes around the path only, after `/select,'.
+    Command::new(OPENER).raw_arg(format!("/select,\"{:}\"", path.display())).spawn().map(|_| ())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    Command::new(OPENER).arg("-R").arg(path).stdin(Stdio::null()).spawn().map(|_| ())
+}
+
+/// Asks the file manager over D-Bus, as Nautilus, Dolphin, Nemo and others understand, and falls back on opening
+/// the folder.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let path = std::path::absolute(path)?;
+    let shown = Command::new("dbus-send")
+        .args(["--session", "--print-reply", "--reply-timeout=2000", "--dest=org.freedesktop.FileManager1"])
+        .args(["/org/freedesktop/FileManager1", "org.freedesktop.FileManager1.ShowItems"])
+        .arg(format!("array:string:{:}", file_uri(&path)))
+        .arg("string:")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status();
+    match (shown, path.parent()) {
+        (Ok(status), _) if status.success() => Ok(()),
+        (_, Some(dir)) => open_path(dir),
+        (_, None) => open_path(&path),
+    }
+}
+
+/// `path', which must be absolute, as a `file://' URI.  Everything but letters, digits and `-._~/' is escaped,
+/// which also keeps commas out of `dbus-send' arrays.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn file_uri(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+    let mut uri = String::from("file://");
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => uri.push(byte as char),
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// The file to open or reveal for `file': an archive's member can only be shown as its archive.
+pub fn on_disk(file: &FileInfo) -> &Path {
+    file.archive.as_deref().unwrap_or(&file.name)
+}
+
+/// The longest path Windows programs take without the `\\?\' prefix, counting the nul ending it.
+const MAX_PATH: usize = 260;
+
+/// Names Windows keeps for devices, whatever the extension, which only a path with the `\\?\' prefix can give a file.
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8", "COM9",
+    "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// `path' without the `\\?\' prefix `fs::canonicalize' gives Windows paths, spelled as the user would, so the folder
+/// isn't taken for another than the one picked in the app, and Explorer, which doesn't take such paths, can show it.
+/// The prefix stays where the path needs it: when too long without, or naming what only it allows, such as a file
+/// called `nul' or ending in a dot.  Paths without it are returned as they are.
+pub fn plain_path(path: &Path) -> PathBuf {
+    let Some(verbatim) = path.to_str() else {
+        return path.to_path_buf();
+    };
+    let plain = match (verbatim.strip_prefix(r"\\?\UNC\"), verbatim.strip_prefix(r"\\?\")) {
+        (Some(share), _) => format!(r"\\{:}", share),
+        (None, Some(local)) if matches!(local.as_bytes(), [drive, b':', b'\\', ..] if drive.is_ascii_alphabetic()) => local.to_owned(),
+        _ => return path.to_path_buf(),
+    };
+    let needs_prefix = |name: &str| {
+        let stem = name.split('.').next().unwrap_or_default().trim_end();
+        matches!(name, "." | "..")
+            || name.ends_with(['.', ' '])
+            || name.contains('/')
+            || RESERVED_NAMES.iter().any(|reserved| stem.eq_ignore_ascii_case(reserved))
+    };
+    match plain.encode_utf16().count() < MAX_PATH && !plain.split('\\').any(needs_prefix) {
+        true => PathBuf::from(plain),
+        false => path.to_path_buf(),
+    }
+}
+
+/// Whether `path' is on a network share named by its server, as in `\\server\share\file'.
+pub fn network_share(path: &Path) -> bool {
+    let path = path.to_string_lossy();
+    path.starts_with(r"\\?\UNC\") || (path.starts_with(r"\\") && !path.starts_with(r"\\?\") && !path.starts_with(r"\\.\"))
+}
+
+/// Refuse to trash `path' where that would delete it for good: Windows has no recycle bin on network shares.  Shares
+/// mapped to a drive letter look like local drives, so they aren't caught.
+pub fn check_trash(path: &Path) -> io::Result<()> {
+    if cfg!(windows) && network_share(path) {
+        return Err(io::Error::new(io::ErrorKind::Unsupported, "network shares have no recycle bin, the file would be deleted for good"));
+    }
+    Ok(())
+}
+
+/// Move `path' to the trash or recycle bin, see `check_trash'.
+pub fn trash(path: &Path, _permit: &Permit) -> io::Result<()> {
+    check_trash(path)?;
+    trash::delete(path).map_err(|e| io::Error::other(e.to_string()))
+}
+
+/// Remove `path' for good, unlike `trash'.
+pub fn delete(path: &Path, _permit: &Permit) -> io::Result<()> {
+    fs::remove_file(path)
+}
+
+/// Remove the folder `path', which must be empty.
+pub fn remove_empty_dir(path: &Path, _permit: &Permit) -> io::Result<()> {
+    fs::remove_dir(path)
+}
+
+/// Open every distinct folder containing a member of `group', reporting the result per folder.
+pub fn open_containing_folders<'a>(group: impl IntoIterator<Item = &'a FileInfo>) -> Vec<(PathBuf, io::Result<()>)> {
+    relate::containing_folders(group)
+        .into_iter()
+        .map(|dir| {
+            let result = open_path(&dir);
+            (dir, result)
+        })
+        .collect()
+}
+
+/// Make `dst', which must not exist yet, a symbolic link to `src'.  Windows only lets administrators and
+/// developer mode make them.
+#[cfg(unix)]
+pub fn symlink(src: &Path, dst: &Path, _permit: &Permit) -> io::Result<()> {
+    std::os::unix::fs::symlink(src, dst)
+}
+
+#[cfg(windows)]
+pub fn symlink(src: &Path, dst: &Path, _permit: &Permit) -> io::Result<()> {
+    std::os::windows::fs::symlink_file(src, dst)
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn symlink(_src: &Path, _dst: &Path, _permit: &Permit) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "symbolic links aren't supported here"))
+}
+
+/// Files macOS keeps beside the user's, on its own disks and any it has written to: folder view settings, the
+/// resource forks of files on disks that can't hold them, and the Spotlight index, trash and event log of a disk.
+/// They are alike from folder to folder, so they would make up many groups of copies nobody means to remove.
+pub const MAC_METADATA: [&str; 5] = [".DS_Store", "._*", ".Spotlight-V100", ".Trashes", ".fseventsd"];
+
+/// Give `path' the times `metadata' has, the modification time and, where the platform lets it be set, the creation
+/// time, as after replacing a file with a clone of another, so it still looks as old as it was.  Linux keeps
+/// creation times to itself.
+pub fn set_times(path: &Path, metadata: &fs::Metadata) -> io::Result<()> {
+    let times = fs::FileTimes::new().set_accessed(metadata.accessed()?).set_modified(metadata.modified()?);
+    #[cfg(target_os = "macos")]
+    let times = {
+        use std::os::macos::fs::FileTimesExt;
+        metadata.created().map_or(times, |created| times.set_created(created))
+    };
+    #[cfg(windows)]
+    let times = {
+        use std::os::windows::fs::FileTimesExt;
+        metadata.created().map_or(times, |created| times.set_created(created))
+    };
+    // Setting times needs no write access, which a read-only file wouldn't give.
+    #[cfg(windows)]
+    let file = {
+        use std::os::windows::fs::OpenOptionsExt;
+        fs::OpenOptions::new().access_mode(FILE_WRITE_ATTRIBUTES).open(path)?
+    };
+    #[cfg(not(windows))]
+    let file = fs::File::open(path)?;
+    file.set_times(times)
+}
+
+/// Make `dst', which must not exist yet, a copy-on-write clone of `src'.  The two share storage until either
+/// is changed.  Only some filesystems can do this, see `supports_reflinks'.
+#[cfg(target_os = "linux")]
+pub fn reflink(src: &Path, dst: &Path, _permit: &Permit) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let src = fs::File::open(src)?;
+    let clone = fs::OpenOptions::new().write(true).create_new(true).open(dst)?;
+    // SAFETY: both descriptors are open for as long as the call lasts.
+    if unsafe { libc::ioctl(clone.as_raw_fd(), libc::FICLONE, src.as_raw_fd()) } == -1 {
+        let e = io::Error::last_os_error();
+        drop(clone);
+        let _ = fs::remove_file(dst);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+pub fn reflink(src: &Path, dst: &Path, _permit: &Permit) -> io::Result<()> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let src = CString::new(src.as_os_str().as_bytes())?;
+    let dst = CString::new(dst.as_os_str().as_bytes())?;
+    // SAFETY: both are valid, nul terminated paths.
+    if unsafe { libc::clonefile(src.as_ptr(), dst.as_ptr(), 0) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// ReFS block cloning isn't wired up, and other platforms have no such call.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn reflink(_src: &Path, _dst: &Path, _permit: &Permit) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "reflinks aren't supported on this platform"))
+}
+
+/// Whether files in `dir' can be cloned with `reflink', going by the type of its filesystem so nothing is written.
+/// Btrfs and XFS on Linux and APFS on macOS can, given a recent enough system; an XFS made without reflinks still
+/// fails in `reflink', which leaves the copy as it was.
+pub fn supports_reflinks(dir: &Path) -> bool {
+    filesystem_name(dir).is_some_and(|name| matches!(name.as_str(), "btrfs" | "xfs" | "apfs"))
+}
+
+/// The name of the filesystem holding `path', for telling the user where reflinks work.
+#[cfg(target_os = "linux")]
+pub fn filesystem_name(path: &Path) -> Option<String> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statfs' only writes into `stat', which is plain data.
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } == -1 {
+        return None;
+    }
+    let name = match stat.f_type as u64 {
+        0x9123683e => "btrfs",
+        0x58465342 => "xfs",
+        0xef53 => "ext4",
+        0x01021994 => "tmpfs",
+        0x2fc12fc1 => "zfs",
+        0xf2f52010 => "f2fs",
+        0x6969 => "nfs",
+        0x65735546 => "fuse",
+        0x4d44 => "vfat",
+        0x5346544e => "ntfs",
+        0x794c7630 => "overlayfs",
+        other => return Some(format!("filesystem type {:#x}", other)),
+    };
+    Some(name.to_owned())
+}
+
+#[cfg(target_os = "macos")]
+pub fn filesystem_name(path: &Path) -> Option<String> {
+    use std::{ffi::{CStr, CString}, os::unix::ffi::OsStrExt};
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    // SAFETY: `statfs' only writes into `stat', which is plain data, and leaves a nul terminated type name.
+    let mut stat = unsafe { std::mem::zeroed::<libc::statfs>() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } == -1 {
+        return None;
+    }
+    let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn filesystem_name(_path: &Path) -> Option<String> {
+    None
+}
+
+/// What sort of storage a device is, as far as it matters for reading from it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    /// A spinning disk, where reading several files at once makes the heads seek back and forth between them.
+    Rotational,
+    /// Flash storage such as an SSD or NVMe drive, which reads all the faster for being asked more at once.
+    SolidState,
+    /// Network shares, filesystems spread over several disks, or a platform that won't say.
+    Unknown,
+}
+
+/// The kind of storage behind `device', a device id as in `FileInfo::device'.  Linux tells whether a block device
+/// spins; partitions are asked about through the disk holding them.
+#[cfg(target_os = "linux")]
+pub fn device_kind(device: u64) -> DeviceKind {
+    let block = PathBuf::from(format!("/sys/dev/block/{:}:{:}", libc::major(device), libc::minor(device)));
+    let rotational = fs::read_to_string(block.join("queue/rotational")).or_else(|_| fs::read_to_string(block.join("../queue/rotational")));
+    match rotational.as_deref().map(str::trim) {
+        Ok("1") => DeviceKind::Rotational,
+        Ok("0") => DeviceKind::SolidState,
+        _ => DeviceKind::Unknown,
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn device_kind(_device: u64) -> DeviceKind {
+    DeviceKind::Unknown
+}
+
+/// The files other processes have open, so that removing or replacing one could pull it out from under that program.
+/// Made once for a whole plan: on Linux every process's open descriptors are read up front, which misses those of
+/// processes belonging to other users.  Elsewhere each file is asked about when it is looked up.
+#[derive(Debug, Default)]
+pub struct OpenFiles {
+    /// Device and inode of each file open on Linux.
+    #[cfg(target_os = "linux")]
+    ids: std::collections::HashSet<(u64, u64)>,
+}
+
+impl OpenFiles {
+    #[cfg(target_os = "linux")]
+    pub fn read() -> Self {
+        use std::os::unix::fs::MetadataExt;
+        let own = std::process::id().to_string();
+        let Ok(processes) = fs::read_dir("/proc") else {
+            return Self::default();
+        };
+        let ids = processes
+            .flatten()
+            .filter(|process| {
+                let name = process.file_name();
+                name.to_str().is_some_and(|name| name != own && name.bytes().all(|b| b.is_ascii_digit()))
+            })
+            .flat_map(|process| fs::read_dir(process.path().join("fd")).into_iter().flatten().flatten())
+            // Each descriptor is a link to what is open, and following it reaches the same file by any of its names.
+            .filter_map(|fd| fs::metadata(fd.path()).ok())
+            .map(|open| (open.dev(), open.ino()))
+            .collect();
+        Self { ids }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn read() -> Self {
+        Self::default()
+    }
+
+    /// Whether another process has `path' open.
+    #[cfg(target_os = "linux")]
+    pub fn contains(&self, path: &Path) -> bool {
+        use std::os::unix::fs::MetadataExt;
+        fs::metadata(path).is_ok_and(|metadata| self.ids.contains(&(metadata.dev(), metadata.ino())))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn contains(&self, path: &Path) -> bool {
+        in_use(path)
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn in_use(path: &Path) -> bool {
+    // `lsof' only succeeds when it finds the file open somewhere.
+    Command::new("lsof")
+        .args(["-t", "--"])
+        .arg(path)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+#[cfg(windows)]
+const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+#[cfg(windows)]
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// Windows tells us directly: a file some program has open without sharing it can't be opened exclusively.
+#[cfg(windows)]
+fn in_use(path: &Path) -> bool {
+    use std::os::windows::fs::OpenOptionsExt;
+    fs::OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .open(path)
+        .is_err_and(|e| e.raw_os_error() == Some(ERROR_SHARING_VIOLATION))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn in_use(_path: &Path) -> bool {
+    false
+}
+
+/// Whether another program holds an exclusive lock on `file', as one writing it out may.  Unix locks only bind those
+/// who ask for them, so a shared lock is asked for, and let go straight away.  Filesystems without locks say no.
+pub fn locked(file: &fs::File) -> bool {
+    match file.try_lock_shared() {
+        Ok(()) => {
+            let _ = file.unlock();
+            false
+        },
+        Err(fs::TryLockError::WouldBlock) => true,
+        Err(fs::TryLockError::Error(_)) => false,
+    }
+}
+
+/// Whether `e', from opening or reading a file, is Windows refusing because another program has the file open
+/// without sharing it, or has locked the part being read.
+#[cfg(windows)]
+pub fn is_lock_error(e: &io::Error) -> bool {
+    matches!(e.raw_os_error(), Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION))
+}
+
+/// Elsewhere, locks don't get in the way of reading.
+#[cfg(not(windows))]
+pub fn is_lock_error(_e: &io::Error) -> bool {
+    false
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn resource_usage() -> Option<libc::rusage> {
+    // SAFETY: `getrusage' only writes into `usage', which is plain data.
+    let mut usage = unsafe { std::mem::zeroed::<libc::rusage>() };
+    (unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } == 0).then_some(usage)
+}
+
+/// Processor time this process has used so far, in every thread, user and system.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn cpu_time() -> Option<std::time::Duration> {
+    let usage = resource_usage()?;
+    let time = |t: libc::timeval| std::time::Duration::new(t.tv_sec as u64, t.tv_usec as u32 * 1000);
+    Some(time(usage.ru_utime) + time(usage.ru_stime))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn cpu_time() -> Option<std::time::Duration> {
+    None
+}
+
+/// The most memory this process has held at once, in bytes.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+pub fn peak_memory() -> Option<u64> {
+    let max_rss = resource_usage()?.ru_maxrss as u64;
+    // macOS counts in bytes, everyone else in kibibytes.
+    Some(if cfg!(target_os = "macos") { max_rss } else { max_rss * 1024 })
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn peak_memory() -> Option<u64> {
+    None
+}
+
+/// Run the calling thread at a lower priority, so work done in the background leaves the machine usable.
+/// Linux keeps a nice value per thread, and unless told otherwise reads a thread's files at the disk priority
+/// that follows from it.
+#[cfg(target_os = "linux")]
+pub fn lower_priority() -> io::Result<()> {
+    // SAFETY: only changes the scheduling of the calling thread.
+    match unsafe { libc::setpriority(libc::PRIO_PROCESS, 0, 10) } {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+/// The background class has both the processor and the disk throttled for the thread.
+#[cfg(target_os = "macos")]
+pub fn lower_priority() -> io::Result<()> {
+    // SAFETY: only changes the scheduling of the calling thread.
+    match unsafe { libc::pthread_set_qos_class_self_np(libc::qos_class_t::QOS_CLASS_BACKGROUND, 0) } {
+        0 => Ok(()),
+        e => Err(io::Error::from_raw_os_error(e)),
+    }
+}
+
+/// Elsewhere, threads keep their priority.
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn lower_priority() -> io::Result<()> {
+    Err(io::ErrorKind::Unsupported.into())
+}
@@ -0,0 +1,270 @@
+//! Decide which file in a group of duplicates survives a deduplication.
+
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt, str::FromStr,
+    iter::Sum,
+    ops::AddAssign,
+    path::PathBuf,
+};
+use chrono::NaiveDate;
+use regex::Regex;
+use serde::{Serialize, Deserialize};
+use itertools::Itertools;
+use crate::{
+    paths, photo,
+    relate::{self, FileInfo, RelatedFiles},
+};
+
+/// The rule used to pick the canonical copy of each duplicate group.
+/// Every policy falls back to comparing full paths, so the choice is always deterministic.
+/// Except for `RegexPriority', `CaptureDateName' and `DatedFolder', which state outright where copies belong, the
+/// copy using the least disk is kept first: linking to a sparse copy keeps it sparse, and deleting it would free less
+/// than its size.
+/// Above all, a real file is always kept over a followed symbolic link, which would dangle without it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeepPolicy {
+    /// Keep the file with the earliest creation time.
+    OldestCreated,
+    /// Keep the file with the latest creation time.
+    NewestCreated,
+    /// Keep the file with the fewest characters in its path.
+    ShortestPath,
+    /// Keep the file with the fewest path components.
+    ShallowestDirectory,
+    /// Keep the file whose path sorts first.
+    Alphabetical,
+    /// Keep the file matching the earliest pattern in the list.
+    /// Files matching no pattern rank below every file that does.
+    RegexPriority(Vec<String>),
+    /// For photos, keep the file whose name holds the date it was taken, going by its EXIF metadata.
+    CaptureDateName,
+    /// For photos, keep the file inside folders named after the date it was taken, going by its EXIF metadata.
+    DatedFolder,
+}
+
+impl fmt::Display for KeepPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeepPolicy::OldestCreated => write!(f, "oldest"),
+            KeepPolicy::NewestCreated => write!(f, "newest"),
+            KeepPolicy::ShortestPath => write!(f, "shortest"),
+            KeepPolicy::ShallowestDirectory => write!(f, "shallowest"),
+            KeepPolicy::Alphabetical => write!(f, "alphabetical"),
+            KeepPolicy::RegexPriority(pats) => write!(f, "regex:{:}", pats.join(",")),
+            KeepPolicy::CaptureDateName => write!(f, "capture-name"),
+            KeepPolicy::DatedFolder => write!(f, "dated-folder"),
+        }
+    }
+}
+
+/// Parse the names produced by `Display', e.g. `oldest' or `regex:/originals/,/photos/'.
+impl FromStr for KeepPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "oldest" => Ok(KeepPolicy::OldestCreated),
+            "newest" => Ok(KeepPolicy::NewestCreated),
+            "shortest" => Ok(KeepPolicy::ShortestPath),
+            "shallowest" => Ok(KeepPolicy::ShallowestDirectory),
+            "alphabetical" => Ok(KeepPolicy::Alphabetical),
+            "capture-name" => Ok(KeepPolicy::CaptureDateName),
+            "dated-folder" => Ok(KeepPolicy::DatedFolder),
+            _ => match s.strip_prefix("regex:") {
+                Some(pats) => Ok(KeepPolicy::RegexPriority(pats.split(',').map(str::to_owned).collect())),
+                None => Err(format!("Unknown keep policy '{:}', expected one of oldest, newest, shortest, shallowest, alphabetical, capture-name, dated-folder or regex:PATTERN,...", s)),
+            },
+        }
+    }
+}
+
+/// The outcome of applying a `KeepPolicy' to a single group.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Resolution<'a> {
+    pub keep: &'a FileInfo,
+    pub remove: Vec<&'a FileInfo>,
+}
+
+impl Resolution<'_> {
+    /// What removing the copies would free.  Hard links to one file share its blocks, so they count once, and not at
+    /// all when linked to the copy kept.  Followed symbolic links free nothing, their target being another copy.
+    pub fn reclaimable(&self) -> Reclaim {
+        let mut files = self.keep.file_id.into_iter().collect::<HashSet<(u64, u64)>>();
+        let mut reclaim = Reclaim::default();
+        for fi in &self.remove {
+            if fi.link_target.is_some() || fi.file_id.is_some_and(|id| !files.insert(id)) {
+                continue;
+            }
+            reclaim += Reclaim { size: fi.size, allocated: fi.allocated() };
+        }
+        reclaim
+    }
+}
+
+/// Bytes that deduplicating would free.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Reclaim {
+    /// By the sizes of the files.
+    pub size: u64,
+    /// By the disk they use, which is less for sparse files.
+    pub allocated: u64,
+}
+
+impl AddAssign for Reclaim {
+    fn add_assign(&mut self, other: Self) {
+        self.size += other.size;
+        self.allocated += other.allocated;
+    }
+}
+
+impl Sum for Reclaim {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        let mut total = Reclaim::default();
+        for reclaim in iter {
+            total += reclaim;
+        }
+        total
+    }
+}
+
+/// A compiled `KeepPolicy' ready to be applied to groups.
+#[derive(Debug, Clone)]
+pub struct Resolver {
+    policy: KeepPolicy,
+    patterns: Vec<Regex>,
+    reference: Option<PathBuf>,
+}
+
+impl Resolver {
+    /// Compile `policy'.  Only fails when a `RegexPriority' pattern is invalid.
+    pub fn new(policy: KeepPolicy) -> Result<Self, regex::Error> {
+        let patterns = match &policy {
+            KeepPolicy::RegexPriority(pats) => pats
+                .iter()
+                .map(|pat| Regex::new(pat))
+                .collect::<Result<Vec<Regex>, regex::Error>>()?,
+            _ => Vec::new(),
+        };
+        Ok(Self { policy, patterns, reference: None })
+    }
+
+    /// Never remove files inside `reference', and keep one of them whenever a group has any.
+    pub fn with_reference(self, reference: PathBuf) -> Self {
+        Self { reference: Some(reference), ..self }
+    }
+
+    pub fn policy(&self) -> &KeepPolicy {
+        &self.policy
+    }
+
+    /// Whether `info' is in the reference folder, however either is spelled, and so must be left alone.
+    pub fn is_protected(&self, info: &FileInfo) -> bool {
+        self.reference.as_ref().is_some_and(|reference| paths::starts_with(&info.name, reference))
+    }
+
+    fn priority(&self, info: &FileInfo) -> usize {
+        let name = info.name.to_string_lossy();
+        self.patterns
+            .iter()
+            .position(|re| re.is_match(&name))
+            .unwrap_or(self.patterns.len())
+    }
+
+    /// The date the photo duplicated in `group' was taken, when the policy goes by it.  The copies hold the same
+    /// metadata, so it is read from the first loose one with any.
+    fn capture_date(&self, group: &[&FileInfo]) -> Option<NaiveDate> {
+        match self.policy {
+            KeepPolicy::CaptureDateName | KeepPolicy::DatedFolder => {
+                photo::capture_date(group.iter().filter(|info| !info.in_archive()).map(|info| &*info.name))
+            },
+            _ => None,
+        }
+    }
+
+    /// Order two files so that the one to keep comes first.
+    /// Loose files come before archive members, so a group keeps a file it can link to when it has one.
+    pub fn compare(&self, a: &FileInfo, b: &FileInfo) -> Ordering {
+        self.compare_captured(a, b, self.capture_date(&[a, b]))
+    }
+
+    /// Like `compare', for two copies of a photo taken on `captured', if known.
+    fn compare_captured(&self, a: &FileInfo, b: &FileInfo, captured: Option<NaiveDate>) -> Ordering {
+        let protected = self.is_protected(b).cmp(&self.is_protected(a));
+        let loose = a.in_archive().cmp(&b.in_archive());
+        let link = a.link_target.is_some().cmp(&b.link_target.is_some());
+        let allocated = match self.policy {
+            KeepPolicy::RegexPriority(_) | KeepPolicy::CaptureDateName | KeepPolicy::DatedFolder => Ordering::Equal,
+            _ => a.allocated().cmp(&b.allocated()),
+        };
+        let ord = match self.policy {
+            KeepPolicy::OldestCreated => a.time().oldest_first(&b.time()),
+            KeepPolicy::NewestCreated => a.time().newest_first(&b.time()),
+            KeepPolicy::ShortestPath => a.name.as_os_str().len().cmp(&b.name.as_os_str().len()),
+            KeepPolicy::ShallowestDirectory => a.name.components().count().cmp(&b.name.components().count()),
+            KeepPolicy::Alphabetical => Ordering::Equal,
+            KeepPolicy::RegexPriority(_) => self.priority(a).cmp(&self.priority(b)),
+            KeepPolicy::CaptureDateName => captured.map_or(Ordering::Equal, |date| {
+                photo::name_has_date(&b.name, date).cmp(&photo::name_has_date(&a.name, date))
+            }),
+            KeepPolicy::DatedFolder => captured.map_or(Ordering::Equal, |date| {
+                photo::in_dated_folder(&b.name, date).cmp(&photo::in_dated_folder(&a.name, date))
+            }),
+        };
+        protected.then(loose).then(link).then(allocated).then(ord).then_with(|| a.name.cmp(&b.name))
+    }
+
+    /// Pick the survivor of `group'.  Returns `None' for an empty group.
+    pub fn survivor<'a>(&self, group: impl IntoIterator<Item = &'a FileInfo>) -> Option<&'a FileInfo> {
+        let group = group.into_iter().collect::<Vec<&FileInfo>>();
+        let captured = self.capture_date(&group);
+        group.into_iter().min_by(|a, b| self.compare_captured(a, b, captured))
+    }
+
+    /// Split `group' into the survivor and the files it makes redundant.
+    /// Other files in the reference folder are neither, they are simply left alone, and so are archive members,
+    /// which can't be removed without rewriting their archive.
+    pub fn resolve<'a>(&self, group: &'a HashSet<FileInfo>) -> Option<Resolution<'a>> {
+        let keep = self.survivor(group)?;
+        Some(Resolution { keep, remove: self.removable(group, keep) })
+    }
+
+    /// Like `resolve', but keep an archive member and remove every loose copy of it, since the archive
+    /// already holds the contents.  Returns `None' unless `group' has an archive member.
+    pub fn resolve_against_archives<'a>(&self, group: &'a HashSet<FileInfo>) -> Option<Resolution<'a>> {
+        let keep = self.survivor(group.iter().filter(|info| info.in_archive()))?;
+        Some(Resolution { keep, remove: self.removable(group, keep) })
+    }
+
+    fn removable<'a>(&self, group: &'a HashSet<FileInfo>, keep: &FileInfo) -> Vec<&'a FileInfo> {
+        let mut remove = group
+            .iter()
+            .filter(|info| *info != keep && !self.is_protected(info) && !info.in_archive())
+            .collect::<Vec<&FileInfo>>();
+        remove.sort_by(|a, b| a.name.cmp(&b.name));
+        remove
+    }
+
+    /// Resolve every group in `related' that actually contains duplicates, keyed by hash.
+    pub fn resolve_all<'a>(&self, related: &'a RelatedFiles) -> Vec<(&'a String, Resolution<'a>)> {
+        Self::resolve_each(related, |group| self.resolve(group))
+    }
+
+    /// `resolve_against_archives' every group in `related' with an archive member, keyed by hash.
+    pub fn resolve_all_against_archives<'a>(&self, related: &'a RelatedFiles) -> Vec<(&'a String, Resolution<'a>)> {
+        Self::resolve_each(related, |group| self.resolve_against_archives(group))
+    }
+
+    fn resolve_each<'a>(
+        related: &'a RelatedFiles,
+        resolve: impl Fn(&'a HashSet<FileInfo>) -> Option<Resolution<'a>>,
+    ) -> Vec<(&'a String, Resolution<'a>)> {
+        related.files
+            .iter()
+            .filter(|(_, group)| relate::holds_duplicates(group))
+            .filter_map(|(hash, group)| resolve(group).map(|res| (hash, res)))
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .collect()
+    }
+}
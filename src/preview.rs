@@ -0,0 +1,69 @@
+//! Show what is in a file, so the user can tell copies apart before removing any: a thumbnail of a picture, the
+//! first lines of a text, and what the filesystem says about it, which `FileInfo' already holds.
+
+use std::{fs, io::Read};
+use crate::{
+    images::{self, Thumbnail},
+    relate::FileInfo,
+};
+
+/// Thumbnails fit in a square this many pixels wide.
+pub const THUMBNAIL_SIZE: u32 = 256;
+/// Lines of a text shown at most.
+pub const TEXT_LINES: usize = 20;
+/// Bytes read from the start of a file to tell whether it is text, and to take its first lines from.
+const TEXT_BYTES: u64 = 8 * 1024;
+
+/// What a file holds, as far as it can be shown.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Content {
+    Image(Thumbnail),
+    /// The first `TEXT_LINES' lines at most, cut short when they run past the first `TEXT_BYTES' bytes.
+    Text(Vec<String>),
+    /// Neither a picture we can read nor UTF-8 text.
+    Binary,
+    /// A member of an archive, which can't be read on its own.
+    InArchive,
+    /// The file couldn't be read, for this reason.
+    Unreadable(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preview {
+    pub file: FileInfo,
+    pub content: Content,
+}
+
+/// Look into `file'.  Pictures are decoded in full to be shrunk, so large ones take a moment.
+pub fn preview(file: &FileInfo) -> Preview {
+    let content = if file.in_archive() {
+        Content::InArchive
+    } else if images::is_image(&file.name) {
+        match images::thumbnail(&file.name, THUMBNAIL_SIZE) {
+            Ok(thumbnail) => Content::Image(thumbnail),
+            Err(e) => Content::Unreadable(e.to_string()),
+        }
+    } else {
+        text(file)
+    };
+    Preview { file: file.clone(), content }
+}
+
+/// The first lines of `file', or `Content::Binary' when it doesn't read as text.
+fn text(file: &FileInfo) -> Content {
+    let mut start = Vec::new();
+    let read = fs::File::open(&file.name).and_then(|f| f.take(TEXT_BYTES).read_to_end(&mut start));
+    if let Err(e) = read {
+        return Content::Unreadable(format!("{:}: {:}", file.name.display(), e));
+    }
+    // The last character may have been cut in half, which is no reason to take the file for binary.
+    let text = match std::str::from_utf8(&start) {
+        Ok(text) => text,
+        Err(e) if e.error_len().is_none() => std::str::from_utf8(&start[..e.valid_up_to()]).expect("Checked above"),
+        Err(_) => return Content::Binary,
+    };
+    if text.contains('\0') {
+        return Content::Binary;
+    }
+    Content::Text(text.lines().take(TEXT_LINES).map(str::to_owned).collect())
+}
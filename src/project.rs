@@ -0,0 +1,404 @@
+//! Persist deduplication work, so it can be listed and resumed in a later session.
+//! Projects are kept in the database of `store'.  Earlier versions wrote each to a JSON file in the `projects'
+//! folder of the data directory, and those are moved into the database the first time they are needed.
+
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    collections::{BTreeMap, BTreeSet},
+    time::SystemTime,
+};
+use serde::{Serialize, Deserialize};
+use sha2::{Sha256, Digest};
+use crate::{
+    actions::{Action, Outcome},
+    lock::{self, Lock},
+    paths,
+    persist,
+    policy::KeepPolicy,
+    rules::Rule,
+    relate::{WalkConf, WalkInfo, RelatedFiles},
+    settings::Profile,
+    store::{self, Store},
+    usage::ScanUsage,
+};
+
+const PROJECTS_DIR: &str = "projects";
+/// The extension of the project files written by earlier versions.
+const LEGACY_EXTENSION: &str = "json";
+/// What a project file becomes once moved into the database, so it's kept but never read again.
+const IMPORTED_EXTENSION: &str = "imported";
+const LOCK_EXTENSION: &str = "lock";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Format(serde_json::Error),
+    Store(store::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(serde_json::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+fn store_error(e: store::Error) -> Error {
+    Error {
+        path: e.path.clone(),
+        error_type: ErrorType::Store(e),
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed project file: {:}", self.path.display(), e),
+            // Says where itself.
+            ErrorType::Store(e) => write!(f, "{:}", e),
+        }
+    }
+}
+
+/// Choices the user has made about the duplicate groups, keyed by content hash.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Decisions {
+    /// Files the user has marked for removal.
+    pub remove: BTreeMap<String, BTreeSet<PathBuf>>,
+    /// Files already acted on, so a group can be dealt with a few copies at a time, across sessions.
+    #[serde(default)]
+    pub done: BTreeMap<String, BTreeSet<PathBuf>>,
+}
+
+/// How far the user has got with a duplicate group, see `Project::group_status'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GroupStatus {
+    /// No copy has been acted on.
+    Open,
+    /// Some copies have been acted on, and more than one is left.
+    Partial { done: usize, remaining: usize },
+    /// At most one copy is left untouched.
+    Resolved,
+}
+
+/// How the user last deduplicated the project, offered again next time.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preferences {
+    pub action: Option<Action>,
+    pub keep: Option<KeepPolicy>,
+    /// The selection rules last composed, see `rules::select'.
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// Everything we know about deduplicating one directory.
+#[derive(Serialize, Deserialize)]
+pub struct Project {
+    /// The directory being deduplicated, which the project is known by.
+    pub path: PathBuf,
+    /// More directories deduplicated along with `path', such as a backup of it elsewhere, see `roots'.
+    #[serde(default)]
+    pub extra_roots: Vec<PathBuf>,
+    /// A directory `path' is compared against.  Only files in `path' with a copy here are reported,
+    /// and nothing here is ever changed.
+    #[serde(default)]
+    pub reference: Option<PathBuf>,
+    pub created: SystemTime,
+    pub updated: SystemTime,
+    /// The filters used for every scan of the project.
+    #[serde(default)]
+    pub walk_conf: WalkConf,
+    pub walk: Option<WalkInfo>,
+    pub related: Option<RelatedFiles>,
+    /// What producing `related' cost.
+    #[serde(default)]
+    pub usage: Option<ScanUsage>,
+    pub decisions: Decisions,
+    #[serde(default)]
+    pub preferences: Preferences,
+}
+
+/// The part of a `Project' needed to offer it for resumption.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Summary {
+    /// The directory being deduplicated.
+    pub path: PathBuf,
+    pub updated: SystemTime,
+    /// Whether hashing finished before the project was saved.
+    pub related: bool,
+}
+
+/// The directory holding all project files under the data directory `data_dir'.
+pub fn projects_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(PROJECTS_DIR)
+}
+
+impl Project {
+    /// A new project for `path', spelled as in `paths::normalize' so every spelling gets the same project.
+    pub fn new(path: PathBuf) -> Self {
+        let now = SystemTime::now();
+        Self {
+            path: paths::normalize(&path),
+            extra_roots: Vec::new(),
+            reference: None,
+            created: now,
+            updated: now,
+            walk_conf: WalkConf::default(),
+            walk: None,
+            related: None,
+            usage: None,
+            decisions: Decisions::default(),
+            preferences: Preferences::default(),
+        }
+    }
+
+    /// Compare against `reference' from now on.  Results made against another reference don't apply, so a change
+    /// drops them.  Returns whether anything changed.
+    pub fn set_reference(&mut self, reference: Option<PathBuf>) -> bool {
+        let reference = reference.map(|reference| paths::normalize(&reference));
+        if self.reference == reference {
+            return false;
+        }
+        self.reference = reference;
+        self.forget_results();
+        true
+    }
+
+    /// Deduplicate `extra_roots' along with `path' from now on, in place of any before.  Results made over other
+    /// directories don't apply, so a change drops them.  Returns whether anything changed.
+    pub fn set_extra_roots(&mut self, extra_roots: Vec<PathBuf>) -> bool {
+        let extra_roots = extra_roots.iter().map(|root| paths::normalize(root)).collect::<Vec<PathBuf>>();
+        if self.extra_roots == extra_roots {
+            return false;
+        }
+        self.extra_roots = extra_roots;
+        self.forget_results();
+        true
+    }
+
+    /// Scan with the filters of `profile' from now on, and deduplicate as it says where it says anything.  Results
+    /// are kept until the next scan, as when the filters are changed by hand.
+    pub fn apply_profile(&mut self, profile: &Profile) {
+        self.walk_conf = profile.filters.clone();
+        self.preferences.keep = profile.keep.clone().or(self.preferences.keep.take());
+        self.preferences.action = profile.action.or(self.preferences.action);
+    }
+
+    /// The directories being deduplicated: `path', then `extra_roots' but those inside another.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        paths::distinct_roots(&[vec![self.path.clone()], self.extra_roots.clone()].concat())
+    }
+
+    /// The directories a scan walks: `roots' and the reference, if any.
+    pub fn scanned(&self) -> Vec<PathBuf> {
+        self.roots().into_iter().chain(self.reference.clone()).collect()
+    }
+
+    fn forget_results(&mut self) {
+        self.walk = None;
+        self.related = None;
+        self.usage = None;
+        self.decisions = Decisions::default();
+    }
+
+    /// Take note of the `outcomes' of carrying out a plan.  The files acted on are done and no longer marked for
+    /// removal, and those that were removed leave their groups.  Outcomes of a dry run must not be recorded.
+    pub fn record(&mut self, outcomes: &[Outcome]) {
+        for step in outcomes.iter().filter(|outcome| outcome.result.is_ok()).map(|outcome| &outcome.step) {
+            self.decisions.done.entry(step.hash.clone()).or_default().insert(step.target.clone());
+            if let Some(picked) = self.decisions.remove.get_mut(&step.hash) {
+                picked.remove(&step.target);
+                if picked.is_empty() {
+                    self.decisions.remove.remove(&step.hash);
+                }
+            }
+            if !matches!(step.action, Action::Trash | Action::Delete) {
+                continue;
+            }
+            if let Some(group) = self.related.as_mut().and_then(|related| related.files.get_mut(&step.hash)) {
+                group.retain(|fi| *fi.name != *step.target);
+            }
+        }
+    }
+
+    /// Whether `path' is in the reference folder, however either is spelled, and so must be left alone.
+    pub fn in_reference(&self, path: &Path) -> bool {
+        self.reference.as_ref().is_some_and(|reference| paths::starts_with(path, reference))
+    }
+
+    /// Mark `path', a copy in the group with `hash', for removal, or unmark it if it was.  A copy in the reference
+    /// folder is never marked, so returns whether the mark changed.
+    pub fn toggle_mark(&mut self, hash: &str, path: &Path) -> bool {
+        if self.in_reference(path) {
+            return false;
+        }
+        let marked = self.decisions.remove.entry(hash.to_owned()).or_default();
+        if !marked.remove(path) {
+            marked.insert(path.to_path_buf());
+        }
+        if marked.is_empty() {
+            self.decisions.remove.remove(hash);
+        }
+        true
+    }
+
+    /// How far the group of files with `hash' has been dealt with.
+    pub fn group_status(&self, hash: &str) -> GroupStatus {
+        let Some(done) = self.decisions.done.get(hash) else {
+            return GroupStatus::Open;
+        };
+        let group = self.related.as_ref().and_then(|related| related.files.get(hash));
+        let remaining = group.map_or(0, |group| group.iter().filter(|fi| !done.contains(&*fi.name)).count());
+        match remaining {
+            0 | 1 => GroupStatus::Resolved,
+            remaining => GroupStatus::Partial { done: done.len(), remaining },
+        }
+    }
+
+    /// A stable identifier derived from the project directory, used to name the project file.
+    pub fn id(&self) -> String {
+        let hash = format!("{:x}", Sha256::digest(self.path.as_os_str().as_encoded_bytes()));
+        hash[..16].to_owned()
+    }
+
+    /// Where earlier versions stored this project under the data directory `data_dir'.
+    pub fn legacy_file(&self, data_dir: &Path) -> PathBuf {
+        projects_dir(data_dir).join(self.id()).with_extension(LEGACY_EXTENSION)
+    }
+
+    /// Take the lock guarding this project under `data_dir' against concurrent changes.
+    /// Hold on to the result for as long as the project may be saved.
+    pub fn lock(&self, data_dir: &Path) -> Result<Lock, lock::Error> {
+        Lock::acquire(&self.lock_path(data_dir))
+    }
+
+    /// Where the lock taken by `lock' lives.
+    pub fn lock_path(&self, data_dir: &Path) -> PathBuf {
+        projects_dir(data_dir).join(self.id()).with_extension(LOCK_EXTENSION)
+    }
+
+    /// Write the project under `data_dir', replacing any earlier save of the same directory.
+    /// Returns the database it was written to.
+    pub fn save(&mut self, data_dir: &Path) -> Result<PathBuf, Error> {
+        self.updated = SystemTime::now();
+        Store::open(data_dir).and_then(|mut store| store.save(self)).map_err(store_error)?;
+        Ok(Store::file(data_dir))
+    }
+
+    /// Like `save', writing only what the user decided and the files `outcomes' removed, as after `record'.  The
+    /// saved scan is otherwise left alone, so this is for when the scan itself hasn't changed since it was saved.
+    pub fn save_decisions(&mut self, data_dir: &Path, outcomes: &[Outcome]) -> Result<PathBuf, Error> {
+        self.updated = SystemTime::now();
+        Store::open(data_dir).and_then(|mut store| store.save_decisions(self, outcomes)).map_err(store_error)?;
+        Ok(Store::file(data_dir))
+    }
+
+    /// Load the saved project for `path' under `data_dir', or start and save a new one.
+    pub fn open(data_dir: &Path, path: PathBuf) -> Result<Self, Error> {
+        Self::open_with(data_dir, path, &WalkConf::default())
+    }
+
+    /// Like `open', starting a new project with the filters `walk_conf'.
+    pub fn open_with(data_dir: &Path, path: PathBuf, walk_conf: &WalkConf) -> Result<Self, Error> {
+        Self::open_from(data_dir, path, walk_conf, Store::load)
+    }
+
+    /// Like `open_with', leaving the last scan in the store rather than reading every file of it: `walk' and
+    /// `related' are `None', and the groups are read as needed with `Store::groups' and `Store::group'.
+    pub fn open_unscanned(data_dir: &Path, path: PathBuf, walk_conf: &WalkConf) -> Result<Self, Error> {
+        Self::open_from(data_dir, path, walk_conf, Store::load_unscanned)
+    }
+
+    fn open_from(
+        data_dir: &Path, path: PathBuf, walk_conf: &WalkConf, load: fn(&Store, &Path) -> Result<Option<Self>, store::Error>,
+    ) -> Result<Self, Error> {
+        if let Some(project) = Self::load_from(data_dir, &path, load)? {
+            return Ok(project);
+        }
+        let mut project = Self { walk_conf: walk_conf.clone(), ..Self::new(path) };
+        project.save(data_dir)?;
+        Ok(project)
+    }
+
+    /// Read the project for `path' saved under `data_dir', if there is one.
+    pub fn load(data_dir: &Path, path: &Path) -> Result<Option<Self>, Error> {
+        Self::load_from(data_dir, path, Store::load)
+    }
+
+    /// Like `load', reading only the duplicate groups and empty files of the scan, see `Store::load_duplicates'.
+    pub fn load_duplicates(data_dir: &Path, path: &Path) -> Result<Option<Self>, Error> {
+        Self::load_from(data_dir, path, Store::load_duplicates)
+    }
+
+    fn load_from(data_dir: &Path, path: &Path, load: fn(&Store, &Path) -> Result<Option<Self>, store::Error>) -> Result<Option<Self>, Error> {
+        let mut store = Store::open(data_dir).map_err(store_error)?;
+        let legacy = Self::new(path.to_path_buf()).legacy_file(data_dir);
+        if legacy.exists() || persist::backup_path(&legacy).exists() {
+            import(&mut store, &legacy)?;
+        }
+        load(&store, &paths::normalize(path)).map_err(store_error)
+    }
+
+    /// Read a project file written by an earlier version.  A damaged file is replaced by the previous save, when
+    /// there is one.
+    pub fn load_legacy(file: &Path) -> Result<Self, Error> {
+        persist::read_recovering(
+            file,
+            |contents| serde_json::from_slice(contents).map_err(format_error(file)),
+            |e| io_error(file)(e),
+        )
+    }
+}
+
+/// Move the project in the legacy project `file' into `store', unless a newer save is already there.
+fn import(store: &mut Store, file: &Path) -> Result<(), Error> {
+    let project = Project::load_legacy(file)?;
+    if !store.contains(&project.path).map_err(store_error)? {
+        store.save(&project).map_err(store_error)?;
+    }
+    fs::rename(file, file.with_extension(IMPORTED_EXTENSION)).map_err(io_error(file))?;
+    // Otherwise the backup would be taken for the project file next time.
+    let _ = fs::remove_file(persist::backup_path(file));
+    Ok(())
+}
+
+/// List the projects saved under `data_dir', most recently updated first.
+/// Project files of earlier versions that fail to load are returned separately, so one bad file doesn't hide the rest.
+pub fn list(data_dir: &Path) -> Result<(Vec<Summary>, Vec<Error>), Error> {
+    let mut store = Store::open(data_dir).map_err(store_error)?;
+    let dir = projects_dir(data_dir);
+    let entries = match fs::read_dir(&dir) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        entries => Some(entries.map_err(io_error(&dir))?),
+    };
+    let mut errors = Vec::new();
+    for entry in entries.into_iter().flatten() {
+        let file = entry.map_err(io_error(&dir))?.path();
+        if file.extension().is_some_and(|ext| ext == LEGACY_EXTENSION) {
+            errors.extend(import(&mut store, &file).err());
+        }
+    }
+    let summaries = store.summaries().map_err(store_error)?;
+    Ok((summaries, errors))
+}
@@ -1,17 +1,24 @@
 /// Find files in a directory hierarchy with the same contents, and group them based on content.
 
 use std::{
-    fs, time, time::Duration,
-    path::PathBuf, io,
-    collections::{HashSet, HashMap},
-    sync::mpsc, sync::mpsc::{Sender, Receiver, RecvTimeoutError},
+    borrow::Cow, cmp, fmt, fs, mem, panic, slice, time,
+    path::{Path, PathBuf}, io, io::Read,
+    str::FromStr,
+    collections::{HashSet, HashMap, BTreeSet},
+    sync::{atomic::{self, AtomicBool, AtomicUsize}, Arc, Mutex, PoisonError},
+    sync::mpsc, sync::mpsc::{Sender, Receiver},
     thread,
 };
+use serde::{Serialize, Deserialize};
 use sha2::{Sha256, Digest};
+use xxhash_rust::xxh3::Xxh3;
 use walkdir::WalkDir;
-use itertools::Itertools;
+use memmap2::Mmap;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use crate::{archive, denylist::Denylist, paths, platform::{self, DeviceKind}, throttle::Throttle, tr};
 
-/// This type tracks content equality of files via a SDha256 hash and content size on bytes according to the operating system.
+/// This type tracks content equality of files via a content hash (see `HashAlgo') and content size on bytes according
+/// to the operating system.
 /// The system path is tracked to differentiate files on the filesystem.
 /// The creation time is included, so we can prioritize files with equivalent contents using the age.
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -20,22 +27,154 @@ pub struct HashedFile {
     pub info: FileInfo,
 }
 
-unsafe impl Send for HashedFile {}
+// Hashing threads are handed files and conf, and send back hashes, errors and progress.  The compiler checks that
+// every one of them may cross threads, rather than taking our word for it.
+const _: () = {
+    const fn send<T: Send>() {}
+    const fn sync<T: Sync>() {}
+    send::<FileInfo>();
+    send::<HashedFile>();
+    send::<Error>();
+    send::<Progress>();
+    send::<WalkInfo>();
+    send::<RelatedFiles>();
+    sync::<RelateConf>();
+    sync::<WalkFilter>();
+    sync::<CancellationToken>();
+    sync::<Comparators>();
+};
 
-#[derive(Debug)]
+/// What went wrong with a file.  See `Error::code' to tell them apart without matching.
+#[derive(Debug, thiserror::Error)]
 pub enum ErrorType {
+    #[error(transparent)]
     IO(io::Error),
+    #[error(transparent)]
     WalkDir(walkdir::Error),
+    /// The file changed size while it was read: the size it had when found, then the bytes read.
+    #[error("read {1:} bytes, expected {0:}")]
     WrongSize(u64, u64),
-    NoCreatedTime(io::Error),
+    /// The file was written to while it was read, keeping its size.
+    #[error("modified while it was read")]
+    Modified,
+    /// Another program has the file locked, or on Windows open without sharing it.
+    #[error("locked by another program")]
+    Locked,
+    /// A `ContentComparator' couldn't make sense of the contents.
+    #[error("unreadable contents: {0:}")]
+    Content(String),
 }
 
-#[derive(Debug)]
+#[derive(Debug, thiserror::Error)]
+#[error("{}: {}", .path.display(), .error_type)]
 pub struct Error {
     path: PathBuf,
+    #[source]
     error_type: ErrorType,
 }
 
+/// What went wrong with a file, broadly, for reporting errors together.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The file or folder couldn't be opened.
+    PermissionDenied,
+    /// The file was moved or deleted after it was found.
+    Vanished,
+    /// The file kept changing while it was read, so there is no telling what it holds.
+    Changed,
+    /// Another program kept the file to itself, see `RelateConf::retry_locked'.
+    Locked,
+    /// Anything else, such as a failing disk or a link loop.
+    IO,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ErrorKind::PermissionDenied => write!(f, "{:}", tr!("error-permission-denied")),
+            ErrorKind::Vanished => write!(f, "{:}", tr!("error-vanished")),
+            ErrorKind::Changed => write!(f, "{:}", tr!("error-changed")),
+            ErrorKind::Locked => write!(f, "{:}", tr!("error-locked")),
+            ErrorKind::IO => write!(f, "{:}", tr!("error-io")),
+        }
+    }
+}
+
+impl Error {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn error_type(&self) -> &ErrorType {
+        &self.error_type
+    }
+
+    /// A name for what went wrong that stays the same between releases, unlike the messages: `io', `walk',
+    /// `wrong_size', `modified', `locked' or `content'.
+    pub fn code(&self) -> &'static str {
+        match self.error_type {
+            ErrorType::IO(_) => "io",
+            ErrorType::WalkDir(_) => "walk",
+            ErrorType::WrongSize(_, _) => "wrong_size",
+            ErrorType::Modified => "modified",
+            ErrorType::Locked => "locked",
+            ErrorType::Content(_) => "content",
+        }
+    }
+
+    /// Failing to read `path', outside this module.
+    pub fn io(path: &Path, e: io::Error) -> Self {
+        Self { path: path.to_path_buf(), error_type: ErrorType::IO(e) }
+    }
+
+    /// `path' holding what a `ContentComparator' can't make sense of, for the reason `e'.
+    pub fn content(path: &Path, e: impl fmt::Display) -> Self {
+        Self { path: path.to_path_buf(), error_type: ErrorType::Content(e.to_string()) }
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        let io = match &self.error_type {
+            ErrorType::IO(e) => Some(e),
+            ErrorType::WalkDir(e) => e.io_error(),
+            ErrorType::WrongSize(_, _) | ErrorType::Modified => return ErrorKind::Changed,
+            ErrorType::Locked => return ErrorKind::Locked,
+            ErrorType::Content(_) => None,
+        };
+        match io.map(io::Error::kind) {
+            Some(io::ErrorKind::PermissionDenied) => ErrorKind::PermissionDenied,
+            Some(io::ErrorKind::NotFound) => ErrorKind::Vanished,
+            _ => ErrorKind::IO,
+        }
+    }
+}
+
+/// For code working in `io::Result's, keeping the kind of the underlying error where there is one.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e.error_type {
+            ErrorType::IO(io) => io.kind(),
+            ErrorType::WalkDir(walk) => walk.io_error().map_or(io::ErrorKind::Other, io::Error::kind),
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+/// Errors are exported as the path, their kind, code and a description.  The underlying errors can't be rebuilt,
+/// so there is no `Deserialize'.
+impl Serialize for Error {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut error = serializer.serialize_struct("Error", 4)?;
+        error.serialize_field("path", &self.path)?;
+        error.serialize_field("kind", &self.kind())?;
+        error.serialize_field("code", self.code())?;
+        error.serialize_field("message", &self.to_string())?;
+        error.end()
+    }
+}
+
 fn io_error<'a>(path: &'a PathBuf) -> impl FnOnce(io::Error) -> Error {
     let path = path.clone();
     move |e| {
@@ -56,221 +195,1586 @@ fn walkdir_error<'a>(path: &'a PathBuf) -> impl FnOnce(walkdir::Error) -> Error
     }
 }
 
-fn wrong_size<'a>(path: &'a PathBuf, expected: u64, actual: u64) -> Error {
+/// Failing to open or read `path', which Windows may refuse for another program having it locked.
+fn read_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        let error_type = if platform::is_lock_error(&e) { ErrorType::Locked } else { ErrorType::IO(e) };
+        Error { path, error_type }
+    }
+}
+
+fn wrong_size(path: &Path, expected: u64, actual: u64) -> Error {
     Error {
-        path: path.clone(),
+        path: path.to_path_buf(),
         error_type: ErrorType::WrongSize(expected, actual),
     }
 }
 
-fn no_created<'a>(path: &'a PathBuf) -> impl FnOnce(io::Error) -> Error {
-    let path = path.clone();
-    move |e| {
-        Error {
-            path,
-            error_type: ErrorType::NoCreatedTime(e),
+/// The content hash used to tell files apart.
+/// Hashes made with different algorithms are never comparable, so a set of results records the one it used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum HashAlgo {
+    #[default]
+    Sha256,
+    /// Several times faster than SHA-256 on large files, and still cryptographic.
+    Blake3,
+    /// Faster again, but not cryptographic.  Fine for finding accidental duplicates.
+    Xxh128,
+}
+
+impl fmt::Display for HashAlgo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashAlgo::Sha256 => write!(f, "sha256"),
+            HashAlgo::Blake3 => write!(f, "blake3"),
+            HashAlgo::Xxh128 => write!(f, "xxh128"),
+        }
+    }
+}
+
+/// Parse the names produced by `Display'.
+impl FromStr for HashAlgo {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sha256" => Ok(HashAlgo::Sha256),
+            "blake3" => Ok(HashAlgo::Blake3),
+            "xxh128" => Ok(HashAlgo::Xxh128),
+            _ => Err(format!("Unknown hash algorithm '{:}', expected one of sha256, blake3 or xxh128", s)),
         }
     }
 }
 
+/// A hash in progress for one of the `HashAlgo's.
+pub enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Xxh128(Box<Xxh3>),
+}
+
+impl Hasher {
+    pub fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgo::Xxh128 => Hasher::Xxh128(Box::new(Xxh3::new())),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            },
+            Hasher::Xxh128(h) => h.update(data),
+        }
+    }
+
+    /// The hash as lowercase hex.
+    pub fn finish(self) -> String {
+        match self {
+            Hasher::Sha256(h) => format!("{:x}", h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Xxh128(h) => format!("{:032x}", h.digest128()),
+        }
+    }
+}
+
+/// So anything read can be copied straight into a hash, see `io::copy'.
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A way of telling files apart by what they hold rather than how it is stored, so that e.g. the same song with
+/// different tags is recognised.  Files with equal keys hold the same, see `similar::SimilarFiles'.
+pub trait ContentComparator: Send + Sync {
+    fn key(&self, path: &Path) -> Result<String, Error>;
+}
+
+/// The plainest `ContentComparator': files hold the same when their bytes are, as when scanning.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExactHash {
+    pub algo: HashAlgo,
+}
+
+impl ContentComparator for ExactHash {
+    fn key(&self, path: &Path) -> Result<String, Error> {
+        let mut file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        let mut hasher = Hasher::new(self.algo);
+        read_chunks(&mut file, &mut hasher, None, |_| ()).map_err(|e| Error::io(path, e))?;
+        Ok(hasher.finish())
+    }
+}
+
+/// `ContentComparator's chosen by file name extension, falling back to a default one for the rest.  Is itself a
+/// `ContentComparator', handing each file to the one registered for it.
+pub struct Comparators {
+    default: Box<dyn ContentComparator>,
+    /// By lower case extension.
+    by_extension: HashMap<String, Box<dyn ContentComparator>>,
+}
+
+impl Comparators {
+    pub fn new(default: impl ContentComparator + 'static) -> Self {
+        Self { default: Box::new(default), by_extension: HashMap::new() }
+    }
+
+    /// Compare files named with `extension', in any case, with `comparator' instead of any registered before.
+    pub fn register(&mut self, extension: &str, comparator: impl ContentComparator + 'static) {
+        self.by_extension.insert(extension.to_lowercase(), Box::new(comparator));
+    }
+
+    /// Whether a comparator was registered for the extension of `path'.
+    pub fn registered(&self, path: &Path) -> bool {
+        Self::extension(path).is_some_and(|extension| self.by_extension.contains_key(&extension))
+    }
+
+    /// The comparator for `path', the default one if none was registered for its extension.
+    pub fn for_path(&self, path: &Path) -> &dyn ContentComparator {
+        Self::extension(path).and_then(|extension| self.by_extension.get(&extension)).unwrap_or(&self.default).as_ref()
+    }
+
+    fn extension(path: &Path) -> Option<String> {
+        path.extension().map(|extension| extension.to_string_lossy().to_lowercase())
+    }
+}
+
+/// Exact hashing with the default `HashAlgo', for every file.
+impl Default for Comparators {
+    fn default() -> Self {
+        Self::new(ExactHash::default())
+    }
+}
+
+impl ContentComparator for Comparators {
+    fn key(&self, path: &Path) -> Result<String, Error> {
+        self.for_path(path).key(path)
+    }
+}
+
+/// Files larger than this report how far along they are every time this much more is hashed, so the progress
+/// doesn't stall on them.
+pub const PROGRESS_INTERVAL: u64 = 64 * 1024 * 1024;
+
+/// Files at least this large are hashed through a memory map unless configured otherwise,
+/// see `RelateConf::mmap_threshold'.
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Files that change while they are read are read again this many times unless configured otherwise,
+/// see `RelateConf::change_retries'.
+pub const CHANGE_RETRIES: u8 = 2;
+
 /// Open file at `path', and produce a `FileInfo' or an `Error'.
-pub fn hash_from_file_info<'a>(info: &'a FileInfo) -> Result<HashedFile, Error> {
-    let mut file = fs::File::open(&info.name).map_err(io_error(&info.name))?;
-    let mut hasher = Sha256::new();
-    let n = io::copy(&mut file, &mut hasher).map_err(io_error(&info.name))?;
+/// Files of at least `MMAP_THRESHOLD' bytes are mapped into memory rather than read.
+pub fn hash_from_file_info<'a>(info: &'a FileInfo, algo: HashAlgo) -> Result<HashedFile, Error> {
+    hash_file(info, algo, Some(MMAP_THRESHOLD), None, &mpsc::channel().0)
+}
+
+/// Like `hash_from_file_info', mapping files of at least `mmap_threshold' bytes, or none without one, and
+/// telling `report' how far along large files are.  With a `throttle' every file is read in chunks, each waiting
+/// its turn, as the kernel reads a mapped file ahead as it pleases.
+fn hash_file(
+    info: &FileInfo, algo: HashAlgo, mmap_threshold: Option<u64>, throttle: Option<&Throttle>, report: &Sender<Progress>,
+) -> Result<HashedFile, Error> {
+    if info.in_archive() {
+        return hash_member(info, algo, throttle, report);
+    }
+    let mut file = fs::File::open(&info.name).map_err(read_error(&info.name))?;
+    // Likely being written out, so whatever it holds now would be of no use.
+    if platform::locked(&file) {
+        return Err(Error { path: info.name.to_path_buf(), error_type: ErrorType::Locked });
+    }
+    let mut hasher = Hasher::new(algo);
+    let reading = |bytes| send_progress(report, Progress::FileReading { path: info.name.to_path_buf(), bytes });
+    let mapped = mmap_threshold.filter(|threshold| info.size >= *threshold && throttle.is_none()).and_then(|_| map(&file));
+    let n = match mapped {
+        // The hasher is handed the file a large slice at a time, rather than a copy of each chunk in turn.
+        Some(map) => {
+            let mut n = 0;
+            for slice in map.chunks(PROGRESS_INTERVAL as usize) {
+                hasher.update(slice);
+                n += slice.len() as u64;
+                if n < info.size {
+                    reading(n);
+                }
+            }
+            n
+        },
+        None => {
+            let mut reported = 0;
+            let read = |n| {
+                if n >= reported + PROGRESS_INTERVAL && n < info.size {
+                    reported = n;
+                    reading(n);
+                }
+            };
+            read_chunks(&mut file, &mut hasher, throttle, read).map_err(read_error(&info.name))?
+        },
+    };
     if info.size != n {
         return Err(wrong_size(&info.name, info.size, n));
     }
-    let hash = format!("{:x}", hasher.finalize());
+    // Written to in place, as databases and disk images are.  Where the time can't be had, the size has to do.
+    let modified = file.metadata().ok().and_then(|metadata| metadata.modified().ok());
+    if info.modified.is_some() && modified.is_some() && info.modified != modified {
+        return Err(Error { path: info.name.to_path_buf(), error_type: ErrorType::Modified });
+    }
+    let hash = hasher.finish();
     Ok(HashedFile {
         hash,
         info: info.clone(),
     })
 }
 
+/// Like `hash_file', for `info' inside an archive, which is read rather than mapped, see `archive::read_member'.
+fn hash_member(info: &FileInfo, algo: HashAlgo, throttle: Option<&Throttle>, report: &Sender<Progress>) -> Result<HashedFile, Error> {
+    let mut hasher = Hasher::new(algo);
+    let mut reported = 0;
+    let read = |n| {
+        if n >= reported + PROGRESS_INTERVAL && n < info.size {
+            reported = n;
+            send_progress(report, Progress::FileReading { path: info.name.to_path_buf(), bytes: n });
+        }
+    };
+    let n = archive::read_member(info, |member| read_chunks(member, &mut hasher, throttle, read))?;
+    if info.size != n {
+        return Err(wrong_size(&info.name, info.size, n));
+    }
+    // Its files take the archive's time, see `archive::members'.
+    let archive = info.archive.as_deref().unwrap_or(&info.name);
+    let modified = fs::metadata(archive).ok().and_then(|metadata| metadata.modified().ok());
+    if info.modified.is_some() && modified.is_some() && info.modified != modified {
+        return Err(Error { path: info.name.to_path_buf(), error_type: ErrorType::Modified });
+    }
+    Ok(HashedFile {
+        hash: hasher.finish(),
+        info: info.clone(),
+    })
+}
+
+/// Like `hash_file', but a file that changes while it is read is looked up and read again, at most `retries' times,
+/// in case it was only being written out.  The result is for the file as it was when last read.
+fn hash_settled(
+    info: &FileInfo, algo: HashAlgo, mmap_threshold: Option<u64>, retries: u8, throttle: Option<&Throttle>, report: &Sender<Progress>,
+) -> Result<HashedFile, Error> {
+    let mut hashed = hash_file(info, algo, mmap_threshold, throttle, report);
+    for _ in 0..retries {
+        // Archives are looked into only by walking.
+        if info.in_archive() || !hashed.as_ref().is_err_and(|e| e.kind() == ErrorKind::Changed) {
+            break;
+        }
+        // Looked up as the walk found it, through the link where it followed one.
+        let info = FileInfo::from_path(&info.name, info.link_target.is_some())?;
+        hashed = hash_file(&info, algo, mmap_threshold, throttle, report);
+    }
+    hashed
+}
+
+/// Map `file' into memory, or `None' where that isn't possible, e.g. on some network filesystems, so it is read
+/// in chunks instead.
+fn map(file: &fs::File) -> Option<Mmap> {
+    // Safety: the map is only read, and dropped once hashed.  A file changed meanwhile hashes to something else,
+    // just as when read, and the size is checked afterwards.  A file truncated meanwhile ends the process with
+    // SIGBUS though, which is why only large files are mapped, and mapping can be turned off.
+    let map = unsafe { Mmap::map(file) }.ok()?;
+    #[cfg(unix)]
+    let _ = map.advise(memmap2::Advice::Sequential);
+    Some(map)
+}
+
+/// Feed `file' to `hasher' in chunks, returning how many bytes it had.  `read' is told the bytes so far after each.
+/// Each chunk waits for `throttle' first.
+fn read_chunks(file: &mut (impl Read + ?Sized), hasher: &mut Hasher, throttle: Option<&Throttle>, mut read: impl FnMut(u64)) -> io::Result<u64> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut n = 0;
+    loop {
+        if let Some(throttle) = throttle {
+            throttle.take(buf.len() as u64);
+        }
+        match file.read(&mut buf) {
+            Ok(0) => return Ok(n),
+            Ok(len) => {
+                hasher.update(&buf[..len]);
+                n += len as u64;
+                read(n);
+            },
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+    }
+}
+
 /// Check the length and hash of two files, `FileInfo', are equal ignoring the path.
 pub fn file_content_equal<'a>(file_a: &'a HashedFile, file_b: &'a HashedFile) -> bool {
     file_a.info.size == file_b.info.size && file_a.hash == file_b.hash
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// Compare the contents of the files at `a' and `b' byte for byte.
+/// Slower than comparing hashes, but immune to collisions.
+pub fn contents_identical(a: &Path, b: &Path) -> io::Result<bool> {
+    let (mut file_a, mut file_b) = (fs::File::open(a)?, fs::File::open(b)?);
+    if file_a.metadata()?.len() != file_b.metadata()?.len() {
+        return Ok(false);
+    }
+    streams_identical(&mut file_a, &mut file_b)
+}
+
+/// Compare what `a' and `b' hold byte for byte, reading both to the end unless they differ sooner.
+pub fn streams_identical(a: &mut dyn Read, b: &mut dyn Read) -> io::Result<bool> {
+    let (mut buf_a, mut buf_b) = (vec![0; 64 * 1024], vec![0; 64 * 1024]);
+    loop {
+        let n = match a.read(&mut buf_a) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if n == 0 {
+            // `b' should be done too, unless it is longer, or grew since the lengths were checked.
+            return Ok(b.read(&mut buf_b[..1])? == 0);
+        }
+        match b.read_exact(&mut buf_b[..n]) {
+            Ok(()) => (),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(e) => return Err(e),
+        }
+        if buf_a[..n] != buf_b[..n] {
+            return Ok(false);
+        }
+    }
+}
+
+/// The distinct directories holding the members of `group', in sorted order.
+pub fn containing_folders<'a>(group: impl IntoIterator<Item = &'a FileInfo>) -> BTreeSet<PathBuf> {
+    group
+        .into_iter()
+        .filter_map(|info| info.name.parent())
+        .map(|dir| dir.to_path_buf())
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct FileInfo {
-    pub name: PathBuf,
+    /// Shared, so that the walk, the groups and the queues all point at one copy of each path.
+    pub name: Arc<Path>,
     pub size: u64,
-    pub created: time::SystemTime,
+    /// `None' where the filesystem doesn't keep creation times, as many Linux and network filesystems don't.
+    #[serde(default)]
+    pub created: Option<time::SystemTime>,
+    /// When the contents last changed.  `None' where the platform doesn't tell us, and for files walked before this was
+    /// recorded.
+    #[serde(default)]
+    pub modified: Option<time::SystemTime>,
+    /// Bytes of disk actually used, which is less than `size' for sparse files.
+    /// `None' where the platform doesn't tell us, and for files walked before this was recorded.
+    #[serde(default)]
+    pub allocated: Option<u64>,
+    /// Where the path points, when it is a symbolic link.
+    #[serde(default)]
+    pub link_target: Option<PathBuf>,
+    /// The device and inode on Unix, or the volume and file index on Windows.
+    /// Paths with the same id are hard links to a single file.
+    #[serde(default)]
+    pub file_id: Option<(u64, u64)>,
+    /// The archive holding the file, when it is a member of a zip or tar rather than a file of its own.
+    /// `name' is then the archive's path followed by the member's path inside it.
+    #[serde(default)]
+    pub archive: Option<Arc<Path>>,
+}
+
+/// The age of a file, for ordering files by it.  Without a creation time the modification time stands in, as the
+/// contents can't have changed before the file was made.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileTime {
+    Created(time::SystemTime),
+    Modified(time::SystemTime),
+    Unknown,
+}
+
+impl FileTime {
+    pub fn time(&self) -> Option<time::SystemTime> {
+        match self {
+            FileTime::Created(time) | FileTime::Modified(time) => Some(*time),
+            FileTime::Unknown => None,
+        }
+    }
+
+    /// Order `self' and `other' oldest first, with files of unknown age after the rest.
+    pub fn oldest_first(&self, other: &Self) -> cmp::Ordering {
+        match (self.time(), other.time()) {
+            (Some(a), Some(b)) => a.cmp(&b),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+
+    /// Order `self' and `other' newest first, with files of unknown age after the rest.
+    pub fn newest_first(&self, other: &Self) -> cmp::Ordering {
+        match (self.time(), other.time()) {
+            (Some(a), Some(b)) => b.cmp(&a),
+            (a, b) => a.is_none().cmp(&b.is_none()),
+        }
+    }
+}
+
+/// What a walk does with symbolic links.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum SymlinkPolicy {
+    /// Leave links out altogether.
+    #[default]
+    Skip,
+    /// Treat links as what they point at, entering linked folders.  Loops are reported as errors.
+    Follow,
+    /// Record links in `WalkInfo::links', without hashing what they point at.
+    Report,
+}
+
+impl fmt::Display for SymlinkPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SymlinkPolicy::Skip => write!(f, "skip"),
+            SymlinkPolicy::Follow => write!(f, "follow"),
+            SymlinkPolicy::Report => write!(f, "report"),
+        }
+    }
+}
+
+/// Parse the names produced by `Display'.
+impl FromStr for SymlinkPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "skip" => Ok(SymlinkPolicy::Skip),
+            "follow" => Ok(SymlinkPolicy::Follow),
+            "report" => Ok(SymlinkPolicy::Report),
+            _ => Err(format!("Unknown symlink policy '{:}', expected one of skip, follow or report", s)),
+        }
+    }
+}
+
+/// Which files a walk visits.
+/// Patterns without a `/' match the name of a file or folder anywhere below the root, e.g. `node_modules' or `*.tmp'.
+/// Patterns with one match the path relative to the root, e.g. `photos/**/*.jpg'.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WalkConf {
+    /// When not empty, only files matching one of these are visited.  Folders are always entered.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Files and folders matching any of these are skipped, folders with everything in them.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Files smaller than this many bytes are skipped.  Empty files are all alike, so `Some(1)' is a common choice.
+    #[serde(default)]
+    pub min_size: Option<u64>,
+    /// Files larger than this many bytes are skipped.
+    #[serde(default)]
+    pub max_size: Option<u64>,
+    #[serde(default)]
+    pub symlinks: SymlinkPolicy,
+    /// Skip files no other file has the size of, as nothing can be a copy of them.  Finding out takes a walk of its
+    /// own first, which only counts sizes, but on large trees most files are then never kept or hashed.
+    #[serde(default)]
+    pub skip_unique_sizes: bool,
+    /// Look inside zip and tar archives, taking each file in them for one of its own, see `archive'.  Only archives
+    /// the filters let through are looked into, and the filters apply to the files in them as to any other, their
+    /// paths inside the archive standing for their paths below the root.
+    #[serde(default)]
+    pub archives: bool,
+}
+
+/// Globs split by what they are matched against.
+#[derive(Debug, Clone)]
+struct Patterns {
+    names: GlobSet,
+    paths: GlobSet,
+}
+
+impl Patterns {
+    fn new(pats: &[String]) -> Result<Self, globset::Error> {
+        let (mut names, mut paths) = (GlobSetBuilder::new(), GlobSetBuilder::new());
+        for pat in pats {
+            let glob = Glob::new(pat)?;
+            if pat.contains('/') {
+                paths.add(glob);
+            } else {
+                names.add(glob);
+            }
+        }
+        Ok(Self { names: names.build()?, paths: paths.build()? })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.names.is_empty() && self.paths.is_empty()
+    }
+
+    /// Whether `relative', a path below the walk's root, matches.
+    fn is_match(&self, relative: &Path) -> bool {
+        relative.file_name().is_some_and(|name| self.names.is_match(name)) || self.paths.is_match(relative)
+    }
+}
+
+/// A compiled `WalkConf' ready to be walked with.
+#[derive(Debug, Clone)]
+pub struct WalkFilter {
+    include: Patterns,
+    exclude: Patterns,
+    min_size: Option<u64>,
+    max_size: Option<u64>,
+    symlinks: SymlinkPolicy,
+    skip_unique_sizes: bool,
+    archives: bool,
+    /// The sizes more than one file has, once counted, see `WalkConf::skip_unique_sizes'.
+    shared_sizes: Option<Arc<HashSet<u64>>>,
+}
+
+impl WalkFilter {
+    /// Compile `conf'.  Only fails when a pattern is invalid.
+    pub fn new(conf: &WalkConf) -> Result<Self, globset::Error> {
+        Ok(Self {
+            include: Patterns::new(&conf.include)?,
+            exclude: Patterns::new(&conf.exclude)?,
+            min_size: conf.min_size,
+            max_size: conf.max_size,
+            symlinks: conf.symlinks,
+            skip_unique_sizes: conf.skip_unique_sizes,
+            archives: conf.archives,
+            shared_sizes: None,
+        })
+    }
+
+    /// Whether a file of `size' bytes is within the size limits, and has a size another file has too, when asked
+    /// for and counted.
+    fn admits_size(&self, size: u64) -> bool {
+        self.min_size.is_none_or(|min| size >= min)
+            && self.max_size.is_none_or(|max| size <= max)
+            && self.shared_sizes.as_ref().is_none_or(|sizes| sizes.contains(&size))
+    }
+
+    /// The files in `fi' this filter admits, when it looks into archives and `fi' is one, see `WalkConf::archives'.
+    pub fn members(&self, fi: &FileInfo) -> Result<Vec<FileInfo>, Error> {
+        if !self.archives || !archive::is_archive(&fi.name) {
+            return Ok(Vec::new());
+        }
+        let mut members = archive::members(fi)?;
+        members.retain(|member| {
+            let inside = Path::new(archive::member_name(member).unwrap_or_default());
+            let excluded = inside.ancestors().any(|path| !path.as_os_str().is_empty() && self.exclude.is_match(path));
+            !excluded && self.admits_size(member.size) && (self.include.is_empty() || self.include.is_match(inside))
+        });
+        Ok(members)
+    }
+
+    /// This filter with the sizes below `roots' counted, when it skips unique sizes and they aren't yet.  Files
+    /// admitted by the rest of the filter are counted, across all the roots, as a copy may be in any of them, so
+    /// folders walked apart, like a reference folder, are to be counted along with the others up front.
+    pub fn counting_sizes(&self, roots: &[PathBuf], stop: impl Fn() -> bool) -> Cow<'_, Self> {
+        if !self.skip_unique_sizes || self.shared_sizes.is_some() {
+            return Cow::Borrowed(self);
+        }
+        let mut counts = HashMap::<u64, u8>::new();
+        for root in paths::distinct_roots(roots) {
+            let walk = WalkDir::new(&root)
+                .follow_links(self.follows_links())
+                .into_iter()
+                .filter_entry(|entry| self.admits(&root, entry))
+                .take_while(|_| !stop());
+            for entry in walk.flatten() {
+                let reported_link = self.symlinks == SymlinkPolicy::Report && entry.path_is_symlink();
+                let mut count = |size| {
+                    let count = counts.entry(size).or_default();
+                    *count = count.saturating_add(1);
+                };
+                match entry.metadata() {
+                    Ok(metadata) if metadata.is_file() && !reported_link && self.admits_size(metadata.len()) => {
+                        count(metadata.len());
+                        let archive = FileInfo::from_metadata(entry.path(), &metadata, false);
+                        self.members(&archive).into_iter().flatten().for_each(|member| count(member.size));
+                    },
+                    _ => (),
+                }
+            }
+        }
+        let shared = counts.into_iter().filter(|(_, count)| *count > 1).map(|(size, _)| size).collect();
+        Cow::Owned(Self { shared_sizes: Some(Arc::new(shared)), ..self.clone() })
+    }
+
+    /// Whether to visit `entry', found below `root'.
+    fn admits(&self, root: &Path, entry: &walkdir::DirEntry) -> bool {
+        // The root is what the user asked for, whatever it is called.
+        let relative = match entry.path().strip_prefix(root) {
+            Ok(relative) if entry.depth() > 0 => relative,
+            _ => return true,
+        };
+        if self.exclude.is_match(relative) || (self.symlinks == SymlinkPolicy::Skip && entry.path_is_symlink()) {
+            return false;
+        }
+        entry.file_type().is_dir() || self.include.is_empty() || self.include.is_match(relative)
+    }
+
+    pub fn follows_links(&self) -> bool {
+        self.symlinks == SymlinkPolicy::Follow
+    }
+
+    /// Whether a walk of `root' would hash `fi', a file found on its own rather than by walking, going by its size
+    /// and the names of the folders it is in.  Links are taken as hashed only when followed.
+    pub fn admits_file(&self, root: &Path, fi: &FileInfo) -> bool {
+        let Ok(relative) = fi.name.strip_prefix(root) else {
+            return false;
+        };
+        let excluded = relative.ancestors().any(|path| !path.as_os_str().is_empty() && self.exclude.is_match(path));
+        let link = fi.link_target.is_some() && !self.follows_links();
+        !excluded && !link && self.admits_size(fi.size) && (self.include.is_empty() || self.include.is_match(relative))
+    }
+}
+
+impl Default for WalkFilter {
+    /// Visit everything.
+    fn default() -> Self {
+        Self::new(&WalkConf::default()).expect("No patterns can't be invalid")
+    }
 }
 
+#[derive(Serialize, Deserialize)]
 pub struct WalkInfo {
     pub total_size: u64,
+    /// Every file the walk kept, so this grows with the tree rather than with its copies.  `skip_unique_sizes' is
+    /// what keeps it small on large trees; each path is still held whole.
     pub files: HashSet<FileInfo>,
+    /// Links found with `SymlinkPolicy::Report'.  They aren't in `files', so they are never hashed.
+    #[serde(default)]
+    pub links: HashSet<FileInfo>,
+    /// Paths reaching the same file, through hard links or followed symbolic links, sorted.
+    /// Only the first of each is in `files', so the file is hashed and counted once and never taken for a duplicate.
+    #[serde(default)]
+    pub hard_links: Vec<Vec<PathBuf>>,
+    /// The walk was cancelled, so some files were never found.
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(skip)]
     pub errors: Vec<Error>,
 }
 
 impl FileInfo {
     fn from_entry(entry: walkdir::DirEntry) -> Result<Self, Error> {
         let metadata = entry.metadata().map_err(walkdir_error(&entry.path().to_path_buf()))?;
-        let size = metadata.len();
-        let created = metadata.created().map_err(no_created(&entry.path().to_path_buf()))?;
-        Ok(Self {
-            name: entry.path().to_path_buf(),
+        Ok(Self::from_metadata(entry.path(), &metadata, entry.path_is_symlink()))
+    }
+
+    /// Look up `path' on its own, as a walk following links or not would find it.
+    pub fn from_path(path: &Path, follow_links: bool) -> Result<Self, Error> {
+        let is_symlink = fs::symlink_metadata(path).map_err(io_error(&path.to_path_buf()))?.is_symlink();
+        let metadata = match follow_links {
+            true => fs::metadata(path),
+            false => fs::symlink_metadata(path),
+        };
+        Ok(Self::from_metadata(path, &metadata.map_err(io_error(&path.to_path_buf()))?, is_symlink))
+    }
+
+    fn from_metadata(path: &Path, metadata: &fs::Metadata, is_symlink: bool) -> Self {
+        let (file_id, size) = identify(path, metadata);
+        Self {
+            name: path.into(),
             size,
-            created,
-        })
+            created: metadata.created().ok(),
+            modified: metadata.modified().ok(),
+            allocated: allocated_size(metadata),
+            // Windows junctions point at verbatim paths.
+            link_target: if is_symlink { fs::read_link(path).ok().map(|target| platform::plain_path(&target)) } else { None },
+            file_id,
+            archive: None,
+        }
+    }
+
+    /// When the file came to be, as near as its filesystem can tell.
+    pub fn time(&self) -> FileTime {
+        match (self.created, self.modified) {
+            (Some(created), _) => FileTime::Created(created),
+            (None, Some(modified)) => FileTime::Modified(modified),
+            (None, None) => FileTime::Unknown,
+        }
+    }
+
+    /// Bytes of disk used, assuming the file isn't sparse when we don't know.
+    pub fn allocated(&self) -> u64 {
+        self.allocated.unwrap_or(self.size)
+    }
+
+    /// Whether some of the file's contents take no disk space.
+    pub fn is_sparse(&self) -> bool {
+        self.allocated() < self.size
+    }
+
+    /// Whether the file is a member of an archive, which can't be removed or linked to on its own.
+    pub fn in_archive(&self) -> bool {
+        self.archive.is_some()
+    }
+
+    /// The device holding the file, or the volume on Windows, where the platform tells us.
+    pub fn device(&self) -> Option<u64> {
+        self.file_id.map(|(device, _)| device)
     }
 }
 
-unsafe impl Send for FileInfo {}
+#[cfg(unix)]
+fn allocated_size(metadata: &fs::Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    // Always counted in 512 byte units, whatever the block size of the filesystem.
+    Some(metadata.blocks() * 512)
+}
+
+#[cfg(not(unix))]
+fn allocated_size(_metadata: &fs::Metadata) -> Option<u64> {
+    None
+}
+
+/// The file's id, see `FileInfo::file_id', and its size.
+#[cfg(unix)]
+fn identify(_path: &Path, metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64) {
+    use std::os::unix::fs::MetadataExt;
+    (Some((metadata.dev(), metadata.ino())), metadata.len())
+}
+
+/// The standard library only offers the id on nightly, so ask for it through a handle.  The size is taken from
+/// there too: Windows keeps one in every folder listing a file, and only updates the one it was written through, so
+/// a walk may find an old size for a hard link.
+#[cfg(windows)]
+fn identify(path: &Path, metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64) {
+    let info = winapi_util::Handle::from_path_any(path).and_then(|handle| winapi_util::file::information(&handle));
+    match info {
+        Ok(info) => {
+            // A link that isn't followed is opened through, so only a file's own size is taken.
+            let size = if metadata.is_file() { info.file_size() } else { metadata.len() };
+            (Some((info.volume_serial_number(), info.file_index())), size)
+        },
+        Err(_) => (None, metadata.len()),
+    }
+}
+
+#[cfg(not(any(unix, windows)))]
+fn identify(_path: &Path, metadata: &fs::Metadata) -> (Option<(u64, u64)>, u64) {
+    (None, metadata.len())
+}
 
 impl WalkInfo {
     fn new() -> Self {
         WalkInfo {
             total_size: 0,
             files: HashSet::new(),
+            links: HashSet::new(),
+            hard_links: Vec::new(),
+            partial: false,
             errors: Vec::new(),
         }
     }
 
+    /// Keep one path per file in `files', moving the others to `hard_links'.
+    fn merge_hard_links(self) -> Self {
+        let mut by_id = HashMap::<(u64, u64), Vec<&FileInfo>>::new();
+        for fi in &self.files {
+            if let Some(id) = fi.file_id {
+                by_id.entry(id).or_default().push(fi);
+            }
+        }
+        let mut merged = by_id
+            .into_values()
+            .filter(|same| same.len() > 1)
+            .map(|mut same| {
+                same.sort_by(|a, b| a.name.cmp(&b.name));
+                same.into_iter().cloned().collect::<Vec<FileInfo>>()
+            })
+            .collect::<Vec<Vec<FileInfo>>>();
+        merged.sort_by(|a, b| a[0].name.cmp(&b[0].name));
+        let (mut files, mut total_size, mut hard_links) = (self.files, self.total_size, self.hard_links);
+        for same in merged {
+            for fi in &same[1..] {
+                files.remove(fi);
+                total_size -= fi.size;
+            }
+            hard_links.push(same.into_iter().map(|fi| fi.name.to_path_buf()).collect());
+        }
+        Self { files, total_size, hard_links, ..self }
+    }
+
     fn insert_error(self, error: Error) -> Self {
-        let total_size = self.total_size;
-        let files = self.files;
         let mut errors = self.errors;
         errors.push(error);
-        Self {
-            total_size,
-            files,
-            errors,
-        }
+        Self { errors, ..self }
     }
 
-    fn insert_entry(self, entry: walkdir::DirEntry) -> Self {
+    /// Add `entry', if `filter' admits it, handing it to `found' if it is a file to hash.
+    fn insert_entry(self, entry: walkdir::DirEntry, filter: &WalkFilter, found: &mut impl FnMut(&FileInfo)) -> Self {
+        let is_file = entry.file_type().is_file();
+        // Unless followed, the entry's metadata describes the link rather than what it points at.
+        let is_reported_link = filter.symlinks == SymlinkPolicy::Report && entry.path_is_symlink();
         match FileInfo::from_entry(entry) {
             Err(e) => self.insert_error(e),
+            Ok(fi) if is_reported_link => {
+                let mut links = self.links;
+                links.insert(fi);
+                Self { links, ..self }
+            },
+            Ok(fi) if is_file && !filter.admits_size(fi.size) => self,
             Ok(fi) => {
+                let members = if is_file { filter.members(&fi) } else { Ok(Vec::new()) };
+                found(&fi);
                 let total_size = self.total_size + fi.size;
                 let mut files = self.files;
-                let errors = self.errors;
                 files.insert(fi);
-                Self { total_size, files, errors }
+                let walk = Self { total_size, files, ..self };
+                match members {
+                    Err(e) => walk.insert_error(e),
+                    Ok(members) => members.into_iter().fold(walk, |mut walk, member| {
+                        found(&member);
+                        walk.total_size += member.size;
+                        walk.files.insert(member);
+                        walk
+                    }),
+                }
             }
         }
     }
 
+    /// Combine the walks of two separate folders.  A path found by both, when one folder is inside the other or
+    /// the two are spellings of the same folder, is kept once.
+    pub fn merge(self, other: Self) -> Self {
+        let seen = self.files.iter().chain(&self.links).map(|fi| paths::key(&fi.name)).collect::<HashSet<PathBuf>>();
+        let (other_files, repeated) = other.files
+            .into_iter()
+            .partition::<HashSet<FileInfo>, _>(|fi| !seen.contains(&paths::key(&fi.name)));
+        let mut files = self.files;
+        files.extend(other_files);
+        let mut links = self.links;
+        links.extend(other.links.into_iter().filter(|fi| !seen.contains(&paths::key(&fi.name))));
+        let mut hard_links = self.hard_links;
+        hard_links.extend(other.hard_links);
+        let mut errors = self.errors;
+        errors.extend(other.errors);
+        let partial = self.partial || other.partial;
+        // A file may be linked from both folders.
+        let total_size = self.total_size + other.total_size - repeated.iter().map(|fi| fi.size).sum::<u64>();
+        Self { total_size, files, links, hard_links, partial, errors }.merge_hard_links()
+    }
+
     /// Return all unique PathBufs found recursively in `path'.
     pub fn walk(path: PathBuf) -> Self {
-        WalkDir::new(path)
+        Self::walk_filtered(path, &WalkFilter::default())
+    }
+
+    /// Like `walk', but only visiting what `filter' admits.
+    pub fn walk_filtered(path: PathBuf, filter: &WalkFilter) -> Self {
+        Self::walk_cancellable(path, filter, &CancellationToken::default())
+    }
+
+    /// Like `walk_filtered', but stopping early once `cancel' is cancelled, leaving the walk `partial'.
+    /// Paths are found below `path' normalized, see `paths::normalize'.
+    pub fn walk_cancellable(path: PathBuf, filter: &WalkFilter, cancel: &CancellationToken) -> Self {
+        let filter = filter.counting_sizes(slice::from_ref(&path), || cancel.is_cancelled());
+        Self::walk_each(path, &filter, || cancel.is_cancelled(), |_| ())
+    }
+
+    /// Like `walk_cancellable', but walking each of `roots' and merging what is found, see `WalkInfo::merge'.
+    /// Roots inside another are left out, see `paths::distinct_roots'.
+    pub fn walk_roots(roots: &[PathBuf], filter: &WalkFilter, cancel: &CancellationToken) -> Self {
+        let filter = filter.counting_sizes(roots, || cancel.is_cancelled());
+        paths::distinct_roots(roots)
+            .into_iter()
+            .map(|root| Self::walk_cancellable(root, &filter, cancel))
+            .reduce(Self::merge)
+            .unwrap_or_else(Self::new)
+    }
+
+    /// Like `walk_filtered', but stopping early once `stop' says so, and handing each file to `found' as soon as
+    /// it is found.  Every file is handed over, even those `merge_hard_links' later sets aside.
+    fn walk_each(path: PathBuf, filter: &WalkFilter, stop: impl Fn() -> bool, mut found: impl FnMut(&FileInfo)) -> Self {
+        let path = paths::normalize(&path);
+        let mut stopped = false;
+        let walk = WalkDir::new(&path)
+            .follow_links(filter.symlinks == SymlinkPolicy::Follow)
             .into_iter()
+            .filter_entry(|entry| filter.admits(&path, entry))
+            .take_while(|_| {
+                stopped = stop();
+                !stopped
+            })
             .fold(WalkInfo::new(), |acc, entry| {
                 match entry {
                     Err(e) => acc.insert_error(Error { path: "<no path>".to_owned().into(), error_type: ErrorType::IO(e.into()) }),
-                    Ok(entry) => acc.insert_entry(entry),
+                    Ok(entry) => acc.insert_entry(entry, filter, &mut found),
                 }
             })
+            .merge_hard_links();
+        Self { partial: stopped, ..walk }
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct RelatedFiles {
+    /// Results saved before the algorithm was configurable were all made with SHA-256.
+    #[serde(default)]
+    pub algo: HashAlgo,
     pub files: HashMap<String, HashSet<FileInfo>>,
+    /// Hashing stopped at the deadline or was cancelled, so some files were never compared.
+    /// The groups found are still genuine, but there may be more.
+    #[serde(default)]
+    pub partial: bool,
+    #[serde(skip)]
     pub errors: Vec<Error>,
 }
 
+/// A set of files sharing the same contents, as found by `RelatedFiles::duplicate_groups'.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Group<'a> {
+    pub hash: &'a str,
+    pub size: u64,
+    /// The members, sorted by path.
+    pub files: Vec<&'a FileInfo>,
+}
+
+impl Group<'_> {
+    /// Bytes that would be freed by keeping a single copy, judging by file sizes.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64).saturating_sub(1)
+    }
+
+    /// Bytes of disk that would be freed by keeping only the copy using the least disk.
+    /// Less than `wasted' when some copies are sparse.
+    pub fn wasted_allocated(&self) -> u64 {
+        let total = self.files.iter().map(|fi| fi.allocated()).sum::<u64>();
+        total - self.files.iter().map(|fi| fi.allocated()).min().unwrap_or(0)
+    }
+}
+
+/// How much of the duplication lies in one folder, as found by `RelatedFiles::by_top_folder'.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FolderSummary<'a> {
+    pub folder: PathBuf,
+    /// Bytes freed by removing the copies in the folder, keeping a copy elsewhere, or one here when every copy is.
+    pub wasted: u64,
+    /// Files in the folder with a copy somewhere.
+    pub files: usize,
+    /// The groups with a member in the folder, most wasteful first.
+    pub groups: Vec<Group<'a>>,
+}
+
+/// The folder directly below `root' holding `path', or `root' itself for the files directly in it.
+/// A path outside `root', e.g. in a reference folder, belongs to its own folder.
+fn top_folder(root: &Path, path: &Path) -> PathBuf {
+    let Ok(relative) = path.strip_prefix(root) else {
+        return path.parent().unwrap_or(path).to_path_buf();
+    };
+    let mut components = relative.components();
+    match (components.next(), components.next()) {
+        (Some(top), Some(_)) => root.join(top),
+        _ => root.to_path_buf(),
+    }
+}
+
+/// The phases of finding duplicates, in the order they happen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stage {
+    Walking,
+    Hashing,
+}
+
+/// Updates sent while relating files, so callers can show what is happening rather than a bare fraction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Progress {
+    StageChanged(Stage),
+    /// A worker has begun reading the file.
+    FileStarted(PathBuf),
+    /// A large file has been hashed this far, sent every `PROGRESS_INTERVAL' bytes.  Only until it is done, when
+    /// `FileHashed' or `FileFailed' counts the whole file.
+    FileReading { path: PathBuf, bytes: u64 },
+    /// The file was read completely.  `bytes' is how much was hashed.
+    FileHashed { path: PathBuf, bytes: u64 },
+    /// The file could not be hashed, the reason is kept in `RelatedFiles::errors'.  Its `bytes' count as done all
+    /// the same, so the progress still reaches the total.
+    FileFailed { path: PathBuf, bytes: u64 },
+    /// `RelatedFiles::resume' found this many files, adding up to `bytes', already hashed.
+    Resumed { files: usize, bytes: u64 },
+    /// `RelatedFiles::walk_and_relate' found another file to hash, adding to the total.
+    FileFound { bytes: u64 },
+    /// Every file has been dealt with.
+    Done,
+}
+
+/// Send `progress' to whoever is listening.  Nobody listening is not a reason to stop hashing.
+fn send_progress(report: &Sender<Progress>, progress: Progress) {
+    let _ = report.send(progress);
+}
+
 impl RelatedFiles {
-    pub fn relate<'a, 'b>(walk: &'a WalkInfo, conf: &'b RelateConf, report: Sender<f32>) -> Self {
-        if walk.total_size as usize > conf.size_threshold && walk.files.len() > conf.size_threshold {
-            return Self::relate_sequential(walk, report);
-        }
-        // We've met the criteria for parallel execution.
-        let (tx, rx): (Sender<Result<HashedFile, Error>>, Receiver<Result<HashedFile, Error>>) = mpsc::channel();
-        let mut done = 0;
-        let mut threads = Vec::new();
-        let total = walk.total_size;
-        let chunk_size = total / conf.max_threads as u64;
-        for chunk in &walk.files.iter().chunks(if chunk_size > 1 { chunk_size as usize } else { 1 }) {
-            let tx = tx.clone();
-            let chunk = chunk.into_iter().cloned().collect::<Vec<FileInfo>>();
-            let child = thread::spawn(move || {
-                chunk.into_iter().for_each(|info| {
-                    let file = hash_from_file_info(&info);
-                    tx.send(file).expect("Relate manager died unexpectedly!");
-                })
+    fn new(algo: HashAlgo) -> Self {
+        Self {
+            algo,
+            files: HashMap::new(),
+            partial: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Forget the contents the user never wants flagged.  Returns how many were dropped.
+    pub fn remove_denied(&mut self, denylist: &Denylist) -> usize {
+        let len = self.files.len();
+        self.files.retain(|hash, _| !denylist.contains(self.algo, hash));
+        len - self.files.len()
+    }
+
+    /// For comparing a folder against a `reference' one: keep only the groups with members both inside and
+    /// outside `reference', since those are the only ones where something outside has a reference copy.
+    pub fn retain_reference_duplicates(&mut self, reference: &Path) {
+        let reference = paths::key(reference);
+        self.files.retain(|_, group| {
+            let inside = group.iter().filter(|fi| paths::key(&fi.name).starts_with(&reference)).count();
+            inside > 0 && inside < group.len()
+        });
+    }
+
+    /// The groups with more than one member, most wasteful first.  Empty files are left out, see `empty_files'.
+    pub fn duplicate_groups(&self) -> Vec<Group<'_>> {
+        let mut groups = self.files
+            .iter()
+            .filter(|(_, group)| holds_duplicates(group))
+            .map(|(hash, group)| {
+                let mut files = group.iter().collect::<Vec<&FileInfo>>();
+                files.sort_by(|a, b| a.name.cmp(&b.name));
+                Group { hash, size: files[0].size, files }
+            })
+            .collect::<Vec<Group>>();
+        groups.sort_by(|a, b| b.wasted().cmp(&a.wasted()).then_with(|| a.hash.cmp(b.hash)));
+        groups
+    }
+
+    /// The duplication below `root', split by the folders directly in it, most wasteful first.
+    pub fn by_top_folder(&self, root: &Path) -> Vec<FolderSummary<'_>> {
+        let mut folders = HashMap::<PathBuf, FolderSummary>::new();
+        for group in self.duplicate_groups() {
+            let mut members = HashMap::<PathBuf, usize>::new();
+            for fi in &group.files {
+                *members.entry(top_folder(root, &fi.name)).or_default() += 1;
+            }
+            for (folder, n) in members {
+                let summary = folders.entry(folder.clone()).or_insert_with(|| FolderSummary { folder, wasted: 0, files: 0, groups: Vec::new() });
+                let kept = if n == group.files.len() { 1 } else { 0 };
+                summary.wasted += group.size * (n - kept) as u64;
+                summary.files += n;
+                summary.groups.push(group.clone());
+            }
+        }
+        let mut folders = folders.into_values().collect::<Vec<FolderSummary>>();
+        folders.sort_by(|a, b| b.wasted.cmp(&a.wasted).then_with(|| a.folder.cmp(&b.folder)));
+        folders
+    }
+
+    /// Record the result of hashing a file of `size' bytes, and let `report' know about it.
+    fn insert(&mut self, (size, result): Attempt, report: &Sender<Progress>) {
+        match result {
+            Err(err) => {
+                send_progress(report, Progress::FileFailed { path: err.path.clone(), bytes: size });
+                self.errors.push(err);
+            },
+            Ok(file) => {
+                send_progress(report, Progress::FileHashed { path: file.info.name.to_path_buf(), bytes: file.info.size });
+                self.files.entry(file.hash).or_default().insert(file.info);
+            },
+        }
+    }
+
+    /// Hash every file in `walk', on as many threads as `conf.strategy' decides.
+    pub fn relate<'a, 'b>(walk: &'a WalkInfo, conf: &'b RelateConf, report: Sender<Progress>) -> Self {
+        let threads = match conf.strategy.decide(walk.files.len(), walk.total_size) {
+            Strategy::Parallel { threads } => threads.max(1),
+            _ => return Self::relate_sequential(walk, conf, report),
+        };
+        send_progress(&report, Progress::StageChanged(Stage::Hashing));
+        let (tx, rx): (Sender<Attempt>, Receiver<Attempt>) = mpsc::channel();
+        let stopped = Arc::new(AtomicBool::new(false));
+        // Devices don't wait on each other's reads, so each gets a pipeline of its own, all running at once.
+        let devices = by_device(&walk.files);
+        let kinds = devices.keys().map(|device| device.map_or(DeviceKind::Unknown, platform::device_kind)).collect::<Vec<DeviceKind>>();
+        let shares = share_threads(threads, &kinds);
+        let throttle = conf.io_limit.map(Throttle::new);
+        let mut related = Self::new(conf.hash_algo);
+        // The workers borrow the walk's files rather than each device's queue holding copies of them.
+        thread::scope(|s| {
+            let mut threads = Vec::new();
+            for ((files, share), kind) in devices.into_values().zip(shares).zip(kinds) {
+                let pipeline = Pipeline {
+                    tx: tx.clone(),
+                    report: report.clone(),
+                    stopped: stopped.clone(),
+                    algo: conf.hash_algo,
+                    mmap_threshold: conf.mmap_threshold,
+                    change_retries: conf.change_retries,
+                    throttle: throttle.as_ref(),
+                    low_priority: conf.low_priority,
+                    deadline: conf.deadline,
+                    cancel: conf.cancel.clone(),
+                };
+                threads.extend(pipeline.spawn(s, files, kind, conf.device_threads.unwrap_or(share).max(1)));
+            }
+            // Only the workers hold senders now, so the loop below ends once they have all finished.
+            drop(tx);
+            for result in rx {
+                related.insert(result, &report);
+            }
+            threads.into_iter().for_each(|th| {
+                let _ = th.join();
             });
-            threads.push(child);
-        }
-        let mut files: HashMap<String, HashSet<FileInfo>> = HashMap::new();
-        let mut errors = Vec::new();
-        while !threads.iter().all(|th| th.is_finished()) {
-            match rx.recv_timeout(Duration::from_millis(500)) {
-                Err(RecvTimeoutError::Timeout) => continue,
-                Err(RecvTimeoutError::Disconnected) => break,
-                Ok(result) => {
-                    match result {
-                        Err(err) => {
-                            errors.push(err);
-                        },
-                        Ok(file) => {
-                            match files.get_mut(&file.hash) {
-                                Some(hs) => {
-                                    hs.insert(file.info);
-                                },
-                                None => {
-                                    let mut hs = HashSet::new();
-                                    hs.insert(file.info);
-                                    files.insert(file.hash, hs);
-                                }
-                            }
-                        },
+        });
+        related.retry_locked(walk, conf, throttle.as_ref());
+        // Files a cancelled walk never found were never compared either.
+        related.partial = walk.partial || stopped.load(atomic::Ordering::Relaxed);
+        send_progress(&report, Progress::Done);
+        related
+    }
+
+    /// Carry on hashing `walk' where these partial results left off, only hashing the files not already in them.
+    /// Results for files that changed or went away since are dropped.  Results made with another algorithm can't
+    /// be combined with new ones, so then everything is hashed again.
+    pub fn resume(self, walk: &WalkInfo, conf: &RelateConf, report: Sender<Progress>) -> Self {
+        let done = match self.algo == conf.hash_algo {
+            true => self.files,
+            false => HashMap::new(),
+        };
+        let done = done
+            .into_iter()
+            .map(|(hash, group)| (hash, group.into_iter().filter(|fi| walk.files.contains(fi)).collect::<HashSet<FileInfo>>()))
+            .filter(|(_, group)| !group.is_empty())
+            .collect::<HashMap<String, HashSet<FileInfo>>>();
+        let hashed = done.values().flatten().collect::<HashSet<&FileInfo>>();
+        send_progress(&report, Progress::Resumed { files: hashed.len(), bytes: hashed.iter().map(|fi| fi.size).sum() });
+        let files = walk.files.iter().filter(|fi| !hashed.contains(fi)).cloned().collect::<HashSet<FileInfo>>();
+        let rest = WalkInfo {
+            total_size: files.iter().map(|fi| fi.size).sum(),
+            files,
+            links: HashSet::new(),
+            hard_links: Vec::new(),
+            partial: walk.partial,
+            errors: Vec::new(),
+        };
+        let mut related = Self::relate(&rest, conf, report);
+        for (hash, group) in done {
+            related.files.entry(hash).or_default().extend(group);
+        }
+        related
+    }
+
+    /// Walk `roots' and hash what is found as soon as it is found, rather than after the whole walk as `relate'
+    /// does.  Where listing folders is slow, as on network filesystems, the hashing then gets done meanwhile.
+    /// Files `known' has a hash for are taken to have it rather than read again, like the results `resume' carries
+    /// on from.  The walks of the roots are merged as in `WalkInfo::walk_roots'.
+    /// Totals aren't known up front, so a parallel `conf.strategy' uses every thread it may, and
+    /// `conf.device_threads' is ignored.  The walk stops at the deadline too.
+    pub fn walk_and_relate(
+        roots: &[PathBuf],
+        filter: &WalkFilter,
+        conf: &RelateConf,
+        known: impl Fn(&FileInfo) -> Option<String>,
+        report: Sender<Progress>,
+    ) -> (WalkInfo, Self) {
+        let threads = match conf.strategy {
+            Strategy::Sequential => 1,
+            Strategy::Parallel { threads } => threads.max(1),
+            Strategy::Auto { max_threads, .. } => max_threads.max(1),
+        };
+        send_progress(&report, Progress::StageChanged(Stage::Walking));
+        let stopped = AtomicBool::new(false);
+        let (queue_tx, queue_rx) = mpsc::channel::<FileInfo>();
+        let queue = Mutex::new(queue_rx);
+        let (tx, rx): (Sender<Attempt>, Receiver<Attempt>) = mpsc::channel();
+        let throttle = conf.io_limit.map(Throttle::new);
+        thread::scope(|s| {
+            for _ in 0..threads {
+                let (tx, report, queue, stopped, throttle) = (tx.clone(), report.clone(), &queue, &stopped, throttle.as_ref());
+                s.spawn(move || {
+                    if conf.low_priority {
+                        // Failing to is no reason not to hash, only a slower machine meanwhile.
+                        let _ = platform::lower_priority();
+                    }
+                    loop {
+                        // The lock is only held while waiting for the next file, not while hashing it.
+                        let Ok(info) = queue.lock().unwrap_or_else(PoisonError::into_inner).recv() else {
+                            break;
+                        };
+                        if stopping(conf.deadline, conf.cancel.as_ref()) {
+                            stopped.store(true, atomic::Ordering::Relaxed);
+                            break;
+                        }
+                        send_progress(&report, Progress::FileStarted(info.name.to_path_buf()));
+                        let file = hash_settled(&info, conf.hash_algo, conf.mmap_threshold, conf.change_retries, throttle, &report);
+                        // Nobody is left to take the results.
+                        if tx.send((info.size, file)).is_err() {
+                            break;
+                        }
                     }
-                    done += 1;
-                    report.send(done as f32 / total as f32).expect("Failed to send results to parent!");
+                });
+            }
+            // Only the workers hold senders now, so the collector finishes once they have all finished.
+            drop(tx);
+            let collector_report = report.clone();
+            let collector = s.spawn(move || {
+                let mut related = Self::new(conf.hash_algo);
+                for result in rx {
+                    related.insert(result, &collector_report);
+                }
+                related
+            });
+            let (mut seen_paths, mut seen_ids, mut reused) = (HashSet::new(), HashSet::new(), Vec::new());
+            let mut found = |fi: &FileInfo| {
+                // Mostly the path itself, which is then shared rather than copied.
+                let key = paths::key(&fi.name);
+                let key = if *key == *fi.name { fi.name.clone() } else { Arc::from(key) };
+                // Another path to a file already found, which the walk sets aside rather than hashing it twice.
+                if !seen_paths.insert(key) || fi.file_id.is_some_and(|id| !seen_ids.insert(id)) {
+                    return;
+                }
+                send_progress(&report, Progress::FileFound { bytes: fi.size });
+                match known(fi) {
+                    Some(hash) => {
+                        send_progress(&report, Progress::Resumed { files: 1, bytes: fi.size });
+                        reused.push((hash, fi.clone()));
+                    },
+                    None => {
+                        let _ = queue_tx.send(fi.clone());
+                    },
+                }
+            };
+            let stop = || stopping(conf.deadline, conf.cancel.as_ref());
+            let filter = filter.counting_sizes(roots, stop);
+            let walk = paths::distinct_roots(roots)
+                .into_iter()
+                .map(|root| WalkInfo::walk_each(root, &filter, stop, &mut found))
+                .reduce(WalkInfo::merge)
+                .unwrap_or_else(WalkInfo::new);
+            // Once the workers have emptied the queue, they are done.
+            drop(queue_tx);
+            send_progress(&report, Progress::StageChanged(Stage::Hashing));
+            // The collector only inserts results, so it fails on nothing but a bug, which is passed on as it was.
+            let mut related = collector.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            for (hash, fi) in reused {
+                related.files.entry(hash).or_default().insert(fi);
+            }
+            related.retry_locked(&walk, conf, throttle.as_ref());
+            related.follow_hard_links(&walk);
+            related.partial = walk.partial || stopped.load(atomic::Ordering::Relaxed);
+            send_progress(&report, Progress::Done);
+            (walk, related)
+        })
+    }
+
+    /// Files are hashed by the first path found to them, which need not be the one `walk' kept when it set the others
+    /// aside as hard links.  Swap each for the kept one.
+    fn follow_hard_links(&mut self, walk: &WalkInfo) {
+        let kept = walk.hard_links
+            .iter()
+            .flat_map(|same| same[1..].iter().map(move |path| (path.as_path(), same[0].as_path())))
+            .collect::<HashMap<&Path, &Path>>();
+        if kept.is_empty() {
+            return;
+        }
+        let by_name = walk.files.iter().map(|fi| (&*fi.name, fi)).collect::<HashMap<&Path, &FileInfo>>();
+        for group in self.files.values_mut() {
+            *group = group
+                .drain()
+                .filter_map(|fi| match kept.get(&*fi.name) {
+                    Some(first) => by_name.get(first).map(|&fi| fi.clone()),
+                    None => Some(fi),
+                })
+                .collect();
+        }
+    }
+
+    /// Hash the files of `walk' that were locked once more, one at a time, when `conf.retry_locked' asks for it.
+    /// They were counted as done when they failed, so progress isn't told about them again.
+    fn retry_locked(&mut self, walk: &WalkInfo, conf: &RelateConf, throttle: Option<&Throttle>) {
+        if !conf.retry_locked {
+            return;
+        }
+        let (locked, errors): (Vec<Error>, Vec<Error>) = mem::take(&mut self.errors).into_iter().partition(|e| e.kind() == ErrorKind::Locked);
+        self.errors = errors;
+        let by_name = walk.files.iter().map(|fi| (&*fi.name, fi)).collect::<HashMap<&Path, &FileInfo>>();
+        let quiet = mpsc::channel().0;
+        for e in locked {
+            let Some(info) = by_name.get(e.path()).filter(|_| !stopping(conf.deadline, conf.cancel.as_ref())) else {
+                self.errors.push(e);
+                continue;
+            };
+            match hash_settled(info, conf.hash_algo, conf.mmap_threshold, conf.change_retries, throttle, &quiet) {
+                Ok(file) => {
+                    self.files.entry(file.hash).or_default().insert(file.info);
                 },
+                Err(e) => self.errors.push(e),
             }
         }
-        report.send(1.0).expect("Failed to send results to parent!");
-        threads.into_iter().for_each(|th| {
-            let _ = th.join();
+    }
+
+    /// The files with nothing in them, sorted by path.  They all have the same contents, but are seldom copies of
+    /// each other, such as markers and placeholders, so they are listed apart from the duplicates.
+    pub fn empty_files(&self) -> Vec<&FileInfo> {
+        let mut files = self.files.values().flatten().filter(|fi| fi.size == 0).collect::<Vec<&FileInfo>>();
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+        files
+    }
+
+    /// The hash of each file, to look files up by.
+    pub fn by_file(&self) -> HashMap<&FileInfo, &str> {
+        self.files.iter().flat_map(|(hash, group)| group.iter().map(move |fi| (fi, hash.as_str()))).collect()
+    }
+
+    pub fn relate_sequential(walk: &WalkInfo, conf: &RelateConf, report: Sender<Progress>) -> Self {
+        if !conf.low_priority {
+            return Self::hash_in_turn(walk, conf, report);
+        }
+        // On a thread of its own, as priority can't be raised again once lowered, and the caller's is left alone.
+        thread::scope(|s| {
+            let hashing = s.spawn(|| {
+                let _ = platform::lower_priority();
+                Self::hash_in_turn(walk, conf, report)
+            });
+            hashing.join().unwrap_or_else(|e| panic::resume_unwind(e))
+        })
+    }
+
+    /// Hash the files of `walk' one by one on the calling thread, see `relate_sequential'.
+    fn hash_in_turn(walk: &WalkInfo, conf: &RelateConf, report: Sender<Progress>) -> Self {
+        send_progress(&report, Progress::StageChanged(Stage::Hashing));
+        let throttle = conf.io_limit.map(Throttle::new);
+        let mut related = Self::new(conf.hash_algo);
+        let files = by_device(&walk.files).into_iter().flat_map(|(device, mut files)| {
+            reading_order(&mut files, device.map_or(DeviceKind::Unknown, platform::device_kind));
+            files
         });
-        Self { files, errors }
+        for info in files {
+            if stopping(conf.deadline, conf.cancel.as_ref()) {
+                related.partial = true;
+                break;
+            }
+            send_progress(&report, Progress::FileStarted(info.name.to_path_buf()));
+            let file = hash_settled(info, conf.hash_algo, conf.mmap_threshold, conf.change_retries, throttle.as_ref(), &report);
+            related.insert((info.size, file), &report);
+        }
+        related.retry_locked(walk, conf, throttle.as_ref());
+        related.partial |= walk.partial;
+        send_progress(&report, Progress::Done);
+        related
     }
+}
 
-    pub fn relate_sequential<'a>(walk: &'a WalkInfo, report: Sender<f32>) -> Self {
-        let mut done = 0;
-        let total = walk.total_size;
-        let mut files: HashMap<String, HashSet<FileInfo>> = HashMap::new();
-        let mut errors = Vec::new();
-        walk.files
-            .iter()
-            .for_each(|info| {
-                match hash_from_file_info(&info) {
-                    Err(err) => {
-                        errors.push(err);
-                    },
-                        Ok(file) => {
-                            match files.get_mut(&file.hash) {
-                                Some(hs) => {
-                                    hs.insert(file.info);
-                                },
-                                None => {
-                                    let mut hs = HashSet::new();
-                                    hs.insert(file.info);
-                                    files.insert(file.hash, hs);
-                                }
-                            }
-                        },
+/// Whether `group', files with the same contents, are duplicates: more than one, and not empty, since every empty
+/// file has the same contents.
+pub fn holds_duplicates(group: &HashSet<FileInfo>) -> bool {
+    group.len() > 1 && group.iter().any(|fi| fi.size > 0)
+}
+
+/// Group `files' by the device holding them, as far as the platform tells us.  Files without a known device
+/// share a group.
+fn by_device(files: &HashSet<FileInfo>) -> HashMap<Option<u64>, Vec<&FileInfo>> {
+    let mut devices = HashMap::<Option<u64>, Vec<&FileInfo>>::new();
+    for info in files {
+        devices.entry(info.device()).or_default().push(info);
+    }
+    devices
+}
+
+/// Sort `files', all on a device of `kind', into the order to hash them in.  A spinning disk gets them by inode, and
+/// then by path, which is about where a filesystem like ext4 puts them, so the heads sweep across the disk rather
+/// than seek back and forth.  Other devices get the largest first, so the last files to start are small and every
+/// worker finishes at about the same time.
+pub fn reading_order(files: &mut [&FileInfo], kind: DeviceKind) {
+    let inode = |info: &FileInfo| info.file_id.map(|(_, inode)| inode);
+    match kind {
+        DeviceKind::Rotational => files.sort_by(|a, b| inode(a).cmp(&inode(b)).then_with(|| a.name.cmp(&b.name))),
+        _ => files.sort_by_key(|info| cmp::Reverse(info.size)),
+    }
+}
+
+/// Threads hashing from a spinning disk at once.  More only make its heads seek between files.
+pub const ROTATIONAL_THREADS: u16 = 2;
+
+/// How many of `threads' hash from each of the devices of `kinds', which are read all at once.  Spinning disks get
+/// `ROTATIONAL_THREADS' at most, the others share the rest evenly.  Every device gets at least one.
+pub fn share_threads(threads: u16, kinds: &[DeviceKind]) -> Vec<u16> {
+    let rotational = kinds.iter().filter(|kind| **kind == DeviceKind::Rotational).count();
+    let others = kinds.len() - rotational;
+    let spare = threads.saturating_sub(ROTATIONAL_THREADS.saturating_mul(rotational.min(u16::MAX as usize) as u16));
+    kinds
+        .iter()
+        .map(|kind| match kind {
+            DeviceKind::Rotational => threads.min(ROTATIONAL_THREADS),
+            _ => spare / others.min(u16::MAX as usize) as u16,
+        }.max(1))
+        .collect()
+}
+
+/// The size of a file a worker tried, and what came of hashing it.
+type Attempt = (u64, Result<HashedFile, Error>);
+
+/// What the workers hashing one device's files share with the rest of `RelatedFiles::relate'.
+struct Pipeline<'a> {
+    tx: Sender<Attempt>,
+    report: Sender<Progress>,
+    stopped: Arc<AtomicBool>,
+    algo: HashAlgo,
+    mmap_threshold: Option<u64>,
+    change_retries: u8,
+    /// Shared with the other devices' pipelines.
+    throttle: Option<&'a Throttle>,
+    low_priority: bool,
+    deadline: Option<time::Instant>,
+    cancel: Option<CancellationToken>,
+}
+
+impl<'a> Pipeline<'a> {
+    /// Start at most `threads' workers hashing `files'.  Each worker takes the next file from a shared queue as
+    /// soon as it is done with the last, so a thread stuck on a large file doesn't hold back the others' share.
+    /// The files are taken in `reading_order' for a device of `kind'.
+    fn spawn<'scope>(
+        self, s: &'scope thread::Scope<'scope, 'a>, mut files: Vec<&'a FileInfo>, kind: DeviceKind, threads: u16,
+    ) -> Vec<thread::ScopedJoinHandle<'scope, ()>> {
+        reading_order(&mut files, kind);
+        let workers = (threads.max(1) as usize).min(files.len());
+        let queue = Arc::new(files);
+        let next = Arc::new(AtomicUsize::new(0));
+        (0..workers)
+            .map(|_| {
+                let (queue, next) = (queue.clone(), next.clone());
+                let (tx, report, stopped) = (self.tx.clone(), self.report.clone(), self.stopped.clone());
+                let (algo, mmap_threshold, change_retries) = (self.algo, self.mmap_threshold, self.change_retries);
+                let (throttle, low_priority) = (self.throttle, self.low_priority);
+                let (deadline, cancel) = (self.deadline, self.cancel.clone());
+                s.spawn(move || {
+                    if low_priority {
+                        // Failing to is no reason not to hash, only a slower machine meanwhile.
+                        let _ = platform::lower_priority();
+                    }
+                    while let Some(info) = queue.get(next.fetch_add(1, atomic::Ordering::Relaxed)) {
+                        if stopping(deadline, cancel.as_ref()) {
+                            stopped.store(true, atomic::Ordering::Relaxed);
+                            break;
+                        }
+                        send_progress(&report, Progress::FileStarted(info.name.to_path_buf()));
+                        let file = hash_settled(info, algo, mmap_threshold, change_retries, throttle, &report);
+                        if tx.send((info.size, file)).is_err() {
+                            break;
+                        }
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+/// The number of threads the machine can run at once, or 1 when it won't say.
+pub fn available_threads() -> u16 {
+    thread::available_parallelism().map_or(1, |n| n.get().min(u16::MAX as usize) as u16)
+}
+
+/// How hashing is spread over threads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Strategy {
+    /// Hash one file at a time, on the calling thread, or one of its own with `RelateConf::low_priority'.
+    Sequential,
+    /// Hash with up to `threads' workers.  `0` will be changed to 1.
+    Parallel { threads: u16 },
+    /// Go parallel with up to `max_threads' workers once there are more than `files' files or more than `bytes'
+    /// bytes to hash.  Below both, starting threads costs more than it saves.
+    Auto { max_threads: u16, files: usize, bytes: u64 },
+}
+
+impl Strategy {
+    /// Settle `Auto' for `files' files adding up to `bytes' bytes.  The others are returned as they are.
+    pub fn decide(self, files: usize, bytes: u64) -> Self {
+        match self {
+            Strategy::Auto { max_threads, files: file_threshold, bytes: byte_threshold } => {
+                if max_threads > 1 && (files > file_threshold || bytes > byte_threshold) {
+                    Strategy::Parallel { threads: max_threads }
+                } else {
+                    Strategy::Sequential
                 }
-                done += 1;
-                report.send(done as f32 / total as f32).expect("Failed to send results to parent!");
-            });
-        Self { files, errors }
+            },
+            strategy => strategy,
+        }
+    }
+}
+
+impl Default for Strategy {
+    /// Use every available core once there are more than a hundred files or a hundred megabytes.
+    fn default() -> Self {
+        Strategy::Auto { max_threads: available_threads(), files: 100, bytes: 100_000_000 }
     }
 }
 
 /// Configure the relating process, since it could be expensive with lots of large files.
 pub struct RelateConf {
-    /// Max number of threads to utilize when it is deemed worthwhile.
-    /// `0` will be changed to 1.
-    pub max_threads: u16,
-    /// How many files present before parallelizing.
-    pub file_threshold: usize,
-    /// Total size of files before parallelizing.
-    pub size_threshold: usize,
+    pub strategy: Strategy,
+    /// Max number of threads reading from any one device.  `None' shares the threads of a parallel `strategy'
+    /// between the devices by what they are, see `share_threads'.
+    pub device_threads: Option<u16>,
+    /// How file contents are hashed.
+    pub hash_algo: HashAlgo,
+    /// Files of at least this many bytes are mapped into memory to be hashed, which saves copying every chunk
+    /// read out of the kernel.  `None' reads every file in chunks.
+    pub mmap_threshold: Option<u64>,
+    /// Read a file that changed while it was read again, this many times at most, before giving up on it.
+    pub change_retries: u8,
+    /// Try the files another program had locked once more after all the others, in case it is done with them by
+    /// then.  Otherwise they are only listed among the errors.
+    pub retry_locked: bool,
+    /// Read at most this many bytes a second, between all the threads hashing, see `throttle'.  `None' reads as fast
+    /// as the disks go.
+    pub io_limit: Option<u64>,
+    /// Hash on threads of lower priority, see `platform::lower_priority'.  Hashing sequentially, that is a thread
+    /// of its own, so the caller's priority is left as it was.
+    pub low_priority: bool,
+    /// Stop starting new files after this, leaving the results `partial'.  Files being hashed are finished.
+    pub deadline: Option<time::Instant>,
+    /// Stop starting new files once this is cancelled, just as at the deadline.
+    pub cancel: Option<CancellationToken>,
+}
+
+fn stopping(deadline: Option<time::Instant>, cancel: Option<&CancellationToken>) -> bool {
+    deadline.is_some_and(|deadline| time::Instant::now() >= deadline) || cancel.is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Asks a walk or relate running on another thread to stop early.  Clones share the same request.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.0.store(true, atomic::Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(atomic::Ordering::Relaxed)
+    }
+}
+
+impl Default for RelateConf {
+    /// Decide on threads with `Strategy::default', hashing with SHA-256, mapping files of `MMAP_THRESHOLD' or more, and
+    /// reading changed files again `CHANGE_RETRIES' times.
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::default(),
+            device_threads: None,
+            hash_algo: HashAlgo::Sha256,
+            mmap_threshold: Some(MMAP_THRESHOLD),
+            change_retries: CHANGE_RETRIES,
+            retry_locked: false,
+            io_limit: None,
+            low_priority: false,
+            deadline: None,
+            cancel: None,
+        }
+    }
 }
@@ -0,0 +1,186 @@
+//! Export scan results for scripts and audits.
+
+use std::{
+    collections::BTreeMap,
+    io, io::Write,
+    path::Path,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::{
+    actions::{Action, Outcome},
+    policy::{KeepPolicy, Reclaim, Resolver},
+    project::{Decisions, Project},
+    relate::{Error, ErrorKind, FileInfo, Group, HashAlgo},
+    usage::ScanUsage,
+};
+
+/// Everything known about the duplicates in a scanned project.
+#[derive(Debug, Serialize)]
+pub struct Report<'a> {
+    /// The directory that was scanned.
+    pub path: &'a Path,
+    /// The folder `path' was compared against, if any.
+    pub reference: Option<&'a Path>,
+    pub algo: HashAlgo,
+    /// Whether hashing was cut short, so more duplicates may exist.
+    pub partial: bool,
+    /// What the scan cost, if it was measured.
+    pub usage: Option<&'a ScanUsage>,
+    /// Most wasteful first.
+    pub groups: Vec<Group<'a>>,
+    /// The policy picking the copy of each group that `reclaimable' keeps: the one last deduplicated with, else
+    /// the oldest.
+    pub keep: KeepPolicy,
+    /// What removing every copy of a group but the one kept would free, by hash.  Empty when `keep' can't be used,
+    /// as with a broken regex.
+    pub reclaimable: BTreeMap<&'a str, Reclaim>,
+    /// `reclaimable' of every group together.
+    pub total_reclaimable: Reclaim,
+    /// Files with nothing in them, which aren't counted as duplicates.  Sorted by path.
+    pub empty_files: Vec<&'a FileInfo>,
+    /// Files that couldn't be walked or hashed.  Only known right after a scan, they aren't saved with the project.
+    pub errors: Vec<&'a Error>,
+    /// The files the user has marked for removal.
+    pub decisions: &'a Decisions,
+}
+
+impl<'a> Report<'a> {
+    /// The report for `project', or `None' if it has not been scanned.
+    pub fn new(project: &'a Project) -> Option<Self> {
+        let related = project.related.as_ref()?;
+        let walk_errors = project.walk.iter().flat_map(|walk| walk.errors.iter());
+        let keep = project.preferences.keep.clone().unwrap_or(KeepPolicy::OldestCreated);
+        let resolver = Resolver::new(keep.clone()).ok().map(|resolver| match &project.reference {
+            Some(reference) => resolver.with_reference(reference.clone()),
+            None => resolver,
+        });
+        let reclaimable = resolver
+            .iter()
+            .flat_map(|resolver| resolver.resolve_all(related))
+            .map(|(hash, res)| (hash.as_str(), res.reclaimable()))
+            .collect::<BTreeMap<&str, Reclaim>>();
+        let total_reclaimable = reclaimable.values().copied().sum();
+        Some(Self {
+            path: &project.path,
+            reference: project.reference.as_deref(),
+            algo: related.algo,
+            partial: related.partial,
+            usage: project.usage.as_ref(),
+            groups: related.duplicate_groups(),
+            keep,
+            reclaimable,
+            total_reclaimable,
+            empty_files: related.empty_files(),
+            errors: walk_errors.chain(related.errors.iter()).collect(),
+            decisions: &project.decisions,
+        })
+    }
+}
+
+/// Write `report' to `out' as pretty printed JSON.  `out' is flushed, so a buffered write that fails, e.g. on a full
+/// disk, is reported rather than lost when `out' is dropped.
+pub fn to_json<W: Write>(mut out: W, report: &Report) -> io::Result<()> {
+    serde_json::to_writer_pretty(&mut out, report)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n', '\r']) {
+        format!("\"{:}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_owned()
+    }
+}
+
+/// Write `report' to `out' as CSV, one row per file, for reviewing in a spreadsheet.
+/// Groups are numbered from 1 in report order.  The action is "remove" for files marked for removal,
+/// "keep" for the rest of a group with some marked, "done" for files already acted on, and empty otherwise.
+/// The creation time is empty where the filesystem doesn't keep one.  `out' is flushed, as by `to_json'.
+pub fn to_csv<W: Write>(mut out: W, report: &Report) -> io::Result<()> {
+    writeln!(out, "group_id,hash,path,size,created,action")?;
+    for (id, group) in report.groups.iter().enumerate() {
+        let remove = report.decisions.remove.get(group.hash);
+        let done = report.decisions.done.get(group.hash);
+        for fi in &group.files {
+            let action = match remove {
+                _ if done.is_some_and(|done| done.contains(&*fi.name)) => "done",
+                Some(remove) if remove.contains(&*fi.name) => "remove",
+                Some(_) => "keep",
+                None => "",
+            };
+            writeln!(
+                out, "{:},{:},{:},{:},{:},{:}",
+                id + 1, group.hash, csv_field(&fi.name.to_string_lossy()), fi.size,
+                fi.created.map(|created| DateTime::<Utc>::from(created).to_rfc3339()).unwrap_or_default(), action,
+            )?;
+        }
+    }
+    out.flush()
+}
+
+/// Write `outcomes' of acting on a plan, or of a dry run of it, to `out' as CSV, one row per step.
+/// The action is "trash", "delete", "link", "clone" or "symlink".  The outcome is "done", "skipped" for a file
+/// another program had open, or "failed", and the error is empty for a step that was done.  `out' is flushed, as by
+/// `to_json'.
+pub fn outcomes_to_csv<W: Write>(mut out: W, outcomes: &[Outcome]) -> io::Result<()> {
+    writeln!(out, "action,path,keep,size,allocated,outcome,error")?;
+    for outcome in outcomes {
+        let step = &outcome.step;
+        let action = match step.action {
+            Action::Trash => "trash",
+            Action::Delete => "delete",
+            Action::HardLink => "link",
+            Action::Reflink => "clone",
+            Action::Symlink => "symlink",
+        };
+        let result = match &outcome.result {
+            Ok(()) => "done",
+            Err(_) if outcome.in_use() => "skipped",
+            Err(_) => "failed",
+        };
+        let error = outcome.result.as_ref().err().map(|e| e.to_string()).unwrap_or_default();
+        writeln!(
+            out, "{:},{:},{:},{:},{:},{:},{:}",
+            action, csv_field(&step.target.to_string_lossy()), csv_field(&step.keep.to_string_lossy()), step.size,
+            step.allocated, result, csv_field(&error),
+        )?;
+    }
+    out.flush()
+}
+
+/// Write the groups of `report' to `out' as `fdupes' prints them: the path of each file on a line of its own, and a
+/// blank line after each group, so scripts reading `fdupes' can read these too.
+pub fn to_fdupes<W: Write>(mut out: W, report: &Report) -> io::Result<()> {
+    for group in &report.groups {
+        for fi in &group.files {
+            writeln!(out, "{:}", fi.name.display())?;
+        }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// `errors' grouped by kind, each in path order.
+pub fn errors_by_kind<'a>(errors: impl IntoIterator<Item = &'a Error>) -> BTreeMap<ErrorKind, Vec<&'a Error>> {
+    let mut kinds = BTreeMap::<ErrorKind, Vec<&Error>>::new();
+    for error in errors {
+        kinds.entry(error.kind()).or_default().push(error);
+    }
+    for errors in kinds.values_mut() {
+        errors.sort_by(|a, b| a.path().cmp(b.path()));
+    }
+    kinds
+}
+
+/// Write `errors' to `out' for reading, under a heading for each kind.
+pub fn write_errors<'a, W: Write>(mut out: W, errors: impl IntoIterator<Item = &'a Error>) -> io::Result<()> {
+    for (kind, errors) in errors_by_kind(errors) {
+        writeln!(out, "{:} ({:}):", kind, errors.len())?;
+        for error in errors {
+            writeln!(out, "  {:}", error)?;
+        }
+    }
+    Ok(())
+}
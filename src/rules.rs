@@ -0,0 +1,164 @@
+//! Select the copies to remove by rules the user composes, such as "mark under /mnt/backup", "keep matching *.orig"
+//! or "prefer containing /originals/", rather than by a keep policy alone.  Rules are written as text, so they can be
+//! given on the command line and kept with a project.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashSet},
+    fmt, str::FromStr,
+    path::{self, PathBuf},
+};
+use globset::{Glob, GlobMatcher};
+use serde::{Serialize, Deserialize};
+use crate::{
+    paths,
+    policy::Resolver,
+    relate::{self, FileInfo, RelatedFiles},
+};
+
+/// What a rule does with the files it applies to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Verb {
+    /// Mark the files for removal.
+    Mark,
+    /// Never mark the files, whatever the other rules say.
+    Keep,
+    /// Keep the best of the files, going by the keep policy, and mark every other copy in their group.
+    Prefer,
+}
+
+/// Which files a rule applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Target {
+    /// The files below a folder, which may be relative to where the app runs.
+    Under(PathBuf),
+    /// The files matching a glob.  Globs without a `/' match the file name, e.g. `*.orig', those with one the
+    /// whole path, as with `WalkConf'.
+    Matching(String),
+    /// The files whose path holds some text, e.g. `/originals/'.
+    Containing(String),
+}
+
+/// One rule, as `<verb> <target> <value>', e.g. `mark under /mnt/backup'.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rule {
+    pub verb: Verb,
+    pub target: Target,
+}
+
+impl fmt::Display for Verb {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verb::Mark => write!(f, "mark"),
+            Verb::Keep => write!(f, "keep"),
+            Verb::Prefer => write!(f, "prefer"),
+        }
+    }
+}
+
+impl fmt::Display for Target {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Target::Under(folder) => write!(f, "under {:}", folder.display()),
+            Target::Matching(glob) => write!(f, "matching {:}", glob),
+            Target::Containing(text) => write!(f, "containing {:}", text),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:} {:}", self.verb, self.target)
+    }
+}
+
+/// Parse the rules produced by `Display'.  The value is the rest of the text, spaces and all, and a folder is spelled
+/// as `paths::normalize' does.
+impl FromStr for Rule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut words = s.trim().splitn(3, ' ');
+        let verb = match words.next() {
+            Some("mark") => Verb::Mark,
+            Some("keep") => Verb::Keep,
+            Some("prefer") => Verb::Prefer,
+            _ => return Err(format!("Unknown rule '{:}', expected it to start with mark, keep or prefer", s)),
+        };
+        let (target, value) = (words.next(), words.next().map(str::trim).filter(|value| !value.is_empty()));
+        let target = match (target, value) {
+            (Some("under"), Some(folder)) => Target::Under(paths::normalize(folder.as_ref())),
+            (Some("matching"), Some(glob)) => Target::Matching(glob.to_owned()),
+            (Some("containing"), Some(text)) => Target::Containing(text.to_owned()),
+            _ => return Err(format!("Unknown rule '{:}', expected e.g. '{:} under FOLDER', 'matching GLOB' or 'containing TEXT'", s, verb)),
+        };
+        Ok(Rule { verb, target })
+    }
+}
+
+/// A `Target' ready to be matched.  A folder is made absolute, as the files scanned may be.
+enum Matcher<'a> {
+    Under(PathBuf),
+    Name(GlobMatcher),
+    Path(GlobMatcher),
+    Containing(&'a str),
+}
+
+impl Matcher<'_> {
+    fn new(target: &Target) -> Result<Matcher<'_>, globset::Error> {
+        Ok(match target {
+            Target::Under(folder) => Matcher::Under(path::absolute(folder).map_or_else(|_| folder.clone(), |folder| paths::normalize(&folder))),
+            Target::Matching(glob) if glob.contains('/') => Matcher::Path(Glob::new(glob)?.compile_matcher()),
+            Target::Matching(glob) => Matcher::Name(Glob::new(glob)?.compile_matcher()),
+            Target::Containing(text) => Matcher::Containing(text),
+        })
+    }
+
+    fn is_match(&self, info: &FileInfo) -> bool {
+        match self {
+            Matcher::Under(folder) if info.name.is_absolute() => paths::starts_with(&info.name, folder),
+            Matcher::Under(folder) => path::absolute(&info.name).is_ok_and(|name| paths::starts_with(&name, folder)),
+            Matcher::Name(glob) => info.name.file_name().is_some_and(|name| glob.is_match(name)),
+            Matcher::Path(glob) => glob.is_match(&info.name),
+            Matcher::Containing(text) => info.name.to_string_lossy().contains(text),
+        }
+    }
+}
+
+/// The files of each duplicate group in `related' that `rules' mark for removal, keyed by hash, as
+/// `Decisions::remove' keeps them.  The rules apply in order, except that `Verb::Keep' always has the last word.
+/// Files `resolver' protects and archive members are never marked, and when the rules would mark every copy in a
+/// group, the one `resolver' keeps stays.  Only fails when a glob is invalid.
+pub fn select(related: &RelatedFiles, rules: &[Rule], resolver: &Resolver) -> Result<BTreeMap<String, BTreeSet<PathBuf>>, globset::Error> {
+    let matchers = rules
+        .iter()
+        .map(|rule| Ok((rule.verb, Matcher::new(&rule.target)?)))
+        .collect::<Result<Vec<(Verb, Matcher)>, globset::Error>>()?;
+    let mut selected = BTreeMap::new();
+    for (hash, group) in related.files.iter().filter(|(_, group)| relate::holds_duplicates(group)) {
+        let mut marked = HashSet::<&FileInfo>::new();
+        for (verb, matcher) in &matchers {
+            let matching = group.iter().filter(|info| matcher.is_match(info));
+            match verb {
+                Verb::Mark => marked.extend(matching),
+                Verb::Keep => (),
+                Verb::Prefer => {
+                    if let Some(best) = resolver.survivor(matching) {
+                        marked.extend(group.iter().filter(|info| *info != best));
+                        marked.remove(best);
+                    }
+                },
+            }
+        }
+        let kept = |info: &FileInfo| matchers.iter().any(|(verb, matcher)| *verb == Verb::Keep && matcher.is_match(info));
+        marked.retain(|info| !kept(info) && !resolver.is_protected(info) && !info.in_archive());
+        if marked.len() == group.len() {
+            if let Some(survivor) = resolver.survivor(group) {
+                marked.remove(survivor);
+            }
+        }
+        if !marked.is_empty() {
+            selected.insert(hash.clone(), marked.into_iter().map(|info| info.name.to_path_buf()).collect());
+        }
+    }
+    Ok(selected)
+}
@@ -0,0 +1,33 @@
+//! Find duplicates in one call: walk some folders, then hash what was found.  With `relate' for the details and
+//! `dedupe' for acting on the results, this makes up the API for programs embedding the engine.
+
+use std::{path::PathBuf, sync::mpsc::{self, Sender}};
+pub use crate::relate::{
+    CancellationToken, FileInfo, Group, HashAlgo, Progress, RelateConf, RelatedFiles, Stage, Strategy, SymlinkPolicy,
+    WalkConf, WalkFilter, WalkInfo,
+};
+
+/// What a scan found.
+pub struct Scan {
+    /// Every file found, and what couldn't be walked.
+    pub walk: WalkInfo,
+    /// The files found by hash.  Hashes shared by several files are duplicates, see `RelatedFiles::duplicate_groups'.
+    pub related: RelatedFiles,
+}
+
+/// Walk `roots' with the filters of `walk_conf', then hash what was found as `conf' says, reporting on the way to
+/// `report'.  A root inside another is only walked once.  Only fails for a pattern in `walk_conf' that isn't valid,
+/// files that can't be read are among the errors of the results.
+pub fn scan(roots: &[PathBuf], walk_conf: &WalkConf, conf: &RelateConf, report: Sender<Progress>) -> Result<Scan, globset::Error> {
+    let filter = WalkFilter::new(walk_conf)?;
+    let walk = WalkInfo::walk_roots(roots, &filter, &conf.cancel.clone().unwrap_or_default());
+    let related = RelatedFiles::relate(&walk, conf, report);
+    Ok(Scan { walk, related })
+}
+
+/// The files below `roots' by hash, walked and hashed with the defaults.
+pub fn duplicates(roots: &[PathBuf]) -> RelatedFiles {
+    let (report, _) = mpsc::channel();
+    let walk = WalkInfo::walk_roots(roots, &WalkFilter::default(), &CancellationToken::default());
+    RelatedFiles::relate(&walk, &RelateConf::default(), report)
+}
@@ -0,0 +1,235 @@
+//! The user's defaults, shared by every project: how to hash, which files new projects look at, the profiles to
+//! start them from, and how the app looks and talks.  They are kept as a TOML file in the configuration directory,
+//! meant to be edited by hand as well as by the app.  Anything missing from the file takes its usual default.
+
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use serde::{Serialize, Deserialize};
+use crate::{
+    actions::{Action, CrossDevice},
+    persist, platform,
+    policy::KeepPolicy,
+    relate::{HashAlgo, RelateConf, Strategy, WalkConf},
+};
+
+const SETTINGS_FILE: &str = "settings.toml";
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Read(toml::de::Error),
+    Write(toml::ser::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Read(e) => write!(f, "{:}: malformed settings: {:}", self.path.display(), e),
+            ErrorType::Write(e) => write!(f, "{:}: couldn't write settings: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// How the app looks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Theme {
+    /// Light or dark, as the desktop is.
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl fmt::Display for Theme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Theme::System => write!(f, "system"),
+            Theme::Light => write!(f, "light"),
+            Theme::Dark => write!(f, "dark"),
+        }
+    }
+}
+
+/// A color for buttons, selections and the like in place of the theme's own, kept as `#rrggbb'.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Accent {
+    pub red: u8,
+    pub green: u8,
+    pub blue: u8,
+}
+
+impl fmt::Display for Accent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.red, self.green, self.blue)
+    }
+}
+
+impl FromStr for Accent {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.trim().strip_prefix('#').unwrap_or(s.trim());
+        let channel = |i: usize| digits.get(i..i + 2).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (digits.len(), channel(0), channel(2), channel(4)) {
+            (6, Some(red), Some(green), Some(blue)) => Ok(Accent { red, green, blue }),
+            _ => Err(format!("Unknown color '{:}', expected one like #3366cc", s)),
+        }
+    }
+}
+
+impl TryFrom<String> for Accent {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Accent> for String {
+    fn from(accent: Accent) -> Self {
+        accent.to_string()
+    }
+}
+
+/// Filters and a way to deduplicate kept under a name, e.g. `Photos cleanup', for projects to start from.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub filters: WalkConf,
+    /// The project's own when `None', see `Project::apply_profile'.
+    pub keep: Option<KeepPolicy>,
+    pub action: Option<Action>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Hash with this many threads, or one file at a time when 1.  `None' uses every core once there is enough
+    /// to hash, see `Strategy::default'.
+    pub threads: Option<u16>,
+    pub hash_algo: HashAlgo,
+    /// Read at most this many bytes a second while hashing, see `RelateConf::io_limit'.
+    pub io_limit: Option<u64>,
+    /// Hash at a lower priority, see `RelateConf::low_priority'.
+    pub low_priority: bool,
+    /// What to do with copies on another filesystem than the file kept for them, which can't be linked to it,
+    /// see `Plan::across_devices'.
+    pub cross_device: CrossDevice,
+    /// The filters a new project starts with.  A project keeps its own once it has been scanned.
+    pub filters: WalkConf,
+    pub theme: Theme,
+    /// The theme's own accent color when `None'.
+    pub accent: Option<Accent>,
+    /// The language to talk to the user in, e.g. `de', or theirs when `None', see `i18n'.
+    pub language: Option<String>,
+    pub profiles: Vec<Profile>,
+}
+
+impl Default for Settings {
+    /// New projects leave out the files macOS keeps beside the user's, see `platform::MAC_METADATA'.
+    fn default() -> Self {
+        Settings {
+            threads: None,
+            hash_algo: HashAlgo::default(),
+            io_limit: None,
+            low_priority: false,
+            cross_device: CrossDevice::default(),
+            filters: WalkConf { exclude: platform::MAC_METADATA.map(str::to_owned).to_vec(), ..Default::default() },
+            theme: Theme::default(),
+            accent: None,
+            language: None,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Where the settings live under the configuration directory `conf_dir'.
+    pub fn file(conf_dir: &Path) -> PathBuf {
+        conf_dir.join(SETTINGS_FILE)
+    }
+
+    /// Read the settings under `conf_dir'.  They are all defaults until something is saved.
+    pub fn load(conf_dir: &Path) -> Result<Self, Error> {
+        let file = Self::file(conf_dir);
+        if !file.exists() && !persist::backup_path(&file).exists() {
+            return Ok(Self::default());
+        }
+        persist::read_recovering(
+            &file,
+            |contents| {
+                let read = |e| Error { path: file.clone(), error_type: ErrorType::Read(e) };
+                toml::from_str(&String::from_utf8_lossy(contents)).map_err(read)
+            },
+            |e| io_error(&file)(e),
+        )
+    }
+
+    pub fn save(&self, conf_dir: &Path) -> Result<(), Error> {
+        let file = Self::file(conf_dir);
+        let contents = toml::to_string_pretty(self).map_err(|e| Error { path: file.clone(), error_type: ErrorType::Write(e) })?;
+        persist::write_atomic(&file, contents.as_bytes()).map_err(io_error(&file))
+    }
+
+    /// The profile called `name', whatever its case.
+    pub fn profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name.to_lowercase() == name.trim().to_lowercase())
+    }
+
+    /// Keep `profile', in place of any with its name.
+    pub fn save_profile(&mut self, profile: Profile) {
+        match self.profiles.iter_mut().find(|old| old.name.to_lowercase() == profile.name.to_lowercase()) {
+            Some(old) => *old = profile,
+            None => self.profiles.push(profile),
+        }
+    }
+
+    /// Forget the profile called `name'.  Returns whether there was one.
+    pub fn remove_profile(&mut self, name: &str) -> bool {
+        let before = self.profiles.len();
+        self.profiles.retain(|profile| profile.name.to_lowercase() != name.trim().to_lowercase());
+        self.profiles.len() != before
+    }
+
+    /// How to spread hashing over threads, see `threads'.
+    pub fn strategy(&self) -> Strategy {
+        match self.threads {
+            None => Strategy::default(),
+            Some(0 | 1) => Strategy::Sequential,
+            Some(threads) => Strategy::Parallel { threads },
+        }
+    }
+
+    /// `RelateConf::default' with these settings.
+    pub fn relate_conf(&self) -> RelateConf {
+        RelateConf {
+            strategy: self.strategy(),
+            hash_algo: self.hash_algo,
+            io_limit: self.io_limit,
+            low_priority: self.low_priority,
+            ..Default::default()
+        }
+    }
+}
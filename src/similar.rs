@@ -0,0 +1,82 @@
+//! Group the files of a scan by what they hold rather than by their bytes, e.g. songs whose tags differ.  What counts
+//! as holding the same is up to a `ContentComparator', or one for each kind of file through `relate::Comparators'.
+//! Nothing guarantees such files are interchangeable, so they are reported apart from exact duplicates, and never
+//! removed automatically.
+
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::{Mutex, PoisonError},
+    thread,
+};
+use crate::relate::{self, ContentComparator, FileInfo, RelatedFiles};
+
+/// Files holding the same, though their bytes differ.
+#[derive(Debug, Default)]
+pub struct SimilarFiles {
+    /// Each sorted by path, and holding at least two different contents along with every exact copy of them.
+    /// Groups are in order of their first path.
+    pub groups: Vec<Vec<FileInfo>>,
+    pub errors: Vec<relate::Error>,
+}
+
+impl SimilarFiles {
+    /// Group the files of `related' that `applies' to by the keys `comparator' gives them, with `threads' threads.
+    pub fn find(related: &RelatedFiles, comparator: &dyn ContentComparator, applies: impl Fn(&Path) -> bool, threads: u16) -> Self {
+        let (keyed, errors) = key_contents(related, applies, threads, |path| comparator.key(path));
+        let mut by_key = HashMap::<String, Vec<&HashSet<FileInfo>>>::new();
+        for (copies, key) in keyed {
+            by_key.entry(key).or_default().push(copies);
+        }
+        Self { groups: groups(by_key.into_values()), errors }
+    }
+}
+
+/// The `key' of each content of `related' that `applies' to one of its files, with `threads' threads, along with
+/// every copy of it.  Each content is looked at once, through its first path, however many copies there are.
+/// Archive members are left out, as they can't be opened on their own.
+pub fn key_contents<K: Send, E: Send>(
+    related: &RelatedFiles, applies: impl Fn(&Path) -> bool, threads: u16, key: impl Fn(&Path) -> Result<K, E> + Sync,
+) -> (Vec<(&HashSet<FileInfo>, K)>, Vec<E>) {
+    let contents = related
+        .files
+        .values()
+        .filter_map(|copies| {
+            let first = copies.iter().filter(|fi| !fi.in_archive() && applies(&fi.name)).min_by(|a, b| a.name.cmp(&b.name))?;
+            Some((first, copies))
+        })
+        .collect::<Vec<(&FileInfo, &HashSet<FileInfo>)>>();
+    let next = Mutex::new(contents.into_iter());
+    let results = Mutex::new((Vec::new(), Vec::new()));
+    thread::scope(|s| {
+        for _ in 0..threads.max(1) {
+            s.spawn(|| loop {
+                let Some((first, copies)) = next.lock().unwrap_or_else(PoisonError::into_inner).next() else {
+                    break;
+                };
+                let key = key(&first.name);
+                let mut results = results.lock().unwrap_or_else(PoisonError::into_inner);
+                match key {
+                    Ok(key) => results.0.push((copies, key)),
+                    Err(e) => results.1.push(e),
+                }
+            });
+        }
+    });
+    results.into_inner().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Sets of contents as groups of files, dropping those of a single content.
+pub fn groups<'a>(sets: impl IntoIterator<Item = Vec<&'a HashSet<FileInfo>>>) -> Vec<Vec<FileInfo>> {
+    let mut groups = sets
+        .into_iter()
+        .filter(|contents| contents.len() > 1)
+        .map(|contents| {
+            let mut files = contents.into_iter().flatten().cloned().collect::<Vec<FileInfo>>();
+            files.sort_by(|a, b| a.name.cmp(&b.name));
+            files
+        })
+        .collect::<Vec<Vec<FileInfo>>>();
+    groups.sort_by(|a, b| a[0].name.cmp(&b[0].name));
+    groups
+}
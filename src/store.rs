@@ -0,0 +1,691 @@
+//! Keep every project in a single SQLite database in the data directory.
+//! Files, hashes, hard links and decisions each get a table, so a project with millions of files is written and
+//! read row by row, and listing the projects reads none of them.  Marking files or acting on them writes only the
+//! decisions that changed, and the groups can be listed and read one at a time, see `Store::groups', or all at once
+//! without the files no other file copies, see `Store::load_duplicates'.  The schema is changed only by appending
+//! to `MIGRATIONS', so a database made by an older version is brought up to date when it is opened.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+use rusqlite::{params, Connection, OptionalExtension, Row, Transaction};
+use crate::{
+    actions::{Action, Outcome},
+    project::{Decisions, Project, Summary},
+    relate::{FileInfo, HashAlgo, RelatedFiles, WalkInfo},
+};
+
+const DATABASE_FILE: &str = "projects.sqlite";
+/// How long to wait for another process to finish writing.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Each entry takes the schema from the version before it to the next, the first from an empty database.
+/// Never change an entry once released, append another.
+const MIGRATIONS: &[&str] = &[
+    "CREATE TABLE projects (
+        id INTEGER PRIMARY KEY,
+        path BLOB NOT NULL UNIQUE,
+        reference BLOB,
+        created INTEGER NOT NULL,
+        updated INTEGER NOT NULL,
+        walk_conf TEXT NOT NULL,
+        preferences TEXT NOT NULL,
+        usage TEXT
+    );
+    CREATE TABLE scans (
+        id INTEGER PRIMARY KEY,
+        project INTEGER NOT NULL UNIQUE REFERENCES projects (id) ON DELETE CASCADE,
+        walked INTEGER NOT NULL,
+        walk_partial INTEGER NOT NULL,
+        total_size INTEGER NOT NULL,
+        algo TEXT,
+        partial INTEGER NOT NULL
+    );
+    CREATE TABLE files (
+        scan INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+        path BLOB NOT NULL,
+        size INTEGER NOT NULL,
+        created INTEGER NOT NULL,
+        modified INTEGER,
+        allocated INTEGER,
+        link_target BLOB,
+        device INTEGER,
+        inode INTEGER,
+        archive BLOB,
+        link INTEGER NOT NULL,
+        walked INTEGER NOT NULL,
+        hash TEXT,
+        PRIMARY KEY (scan, path)
+    );
+    CREATE INDEX files_by_hash ON files (scan, hash);
+    CREATE TABLE hard_links (
+        scan INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+        link_group INTEGER NOT NULL,
+        path BLOB NOT NULL
+    );
+    CREATE TABLE marked (
+        project INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+        hash TEXT NOT NULL,
+        path BLOB NOT NULL,
+        PRIMARY KEY (project, hash, path)
+    );
+    CREATE TABLE done (
+        project INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+        hash TEXT NOT NULL,
+        path BLOB NOT NULL,
+        PRIMARY KEY (project, hash, path)
+    );",
+    // Creation times are optional, as not every filesystem keeps them.
+    "CREATE TABLE files_new (
+        scan INTEGER NOT NULL REFERENCES scans (id) ON DELETE CASCADE,
+        path BLOB NOT NULL,
+        size INTEGER NOT NULL,
+        created INTEGER,
+        modified INTEGER,
+        allocated INTEGER,
+        link_target BLOB,
+        device INTEGER,
+        inode INTEGER,
+        archive BLOB,
+        link INTEGER NOT NULL,
+        walked INTEGER NOT NULL,
+        hash TEXT,
+        PRIMARY KEY (scan, path)
+    );
+    INSERT INTO files_new SELECT * FROM files;
+    DROP TABLE files;
+    ALTER TABLE files_new RENAME TO files;
+    CREATE INDEX files_by_hash ON files (scan, hash);",
+    // Folders deduplicated along with the project's own, in the order given.
+    "CREATE TABLE extra_roots (
+        project INTEGER NOT NULL REFERENCES projects (id) ON DELETE CASCADE,
+        path BLOB NOT NULL
+    );",
+];
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Database(rusqlite::Error),
+    /// A column that should hold JSON doesn't.
+    Format(serde_json::Error),
+    /// The database was made by a newer version, whose schema we don't know.
+    Newer(usize),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn database_error(path: &Path) -> impl FnOnce(rusqlite::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Database(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(serde_json::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Database(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed project: {:}", self.path.display(), e),
+            ErrorType::Newer(version) => {
+                write!(f, "{:}: made by a newer version of the program (schema {:}), please upgrade", self.path.display(), version)
+            },
+        }
+    }
+}
+
+/// Paths are kept as the bytes the operating system uses, so names that aren't valid Unicode survive.
+#[cfg(unix)]
+fn path_to_blob(path: &Path) -> &[u8] {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_blob(blob: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    std::ffi::OsString::from_vec(blob).into()
+}
+
+/// Elsewhere names are nearly always valid Unicode, and those that aren't are stored with replacement characters.
+#[cfg(not(unix))]
+fn path_to_blob(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(not(unix))]
+fn path_from_blob(blob: Vec<u8>) -> PathBuf {
+    String::from_utf8_lossy(&blob).into_owned().into()
+}
+
+/// Nanoseconds since the Unix epoch, negative before it.
+fn time_to_int(time: SystemTime) -> i64 {
+    match time.duration_since(SystemTime::UNIX_EPOCH) {
+        Ok(after) => after.as_nanos() as i64,
+        Err(before) => -(before.duration().as_nanos() as i64),
+    }
+}
+
+fn time_from_int(nanos: i64) -> SystemTime {
+    match nanos {
+        0.. => SystemTime::UNIX_EPOCH + Duration::from_nanos(nanos as u64),
+        _ => SystemTime::UNIX_EPOCH - Duration::from_nanos(nanos.unsigned_abs()),
+    }
+}
+
+/// The columns of `files' making up a `FileInfo', in the order `file_from_row' reads them.
+const FILE_COLUMNS: &str = "path, size, created, modified, allocated, link_target, device, inode, archive";
+
+fn file_from_row(row: &Row) -> rusqlite::Result<FileInfo> {
+    let (device, inode) = (row.get::<_, Option<i64>>(6)?, row.get::<_, Option<i64>>(7)?);
+    Ok(FileInfo {
+        name: path_from_blob(row.get(0)?).into(),
+        size: row.get::<_, i64>(1)? as u64,
+        created: row.get::<_, Option<i64>>(2)?.map(time_from_int),
+        modified: row.get::<_, Option<i64>>(3)?.map(time_from_int),
+        allocated: row.get::<_, Option<i64>>(4)?.map(|allocated| allocated as u64),
+        link_target: row.get::<_, Option<Vec<u8>>>(5)?.map(path_from_blob),
+        // Stored as signed integers, so the largest ids come back negative and are cast back.
+        file_id: device.zip(inode).map(|(device, inode)| (device as u64, inode as u64)),
+        archive: row.get::<_, Option<Vec<u8>>>(8)?.map(|blob| path_from_blob(blob).into()),
+    })
+}
+
+/// A duplicate group of a saved scan, as `Store::groups' lists it without reading its files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupSummary {
+    pub hash: String,
+    pub size: u64,
+    pub copies: u64,
+}
+
+impl GroupSummary {
+    /// Bytes that would be freed by keeping a single copy, as `relate::Group::wasted'.
+    pub fn wasted(&self) -> u64 {
+        self.size * self.copies.saturating_sub(1)
+    }
+}
+
+/// The database of projects.
+pub struct Store {
+    file: PathBuf,
+    connection: Connection,
+}
+
+impl Store {
+    /// Where the database lives under the data directory `data_dir'.
+    pub fn file(data_dir: &Path) -> PathBuf {
+        data_dir.join(DATABASE_FILE)
+    }
+
+    /// Open the database under `data_dir', creating it or bringing its schema up to date as needed.
+    pub fn open(data_dir: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(data_dir).map_err(io_error(data_dir))?;
+        let file = Self::file(data_dir);
+        let mut connection = Connection::open(&file).map_err(database_error(&file))?;
+        connection.busy_timeout(BUSY_TIMEOUT).map_err(database_error(&file))?;
+        connection.pragma_update(None, "foreign_keys", true).map_err(database_error(&file))?;
+        migrate(&mut connection).map_err(|error_type| Error { path: file.clone(), error_type })?;
+        Ok(Self { file, connection })
+    }
+
+    /// Whether a project for `path' has been saved.
+    pub fn contains(&self, path: &Path) -> Result<bool, Error> {
+        self.project_id(path).map(|id| id.is_some())
+    }
+
+    fn project_id(&self, path: &Path) -> Result<Option<i64>, Error> {
+        self.connection
+            .query_row("SELECT id FROM projects WHERE path = ?1", [path_to_blob(path)], |row| row.get(0))
+            .optional()
+            .map_err(database_error(&self.file))
+    }
+
+    /// Write `project', replacing any earlier save of the same directory, all at once.
+    pub fn save(&mut self, project: &Project) -> Result<(), Error> {
+        let file = self.file.clone();
+        let tx = self.connection.transaction().map_err(database_error(&file))?;
+        let id = save_project(&tx, project, &file)?;
+        save_decisions(&tx, id, &project.decisions).map_err(database_error(&file))?;
+        save_scan(&tx, id, project, &file)?;
+        tx.commit().map_err(database_error(&file))
+    }
+
+    /// Write all of `project' but its scan, and take the files `outcomes' trashed or deleted out of the saved scan's
+    /// groups, as `Project::record' does.  Only the decisions that changed are written, so marking a file or acting
+    /// on a few costs little however large the scan.  Use `save' once the scan itself has changed.
+    pub fn save_decisions(&mut self, project: &Project, outcomes: &[Outcome]) -> Result<(), Error> {
+        let file = self.file.clone();
+        let tx = self.connection.transaction().map_err(database_error(&file))?;
+        let id = save_project(&tx, project, &file)?;
+        save_decisions(&tx, id, &project.decisions).map_err(database_error(&file))?;
+        forget_removed(&tx, id, outcomes).map_err(database_error(&file))?;
+        tx.commit().map_err(database_error(&file))
+    }
+
+    /// Read the project saved for `path', if there is one.
+    pub fn load(&self, path: &Path) -> Result<Option<Project>, Error> {
+        self.load_with(path, true)
+    }
+
+    /// Like `load', leaving the last scan in the database: `walk' and `related' are `None', and the groups are read
+    /// as needed with `groups' and `group'.
+    pub fn load_unscanned(&self, path: &Path) -> Result<Option<Project>, Error> {
+        self.load_with(path, false)
+    }
+
+    /// Like `load_unscanned', with `related' holding the duplicate groups and empty files of the last scan.  Files no
+    /// other file has the contents of are left in the database, so reading a scan takes memory for its copies alone.
+    pub fn load_duplicates(&self, path: &Path) -> Result<Option<Project>, Error> {
+        let Some(mut project) = self.load_with(path, false)? else {
+            return Ok(None);
+        };
+        project.related = self.duplicates(path)?;
+        Ok(Some(project))
+    }
+
+    fn load_with(&self, path: &Path, scan: bool) -> Result<Option<Project>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let row = self.connection
+            .query_row(
+                "SELECT id, path, reference, created, updated, walk_conf, preferences, usage FROM projects WHERE path = ?1",
+                [path_to_blob(path)],
+                |row| Ok((
+                    row.get::<_, i64>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, Option<Vec<u8>>>(2)?, row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?, row.get::<_, String>(5)?, row.get::<_, String>(6)?, row.get::<_, Option<String>>(7)?,
+                )),
+            )
+            .optional()
+            .map_err(db)?;
+        let Some((id, path, reference, created, updated, walk_conf, preferences, usage)) = row else {
+            return Ok(None);
+        };
+        let json = |e| format_error(&self.file)(e);
+        let mut project = Project::new(path_from_blob(path));
+        project.reference = reference.map(path_from_blob);
+        project.extra_roots = self.extra_roots(id)?;
+        project.created = time_from_int(created);
+        project.updated = time_from_int(updated);
+        project.walk_conf = serde_json::from_str(&walk_conf).map_err(json)?;
+        project.preferences = serde_json::from_str(&preferences).map_err(json)?;
+        project.usage = usage.map(|usage| serde_json::from_str(&usage)).transpose().map_err(json)?;
+        project.decisions = Decisions { remove: self.decisions("marked", id)?, done: self.decisions("done", id)? };
+        if scan {
+            (project.walk, project.related) = self.scan(id)?;
+        }
+        Ok(Some(project))
+    }
+
+    /// The algorithm the groups of project `path' were hashed with, or `None' while it has none saved.
+    pub fn hash_algo(&self, path: &Path) -> Result<Option<HashAlgo>, Error> {
+        let algo = self.scan_of(path)?.and_then(|(_, algo)| algo);
+        let algo = algo.map(|algo| algo.parse::<HashAlgo>()).transpose();
+        algo.map_err(|e| Error { path: self.file.clone(), error_type: ErrorType::IO(io::Error::new(io::ErrorKind::InvalidData, e)) })
+    }
+
+    /// The duplicate groups of the last scan of project `path', most wasteful first as `RelatedFiles::duplicate_groups'
+    /// has them.  They are counted by the database, so none of the files is read.
+    pub fn groups(&self, path: &Path) -> Result<Vec<GroupSummary>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let Some((scan, _)) = self.scan_of(path)? else {
+            return Ok(Vec::new());
+        };
+        let mut statement = self.connection
+            .prepare(
+                "SELECT hash, MAX(size) AS size, COUNT(*) AS copies FROM files WHERE scan = ?1 AND hash IS NOT NULL
+                 GROUP BY hash HAVING copies > 1 AND size > 0 ORDER BY size * (copies - 1) DESC, hash",
+            )
+            .map_err(db)?;
+        let rows = statement
+            .query_map([scan], |row| Ok(GroupSummary {
+                hash: row.get(0)?,
+                size: row.get::<_, i64>(1)? as u64,
+                copies: row.get::<_, i64>(2)? as u64,
+            }))
+            .map_err(db)?;
+        rows.collect::<rusqlite::Result<Vec<GroupSummary>>>().map_err(db)
+    }
+
+    /// The files of the last scan of project `path' sharing their contents with another or empty, grouped by hash.
+    /// `None' while the scan has no hashes saved.
+    fn duplicates(&self, path: &Path) -> Result<Option<RelatedFiles>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let Some(algo) = self.hash_algo(path)? else {
+            return Ok(None);
+        };
+        let Some((scan, _)) = self.scan_of(path)? else {
+            return Ok(None);
+        };
+        let partial = self.connection.query_row("SELECT partial FROM scans WHERE id = ?1", [scan], |row| row.get(0)).map_err(db)?;
+        let mut statement = self.connection
+            .prepare(&format!(
+                "SELECT {:}, hash FROM files WHERE scan = ?1 AND hash IN (
+                     SELECT hash FROM files WHERE scan = ?1 AND hash IS NOT NULL GROUP BY hash HAVING COUNT(*) > 1 OR MAX(size) = 0
+                 )",
+                FILE_COLUMNS,
+            ))
+            .map_err(db)?;
+        let rows = statement.query_map([scan], |row| Ok((file_from_row(row)?, row.get::<_, String>(9)?))).map_err(db)?;
+        let mut files = HashMap::<String, HashSet<FileInfo>>::new();
+        for row in rows {
+            let (fi, hash) = row.map_err(db)?;
+            files.entry(hash).or_default().insert(fi);
+        }
+        Ok(Some(RelatedFiles { algo, files, partial, errors: Vec::new() }))
+    }
+
+    /// The files of the last scan of project `path' with contents `hash'.
+    pub fn group(&self, path: &Path, hash: &str) -> Result<HashSet<FileInfo>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let Some((scan, _)) = self.scan_of(path)? else {
+            return Ok(HashSet::new());
+        };
+        let mut statement = self.connection
+            .prepare(&format!("SELECT {:} FROM files WHERE scan = ?1 AND hash = ?2", FILE_COLUMNS))
+            .map_err(db)?;
+        let rows = statement.query_map(params![scan, hash], file_from_row).map_err(db)?;
+        rows.collect::<rusqlite::Result<HashSet<FileInfo>>>().map_err(db)
+    }
+
+    /// The id and hash algorithm of the last scan of project `path', if it has one.
+    fn scan_of(&self, path: &Path) -> Result<Option<(i64, Option<String>)>, Error> {
+        self.connection
+            .query_row(
+                "SELECT scans.id, scans.algo FROM scans JOIN projects ON scans.project = projects.id WHERE projects.path = ?1",
+                [path_to_blob(path)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()
+            .map_err(database_error(&self.file))
+    }
+
+    /// The folders deduplicated along with that of project `id'.
+    fn extra_roots(&self, id: i64) -> Result<Vec<PathBuf>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let mut statement = self.connection.prepare("SELECT path FROM extra_roots WHERE project = ?1 ORDER BY rowid").map_err(db)?;
+        let rows = statement.query_map([id], |row| Ok(path_from_blob(row.get(0)?))).map_err(db)?;
+        rows.collect::<rusqlite::Result<Vec<PathBuf>>>().map_err(db)
+    }
+
+    /// The decisions of project `id' kept in `table'.
+    fn decisions(&self, table: &str, id: i64) -> Result<BTreeMap<String, BTreeSet<PathBuf>>, Error> {
+        let mut decisions = BTreeMap::<String, BTreeSet<PathBuf>>::new();
+        for (hash, path) in decision_rows(&self.connection, table, id).map_err(database_error(&self.file))? {
+            decisions.entry(hash).or_default().insert(path);
+        }
+        Ok(decisions)
+    }
+
+    /// The walk and hashes of the last scan of project `id'.
+    fn scan(&self, id: i64) -> Result<(Option<WalkInfo>, Option<RelatedFiles>), Error> {
+        let db = |e| database_error(&self.file)(e);
+        let scan = self.connection
+            .query_row(
+                "SELECT id, walked, walk_partial, total_size, algo, partial FROM scans WHERE project = ?1",
+                [id],
+                |row| Ok((
+                    row.get::<_, i64>(0)?, row.get::<_, bool>(1)?, row.get::<_, bool>(2)?, row.get::<_, i64>(3)?,
+                    row.get::<_, Option<String>>(4)?, row.get::<_, bool>(5)?,
+                )),
+            )
+            .optional()
+            .map_err(db)?;
+        let Some((scan, walked, walk_partial, total_size, algo, partial)) = scan else {
+            return Ok((None, None));
+        };
+        let (mut files, mut links, mut hashed) = (HashSet::new(), HashSet::new(), HashMap::<String, HashSet<FileInfo>>::new());
+        let mut statement = self.connection
+            .prepare(&format!("SELECT {:}, link, walked, hash FROM files WHERE scan = ?1", FILE_COLUMNS))
+            .map_err(db)?;
+        let rows = statement
+            .query_map([scan], |row| Ok((file_from_row(row)?, row.get::<_, bool>(9)?, row.get::<_, bool>(10)?, row.get::<_, Option<String>>(11)?)))
+            .map_err(db)?;
+        for row in rows {
+            let (fi, link, walked, hash) = row.map_err(db)?;
+            if let Some(hash) = hash {
+                hashed.entry(hash).or_default().insert(fi.clone());
+            }
+            match (link, walked) {
+                (true, _) => {
+                    links.insert(fi);
+                },
+                (false, true) => {
+                    files.insert(fi);
+                },
+                (false, false) => (),
+            }
+        }
+        let mut hard_links = Vec::<Vec<PathBuf>>::new();
+        let mut statement = self.connection
+            .prepare("SELECT link_group, path FROM hard_links WHERE scan = ?1 ORDER BY link_group, rowid")
+            .map_err(db)?;
+        let rows = statement.query_map([scan], |row| Ok((row.get::<_, i64>(0)?, path_from_blob(row.get(1)?)))).map_err(db)?;
+        for row in rows {
+            let (group, path) = row.map_err(db)?;
+            match hard_links.get_mut(group as usize) {
+                Some(same) => same.push(path),
+                None => hard_links.push(vec![path]),
+            }
+        }
+        let walk = walked.then(|| WalkInfo {
+            total_size: total_size as u64,
+            files,
+            links,
+            hard_links,
+            partial: walk_partial,
+            errors: Vec::new(),
+        });
+        let algo = algo.map(|algo| algo.parse::<HashAlgo>()).transpose();
+        let algo = algo.map_err(|e| Error { path: self.file.clone(), error_type: ErrorType::IO(io::Error::new(io::ErrorKind::InvalidData, e)) })?;
+        let related = algo.map(|algo| RelatedFiles { algo, files: hashed, partial, errors: Vec::new() });
+        Ok((walk, related))
+    }
+
+    /// Every saved project, most recently updated first.  Only the projects table is read.
+    pub fn summaries(&self) -> Result<Vec<Summary>, Error> {
+        let db = |e| database_error(&self.file)(e);
+        let mut statement = self.connection
+            .prepare(
+                "SELECT path, updated, scans.algo IS NOT NULL FROM projects LEFT JOIN scans ON scans.project = projects.id
+                 ORDER BY updated DESC",
+            )
+            .map_err(db)?;
+        let rows = statement
+            .query_map([], |row| Ok(Summary {
+                path: path_from_blob(row.get(0)?),
+                updated: time_from_int(row.get(1)?),
+                related: row.get(2)?,
+            }))
+            .map_err(db)?;
+        rows.collect::<rusqlite::Result<Vec<Summary>>>().map_err(db)
+    }
+}
+
+/// Apply the migrations the database hasn't had yet, each in a transaction of its own.
+fn migrate(connection: &mut Connection) -> Result<(), ErrorType> {
+    let version = connection.pragma_query_value(None, "user_version", |row| row.get::<_, usize>(0)).map_err(ErrorType::Database)?;
+    if version > MIGRATIONS.len() {
+        return Err(ErrorType::Newer(version));
+    }
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = connection.transaction().map_err(ErrorType::Database)?;
+        tx.execute_batch(migration).map_err(ErrorType::Database)?;
+        tx.pragma_update(None, "user_version", i + 1).map_err(ErrorType::Database)?;
+        tx.commit().map_err(ErrorType::Database)?;
+    }
+    Ok(())
+}
+
+/// The rows of project `id' in the decisions table `table'.
+fn decision_rows(connection: &Connection, table: &str, id: i64) -> rusqlite::Result<BTreeSet<(String, PathBuf)>> {
+    let mut statement = connection.prepare(&format!("SELECT hash, path FROM {:} WHERE project = ?1", table))?;
+    let rows = statement.query_map([id], |row| Ok((row.get::<_, String>(0)?, path_from_blob(row.get(1)?))))?;
+    rows.collect()
+}
+
+/// Write the row of `project' and its extra roots, returning its id.
+fn save_project(tx: &Transaction, project: &Project, file: &Path) -> Result<i64, Error> {
+    let db = |e| database_error(file)(e);
+    let json = |e| format_error(file)(e);
+    let id = tx
+        .query_row(
+            "INSERT INTO projects (path, reference, created, updated, walk_conf, preferences, usage)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT (path) DO UPDATE SET
+                 reference = excluded.reference, created = excluded.created, updated = excluded.updated,
+                 walk_conf = excluded.walk_conf, preferences = excluded.preferences, usage = excluded.usage
+             RETURNING id",
+            params![
+                path_to_blob(&project.path),
+                project.reference.as_deref().map(path_to_blob),
+                time_to_int(project.created),
+                time_to_int(project.updated),
+                serde_json::to_string(&project.walk_conf).map_err(json)?,
+                serde_json::to_string(&project.preferences).map_err(json)?,
+                project.usage.map(|usage| serde_json::to_string(&usage)).transpose().map_err(json)?,
+            ],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(db)?;
+    tx.execute("DELETE FROM extra_roots WHERE project = ?1", [id]).map_err(db)?;
+    let mut insert = tx.prepare("INSERT INTO extra_roots (project, path) VALUES (?1, ?2)").map_err(db)?;
+    for root in &project.extra_roots {
+        insert.execute(params![id, path_to_blob(root)]).map_err(db)?;
+    }
+    Ok(id)
+}
+
+/// Bring the decisions of project `id' in line with `decisions', writing only the rows that changed.
+fn save_decisions(tx: &Transaction, id: i64, decisions: &Decisions) -> rusqlite::Result<()> {
+    for (table, decisions) in [("marked", &decisions.remove), ("done", &decisions.done)] {
+        let saved = decision_rows(tx, table, id)?;
+        let wanted = decisions
+            .iter()
+            .flat_map(|(hash, paths)| paths.iter().map(move |path| (hash.clone(), path.clone())))
+            .collect::<BTreeSet<(String, PathBuf)>>();
+        let mut insert = tx.prepare(&format!("INSERT INTO {:} (project, hash, path) VALUES (?1, ?2, ?3)", table))?;
+        for (hash, path) in wanted.difference(&saved) {
+            insert.execute(params![id, hash, path_to_blob(path)])?;
+        }
+        let mut delete = tx.prepare(&format!("DELETE FROM {:} WHERE project = ?1 AND hash = ?2 AND path = ?3", table))?;
+        for (hash, path) in saved.difference(&wanted) {
+            delete.execute(params![id, hash, path_to_blob(path)])?;
+        }
+    }
+    Ok(())
+}
+
+/// Take the files `outcomes' trashed or deleted out of their groups in the last scan of project `id'.  Those the
+/// walk found keep their row without a hash, just as `save' would write them after `Project::record'.
+fn forget_removed(tx: &Transaction, id: i64, outcomes: &[Outcome]) -> rusqlite::Result<()> {
+    let Some(scan) = tx.query_row("SELECT id FROM scans WHERE project = ?1", [id], |row| row.get::<_, i64>(0)).optional()? else {
+        return Ok(());
+    };
+    let mut unhash = tx.prepare("UPDATE files SET hash = NULL WHERE scan = ?1 AND path = ?2 AND hash = ?3")?;
+    let mut delete = tx.prepare("DELETE FROM files WHERE scan = ?1 AND path = ?2 AND hash IS NULL AND NOT walked AND NOT link")?;
+    let removed = outcomes
+        .iter()
+        .filter(|outcome| outcome.result.is_ok() && matches!(outcome.step.action, Action::Trash | Action::Delete))
+        .map(|outcome| &outcome.step);
+    for step in removed {
+        unhash.execute(params![scan, path_to_blob(&step.target), step.hash])?;
+        delete.execute(params![scan, path_to_blob(&step.target)])?;
+    }
+    Ok(())
+}
+
+/// Write the walk and groups of `project' in place of those of its last scan.
+fn save_scan(tx: &Transaction, id: i64, project: &Project, file: &Path) -> Result<(), Error> {
+    let db = |e| database_error(file)(e);
+    // The files of the previous scan go with it.
+    tx.execute("DELETE FROM scans WHERE project = ?1", [id]).map_err(db)?;
+    if project.walk.is_none() && project.related.is_none() {
+        return Ok(());
+    }
+    let walk = project.walk.as_ref();
+    let related = project.related.as_ref();
+    let scan = tx
+        .query_row(
+            "INSERT INTO scans (project, walked, walk_partial, total_size, algo, partial) VALUES (?1, ?2, ?3, ?4, ?5, ?6) RETURNING id",
+            params![
+                id,
+                walk.is_some(),
+                walk.is_some_and(|walk| walk.partial),
+                walk.map_or(0, |walk| walk.total_size as i64),
+                related.map(|related| related.algo.to_string()),
+                related.is_some_and(|related| related.partial),
+            ],
+            |row| row.get::<_, i64>(0),
+        )
+        .map_err(db)?;
+    let mut insert = tx
+        .prepare(&format!(
+            "INSERT INTO files (scan, {:}, link, walked, hash) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)
+             ON CONFLICT (scan, path) DO UPDATE SET hash = excluded.hash",
+            FILE_COLUMNS,
+        ))
+        .map_err(db)?;
+    let walked = walk.into_iter().flat_map(|walk| {
+        walk.files.iter().map(|fi| (fi, false, true, None)).chain(walk.links.iter().map(|fi| (fi, true, true, None)))
+    });
+    let hashed = related.into_iter().flat_map(|related| {
+        related.files.iter().flat_map(|(hash, group)| group.iter().map(move |fi| (fi, false, false, Some(hash))))
+    });
+    for (fi, link, walked, hash) in walked.chain(hashed) {
+        insert
+            .execute(params![
+                scan,
+                path_to_blob(&fi.name),
+                fi.size as i64,
+                fi.created.map(time_to_int),
+                fi.modified.map(time_to_int),
+                fi.allocated.map(|allocated| allocated as i64),
+                fi.link_target.as_deref().map(path_to_blob),
+                fi.file_id.map(|(device, _)| device as i64),
+                fi.file_id.map(|(_, inode)| inode as i64),
+                fi.archive.as_deref().map(path_to_blob),
+                link,
+                walked,
+                hash,
+            ])
+            .map_err(db)?;
+    }
+    let mut insert = tx.prepare("INSERT INTO hard_links (scan, link_group, path) VALUES (?1, ?2, ?3)").map_err(db)?;
+    for (group, same) in walk.iter().flat_map(|walk| walk.hard_links.iter()).enumerate() {
+        for path in same {
+            insert.execute(params![scan, group as i64, path_to_blob(path)]).map_err(db)?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,212 @@
+//! A log of every scan, kept apart from the projects, so what the engine was doing can be pieced together after
+//! a crash.  Each scan appends a record when it starts, when the walk is done and when it finishes, as JSON Lines
+//! in the data directory.  Every record is flushed to disk before the scan carries on, so a scan
+//! that started but never finished is one that crashed or was killed.
+
+use std::{
+    fmt, fs, io,
+    io::Write,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, SystemTime},
+};
+use serde::{Serialize, Deserialize};
+use crate::{
+    relate::{HashAlgo, Strategy, WalkConf},
+    usage::ScanUsage,
+};
+
+const LOG_FILE: &str = "scans.jsonl";
+const OLD_SUFFIX: &str = "old";
+/// Once the log grows past this, it is moved aside and a new one begun, keeping one older log.
+const MAX_LOG_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug)]
+pub enum ErrorType {
+    IO(io::Error),
+    Format(serde_json::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn io_error(path: &Path) -> impl FnOnce(io::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::IO(e),
+        }
+    }
+}
+
+fn format_error(path: &Path) -> impl FnOnce(serde_json::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Format(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::IO(e) => write!(f, "{:}: {:}", self.path.display(), e),
+            ErrorType::Format(e) => write!(f, "{:}: malformed scan log: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// How a scan was set up.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanConf {
+    pub dir: PathBuf,
+    /// See `project::Project::extra_roots'.
+    #[serde(default)]
+    pub extra_roots: Vec<PathBuf>,
+    pub reference: Option<PathBuf>,
+    pub walk: WalkConf,
+    pub strategy: Strategy,
+    pub device_threads: Option<u16>,
+    pub hash_algo: HashAlgo,
+    /// See `RelateConf::mmap_threshold'.  Mapped files going away mid-hash crash the scan.
+    #[serde(default)]
+    pub mmap_threshold: Option<u64>,
+    /// See `RelateConf::io_limit'.  A limited scan goes no faster than it.
+    #[serde(default)]
+    pub io_limit: Option<u64>,
+    pub max_duration: Option<Duration>,
+    /// The scan carried on from an earlier one's results.
+    #[serde(default)]
+    pub resumed: bool,
+    /// Files were hashed as they were found, see `RelatedFiles::walk_and_relate'.
+    #[serde(default)]
+    pub pipelined: bool,
+}
+
+/// What a scan found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanOutcome {
+    pub groups: usize,
+    pub errors: usize,
+    /// Hashing stopped at the time limit, or was cancelled.
+    pub partial: bool,
+    /// The user stopped or paused the scan.
+    #[serde(default)]
+    pub cancelled: bool,
+    #[serde(default)]
+    pub usage: Option<ScanUsage>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event {
+    Started(ScanConf),
+    Walked { files: usize, bytes: u64 },
+    Finished(ScanOutcome),
+    /// The scan gave up, e.g. because its results couldn't be saved.
+    Failed { error: String },
+}
+
+/// One line of the log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Record {
+    pub time: SystemTime,
+    /// Tells the records of scans running at the same time apart.
+    pub scan: String,
+    pub version: String,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+/// Scans started by this process so far, so two starting within the same millisecond still get separate ids.
+static STARTED: AtomicU64 = AtomicU64::new(0);
+
+/// The scan log under the data directory `data_dir'.
+pub fn log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(LOG_FILE)
+}
+
+/// The scan being logged.  Dropping it without `finished' or `failed' leaves the scan looking unfinished,
+/// just as a crash would.
+#[derive(Debug)]
+pub struct ScanLog {
+    data_dir: PathBuf,
+    scan: String,
+}
+
+impl ScanLog {
+    /// Log the start of a scan configured by `conf'.
+    pub fn start(data_dir: &Path, conf: ScanConf) -> Result<Self, Error> {
+        let path = log_path(data_dir);
+        if fs::metadata(&path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+            fs::rename(&path, path.with_extension(OLD_SUFFIX)).map_err(io_error(&path))?;
+        }
+        let since_epoch = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap_or_default();
+        let log = Self {
+            data_dir: data_dir.to_path_buf(),
+            scan: format!("{:}-{:}-{:}", process::id(), since_epoch.as_millis(), STARTED.fetch_add(1, Ordering::Relaxed)),
+        };
+        log.append(Event::Started(conf))?;
+        Ok(log)
+    }
+
+    pub fn walked(&self, files: usize, bytes: u64) -> Result<(), Error> {
+        self.append(Event::Walked { files, bytes })
+    }
+
+    pub fn finished(self, outcome: ScanOutcome) -> Result<(), Error> {
+        self.append(Event::Finished(outcome))
+    }
+
+    pub fn failed(self, error: String) -> Result<(), Error> {
+        self.append(Event::Failed { error })
+    }
+
+    fn append(&self, event: Event) -> Result<(), Error> {
+        let path = log_path(&self.data_dir);
+        let record = Record {
+            time: SystemTime::now(),
+            scan: self.scan.clone(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            event,
+        };
+        let mut line = serde_json::to_vec(&record).map_err(format_error(&path))?;
+        line.push(b'\n');
+        fs::create_dir_all(&self.data_dir).map_err(io_error(&self.data_dir))?;
+        let mut file = fs::OpenOptions::new().create(true).append(true).open(&path).map_err(io_error(&path))?;
+        file.write_all(&line).and_then(|_| file.sync_data()).map_err(io_error(&path))
+    }
+}
+
+/// Every readable record in the log under `data_dir', oldest first.
+pub fn records(data_dir: &Path) -> Result<Vec<Record>, Error> {
+    let path = log_path(data_dir);
+    let contents = match fs::read_to_string(&path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        contents => contents.map_err(io_error(&path))?,
+    };
+    // A crash while appending leaves a line cut short, and the next scan carries on after it, so unlike the
+    // journal a broken line may be anywhere.  It is left out rather than hiding the rest of the log.
+    Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// The start of each scan in `records' that never finished or failed, because it crashed, was killed, or is
+/// still running.
+pub fn unfinished(records: &[Record]) -> Vec<&Record> {
+    records
+        .iter()
+        .filter(|record| matches!(record.event, Event::Started(_)))
+        .filter(|started| {
+            !records.iter().any(|record| {
+                record.scan == started.scan && matches!(record.event, Event::Finished(_) | Event::Failed { .. })
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,58 @@
+//! Keep a scan from reading faster than asked, so one running in the background leaves the disk to everyone else.
+//! A token bucket: reading takes tokens, one per byte, which come back at the limit's rate.  The bucket holds a
+//! second's worth, so a pause doesn't let the next reads through all at once.
+
+use std::{
+    sync::{Mutex, PoisonError},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Shared by every thread reading for a scan, so the limit holds for all of them together.
+#[derive(Debug)]
+pub struct Throttle {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes that may be read right away.  Below 0 when reads have been let through ahead of their time, which the
+    /// next ones wait out.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl Throttle {
+    /// Allow `bytes_per_sec' bytes a second, at least 1.  The bucket starts full.
+    pub fn new(bytes_per_sec: u64) -> Self {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        Throttle { bytes_per_sec, bucket: Mutex::new(Bucket { tokens: bytes_per_sec as f64, refilled: Instant::now() }) }
+    }
+
+    pub fn bytes_per_sec(&self) -> u64 {
+        self.bytes_per_sec
+    }
+
+    /// Wait until reading `bytes' more keeps to the limit.
+    pub fn take(&self, bytes: u64) {
+        let wait = self.reserve(Instant::now(), bytes);
+        if !wait.is_zero() {
+            thread::sleep(wait);
+        }
+    }
+
+    /// Take `bytes' from the bucket at `at', which is never before the last time asked, returning how long the
+    /// caller has to wait before reading them.  Callers asking meanwhile wait their turn after that.
+    pub fn reserve(&self, at: Instant, bytes: u64) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let elapsed = at.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(rate) - bytes as f64;
+        bucket.refilled = bucket.refilled.max(at);
+        match bucket.tokens {
+            tokens if tokens >= 0.0 => Duration::ZERO,
+            tokens => Duration::from_secs_f64(-tokens / rate),
+        }
+    }
+}
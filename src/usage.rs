@@ -0,0 +1,110 @@
+//! Measure what a scan costs, so configurations like thread counts and filters can be compared, and how fast a
+//! running one goes, to tell how long it has left.
+//! CPU time and peak memory come from the operating system and cover the whole process, which is dominated by
+//! the scan while one runs.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+use serde::{Serialize, Deserialize};
+use crate::platform;
+
+/// The resources one scan used.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanUsage {
+    /// Time from the start of the walk to the end of hashing.
+    pub wall_time: Duration,
+    /// Processor time spent by every thread, user and system.  More than `wall_time' when hashing in parallel.
+    /// `None' where the platform doesn't tell us.
+    pub cpu_time: Option<Duration>,
+    /// Bytes of file contents read to hash them.
+    pub bytes_read: u64,
+    /// The most memory the process has held at once, so far.  `None' where the platform doesn't tell us.
+    pub peak_memory: Option<u64>,
+}
+
+impl ScanUsage {
+    /// Bytes hashed per second of wall time.
+    pub fn throughput(&self) -> u64 {
+        match self.wall_time.as_secs_f64() {
+            0.0 => 0,
+            secs => (self.bytes_read as f64 / secs) as u64,
+        }
+    }
+}
+
+/// Started when a scan starts, and read when it ends.
+#[derive(Debug, Clone, Copy)]
+pub struct Meter {
+    started: Instant,
+    cpu_time: Option<Duration>,
+}
+
+impl Meter {
+    pub fn start() -> Self {
+        Self { started: Instant::now(), cpu_time: platform::cpu_time() }
+    }
+
+    /// What the scan used since `start', having read `bytes_read' bytes.
+    pub fn finish(&self, bytes_read: u64) -> ScanUsage {
+        ScanUsage {
+            wall_time: self.started.elapsed(),
+            cpu_time: platform::cpu_time().zip(self.cpu_time).map(|(now, then)| now.saturating_sub(then)),
+            bytes_read,
+            peak_memory: platform::peak_memory(),
+        }
+    }
+}
+
+/// How far back `Throughput' looks.  Long enough to smooth over a run of small files, short enough to follow a
+/// change of disk.
+pub const THROUGHPUT_WINDOW: Duration = Duration::from_secs(10);
+/// Progress closer together than this is merged, so a flood of small files doesn't fill the window.
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How fast hashing has gone over the last `THROUGHPUT_WINDOW', fed the bytes done as progress arrives.
+#[derive(Debug, Clone, Default)]
+pub struct Throughput {
+    /// When each count of bytes done was reached, oldest first.
+    samples: VecDeque<(Instant, u64)>,
+}
+
+impl Throughput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that `done' bytes are hashed by now.
+    pub fn record(&mut self, done: u64) {
+        self.record_at(Instant::now(), done);
+    }
+
+    /// Note that `done' bytes were hashed by `at', which is never before the last time recorded.
+    pub fn record_at(&mut self, at: Instant, done: u64) {
+        match self.samples.len() {
+            // The last sample moves up to now, so the one before it keeps the window's interval apart.
+            2.. if at.duration_since(self.samples[self.samples.len() - 2].0) < SAMPLE_INTERVAL => {
+                *self.samples.back_mut().expect("Checked above") = (at, done);
+            },
+            _ => self.samples.push_back((at, done)),
+        }
+        // The oldest sample in the window is kept just outside it, so the window is covered from end to end.
+        while self.samples.get(1).is_some_and(|(then, _)| at.duration_since(*then) >= THROUGHPUT_WINDOW) {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Bytes hashed per second lately, once there is a second's worth of progress to tell from.
+    pub fn bytes_per_sec(&self) -> Option<u64> {
+        let ((first, first_done), (last, last_done)) = (self.samples.front()?, self.samples.back()?);
+        let secs = last.duration_since(*first).as_secs_f64();
+        (secs >= 1.0).then(|| (last_done.saturating_sub(*first_done) as f64 / secs) as u64)
+    }
+
+    /// How long hashing `remaining' more bytes should take at the pace of late, unless nothing is moving.
+    pub fn time_left(&self, remaining: u64) -> Option<Duration> {
+        let rate = self.bytes_per_sec().filter(|rate| *rate > 0)?;
+        Some(Duration::from_secs_f64(remaining as f64 / rate as f64))
+    }
+}
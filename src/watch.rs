@@ -0,0 +1,223 @@
+//! Keep the results of a scan up to date while its folders change, for a live view of the duplicates.
+//! The folders are watched with `notify', and only the paths that changed are looked at again, rather than walking
+//! everything.  Files gaining another hard link while watched are recognised, but the other paths of a file whose
+//! first path changes, and links found with `SymlinkPolicy::Report', wait for the next scan.
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::{mpsc::{self, Receiver}, Arc},
+    time::{Duration, Instant},
+};
+use notify::{
+    event::{AccessKind, AccessMode},
+    EventKind, RecommendedWatcher, RecursiveMode, Watcher,
+};
+use walkdir::WalkDir;
+use crate::relate::{self, ErrorKind, FileInfo, HashAlgo, RelatedFiles, WalkFilter, WalkInfo};
+
+/// How long to wait for more changes after one, as files are often written in several goes.
+pub const SETTLE: Duration = Duration::from_millis(500);
+/// The longest to keep gathering changes while they keep coming, so a busy folder is still caught up with.
+const MAX_BATCH: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub enum ErrorType {
+    Notify(notify::Error),
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error_type: ErrorType,
+}
+
+fn notify_error(path: &Path) -> impl FnOnce(notify::Error) -> Error {
+    let path = path.to_path_buf();
+    move |e| {
+        Error {
+            path,
+            error_type: ErrorType::Notify(e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.error_type {
+            ErrorType::Notify(e) => write!(f, "{:}: can't watch for changes: {:}", self.path.display(), e),
+        }
+    }
+}
+
+/// What a changed path holds now.
+#[derive(Debug)]
+pub enum Update {
+    /// A file to hold on to, new or changed, with its hash.
+    Hashed(FileInfo, String),
+    /// The file couldn't be read, and is left out until it changes again.
+    Failed(relate::Error),
+    /// Nothing to hash is there any more: whatever was is gone, moved away, or left out by the filter.
+    /// Everything below the path is forgotten too.
+    Gone(PathBuf),
+}
+
+impl Update {
+    pub fn path(&self) -> &Path {
+        match self {
+            Update::Hashed(fi, _) => &fi.name,
+            Update::Failed(e) => e.path(),
+            Update::Gone(path) => path,
+        }
+    }
+}
+
+/// Folders being watched.  Dropping it stops watching.
+pub struct Watch {
+    roots: Vec<PathBuf>,
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl Watch {
+    /// Watch everything below `roots', the folders a scan walked.
+    pub fn start(roots: &[&Path]) -> Result<Self, Error> {
+        let (tx, events) = mpsc::channel();
+        let first = roots.first().copied().unwrap_or(Path::new(""));
+        let mut watcher = notify::recommended_watcher(tx).map_err(notify_error(first))?;
+        for root in roots {
+            watcher.watch(root, RecursiveMode::Recursive).map_err(notify_error(root))?;
+        }
+        Ok(Self { roots: roots.iter().map(|root| root.to_path_buf()).collect(), _watcher: watcher, events })
+    }
+
+    /// The paths touched by the next batch of changes, waiting up to `timeout' for one to begin and then until
+    /// things have been quiet for `SETTLE'.  Empty if nothing changed in time.
+    pub fn changed(&self, timeout: Duration) -> BTreeSet<PathBuf> {
+        let mut changed = BTreeSet::new();
+        let Ok(event) = self.events.recv_timeout(timeout) else {
+            return changed;
+        };
+        self.note(event, &mut changed);
+        let deadline = Instant::now() + MAX_BATCH;
+        while let Ok(event) = self.events.recv_timeout(SETTLE.min(deadline.saturating_duration_since(Instant::now()))) {
+            self.note(event, &mut changed);
+        }
+        changed
+    }
+
+    fn note(&self, event: notify::Result<notify::Event>, changed: &mut BTreeSet<PathBuf>) {
+        match event {
+            // Reading a file, as hashing does, changes nothing.
+            Ok(event) if matches!(event.kind, EventKind::Access(kind) if kind != AccessKind::Close(AccessMode::Write)) => (),
+            Ok(event) if !event.need_rescan() => changed.extend(event.paths),
+            // Changes were missed, e.g. because too many came at once, so anything may have changed.
+            Ok(_) => changed.extend(self.roots.iter().cloned()),
+            Err(e) if !e.paths.is_empty() => changed.extend(e.paths),
+            Err(_) => changed.extend(self.roots.iter().cloned()),
+        }
+    }
+
+    /// Look at each of `changed' again, hashing with `algo' the files `filter' admits.  A folder stands for
+    /// everything in it.
+    pub fn examine(&self, changed: &BTreeSet<PathBuf>, filter: &WalkFilter, algo: HashAlgo) -> Vec<Update> {
+        let mut updates = Vec::new();
+        let mut folders = Vec::<&Path>::new();
+        for path in changed {
+            let Some(root) = self.roots.iter().find(|root| path.starts_with(root)) else {
+                continue;
+            };
+            // Folders come before what is in them, which was looked at with them.
+            if folders.iter().any(|folder| path.starts_with(folder)) {
+                continue;
+            }
+            let is_dir = match filter.follows_links() {
+                true => path.is_dir(),
+                false => fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()),
+            };
+            if !is_dir {
+                updates.extend(examine_file(root, path, filter, algo));
+                continue;
+            }
+            // Forget what the folder held, then find what it holds now.
+            folders.push(path);
+            updates.push(Update::Gone(path.clone()));
+            let entries = WalkDir::new(path).follow_links(filter.follows_links()).into_iter().filter_map(Result::ok);
+            for entry in entries.filter(|entry| !entry.file_type().is_dir()) {
+                let found = examine_file(root, entry.path(), filter, algo);
+                updates.extend(found.into_iter().filter(|update| !matches!(update, Update::Gone(_))));
+            }
+        }
+        updates
+    }
+}
+
+/// The update for the file at `path', followed by those for the files in it when it is an archive `filter' looks
+/// into.
+fn examine_file(root: &Path, path: &Path, filter: &WalkFilter, algo: HashAlgo) -> Vec<Update> {
+    let hash = |fi: &FileInfo| match relate::hash_from_file_info(fi, algo) {
+        Ok(hashed) => Update::Hashed(hashed.info, hashed.hash),
+        Err(e) => Update::Failed(e),
+    };
+    match FileInfo::from_path(path, filter.follows_links()) {
+        Err(e) if e.kind() == ErrorKind::Vanished => vec![Update::Gone(path.to_path_buf())],
+        Err(e) => vec![Update::Failed(e)],
+        Ok(fi) if !filter.admits_file(root, &fi) => vec![Update::Gone(path.to_path_buf())],
+        Ok(fi) => {
+            let mut updates = vec![hash(&fi)];
+            match filter.members(&fi) {
+                Ok(members) => updates.extend(members.iter().map(hash)),
+                Err(e) => updates.push(Update::Failed(e)),
+            }
+            updates
+        },
+    }
+}
+
+/// Bring `walk' and `related', the results of a scan, up to date with `updates'.
+pub fn apply(updates: Vec<Update>, walk: &mut WalkInfo, related: &mut RelatedFiles) {
+    let changed = updates.iter().map(|update| update.path().to_path_buf()).collect::<HashSet<PathBuf>>();
+    let is_changed = |path: &Path| path.ancestors().any(|path| changed.contains(path));
+    // Files in an archive change with it.
+    let is_changed_file = |fi: &FileInfo| is_changed(&fi.name) || fi.archive.as_deref().is_some_and(is_changed);
+    walk.total_size -= walk.files.iter().filter(|fi| is_changed_file(fi)).map(|fi| fi.size).sum::<u64>();
+    walk.files.retain(|fi| !is_changed_file(fi));
+    for group in related.files.values_mut() {
+        group.retain(|fi| !is_changed_file(fi));
+    }
+    related.errors.retain(|e| !is_changed(e.path()));
+    // The first path of each is the one in `walk.files'.
+    walk.hard_links.retain(|same| !is_changed(&same[0]));
+    for same in &mut walk.hard_links {
+        same.retain(|path| !is_changed(path));
+    }
+    walk.hard_links.retain(|same| same.len() > 1);
+    let mut ids = walk.files.iter().filter_map(|fi| Some((fi.file_id?, fi.name.clone()))).collect::<HashMap<(u64, u64), Arc<Path>>>();
+    for update in updates {
+        match update {
+            Update::Gone(_) => (),
+            Update::Failed(e) => related.errors.push(e),
+            Update::Hashed(fi, hash) => {
+                // Another path to a file already there is a hard link to it, not a copy.
+                if let Some(first) = fi.file_id.and_then(|id| ids.get(&id)).filter(|first| **first != fi.name) {
+                    match walk.hard_links.iter_mut().find(|same| *same[0] == **first) {
+                        Some(same) => {
+                            same.push(fi.name.to_path_buf());
+                            same[1..].sort();
+                        },
+                        None => walk.hard_links.push(vec![first.to_path_buf(), fi.name.to_path_buf()]),
+                    }
+                    continue;
+                }
+                if let Some(id) = fi.file_id {
+                    ids.insert(id, fi.name.clone());
+                }
+                walk.total_size += fi.size;
+                walk.files.insert(fi.clone());
+                related.files.entry(hash).or_default().insert(fi);
+            },
+        }
+    }
+    related.files.retain(|_, group| !group.is_empty());
+}
@@ -0,0 +1,317 @@
+use file_deduplicator::{
+    actions::{Action, CrossDevice, DedupOptions, Permit, Plan, Step},
+    archive,
+    journal, persist,
+    platform::{self, OpenFiles},
+    policy::{KeepPolicy, Resolver},
+    relate::{FileInfo, HashAlgo, RelateConf, RelatedFiles, Strategy, WalkConf, WalkFilter, WalkInfo},
+};
+use std::{collections::HashMap, fs, io, io::Write, path::Path, sync::mpsc, time::{Duration, SystemTime}};
+
+const TEST_DIR: &'static str = "scratch/actions";
+
+fn step(target: &Path, keep: &Path) -> Step {
+    Step {
+        action: Action::Delete,
+        hash: "abc".to_owned(),
+        target: target.to_path_buf(),
+        keep: keep.to_path_buf(),
+        size: 5,
+        allocated: 5,
+        modified: None,
+    }
+}
+
+#[test]
+fn test_verify_refuses_differing_copies() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, same, other) = (dir.join("keep"), dir.join("same"), dir.join("other"));
+    fs::write(&keep, "hello").expect("Failed to write file");
+    fs::write(&same, "hello").expect("Failed to write file");
+    fs::write(&other, "jello").expect("Failed to write file");
+
+    let verify = DedupOptions { verify: true, ..Default::default() };
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    assert!(Permit::unless_auditing(true).is_none());
+    assert!(step(&other, &keep).execute(&verify, &OpenFiles::read(), &permit).is_err());
+    assert!(other.exists(), "A differing copy was removed");
+    step(&same, &keep).execute(&verify, &OpenFiles::read(), &permit).expect("Failed to remove an identical copy");
+    assert!(!same.exists());
+    step(&other, &keep).execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect("Failed to remove without verifying");
+    assert!(!other.exists());
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_plan_diff() {
+    let (a, b, c) = (Path::new("a"), Path::new("b"), Path::new("c"));
+    let first = Plan { steps: vec![step(a, c), step(b, c)] };
+    let second = Plan { steps: vec![Step { size: 12, ..step(c, a) }, step(b, a)] };
+    let diff = first.diff(&second);
+    assert_eq!(diff.only_first, vec![step(a, c)]);
+    assert_eq!(diff.only_second, vec![Step { size: 12, ..step(c, a) }]);
+    assert_eq!(diff.net_reclaimable(), 7);
+    assert_eq!(second.diff(&first).net_reclaimable(), -7);
+    assert!(first.diff(&first).only_first.is_empty());
+}
+
+#[test]
+fn test_reflink_keeps_copy_when_unsupported() {
+    const TEST_DIR: &'static str = "scratch/actions_reflink";
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, copy) = (dir.join("keep"), dir.join("copy"));
+    fs::write(&keep, "hello").expect("Failed to write file");
+    fs::write(&copy, "hello").expect("Failed to write file");
+
+    let reflink = Step { action: Action::Reflink, ..step(&copy, &keep) };
+    assert_eq!(reflink.preview(&DedupOptions::default(), &OpenFiles::read()).is_ok(), platform::supports_reflinks(dir));
+    assert_eq!(fs::read_dir(dir).expect("Failed to list test directory").count(), 2, "The preview wrote to the folder");
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let result = reflink.execute(&DedupOptions::default(), &OpenFiles::read(), &permit);
+    assert!(result.is_err() || platform::supports_reflinks(dir), "Cloned where reflinks aren't supported");
+    assert_eq!(fs::read_to_string(&copy).expect("The copy is gone"), "hello");
+    assert_eq!(fs::read_dir(dir).expect("Failed to list test directory").count(), 2, "Scratch files were left behind");
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_links_replace_copies_atomically() {
+    const TEST_DIR: &'static str = "scratch/actions_link";
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir.join("folder")).expect("Failed to create test directory");
+    let (keep, copy, temp) = (dir.join("keep"), dir.join("copy"), persist::temp_path(&dir.join("copy")));
+    fs::write(&keep, "hello").expect("Failed to write file");
+    fs::write(&copy, "hello").expect("Failed to write file");
+    fs::write(dir.join("folder/file"), "hello").expect("Failed to write file");
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+
+    // Failing once the link is made, but before it is renamed over the copy.
+    let failed = persist::replace_with(&copy, |temp| fs::hard_link(&keep, temp).and_then(|_| Err(io::Error::other("power failure"))), &permit);
+    assert!(failed.is_err());
+    assert!(!temp.exists(), "The link was left behind");
+    // Failing to rename, as over a folder.
+    assert!(persist::replace_with(&dir.join("folder"), |temp| fs::hard_link(&keep, temp), &permit).is_err());
+    assert!(dir.join("folder/file").exists() && !persist::temp_path(&dir.join("folder")).exists());
+
+    // Stopped for good between the two, the copy is still there, and the next try leaves the link alone.
+    fs::hard_link(&keep, &temp).expect("Failed to link");
+    let link = Step { action: Action::HardLink, ..step(&copy, &keep) };
+    assert_eq!(link.execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect_err("Linked over a file in the way").kind(), io::ErrorKind::AlreadyExists);
+    assert_eq!(fs::read_to_string(&copy).expect("The copy is gone"), "hello");
+    assert!(temp.exists());
+
+    fs::remove_file(&temp).expect("Failed to remove link");
+    link.execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect("Failed to link");
+    fs::write(&keep, "changed").expect("Failed to write file");
+    assert_eq!(fs::read_to_string(&copy).expect("The copy is gone"), "changed", "The copy isn't linked");
+    assert_eq!(fs::read_dir(dir).expect("Failed to list test directory").count(), 3, "Scratch files were left behind");
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_links_across_filesystems() {
+    const CROSS_DIR: &'static str = "scratch/actions_cross";
+    let _ = fs::remove_dir_all(CROSS_DIR);
+    let dir = Path::new(CROSS_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, near, far) = (dir.join("keep"), dir.join("near"), dir.join("far"));
+    // Which devices the files are on is made up, as the scratch directory is all on one.
+    let info = |name: &Path, device| FileInfo {
+        name: name.into(), size: 5, created: None, modified: None, allocated: None, link_target: None,
+        file_id: Some((device, name.as_os_str().len() as u64)), archive: None,
+    };
+    let files = HashMap::from([("abc".to_owned(), [info(&keep, 1), info(&near, 1), info(&far, 2)].into_iter().collect())]);
+    let related = RelatedFiles { algo: HashAlgo::default(), files, partial: false, errors: Vec::new() };
+    let plan = Plan { steps: vec![Step { action: Action::HardLink, ..step(&near, &keep) }, Step { action: Action::HardLink, ..step(&far, &keep) }] };
+
+    let mut refused = plan.clone();
+    let crossings = refused.across_devices(&related, CrossDevice::Refuse);
+    assert_eq!(crossings.len(), 1);
+    assert_eq!((crossings[0].keep.as_path(), crossings[0].copies.as_slice()), (keep.as_path(), [far.clone()].as_slice()));
+    assert!(refused.steps.is_empty(), "Part of a refused group was planned");
+    let mut kept = plan.clone();
+    kept.across_devices(&related, CrossDevice::Keep);
+    assert_eq!(kept.steps.iter().map(|step| step.target.as_path()).collect::<Vec<_>>(), [near.as_path()]);
+    let mut symlinked = plan.clone();
+    symlinked.across_devices(&related, CrossDevice::Symlink);
+    assert_eq!(symlinked.steps.iter().map(|step| step.action).collect::<Vec<_>>(), [Action::HardLink, Action::Symlink]);
+    for policy in [CrossDevice::Refuse, CrossDevice::Symlink, CrossDevice::Keep] {
+        assert_eq!(policy.to_string().parse::<CrossDevice>(), Ok(policy));
+    }
+
+    #[cfg(unix)]
+    {
+        fs::write(&keep, "hello").expect("Failed to write file");
+        fs::write(&far, "hello").expect("Failed to write file");
+        let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+        symlinked.steps[1].execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect("Failed to symlink");
+        assert!(fs::symlink_metadata(&far).expect("The copy is gone").file_type().is_symlink());
+        assert_eq!(fs::read_to_string(&far).expect("The symlink is broken"), "hello");
+    }
+
+    let _ = fs::remove_dir_all(CROSS_DIR);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_files_in_use_are_skipped() {
+    const BUSY_DIR: &'static str = "scratch/actions_busy";
+    let _ = fs::remove_dir_all(BUSY_DIR);
+    let dir = Path::new(BUSY_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, open) = (dir.join("keep"), dir.join("open"));
+    fs::write(&keep, "hello").expect("Failed to write file");
+    fs::write(&open, "hello").expect("Failed to write file");
+    assert!(!OpenFiles::read().contains(&open));
+
+    let mut holder = std::process::Command::new("sh")
+        .arg("-c")
+        .arg("exec 3<\"$0\"; sleep 10")
+        .arg(&open)
+        .spawn()
+        .expect("Failed to start a process holding the file");
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while !OpenFiles::read().contains(&open) && std::time::Instant::now() < deadline {
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let outcomes = Plan { steps: vec![step(&open, &keep)] }.execute(&DedupOptions::default(), &permit);
+    let _ = holder.kill();
+    let _ = holder.wait();
+    assert!(outcomes[0].in_use(), "Expected the open file to be skipped, got {:?}", outcomes[0].result);
+    assert!(open.exists(), "A file in use was removed");
+
+    let _ = fs::remove_dir_all(BUSY_DIR);
+}
+
+#[test]
+fn test_empty_files_are_removed_apart() {
+    const EMPTY_DIR: &'static str = "scratch/actions_empty";
+    let _ = fs::remove_dir_all(EMPTY_DIR);
+    let dir = Path::new(EMPTY_DIR);
+    fs::create_dir_all(dir.join("files/sub")).expect("Failed to create test directory");
+    for (name, contents) in [("a", "hello"), ("b", "hello"), ("empty", ""), ("sub/.keep", "")] {
+        fs::write(dir.join("files").join(name), contents).expect("Failed to write file");
+    }
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let conf = RelateConf { strategy: Strategy::Sequential, ..Default::default() };
+    let related = RelatedFiles::relate(&WalkInfo::walk(dir.join("files")), &conf, progress_tx);
+    assert_eq!(related.duplicate_groups().len(), 1, "Empty files were counted as duplicates");
+    let empty = related.empty_files().into_iter().map(|fi| fi.name.to_path_buf()).collect::<Vec<_>>();
+    assert_eq!(empty, [dir.join("files/empty"), dir.join("files/sub/.keep")]);
+
+    // Nothing to link empty files to, so they are trashed instead.
+    let resolver = Resolver::new(KeepPolicy::OldestCreated).expect("Failed to make a resolver");
+    let plan = Plan::empty_files(&related, &resolver, Action::HardLink);
+    assert!(plan.steps.iter().all(|step| step.action == Action::Trash));
+    let plan = Plan::empty_files(&related, &resolver, Action::Delete);
+    assert_eq!(plan.steps.iter().map(|step| step.target.clone()).collect::<Vec<_>>(), empty);
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let (outcomes, journaled) = journal::execute(&dir.join("data"), dir, &plan, &DedupOptions::default(), &permit);
+    journaled.expect("Failed to write the journal");
+    assert!(outcomes.iter().all(|outcome| outcome.result.is_ok()) && !dir.join("files/empty").exists());
+    let entries = journal::entries(&dir.join("data")).expect("Failed to read the journal");
+    journal::restore(&dir.join("data"), &entries[0], &permit).expect("Failed to restore an empty file");
+    assert_eq!(fs::read(dir.join("files/empty")).expect("Restored file is missing"), b"");
+
+    let _ = fs::remove_dir_all(EMPTY_DIR);
+}
+
+#[test]
+fn test_copies_outlive_their_kept_file() {
+    const TEST_DIR: &'static str = "scratch/actions_kept";
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, copy) = (dir.join("keep"), dir.join("copy"));
+    fs::write(&copy, "hello").expect("Failed to write file");
+    let permit = Permit::unless_auditing(false).expect("Not auditing");
+
+    let gone = step(&copy, &keep).execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect_err("Removed the last copy");
+    assert_eq!(gone.kind(), io::ErrorKind::NotFound);
+    fs::write(&keep, "hello, world").expect("Failed to write file");
+    let changed = step(&copy, &keep).execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect_err("Removed a copy of what changed");
+    assert_eq!(changed.kind(), io::ErrorKind::InvalidData);
+    assert!(step(&copy, &keep).preview(&DedupOptions::default(), &OpenFiles::read()).is_err());
+    assert!(copy.exists(), "A copy was removed though its kept file wasn't there");
+    fs::write(&keep, "hello").expect("Failed to write file");
+    step(&copy, &keep).execute(&DedupOptions::default(), &OpenFiles::read(), &permit).expect("Failed to remove a copy");
+    assert!(!copy.exists());
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_copies_kept_in_archives() {
+    const TEST_DIR: &'static str = "scratch/actions_archives";
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir.join("files")).expect("Failed to create test directory");
+    fs::write(dir.join("files/a.txt"), "hello").expect("Failed to write file");
+    let bundle = dir.join("files/bundle.zip");
+    let mut zip = zip::ZipWriter::new(fs::File::create(&bundle).expect("Failed to create zip"));
+    zip.start_file("a.txt", zip::write::SimpleFileOptions::default()).expect("Failed to add to zip");
+    zip.write_all(b"hello").expect("Failed to write to zip");
+    zip.finish().expect("Failed to finish zip");
+
+    let filter = WalkFilter::new(&WalkConf { archives: true, ..Default::default() }).expect("Failed to make a filter");
+    let conf = RelateConf { strategy: Strategy::Sequential, ..Default::default() };
+    let related = RelatedFiles::relate(&WalkInfo::walk_filtered(dir.join("files"), &filter), &conf, mpsc::channel().0);
+    let resolver = Resolver::new(KeepPolicy::ShortestPath).expect("Failed to make a resolver");
+    let mut plan = Plan::against_archives(&related, &resolver);
+    assert_eq!(plan.steps.len(), 1);
+    assert_eq!(plan.steps[0].keep, archive::member_path(&bundle, "a.txt"));
+    plan.steps[0].action = Action::Delete;
+    let verify = DedupOptions { verify: true, ..Default::default() };
+    plan.preview(&verify).into_iter().for_each(|outcome| outcome.result.expect("A copy in an archive wasn't found"));
+
+    let permit = Permit::unless_auditing(false).expect("Not auditing");
+    fs::rename(&bundle, dir.join("bundle.zip")).expect("Failed to move the archive");
+    assert!(plan.steps[0].execute(&DedupOptions::default(), &OpenFiles::read(), &permit).is_err());
+    assert!(dir.join("files/a.txt").exists(), "A copy was removed though its archive was gone");
+    fs::rename(dir.join("bundle.zip"), &bundle).expect("Failed to move the archive");
+
+    let (outcomes, journaled) = journal::execute(&dir.join("data"), dir, &plan, &verify, &permit);
+    journaled.expect("Failed to write the journal");
+    outcomes.into_iter().for_each(|outcome| outcome.result.expect("Failed to remove a copy kept in an archive"));
+    assert!(!dir.join("files/a.txt").exists());
+    let entries = journal::entries(&dir.join("data")).expect("Failed to read the journal");
+    journal::restore(&dir.join("data"), &entries[0], &permit).expect("Failed to restore out of an archive");
+    assert_eq!(fs::read(dir.join("files/a.txt")).expect("Restored file is missing"), b"hello");
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_copies_edited_since_the_scan_are_left_alone() {
+    let dir = Path::new("scratch/actions-edited");
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    for name in ["a", "b", "c", "d"] {
+        fs::write(dir.join(name), "hello").expect("Failed to write file");
+    }
+    let conf = RelateConf { strategy: Strategy::Sequential, ..Default::default() };
+    let related = RelatedFiles::relate_sequential(&WalkInfo::walk(dir.into()), &conf, mpsc::channel().0);
+    let resolver = Resolver::new(KeepPolicy::Alphabetical).expect("Only regexes can be invalid");
+    let plan = Plan::new(&related, &resolver, Action::Delete);
+    assert_eq!(plan.steps.len(), 3);
+
+    // One grows, and one is rewritten in place to as many bytes.
+    fs::write(dir.join("b"), "hello, world").expect("Failed to write file");
+    let c = fs::OpenOptions::new().write(true).open(dir.join("c")).expect("Failed to open file");
+    c.set_modified(SystemTime::now() + Duration::from_secs(60)).expect("Failed to set the time");
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let outcomes = plan.execute(&DedupOptions::default(), &permit);
+    let failed = outcomes.iter().filter(|outcome| outcome.result.is_err()).map(|outcome| outcome.step.target.clone()).collect::<Vec<_>>();
+    assert_eq!(failed, [dir.join("b"), dir.join("c")]);
+    assert!(dir.join("b").exists() && dir.join("c").exists() && !dir.join("d").exists());
+    let _ = fs::remove_dir_all(dir);
+}
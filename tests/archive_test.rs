@@ -0,0 +1,88 @@
+use file_deduplicator::{
+    archive::{self, ArchiveContents},
+    relate::ContentComparator,
+    scan::{self, RelateConf, Strategy, WalkConf},
+};
+use std::{fs, io::Write, path::Path, sync::mpsc};
+
+const TEST_DIR: &'static str = "scratch/archive";
+const SAME_DIR: &'static str = "scratch/archive-same";
+
+fn write_zip(path: &Path, files: &[(&str, &str)]) {
+    write_zip_with(path, files, zip::write::SimpleFileOptions::default());
+}
+
+fn write_zip_with(path: &Path, files: &[(&str, &str)], options: zip::write::SimpleFileOptions) {
+    let mut zip = zip::ZipWriter::new(fs::File::create(path).expect("Failed to create zip"));
+    for (name, contents) in files {
+        zip.start_file(*name, options).expect("Failed to add to zip");
+        zip.write_all(contents.as_bytes()).expect("Failed to write to zip");
+    }
+    zip.finish().expect("Failed to finish zip");
+}
+
+fn write_tar(path: &Path, files: &[(&str, &str)]) {
+    let mut tar = tar::Builder::new(fs::File::create(path).expect("Failed to create tar"));
+    for (name, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o644);
+        header.set_cksum();
+        tar.append_data(&mut header, name, contents.as_bytes()).expect("Failed to add to tar");
+    }
+    tar.finish().expect("Failed to finish tar");
+}
+
+#[test]
+fn test_copies_inside_archives() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    fs::write(dir.join("a.txt"), "contents").expect("Failed to write file");
+    write_zip(&dir.join("bundle.zip"), &[("docs/a.txt", "contents"), ("docs/b.txt", "other"), ("skip.tmp", "contents")]);
+    write_tar(&dir.join("bundle.tar"), &[("a.txt", "contents")]);
+    let conf = RelateConf { strategy: Strategy::Sequential, ..Default::default() };
+
+    let closed = WalkConf { exclude: vec!["*.tmp".to_owned()], ..Default::default() };
+    let found = scan::scan(&[dir.into()], &closed, &conf, mpsc::channel().0).expect("Failed to scan");
+    assert!(found.related.duplicate_groups().is_empty(), "Archives were looked into without asking");
+
+    let open = WalkConf { archives: true, ..closed };
+    let found = scan::scan(&[dir.into()], &open, &conf, mpsc::channel().0).expect("Failed to scan");
+    let groups = found.related.duplicate_groups();
+    assert_eq!(groups.len(), 1);
+    let mut names = groups[0].files.iter().map(|fi| fi.name.to_path_buf()).collect::<Vec<_>>();
+    names.sort();
+    assert_eq!(names, vec![
+        dir.join("a.txt"),
+        archive::member_path(&dir.join("bundle.tar"), "a.txt"),
+        archive::member_path(&dir.join("bundle.zip"), "docs/a.txt"),
+    ], "The excluded file in the zip was found, or a copy was missed");
+    let member = groups[0].files.iter().find(|fi| fi.in_archive()).expect("No file in an archive");
+    assert_eq!(archive::read_member(member, |read| std::io::read_to_string(read)).expect("Failed to read member"), "contents");
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_archives_holding_the_same_files() {
+    let _ = fs::remove_dir_all(SAME_DIR);
+    let dir = Path::new(SAME_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let files = [("a.txt", "contents"), ("docs/b.txt", "other")];
+    write_zip(&dir.join("packed.zip"), &files);
+    let stored = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Stored)
+        .last_modified_time(zip::DateTime::from_date_and_time(2001, 2, 3, 4, 5, 6).expect("Invalid time"));
+    write_zip_with(&dir.join("repacked.zip"), &[files[1], files[0]], stored);
+    write_tar(&dir.join("packed.tar"), &files);
+    write_zip(&dir.join("changed.zip"), &[("a.txt", "contents"), ("docs/b.txt", "changed")]);
+    assert_ne!(fs::read(dir.join("packed.zip")).unwrap(), fs::read(dir.join("repacked.zip")).unwrap());
+
+    let comparator = ArchiveContents::default();
+    let key = |name: &str| comparator.key(&dir.join(name)).expect("Failed to look into archive");
+    assert_eq!(key("packed.zip"), key("repacked.zip"), "The order, compression or times of the files counted");
+    assert_eq!(key("packed.zip"), key("packed.tar"));
+    assert_ne!(key("packed.zip"), key("changed.zip"));
+    assert!(archive::comparators(Default::default()).registered(&dir.join("PACKED.ZIP")));
+    let _ = fs::remove_dir_all(SAME_DIR);
+}
@@ -0,0 +1,72 @@
+use file_deduplicator::{
+    audio::{self, AudioStream},
+    relate::{Comparators, ContentComparator, ExactHash, RelateConf, RelatedFiles, WalkInfo},
+    similar::SimilarFiles,
+};
+use std::{fs, path::Path, sync::mpsc};
+
+const TEST_DIR: &str = "scratch/audio";
+
+fn chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend(id);
+    out.extend((data.len() as u32).to_le_bytes());
+    out.extend(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// A mono 16 bit WAV file of `samples' at 8 kHz, with `title' as its tag.
+fn wav(samples: impl Iterator<Item = i16>, title: &str) -> Vec<u8> {
+    // PCM, one channel, the sample rate, bytes per second, bytes per sample and bits per sample.
+    let format = [
+        &1u16.to_le_bytes()[..], &1u16.to_le_bytes(), &8000u32.to_le_bytes(), &16000u32.to_le_bytes(), &2u16.to_le_bytes(),
+        &16u16.to_le_bytes(),
+    ].concat();
+    let mut info = b"INFO".to_vec();
+    chunk(&mut info, b"INAM", format!("{:}\0", title).as_bytes());
+    let mut chunks = b"WAVE".to_vec();
+    chunk(&mut chunks, b"fmt ", &format);
+    chunk(&mut chunks, b"LIST", &info);
+    chunk(&mut chunks, b"data", &samples.flat_map(i16::to_le_bytes).collect::<Vec<u8>>());
+    let mut riff = Vec::new();
+    chunk(&mut riff, b"RIFF", &chunks);
+    riff
+}
+
+#[test]
+fn test_retagged_recordings_are_similar() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    fs::create_dir_all(TEST_DIR).expect("Failed to create test directory");
+    let dir = Path::new(TEST_DIR);
+    let tone = || (0..8000).map(|i| ((i % 40) * 800 - 16000) as i16);
+    fs::write(dir.join("song.wav"), wav(tone(), "Song")).expect("Failed to write file");
+    fs::write(dir.join("retagged.WAV"), wav(tone(), "Song (Remastered)")).expect("Failed to write file");
+    fs::write(dir.join("other.wav"), wav(tone().map(|sample| sample / 2), "Song")).expect("Failed to write file");
+    fs::write(dir.join("broken.mp3"), "not a recording").expect("Failed to write file");
+    let key = |name: &str| AudioStream::default().key(&dir.join(name));
+    assert_eq!(key("song.wav").ok(), key("retagged.WAV").ok());
+    assert!(key("broken.mp3").is_err());
+
+    let walk = WalkInfo::walk(dir.into());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = RelatedFiles::relate_sequential(&walk, &RelateConf::default(), progress_tx);
+    let similar = SimilarFiles::find(&related, &AudioStream::default(), audio::is_audio, 2);
+    let names = similar.groups
+        .iter()
+        .map(|group| group.iter().map(|fi| fi.name.file_name().unwrap().to_str().unwrap()).collect::<Vec<&str>>())
+        .collect::<Vec<Vec<&str>>>();
+    assert_eq!(names, [["retagged.WAV", "song.wav"]]);
+    assert_eq!(similar.errors.len(), 1);
+
+    let comparators = audio::comparators(Default::default());
+    assert!(comparators.registered(&dir.join("retagged.WAV")));
+    assert!(!comparators.registered(&dir.join("notes.txt")));
+    assert_eq!(comparators.key(&dir.join("retagged.WAV")).ok(), key("song.wav").ok());
+    assert_eq!(comparators.key(&dir.join("broken.mp3")).ok(), None);
+    let mut exact = Comparators::default();
+    exact.register("MP3", ExactHash::default());
+    assert_eq!(exact.key(&dir.join("broken.mp3")).ok(), ExactHash::default().key(&dir.join("broken.mp3")).ok());
+    assert_ne!(exact.key(&dir.join("song.wav")).ok(), exact.key(&dir.join("retagged.WAV")).ok());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
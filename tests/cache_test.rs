@@ -0,0 +1,56 @@
+use std::{fs, path::Path, sync::mpsc, time::{Duration, SystemTime}};
+use file_deduplicator::{
+    cache::HashCache,
+    relate::{HashAlgo, Progress, RelateConf, RelatedFiles, WalkInfo},
+};
+
+const DIR: &'static str = "scratch/cache";
+const CONF_DIR: &'static str = "scratch/cache_conf";
+
+fn rescan(cache: &HashCache) -> (WalkInfo, RelatedFiles, usize) {
+    let walk = WalkInfo::walk(DIR.into());
+    let conf = RelateConf::default();
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let related = cache.known(&walk, conf.hash_algo).resume(&walk, &conf, progress_tx);
+    let hashed = progress_rx.iter().filter(|progress| matches!(progress, Progress::FileHashed { .. })).count();
+    (walk, related, hashed)
+}
+
+#[test]
+fn test_unchanged_files_are_not_hashed_again() {
+    let _ = fs::remove_dir_all(DIR);
+    let _ = fs::remove_dir_all(CONF_DIR);
+    fs::create_dir_all(DIR).expect("Failed to create test folder");
+    fs::create_dir_all(CONF_DIR).expect("Failed to create test configuration folder");
+    let dir = Path::new(DIR);
+    for (name, contents) in [("a.txt", "same"), ("b.txt", "same"), ("c.txt", "other"), ("d.txt", "gone")] {
+        fs::write(dir.join(name), contents).expect("Failed to write test file");
+    }
+
+    let mut cache = HashCache::load(Path::new(CONF_DIR)).expect("Failed to load an empty cache");
+    assert!(cache.is_empty());
+    let (walk, related, hashed) = rescan(&cache);
+    assert_eq!(hashed, 4);
+    cache.update(&[dir], &walk, &related);
+    cache.save(Path::new(CONF_DIR)).expect("Failed to save the cache");
+
+    let mut cache = HashCache::load(Path::new(CONF_DIR)).expect("Failed to load the cache");
+    assert_eq!(cache.len(), 4);
+    let (_, related, hashed) = rescan(&cache);
+    assert_eq!(hashed, 0, "Unchanged files were hashed again");
+    assert_eq!(related.duplicate_groups().len(), 1);
+
+    // Same size, so only the modification time gives the change away.
+    fs::write(dir.join("c.txt"), "same!").expect("Failed to change test file");
+    let file = fs::File::options().write(true).open(dir.join("c.txt")).expect("Failed to open test file");
+    file.set_modified(SystemTime::now() + Duration::from_secs(60)).expect("Failed to set modification time");
+    fs::remove_file(dir.join("d.txt")).expect("Failed to remove test file");
+    let (walk, related, hashed) = rescan(&cache);
+    assert_eq!(hashed, 1);
+    cache.update(&[dir], &walk, &related);
+    assert_eq!(cache.len(), 3, "The removed file is still cached");
+    assert!(cache.known(&walk, HashAlgo::Xxh128).files.is_empty(), "Hashes of another algorithm were used");
+
+    let _ = fs::remove_dir_all(DIR);
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
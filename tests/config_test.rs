@@ -0,0 +1,13 @@
+use file_deduplicator::config::Dirs;
+use std::{fs, path::Path};
+
+const TEST_DIR: &str = "scratch/config";
+
+#[test]
+fn test_chosen_directory_holds_everything() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dirs = Dirs::locate(Some(Path::new(TEST_DIR).join("nested"))).expect("Failed to set up the directories");
+    assert_eq!(dirs.config, dirs.data);
+    assert!(dirs.data.is_dir());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
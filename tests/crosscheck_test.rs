@@ -0,0 +1,45 @@
+use file_deduplicator::{
+    crosscheck::{Crosscheck, ErrorType},
+    project::Project,
+    relate::{FileInfo, HashAlgo, RelatedFiles},
+};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}};
+
+const TEST_DIR: &'static str = "scratch/crosscheck";
+
+fn info(name: &str) -> FileInfo {
+    FileInfo { name: Path::new(name).into(), size: 5, created: None, modified: None, allocated: None, link_target: None, file_id: None, archive: None }
+}
+
+fn save(data_dir: &Path, path: &str, algo: HashAlgo, files: &[(&str, &str)]) {
+    let mut related = RelatedFiles { algo, files: HashMap::new(), partial: false, errors: Vec::new() };
+    for (hash, name) in files {
+        related.files.entry(hash.to_string()).or_default().insert(info(name));
+    }
+    let mut project = Project::new(path.into());
+    project.related = Some(related);
+    project.save(data_dir).expect("Failed to save project");
+}
+
+#[test]
+fn test_files_already_in_another_scan() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let (archive_dir, data_dir) = (Path::new(TEST_DIR).join("archive_data"), Path::new(TEST_DIR).join("data"));
+    save(&archive_dir, "/archive", HashAlgo::Blake3, &[("a", "/archive/2023/a.jpg"), ("a", "/archive/copy/a.jpg"), ("b", "/archive/b.jpg")]);
+    // The import is inside the archive's scan too, and is no copy of itself.
+    save(&data_dir, "/archive/import", HashAlgo::Blake3, &[("a", "/archive/import/a.jpg"), ("c", "/archive/import/c.jpg")]);
+    let check = Crosscheck::load(&archive_dir, Path::new("/archive"), &data_dir, Path::new("/archive/import")).expect("Failed to crosscheck");
+    assert_eq!(check.known.len(), 1);
+    assert_eq!(*check.known[0].file.name, *Path::new("/archive/import/a.jpg"));
+    assert_eq!(check.known[0].copies, [PathBuf::from("/archive/2023/a.jpg"), PathBuf::from("/archive/copy/a.jpg")]);
+    assert_eq!(check.unknown.iter().map(|fi| fi.name.to_path_buf()).collect::<Vec<_>>(), [PathBuf::from("/archive/import/c.jpg")]);
+    assert_eq!(check.known_size(), 5);
+    assert!(!check.partial);
+
+    save(&data_dir, "/other", HashAlgo::Sha256, &[("a", "/other/a.jpg")]);
+    let different = Crosscheck::load(&archive_dir, Path::new("/archive"), &data_dir, Path::new("/other"));
+    assert!(matches!(different.map(|_| ()).unwrap_err().error_type, ErrorType::DifferentAlgo { .. }));
+    let missing = Crosscheck::load(&archive_dir, Path::new("/nowhere"), &data_dir, Path::new("/other"));
+    assert!(matches!(missing.map(|_| ()).unwrap_err().error_type, ErrorType::NotScanned));
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
@@ -1,7 +1,9 @@
-use file_deduplicator::relate;
+use file_deduplicator::{platform::DeviceKind, relate};
 use std::{fs,
+          path::Path,
           sync::mpsc, sync::mpsc::{Sender, Receiver},
           thread,
+          time::Instant,
           collections::BTreeSet,
 };
 use serial_test::serial;
@@ -13,9 +15,16 @@ use gen::{Cfg, gen};
 const TEST_DIR: &'static str = "scratch/data";
 
 const RELATE_CONF: relate::RelateConf = relate::RelateConf {
-    max_threads: 12,
-    file_threshold: 100,
-    size_threshold: 4_000_000_000,
+    strategy: relate::Strategy::Parallel { threads: 12 },
+    device_threads: None,
+    hash_algo: relate::HashAlgo::Sha256,
+    mmap_threshold: Some(relate::MMAP_THRESHOLD),
+    change_retries: relate::CHANGE_RETRIES,
+    retry_locked: false,
+    io_limit: None,
+    low_priority: false,
+    deadline: None,
+    cancel: None,
 };
 
 fn check_related<'a, 'b>(gen_info: &'a gen::GenInfo, related: &'b relate::RelatedFiles) {
@@ -36,25 +45,37 @@ fn check_related<'a, 'b>(gen_info: &'a gen::GenInfo, related: &'b relate::Relate
 }
 
 fn test_with_config(cfg: Cfg) {
+    test_with_config_and_conf(cfg, RELATE_CONF);
+}
+
+fn test_with_config_and_conf(cfg: Cfg, conf: relate::RelateConf) {
     let _ = fs::remove_dir_all(TEST_DIR);
 
     let file_count = cfg.file_count();
     let gen_info = gen(TEST_DIR, cfg).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
     println!("{:?}", &gen_info);
-    let (progress_tx, progress_rx): (Sender<f32>, Receiver<f32>) = mpsc::channel();
+    let (progress_tx, progress_rx): (Sender<relate::Progress>, Receiver<relate::Progress>) = mpsc::channel();
     let (result_tx, result_rx): (Sender<relate::RelatedFiles>, Receiver<relate::RelatedFiles>) = mpsc::channel();
     let th = thread::spawn(move || {
         let walk_info = relate::WalkInfo::walk(TEST_DIR.into());
-        let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+        let related = relate::RelatedFiles::relate(&walk_info, &conf, progress_tx);
         let _ = result_tx.send(related);
     });
-    let mut progress = 0.0;
-    for _ in 0..file_count {
-        let new_progress = progress_rx.recv().expect("Failed to get progress during file relation.");
-        assert!(progress < new_progress, "Progress did not go up as expected.");
-        progress = new_progress;
+    let mut started = 0;
+    let mut hashed = 0;
+    let mut failed = 0;
+    assert_eq!(progress_rx.recv(), Ok(relate::Progress::StageChanged(relate::Stage::Hashing)));
+    loop {
+        match progress_rx.recv().expect("Failed to get progress during file relation.") {
+            relate::Progress::FileStarted(_) => started += 1,
+            relate::Progress::FileHashed { .. } => hashed += 1,
+            relate::Progress::FileFailed { .. } => failed += 1,
+            relate::Progress::Done => break,
+            progress => panic!("Unexpected progress {:?}", progress),
+        }
     }
-    assert!(progress > 0.0, "Unexpected progress value");
+    assert_eq!(started, hashed + failed, "Not every started file was reported as finished.");
+    assert_eq!(hashed, file_count, "Not every generated file was hashed.");
     let result = result_rx.recv().expect("Failed to get result from RelatedFile::relate");
     println!("{:?}", result);
     let _ = th.join();
@@ -86,3 +107,410 @@ fn test_multiple_groups() {
 fn test_with_lots_of_groups_and_files() {
     test_with_config(Cfg::new(200, 30, 1, 10_000_000).unwrap());
 }
+
+#[test]
+#[serial]
+fn test_every_hash_algo() {
+    for hash_algo in [relate::HashAlgo::Blake3, relate::HashAlgo::Xxh128] {
+        test_with_config_and_conf(Cfg::new(20, 4, 1, 10_000_000).unwrap(), relate::RelateConf { hash_algo, ..RELATE_CONF });
+    }
+}
+
+#[test]
+#[serial]
+fn test_mapped_files_hash_the_same() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    gen(TEST_DIR, Cfg::new(20, 4, 1, 1_000_000).unwrap()).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
+    let walk_info = relate::WalkInfo::walk(TEST_DIR.into());
+    for hash_algo in [relate::HashAlgo::Sha256, relate::HashAlgo::Blake3, relate::HashAlgo::Xxh128] {
+        let hashed = |mmap_threshold| {
+            let (progress_tx, _progress_rx) = mpsc::channel();
+            relate::RelatedFiles::relate(&walk_info, &relate::RelateConf { hash_algo, mmap_threshold, ..RELATE_CONF }, progress_tx).files
+        };
+        assert_eq!(hashed(Some(0)), hashed(None), "{:}", hash_algo);
+    }
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+#[serial]
+fn test_walk_filters() {
+    const FILTER_DIR: &'static str = "scratch/filter";
+    let _ = fs::remove_dir_all(FILTER_DIR);
+    for file in ["a.jpg", "b.tmp", "node_modules/c.jpg", "photos/d.jpg", "photos/e.txt"] {
+        let path = std::path::Path::new(FILTER_DIR).join(file);
+        fs::create_dir_all(path.parent().unwrap()).expect("Failed to create test folder");
+        fs::write(&path, file).expect("Failed to write test file");
+    }
+    let walked = |include: &[&str], exclude: &[&str]| {
+        let conf = relate::WalkConf {
+            include: include.iter().map(|pat| pat.to_string()).collect(),
+            exclude: exclude.iter().map(|pat| pat.to_string()).collect(),
+            ..Default::default()
+        };
+        let filter = relate::WalkFilter::new(&conf).expect("Failed to compile filter");
+        relate::WalkInfo::walk_filtered(FILTER_DIR.into(), &filter)
+            .files
+            .into_iter()
+            .filter(|fi| fi.name.is_file())
+            .map(|fi| fi.name.strip_prefix(FILTER_DIR).unwrap().to_str().unwrap().to_owned())
+            .collect::<BTreeSet<String>>()
+    };
+    let expect = |files: &[&str]| files.iter().map(|file| file.to_string()).collect::<BTreeSet<String>>();
+    assert_eq!(walked(&[], &[]).len(), 5);
+    assert_eq!(walked(&[], &["node_modules", "*.tmp"]), expect(&["a.jpg", "photos/d.jpg", "photos/e.txt"]));
+    assert_eq!(walked(&["*.jpg"], &["node_modules"]), expect(&["a.jpg", "photos/d.jpg"]));
+    assert_eq!(walked(&["photos/*"], &[]), expect(&["photos/d.jpg", "photos/e.txt"]));
+    let sized = relate::WalkConf { min_size: Some(6), max_size: Some(12), ..Default::default() };
+    let sized = relate::WalkInfo::walk_filtered(FILTER_DIR.into(), &relate::WalkFilter::new(&sized).expect("Failed to compile filter"));
+    let mut sized = sized.files.into_iter().filter(|fi| fi.name.is_file()).map(|fi| fi.size).collect::<Vec<u64>>();
+    sized.sort();
+    assert_eq!(sized, vec![12, 12], "Only the files in photos are 6 to 12 bytes long");
+    let shared = relate::WalkFilter::new(&relate::WalkConf { skip_unique_sizes: true, ..Default::default() }).expect("Failed to compile filter");
+    let shared_names = |walk: relate::WalkInfo| {
+        walk.files.into_iter().filter(|fi| fi.name.is_file()).map(|fi| fi.name.file_name().unwrap().to_str().unwrap().to_owned()).collect::<BTreeSet<String>>()
+    };
+    let walk = relate::WalkInfo::walk_filtered(FILTER_DIR.into(), &shared);
+    assert_eq!(shared_names(walk), expect(&["a.jpg", "b.tmp", "d.jpg", "e.txt"]));
+    assert!(relate::WalkFilter::new(&relate::WalkConf { include: vec!["[".to_owned()], ..Default::default() }).is_err());
+    let _ = fs::remove_dir_all(FILTER_DIR);
+
+    // A copy in another root keeps a size from being unique.
+    const ROOTS_DIR: &'static str = "scratch/filter-roots";
+    let _ = fs::remove_dir_all(ROOTS_DIR);
+    let dir = Path::new(ROOTS_DIR);
+    fs::create_dir_all(dir.join("folder")).expect("Failed to create test folder");
+    for (file, contents) in [("folder/c.jpg", "copy"), ("folder/d.txt", "unique"), ("b.tmp", "copy")] {
+        fs::write(dir.join(file), contents).expect("Failed to write test file");
+    }
+    let walk = relate::WalkInfo::walk_roots(&[dir.join("folder"), dir.join("b.tmp")], &shared, &Default::default());
+    assert_eq!(shared_names(walk), expect(&["b.tmp", "c.jpg"]));
+    let _ = fs::remove_dir_all(ROOTS_DIR);
+}
+
+#[test]
+#[serial]
+fn test_deadline_leaves_partial_results() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    gen(TEST_DIR, Cfg::new(20, 4, 1, 10_000).unwrap()).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
+    let walk_info = relate::WalkInfo::walk(TEST_DIR.into());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let conf = relate::RelateConf { deadline: Some(Instant::now()), ..RELATE_CONF };
+    let related = relate::RelatedFiles::relate(&walk_info, &conf, progress_tx);
+    assert!(related.partial);
+    assert!(related.files.is_empty(), "Files were started after the deadline");
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    assert!(!relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx).partial);
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[cfg(unix)]
+#[test]
+#[serial]
+fn test_symlink_policies() {
+    const LINK_DIR: &'static str = "scratch/links";
+    let _ = fs::remove_dir_all(LINK_DIR);
+    let dir = std::path::Path::new(LINK_DIR);
+    fs::create_dir_all(dir.join("real")).expect("Failed to create test folder");
+    fs::write(dir.join("real/file.txt"), "contents").expect("Failed to write test file");
+    std::os::unix::fs::symlink("real/file.txt", dir.join("file_link.txt")).expect("Failed to create link");
+    std::os::unix::fs::symlink("real", dir.join("dir_link")).expect("Failed to create link");
+    let walk = |symlinks| {
+        let conf = relate::WalkConf { symlinks, ..Default::default() };
+        relate::WalkInfo::walk_filtered(LINK_DIR.into(), &relate::WalkFilter::new(&conf).expect("Failed to compile filter"))
+    };
+    let files = |walk: &relate::WalkInfo| walk.files.iter().filter(|fi| !fi.name.is_dir()).count();
+
+    let skipped = walk(relate::SymlinkPolicy::Skip);
+    assert_eq!((files(&skipped), skipped.links.len()), (1, 0));
+    let reported = walk(relate::SymlinkPolicy::Report);
+    assert_eq!((files(&reported), reported.links.len()), (1, 2));
+    assert!(reported.links.iter().any(|fi| fi.link_target.as_deref() == Some(std::path::Path::new("real/file.txt"))));
+    let followed = walk(relate::SymlinkPolicy::Follow);
+    assert_eq!((files(&followed), followed.links.len()), (1, 0));
+    assert!(
+        followed.hard_links.iter().any(|same| same.len() == 3),
+        "Expected the file, its link, and the file through the folder link to be one file",
+    );
+    let _ = fs::remove_dir_all(LINK_DIR);
+}
+
+#[test]
+#[serial]
+fn test_hard_links_are_not_duplicates() {
+    const HARD_LINK_DIR: &'static str = "scratch/hard_links";
+    let _ = fs::remove_dir_all(HARD_LINK_DIR);
+    let dir = std::path::Path::new(HARD_LINK_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test folder");
+    for name in ["a.txt", "c.txt"] {
+        fs::write(dir.join(name), "contents").expect("Failed to write test file");
+    }
+    fs::hard_link(dir.join("a.txt"), dir.join("b.txt")).expect("Failed to create hard link");
+
+    let walk_info = relate::WalkInfo::walk(HARD_LINK_DIR.into());
+    assert_eq!(walk_info.hard_links, vec![vec![dir.join("a.txt"), dir.join("b.txt")]]);
+    assert!(!walk_info.files.iter().any(|fi| *fi.name == dir.join("b.txt")));
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    let groups = related.duplicate_groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].files.len(), 2, "The hard link was counted as a duplicate");
+    let _ = fs::remove_dir_all(HARD_LINK_DIR);
+}
+
+#[test]
+#[serial]
+fn test_path_spellings_are_not_duplicates() {
+    const SPELLING_DIR: &'static str = "scratch/spellings";
+    let _ = fs::remove_dir_all(SPELLING_DIR);
+    let dir = std::path::Path::new(SPELLING_DIR);
+    fs::create_dir_all(dir.join("sub")).expect("Failed to create test folder");
+    for name in ["a.txt", "sub/b.txt"] {
+        fs::write(dir.join(name), "contents").expect("Failed to write test file");
+    }
+
+    // The same folder twice, spelled differently, and once more through a folder inside it.
+    let walk_info = relate::WalkInfo::walk("scratch//spellings/./sub/..".into())
+        .merge(relate::WalkInfo::walk(SPELLING_DIR.into()))
+        .merge(relate::WalkInfo::walk(dir.join("sub")));
+    assert!(walk_info.files.iter().all(|fi| fi.name.starts_with(dir)));
+    assert_eq!(walk_info.files.iter().filter(|fi| fi.name.is_file()).count(), 2);
+    assert_eq!(walk_info.hard_links, Vec::<Vec<std::path::PathBuf>>::new());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    let groups = related.duplicate_groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].files.len(), 2, "A file was taken for a copy of itself");
+    let _ = fs::remove_dir_all(SPELLING_DIR);
+}
+
+#[test]
+fn test_strategy_decision_boundaries() {
+    use relate::Strategy;
+    let auto = Strategy::Auto { max_threads: 8, files: 100, bytes: 1_000 };
+    assert_eq!(auto.decide(100, 1_000), Strategy::Sequential, "Reaching a threshold isn't passing it");
+    assert_eq!(auto.decide(101, 0), Strategy::Parallel { threads: 8 });
+    assert_eq!(auto.decide(2, 1_001), Strategy::Parallel { threads: 8 }, "A few large files are worth parallelizing");
+    assert_eq!(auto.decide(0, 0), Strategy::Sequential);
+    let single_core = Strategy::Auto { max_threads: 1, files: 100, bytes: 1_000 };
+    assert_eq!(single_core.decide(1_000_000, 1_000_000), Strategy::Sequential);
+    assert_eq!(Strategy::Sequential.decide(1_000_000, 1_000_000), Strategy::Sequential);
+    assert_eq!(Strategy::Parallel { threads: 3 }.decide(0, 0), Strategy::Parallel { threads: 3 });
+}
+
+#[test]
+#[serial]
+fn test_sequential_strategy() {
+    test_with_config_and_conf(Cfg::new(20, 4, 1, 10_000_000).unwrap(), relate::RelateConf { strategy: relate::Strategy::Sequential, ..RELATE_CONF });
+}
+
+#[test]
+#[serial]
+fn test_cancellation_leaves_partial_results() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    gen(TEST_DIR, Cfg::new(20, 4, 1, 10_000).unwrap()).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
+    let cancel = relate::CancellationToken::default();
+    let walk_info = relate::WalkInfo::walk_cancellable(TEST_DIR.into(), &relate::WalkFilter::default(), &cancel);
+    assert!(!walk_info.partial);
+    cancel.cancel();
+    for strategy in [relate::Strategy::Sequential, relate::Strategy::Parallel { threads: 4 }] {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let conf = relate::RelateConf { strategy, cancel: Some(cancel.clone()), ..RELATE_CONF };
+        let related = relate::RelatedFiles::relate(&walk_info, &conf, progress_tx);
+        assert!(related.partial);
+        assert!(related.files.is_empty(), "Files were started after cancelling");
+    }
+    let cancelled_walk = relate::WalkInfo::walk_cancellable(TEST_DIR.into(), &relate::WalkFilter::default(), &cancel);
+    assert!(cancelled_walk.partial);
+    assert!(cancelled_walk.files.is_empty());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+#[serial]
+fn test_resume_only_hashes_what_is_missing() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let gen_info = gen(TEST_DIR, Cfg::new(20, 4, 1, 10_000).unwrap()).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
+    let walk_info = relate::WalkInfo::walk(TEST_DIR.into());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let mut related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    // As if the scan had stopped before getting to one of the groups.
+    let missing = related.files.keys().next().cloned().expect("No files were hashed");
+    let missing = related.files.remove(&missing).expect("Group went missing");
+    let (missing, missing_bytes) = (missing.len(), missing.iter().map(|fi| fi.size).sum::<u64>());
+    related.partial = true;
+
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let resumed = related.resume(&walk_info, &RELATE_CONF, progress_tx);
+    let progress = progress_rx.iter().collect::<Vec<relate::Progress>>();
+    // Folders are walked too, and as they can't be hashed they are tried again.
+    let (folders, files) = walk_info.files.iter().partition::<Vec<&relate::FileInfo>, _>(|fi| fi.name.is_dir());
+    assert_eq!(progress.iter().filter(|p| matches!(p, relate::Progress::FileStarted(_))).count(), missing + folders.len());
+    let bytes = files.iter().map(|fi| fi.size).sum::<u64>();
+    assert!(progress.contains(&relate::Progress::Resumed { files: files.len() - missing, bytes: bytes - missing_bytes }));
+    assert!(!resumed.partial);
+    check_related(&gen_info, &resumed);
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+#[serial]
+fn test_hashing_while_walking() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    gen(TEST_DIR, Cfg::new(20, 4, 1, 10_000).unwrap()).expect(&format!("Failed to generate test data in {:}", TEST_DIR));
+    let file = relate::WalkInfo::walk(TEST_DIR.into()).files.into_iter().find(|fi| fi.name.is_file()).expect("No files were generated");
+    fs::hard_link(&file.name, std::path::Path::new(TEST_DIR).join("0link")).expect("Failed to link file");
+    let walk_info = relate::WalkInfo::walk(TEST_DIR.into());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    // As if one group had been hashed by an earlier scan.
+    let (known_hash, known_group) = related.files
+        .iter()
+        .find(|(_, group)| group.iter().all(|fi| fi.name.is_file()))
+        .expect("No group of files was found");
+    let known = |fi: &relate::FileInfo| known_group.contains(fi).then(|| known_hash.clone());
+
+    let (progress_tx, progress_rx) = mpsc::channel();
+    let roots = [std::path::PathBuf::from(TEST_DIR)];
+    let (walked, pipelined) = relate::RelatedFiles::walk_and_relate(&roots, &relate::WalkFilter::default(), &RELATE_CONF, known, progress_tx);
+    let progress = progress_rx.iter().collect::<Vec<relate::Progress>>();
+    assert_eq!((&walked.files, &walked.hard_links), (&walk_info.files, &walk_info.hard_links));
+    assert_eq!(walked.hard_links.len(), 1);
+    // The link may be found first, but the path the walk kept is the one reported.
+    assert_eq!(pipelined.files, related.files);
+    assert!(!pipelined.partial);
+    let count = |found: fn(&relate::Progress) -> bool| progress.iter().filter(|p| found(p)).count();
+    assert_eq!(count(|p| matches!(p, relate::Progress::FileFound { .. })), walk_info.files.len());
+    assert_eq!(count(|p| matches!(p, relate::Progress::FileStarted(_))), walk_info.files.len() - known_group.len());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+#[serial]
+fn test_large_files_report_progress() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    fs::create_dir_all(TEST_DIR).expect("Failed to create test directory");
+    let path = std::path::Path::new(TEST_DIR).join("large");
+    let size = 2 * relate::PROGRESS_INTERVAL + 1;
+    fs::File::create(&path).and_then(|file| file.set_len(size)).expect("Failed to create large file");
+    let walk_info = relate::WalkInfo::walk(path.clone());
+    for mmap_threshold in [None, Some(0)] {
+        let (progress_tx, progress_rx) = mpsc::channel();
+        let conf = relate::RelateConf { strategy: relate::Strategy::Sequential, hash_algo: relate::HashAlgo::Xxh128, mmap_threshold, ..RELATE_CONF };
+        relate::RelatedFiles::relate(&walk_info, &conf, progress_tx);
+        let reading = progress_rx
+            .iter()
+            .filter_map(|progress| match progress {
+                relate::Progress::FileReading { bytes, .. } => Some(bytes),
+                _ => None,
+            })
+            .collect::<Vec<u64>>();
+        assert_eq!(reading, vec![relate::PROGRESS_INTERVAL, 2 * relate::PROGRESS_INTERVAL], "{:?}", mmap_threshold);
+    }
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+#[serial]
+fn test_multiple_roots() {
+    const ROOTS_DIR: &'static str = "scratch/roots";
+    let _ = fs::remove_dir_all(ROOTS_DIR);
+    let dir = std::path::Path::new(ROOTS_DIR);
+    for name in ["pictures/a.jpg", "pictures/inner/b.jpg", "backup/a.jpg", "other/c.jpg"] {
+        fs::create_dir_all(dir.join(name).parent().unwrap()).expect("Failed to create test folder");
+        fs::write(dir.join(name), name.rsplit('/').next().unwrap()).expect("Failed to write test file");
+    }
+
+    let mut project = file_deduplicator::project::Project::new(dir.join("pictures"));
+    project.set_extra_roots(vec![dir.join("backup"), dir.join("pictures/inner"), dir.join("./backup")]);
+    assert_eq!(project.roots(), vec![dir.join("pictures"), dir.join("backup")]);
+    let walk_info = relate::WalkInfo::walk_roots(&project.roots(), &relate::WalkFilter::default(), &Default::default());
+    assert_eq!(walk_info.files.iter().filter(|fi| fi.name.is_file()).count(), 3, "A file below two roots was found twice");
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    let groups = related.duplicate_groups();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].files.iter().map(|fi| fi.name.to_path_buf()).collect::<BTreeSet<_>>(), [dir.join("backup/a.jpg"), dir.join("pictures/a.jpg")].into());
+    let _ = fs::remove_dir_all(ROOTS_DIR);
+}
+
+#[test]
+#[serial]
+fn test_files_changed_during_scan() {
+    const CHANGED_DIR: &'static str = "scratch/changed";
+    let _ = fs::remove_dir_all(CHANGED_DIR);
+    let dir = std::path::Path::new(CHANGED_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test folder");
+    fs::write(dir.join("a.txt"), "before").expect("Failed to write test file");
+    let walk_info = relate::WalkInfo::walk(CHANGED_DIR.into());
+    // Saved again between being found and being read.
+    fs::write(dir.join("a.txt"), "after saving").expect("Failed to write test file");
+
+    let relate = |change_retries| {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let conf = relate::RelateConf { strategy: relate::Strategy::Sequential, change_retries, ..RELATE_CONF };
+        relate::RelatedFiles::relate(&walk_info, &conf, progress_tx)
+    };
+    let file_errors = |related: &relate::RelatedFiles| {
+        related.errors.iter().filter(|e| e.path().is_file()).map(|e| (e.kind(), e.code())).collect::<Vec<_>>()
+    };
+    assert_eq!(file_errors(&relate(0)), [(relate::ErrorKind::Changed, "wrong_size")]);
+    let retried = relate(relate::CHANGE_RETRIES);
+    assert!(file_errors(&retried).is_empty(), "{:?}", retried.errors);
+    let sizes = retried.files.values().flatten().filter(|fi| fi.name.is_file()).map(|fi| fi.size).collect::<Vec<u64>>();
+    assert_eq!(sizes, [12], "The file wasn't looked up again");
+    let _ = fs::remove_dir_all(CHANGED_DIR);
+}
+
+#[test]
+#[serial]
+fn test_locked_files_are_skipped() {
+    const LOCKED_DIR: &'static str = "scratch/locked";
+    let _ = fs::remove_dir_all(LOCKED_DIR);
+    let dir = std::path::Path::new(LOCKED_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test folder");
+    for name in ["a.txt", "b.txt"] {
+        fs::write(dir.join(name), "contents").expect("Failed to write test file");
+    }
+    let walk_info = relate::WalkInfo::walk(LOCKED_DIR.into());
+    let lock = fs::File::open(dir.join("b.txt")).expect("Failed to open test file");
+    lock.lock().expect("Failed to lock test file");
+
+    for retry_locked in [false, true] {
+        let (progress_tx, _progress_rx) = mpsc::channel();
+        let conf = relate::RelateConf { retry_locked, ..RELATE_CONF };
+        let related = relate::RelatedFiles::relate(&walk_info, &conf, progress_tx);
+        let locked = related.errors.iter().filter(|e| e.kind() == relate::ErrorKind::Locked).map(|e| e.path()).collect::<Vec<_>>();
+        assert_eq!(locked, [dir.join("b.txt")], "retry_locked: {:}", retry_locked);
+        assert!(related.duplicate_groups().is_empty());
+    }
+    drop(lock);
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = relate::RelatedFiles::relate(&walk_info, &RELATE_CONF, progress_tx);
+    assert_eq!(related.duplicate_groups().len(), 1);
+    let _ = fs::remove_dir_all(LOCKED_DIR);
+}
+
+#[test]
+fn test_share_threads() {
+    use DeviceKind::*;
+    assert_eq!(relate::share_threads(12, &[SolidState]), [12]);
+    assert_eq!(relate::share_threads(12, &[Rotational]), [relate::ROTATIONAL_THREADS]);
+    // The spinning disks' threads are left to the others.
+    assert_eq!(relate::share_threads(12, &[Rotational, SolidState, Unknown]), [2, 5, 5]);
+    assert_eq!(relate::share_threads(1, &[Rotational, SolidState]), [1, 1]);
+}
+
+#[test]
+fn test_reading_order() {
+    let file = |name: &str, size, inode: Option<u64>| relate::FileInfo {
+        name: Path::new(name).into(), size, created: None, modified: None, allocated: None, link_target: None, file_id: inode.map(|inode| (1, inode)), archive: None,
+    };
+    let (a, b, c) = (file("a", 10, Some(30)), file("b", 30, Some(20)), file("c", 20, None));
+    let names = |files: &[&relate::FileInfo]| files.iter().map(|fi| fi.name.to_string_lossy().into_owned()).collect::<Vec<String>>();
+    let mut files = vec![&a, &b, &c];
+    relate::reading_order(&mut files, DeviceKind::Rotational);
+    assert_eq!(names(&files), ["c", "b", "a"]);
+    relate::reading_order(&mut files, DeviceKind::SolidState);
+    assert_eq!(names(&files), ["b", "c", "a"]);
+}
@@ -0,0 +1,60 @@
+use file_deduplicator::{
+    denylist::Denylist,
+    relate::{FileInfo, HashAlgo, RelatedFiles},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::Path,
+    time::SystemTime,
+};
+
+const CONF_DIR: &'static str = "scratch/denylist_conf";
+
+fn info(name: &str) -> FileInfo {
+    FileInfo {
+        name: Path::new(name).into(),
+        size: 3,
+        created: Some(SystemTime::UNIX_EPOCH),
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    }
+}
+
+#[test]
+fn test_denylist() {
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+    fs::create_dir_all(conf_dir).expect("Failed to create test directory");
+
+    let mut denylist = Denylist::load(conf_dir).expect("A missing denylist should load empty");
+    assert!(denylist.entries.is_empty());
+    assert!(denylist.add(HashAlgo::Sha256, "AAA", "LICENSE".to_owned()));
+    assert!(!denylist.add(HashAlgo::Sha256, "aaa", "LICENSE".to_owned()));
+    assert!(denylist.add(HashAlgo::Blake3, "bbb", String::new()));
+    denylist.save(conf_dir).expect("Failed to save denylist");
+    let mut denylist = Denylist::load(conf_dir).expect("Failed to load denylist");
+    assert!(denylist.contains(HashAlgo::Sha256, "aaa"));
+    assert!(!denylist.contains(HashAlgo::Blake3, "aaa"), "A hash only applies to its own algorithm");
+
+    let mut related = RelatedFiles {
+        algo: HashAlgo::Sha256,
+        files: HashMap::from([
+            ("aaa".to_owned(), HashSet::from([info("a/LICENSE"), info("b/LICENSE")])),
+            ("bbb".to_owned(), HashSet::from([info("a/x"), info("b/x")])),
+        ]),
+        partial: false,
+        errors: Vec::new(),
+    };
+    assert_eq!(related.remove_denied(&denylist), 1);
+    assert_eq!(related.files.keys().collect::<Vec<_>>(), ["bbb"]);
+
+    assert!(denylist.remove(HashAlgo::Sha256, "AAA"));
+    assert!(!denylist.remove(HashAlgo::Sha256, "aaa"));
+    assert_eq!(denylist.entries.len(), 1);
+
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
@@ -0,0 +1,28 @@
+use file_deduplicator::{
+    estimate::{self, EstimateConf},
+    relate::WalkInfo,
+};
+use std::{fs, path::Path};
+
+const TEST_DIR: &'static str = "scratch/estimate";
+
+#[test]
+fn test_estimate_with_every_group_sampled_is_exact() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    for (name, contents) in [("a", "hello"), ("b", "hello"), ("c", "hello"), ("d", "world"), ("e", "abcdefgh"), ("f", "abcdefgh"), ("g", "unique")] {
+        fs::write(dir.join(name), contents).expect("Failed to write file");
+    }
+
+    let walk = WalkInfo::walk(dir.to_path_buf());
+    let estimate = estimate::estimate(&walk, &EstimateConf::default());
+    assert_eq!((estimate.candidate_groups, estimate.candidate_files), (2, 6));
+    assert_eq!(estimate.upper_bound, 5 * 3 + 8);
+    assert!(estimate.exact());
+    assert_eq!((estimate.reclaimable, estimate.margin), (5 * 2 + 8, 0));
+
+    let estimate = estimate::estimate(&walk, &EstimateConf { groups: 0, ..Default::default() });
+    assert!(!estimate.exact());
+    assert_eq!((estimate.reclaimable, estimate.margin), (0, estimate.upper_bound), "An empty sample should say nothing");
+}
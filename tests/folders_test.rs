@@ -0,0 +1,65 @@
+use file_deduplicator::{
+    actions::{self, Action, DedupOptions, Permit, Plan},
+    folders,
+    policy::{KeepPolicy, Resolver},
+    relate::{FileInfo, HashAlgo, RelateConf, RelatedFiles, WalkInfo},
+};
+use std::{collections::HashMap, fs, path::{Path, PathBuf}, sync::mpsc};
+
+const TEST_DIR: &'static str = "scratch/folders";
+
+#[test]
+fn test_copied_folders_are_removed_whole() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    for copy in ["a", "b", "c", "d"] {
+        fs::create_dir_all(dir.join(copy).join("sub")).expect("Failed to create test directory");
+        fs::write(dir.join(copy).join("x.txt"), "top").expect("Failed to write file");
+        fs::write(dir.join(copy).join("sub/y.txt"), "below").expect("Failed to write file");
+    }
+    // Same contents under another name, and a file the scan never saw.
+    fs::rename(dir.join("c/x.txt"), dir.join("c/z.txt")).expect("Failed to rename file");
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = RelatedFiles::relate_sequential(&WalkInfo::walk(dir.into()), &RelateConf::default(), progress_tx);
+    fs::write(dir.join("d/new.txt"), "later").expect("Failed to write file");
+
+    let groups = folders::duplicate_folders(&related, &[dir.to_path_buf()]);
+    let names = |group: &folders::FolderGroup| group.folders.iter().map(|fi| fi.name.to_path_buf()).collect::<Vec<PathBuf>>();
+    // The folders below `a' and `b' would go with them, but not the copies in `c' and `d'.
+    assert_eq!(groups.len(), 2, "{:?}", groups);
+    assert_eq!(names(&groups[0]), ["a/sub", "b/sub", "c/sub", "d/sub"].map(|sub| dir.join(sub)));
+    assert_eq!(names(&groups[1]), [dir.join("a"), dir.join("b")]);
+    assert_eq!((groups[1].size, groups[1].files), (8, 2));
+
+    let resolver = Resolver::new(KeepPolicy::Alphabetical).expect("Only regexes can be invalid");
+    let plan = Plan::folders(&related, &groups[1..], &resolver, Action::Delete);
+    assert_eq!(plan.steps.iter().map(|step| step.keep.clone()).collect::<Vec<PathBuf>>(), [dir.join("a/sub/y.txt"), dir.join("a/x.txt")]);
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    assert!(plan.execute(&DedupOptions { verify: true, dry_run: false }, &permit).iter().all(|outcome| outcome.result.is_ok()));
+    actions::remove_emptied(&dir.join("b"), &permit).expect("Failed to remove the emptied folder");
+    assert!(!dir.join("b").exists());
+    assert!(dir.join("a/sub/y.txt").exists());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_mostly_copied_folders_are_similar() {
+    let info = |name: &str| FileInfo {
+        name: Path::new(name).into(), size: 10, created: None, modified: None, allocated: None, link_target: None, file_id: None, archive: None,
+    };
+    let mut related = RelatedFiles { algo: HashAlgo::Blake3, files: HashMap::new(), partial: false, errors: Vec::new() };
+    for (hash, name) in [
+        ("a", "/b/2023/photos/a.jpg"), ("b", "/b/2023/photos/b.jpg"), ("c", "/b/2023/photos/c.jpg"), ("d", "/b/2023/d.txt"),
+        ("a", "/b/2024/photos/a.jpg"), ("b", "/b/2024/photos/b.jpg"), ("c", "/b/2024/photos/c.jpg"), ("e", "/b/2024/e.txt"),
+        ("f", "/b/2024/f.txt"),
+    ] {
+        related.files.entry(hash.to_owned()).or_default().insert(info(name));
+    }
+    let pairs = folders::similar_folders(&related, &[PathBuf::from("/b")], 50);
+    // The photos are alike, but only `2023' and `2024' are listed, as they hold them.
+    assert_eq!(pairs.len(), 1, "{:?}", pairs);
+    assert_eq!((pairs[0].first.as_path(), pairs[0].second.as_path()), (Path::new("/b/2023"), Path::new("/b/2024")));
+    assert_eq!((pairs[0].shared, pairs[0].total, pairs[0].similarity()), (3, 6, 50));
+    let pairs = folders::similar_folders(&related, &[PathBuf::from("/b")], 51);
+    assert_eq!(pairs.iter().map(|pair| (pair.first.to_str().unwrap(), pair.similarity())).collect::<Vec<_>>(), [("/b/2023/photos", 100)]);
+}
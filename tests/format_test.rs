@@ -0,0 +1,48 @@
+use std::time::{Duration, Instant};
+use file_deduplicator::{format::Localized, usage::{ScanUsage, Throughput}};
+
+#[test]
+fn test_numbers_and_sizes_follow_the_locale() {
+    let en = Localized::new("en_US.UTF-8");
+    assert_eq!(en.number(1234567), "1,234,567");
+    assert_eq!(en.size(512), "512 bytes");
+    assert_eq!(en.size(1536), "1.5 KiB");
+    assert_eq!(en.size(5 * 1024 * 1024 * 1024), "5.0 GiB");
+    let de = Localized::new("de-DE");
+    assert_eq!(de.number(1234567), "1.234.567");
+    assert_eq!(de.size(1536), "1,5 KiB");
+    assert_eq!(Localized::new("nonsense").number(1234567), "1234567");
+}
+
+#[test]
+fn test_scan_usage() {
+    let en = Localized::new("en_US.UTF-8");
+    assert_eq!(en.duration(Duration::from_millis(3450)), "3.5 s");
+    assert_eq!(en.duration(Duration::from_secs(125)), "2 min 5 s");
+    assert_eq!(en.duration(Duration::from_secs(3720)), "1 h 2 min");
+    assert_eq!(Localized::new("de-DE").duration(Duration::from_millis(3450)), "3,5 s");
+    let usage = ScanUsage {
+        wall_time: Duration::from_secs(125),
+        cpu_time: Some(Duration::from_secs(421)),
+        bytes_read: 1536,
+        peak_memory: None,
+    };
+    assert_eq!(en.usage(&usage), "Took 2 min 5 s (CPU 7 min 1 s), read 1.5 KiB");
+    assert_eq!(usage.throughput(), 12);
+}
+
+#[test]
+fn test_throughput() {
+    let start = Instant::now();
+    let mut throughput = Throughput::new();
+    throughput.record_at(start, 0);
+    assert_eq!(throughput.bytes_per_sec(), None);
+    for second in 1..=20 {
+        // A slow start, then 100 bytes a second, which is all the window remembers.
+        throughput.record_at(start + Duration::from_secs(second), if second <= 5 { second } else { 5 + (second - 5) * 100 });
+    }
+    assert_eq!(throughput.bytes_per_sec(), Some(100));
+    assert_eq!(throughput.time_left(1000), Some(Duration::from_secs(10)));
+    throughput.record_at(start + Duration::from_secs(40), 1505);
+    assert_eq!(throughput.time_left(1000), None);
+}
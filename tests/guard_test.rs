@@ -0,0 +1,20 @@
+use file_deduplicator::guard::{self, Conflict, ScanGuard};
+use std::path::Path;
+
+#[test]
+fn test_overlapping_scans_are_refused() {
+    assert!(guard::overlaps(Path::new("a/b"), Path::new("a")));
+    assert!(guard::overlaps(Path::new("a"), Path::new("a/b")));
+    assert!(!guard::overlaps(Path::new("a/b"), Path::new("a/bc")));
+
+    let outer = ScanGuard::claim(Path::new("guard/a")).expect("Failed to claim a free scope");
+    let sibling = ScanGuard::claim(Path::new("guard/b")).expect("Failed to claim a disjoint scope");
+    match ScanGuard::claim(Path::new("guard/a/inner")) {
+        Err(Conflict::Running { running, .. }) => assert_eq!(running, outer.root()),
+        other => panic!("Expected a conflict, got {:?}", other),
+    }
+    assert!(ScanGuard::claim(Path::new("guard")).is_err());
+    drop(outer);
+    drop(sibling);
+    let _inner = ScanGuard::claim(Path::new("guard/a/inner")).expect("Scope was not released");
+}
@@ -0,0 +1,11 @@
+use file_deduplicator::help;
+
+#[test]
+fn test_search_topics() {
+    assert_eq!(help::search("").len(), help::TOPICS.len());
+    let titles = |query| help::search(query).iter().map(|topic| help::text(topic.title)).collect::<Vec<String>>();
+    assert_eq!(titles("Symbolic LINKS"), vec!["Filters"]);
+    assert_eq!(titles("  hard   link "), vec!["Actions"]);
+    assert_eq!(titles("reference"), vec!["Scanning", "Keep policies", "Safety"]);
+    assert!(titles("reference zebra").is_empty());
+}
@@ -0,0 +1,35 @@
+use file_deduplicator::i18n::{self, Catalog};
+use std::{fs, path::Path};
+
+#[test]
+fn test_i18n_catalog() {
+    let catalog = Catalog::new("en_US.UTF-8");
+    assert_eq!(catalog.language(), "en");
+    assert_eq!(Catalog::new("xx").language(), i18n::FALLBACK);
+    assert_eq!(catalog.format("cli-walking", &[("dirs", "/a and /b".into())]), "Walking /a and /b");
+    let done = catalog.format("tui-done", &[("done", "2".into()), ("files", "3".into()), ("action", "link".into())]);
+    assert_eq!(done, "2 of 3 files were linked");
+    assert_eq!(catalog.format("no-such-message", &[]), "no-such-message");
+}
+
+/// Every `tr!' in the applications names a message of the English catalog.
+#[test]
+fn test_i18n_messages_exist() {
+    fn sources(dir: &Path, found: &mut Vec<String>) {
+        for entry in fs::read_dir(dir).expect("Failed to list sources").flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                sources(&path, found);
+            } else if path.extension().is_some_and(|ext| ext == "rs") {
+                found.push(fs::read_to_string(&path).expect("Failed to read source"));
+            }
+        }
+    }
+    let mut found = Vec::new();
+    sources(Path::new("src/bin"), &mut found);
+    let catalog = Catalog::new("en");
+    let ids = found.iter().flat_map(|source| source.split("tr!(\"").skip(1)).filter_map(|rest| rest.split('"').next()).collect::<Vec<&str>>();
+    assert!(ids.len() > 100);
+    let missing = ids.into_iter().filter(|id| !catalog.has(id)).collect::<Vec<&str>>();
+    assert!(missing.is_empty(), "No messages for {:?}", missing);
+}
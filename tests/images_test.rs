@@ -0,0 +1,40 @@
+use file_deduplicator::{
+    images::{self, ImageConf, SimilarImages},
+    relate::{RelateConf, RelatedFiles, WalkInfo},
+};
+use image::{GrayImage, Luma};
+use std::{fs, path::Path, sync::mpsc};
+
+const TEST_DIR: &str = "scratch/images";
+
+fn picture(invert: bool) -> GrayImage {
+    GrayImage::from_fn(64, 48, |x, y| {
+        let shade = ((x * y) % 256) as u8;
+        Luma([if invert { 255 - shade } else { shade }])
+    })
+}
+
+#[test]
+fn test_recompressed_images_are_similar() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    fs::create_dir_all(TEST_DIR).expect("Failed to create test directory");
+    let dir = Path::new(TEST_DIR);
+    picture(false).save(dir.join("original.png")).expect("Failed to write image");
+    picture(false).save(dir.join("recompressed.jpg")).expect("Failed to write image");
+    fs::copy(dir.join("original.png"), dir.join("copy.PNG")).expect("Failed to copy image");
+    picture(true).save(dir.join("other.png")).expect("Failed to write image");
+    fs::write(dir.join("broken.jpg"), "not an image").expect("Failed to write file");
+    assert!(images::dhash(&dir.join("original.png")).is_ok());
+
+    let walk = WalkInfo::walk(dir.into());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let related = RelatedFiles::relate_sequential(&walk, &RelateConf::default(), progress_tx);
+    let similar = SimilarImages::find(&related, &ImageConf::default());
+    let names = similar.groups
+        .iter()
+        .map(|group| group.iter().map(|fi| fi.name.file_name().unwrap().to_str().unwrap()).collect::<Vec<&str>>())
+        .collect::<Vec<Vec<&str>>>();
+    assert_eq!(names, [["copy.PNG", "original.png", "recompressed.jpg"]]);
+    assert_eq!(similar.errors.len(), 1);
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
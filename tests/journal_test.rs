@@ -0,0 +1,119 @@
+use file_deduplicator::{
+    actions::{Action, DedupOptions, Permit, Plan, Step},
+    journal,
+};
+use std::{fs, path::Path, time::{Duration, SystemTime}};
+
+const TEST_DIR: &'static str = "scratch/journal";
+/// The SHA-256 of "hello", which every kept file holds.
+const HELLO: &'static str = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+
+fn step(action: Action, target: &Path, keep: &Path) -> Step {
+    Step {
+        action,
+        hash: HELLO.to_owned(),
+        target: target.to_path_buf(),
+        keep: keep.to_path_buf(),
+        size: 5,
+        allocated: 5,
+        modified: None,
+    }
+}
+
+#[test]
+fn test_journal_and_restore() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    let conf_dir = dir.join("conf");
+    fs::create_dir_all(dir.join("sub")).expect("Failed to create test directory");
+    let (keep, deleted, linked, missing) = (dir.join("keep"), dir.join("sub/deleted"), dir.join("linked"), dir.join("missing"));
+    for file in [&keep, &deleted, &linked] {
+        fs::write(file, "hello").expect("Failed to write file");
+    }
+
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let plan = Plan {
+        steps: vec![
+            step(Action::Delete, &deleted, &keep),
+            step(Action::Delete, &missing, &keep),
+            step(Action::HardLink, &linked, &keep),
+        ],
+    };
+    let (outcomes, journaled) = journal::execute(&conf_dir, dir, &plan, &DedupOptions::default(), &permit);
+    journaled.expect("Failed to write the journal");
+    assert_eq!(outcomes.iter().filter(|outcome| outcome.result.is_ok()).count(), 2);
+    let entries = journal::entries(&conf_dir).expect("Failed to read the journal");
+    assert_eq!(entries.iter().map(|entry| &entry.original).collect::<Vec<_>>(), [&deleted, &linked]);
+    assert!(!deleted.exists());
+
+    fs::remove_dir_all(dir.join("sub")).expect("Failed to remove directory");
+    journal::restore(&conf_dir, &entries[0], &permit).expect("Failed to restore a deleted file");
+    assert_eq!(fs::read_to_string(&deleted).expect("Restored file is missing"), "hello");
+    journal::restore(&conf_dir, &entries[1], &permit).expect("Failed to restore a linked file");
+    fs::write(&linked, "changed").expect("Failed to write file");
+    assert_eq!(fs::read_to_string(&keep).expect("Kept file is missing"), "hello", "The restored file is still linked");
+    assert!(journal::entries(&conf_dir).expect("Failed to read the journal").is_empty());
+
+    let (_, journaled) = journal::execute(&conf_dir, dir, &Plan { steps: vec![step(Action::Delete, &deleted, &keep)] }, &DedupOptions::default(), &permit);
+    journaled.expect("Failed to write the journal");
+    fs::write(&deleted, "other").expect("Failed to write file");
+    let entries = journal::entries(&conf_dir).expect("Failed to read the journal");
+    assert!(journal::restore(&conf_dir, &entries[0], &permit).is_err(), "Restoring over a new file should be refused");
+    assert_eq!(fs::read_to_string(&deleted).expect("File is missing"), "other");
+
+    fs::remove_file(&deleted).expect("Failed to remove file");
+    fs::write(&keep, "changed").expect("Failed to write file");
+    assert!(journal::restore(&conf_dir, &entries[0], &permit).is_err(), "Restoring from a changed copy should be refused");
+    assert!(!deleted.exists());
+
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
+
+#[test]
+fn test_interrupted_run() {
+    let dir = Path::new("scratch/journal_run");
+    let _ = fs::remove_dir_all(dir);
+    let data_dir = dir.join("data");
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (keep, first, second) = (dir.join("keep"), dir.join("first"), dir.join("second"));
+    for file in [&keep, &first, &second] {
+        fs::write(file, "hello").expect("Failed to write file");
+    }
+    let permit = Permit::unless_auditing(false).expect("Changes should be allowed outside audit mode");
+    let started = SystemTime::now() - Duration::from_secs(1);
+    let (first_step, second_step) = (step(Action::Delete, &first, &keep), step(Action::Delete, &second, &keep));
+    let (_, journaled) = journal::execute(&data_dir, dir, &Plan { steps: vec![first_step.clone()] }, &DedupOptions::default(), &permit);
+    journaled.expect("Failed to write the journal");
+    assert_eq!(journal::interrupted(&data_dir).expect("Failed to read the run"), None, "A finished run is left over");
+
+    // As if the power failed after the first step of two.
+    let run = journal::Run { project: dir.to_path_buf(), started, steps: vec![first_step, second_step.clone()] };
+    fs::write(journal::run_path(&data_dir), serde_json::to_vec(&run).unwrap()).expect("Failed to write the run");
+    let found = journal::interrupted(&data_dir).expect("Failed to read the run").expect("The run wasn't found");
+    let entries = journal::entries(&data_dir).expect("Failed to read the journal");
+    let plan = Plan { steps: vec![second_step] };
+    assert_eq!(found.remaining(&entries), plan.steps);
+    assert!(journal::execute(&data_dir, dir, &plan, &DedupOptions::default(), &permit).1.is_err(), "Started over an interrupted run");
+    assert!(second.exists());
+
+    let (outcomes, journaled) = journal::resume(&data_dir, &found, &DedupOptions::default(), &permit);
+    journaled.expect("Failed to write the journal");
+    assert_eq!(outcomes.iter().map(|outcome| &outcome.step.target).collect::<Vec<_>>(), [&second]);
+    assert!(!second.exists());
+    assert_eq!(journal::interrupted(&data_dir).expect("Failed to read the run"), None);
+
+    fs::write(journal::run_path(&data_dir), serde_json::to_vec(&run).unwrap()).expect("Failed to write the run");
+    fs::write(&second, "taken").expect("Failed to write file");
+    let restored = journal::roll_back(&data_dir, &run, &permit).expect("Failed to roll back");
+    assert_eq!(restored.iter().map(|(entry, _)| &entry.original).collect::<Vec<_>>(), [&second, &first]);
+    assert!(restored[0].1.is_err() && restored[1].1.is_ok());
+    assert!(journal::interrupted(&data_dir).expect("Failed to read the run").is_some(), "The run ended with a step left");
+
+    fs::remove_file(&second).expect("Failed to remove file");
+    let restored = journal::roll_back(&data_dir, &run, &permit).expect("Failed to roll back");
+    assert_eq!(restored.iter().map(|(entry, _)| &entry.original).collect::<Vec<_>>(), [&second]);
+    assert!(restored.iter().all(|(_, result)| result.is_ok()));
+    assert!(first.exists() && second.exists());
+    assert_eq!(journal::interrupted(&data_dir).expect("Failed to read the run"), None);
+    let _ = fs::remove_dir_all(dir);
+}
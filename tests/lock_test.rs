@@ -0,0 +1,39 @@
+use file_deduplicator::lock::{self, Lock};
+use std::{fs, path::Path, process::Command};
+
+const LOCK_DIR: &'static str = "scratch/locks";
+
+#[test]
+#[cfg(unix)]
+fn test_held_and_stale_locks() {
+    let _ = fs::remove_dir_all(LOCK_DIR);
+    let path = Path::new(LOCK_DIR).join("test.lock");
+
+    let lock = Lock::acquire(&path).expect("Failed to take a free lock");
+    assert_eq!(lock::holder(&path), Some(std::process::id()));
+    drop(lock);
+    assert!(!path.exists(), "Dropping a lock should remove it.");
+
+    // A lock held by a live process is refused.
+    let mut child = Command::new("sleep").arg("10").spawn().expect("Failed to spawn a lock holder");
+    fs::write(&path, child.id().to_string()).expect("Failed to write lock");
+    match Lock::acquire(&path) {
+        Err(lock::Error { error_type: lock::ErrorType::Held(pid), .. }) => assert_eq!(pid, child.id()),
+        other => panic!("Expected the lock to be held, got {:?}", other),
+    }
+
+    // Once the holder is gone the lock is stale and can be recovered.
+    let _ = child.kill();
+    let _ = child.wait();
+    let lock = Lock::acquire(&path).expect("Failed to recover a stale lock");
+    assert_eq!(lock::holder(lock.path()), Some(std::process::id()));
+
+    // Focus requests are delivered once.
+    assert!(!lock::take_focus_request(&path));
+    lock::request_focus(&path).expect("Failed to request focus");
+    assert!(lock::take_focus_request(&path));
+    assert!(!lock::take_focus_request(&path));
+
+    drop(lock);
+    let _ = fs::remove_dir_all(LOCK_DIR);
+}
@@ -0,0 +1,57 @@
+use file_deduplicator::{
+    manifest::{self, ErrorType, Format},
+    relate::{HashAlgo, RelateConf, RelatedFiles, WalkInfo},
+};
+use std::{fs, io, path::Path, sync::mpsc};
+
+const TEST_DIR: &'static str = "scratch/manifest";
+
+#[test]
+fn test_indexes_round_trip() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir.join("sub")).expect("Failed to create test directory");
+    for (name, contents) in [("a.txt", "same"), ("sub/b.txt", "same"), ("back\\slash.txt", "same"), ("c.txt", "other")] {
+        fs::write(dir.join(name), contents).expect("Failed to write file");
+    }
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let conf = RelateConf { hash_algo: HashAlgo::Blake3, ..Default::default() };
+    let related = RelatedFiles::relate_sequential(&WalkInfo::walk(dir.into()), &conf, progress_tx);
+
+    let mut checksums = Vec::new();
+    manifest::write(&mut checksums, &related, Format::Checksums).expect("Failed to write checksums");
+    let text = String::from_utf8(checksums.clone()).unwrap();
+    assert_eq!(text.lines().count(), 4);
+    assert!(text.lines().any(|line| line.starts_with('\\') && line.ends_with("back\\\\slash.txt")), "{:}", text);
+    let read = manifest::read(&checksums[..], Path::new("SUMS"), Format::Checksums, HashAlgo::Blake3, Path::new("")).expect("Failed to read checksums");
+    assert_eq!((read.files, read.algo), (related.files.clone(), HashAlgo::Blake3));
+
+    let mut sets = Vec::new();
+    manifest::write(&mut sets, &related, Format::Fdupes).expect("Failed to write sets");
+    assert_eq!(String::from_utf8(sets.clone()).unwrap().lines().filter(|line| line.is_empty()).count(), 1);
+    let read = manifest::read(&sets[..], Path::new("sets"), Format::Fdupes, HashAlgo::Blake3, Path::new("")).expect("Failed to read sets");
+    assert_eq!(read.duplicate_groups().len(), 1);
+    assert_eq!(read.duplicate_groups()[0].files, related.duplicate_groups()[0].files);
+
+    // A stale set, whose files no longer all hold the same.
+    fs::write(dir.join("sub/b.txt"), "edited").expect("Failed to write file");
+    let read = manifest::read(&sets[..], Path::new("sets"), Format::Fdupes, HashAlgo::Blake3, Path::new("")).expect("Failed to read sets");
+    assert_eq!(read.duplicate_groups().len(), 1);
+    assert!(read.duplicate_groups()[0].files.iter().all(|fi| !fi.name.ends_with("b.txt")), "An edited file was kept with its old set");
+
+    // Relative to the folder holding the manifest, in binary mode, and with a file that has gone since.
+    let sums = format!("{:}  a.txt\n{:} *sub/b.txt\n{:}  gone.txt\n", "ab".repeat(32), "ab".repeat(32), "cd".repeat(32));
+    let read = manifest::read(io::Cursor::new(sums), Path::new("SUMS"), Format::Checksums, HashAlgo::Sha256, dir).expect("Failed to read checksums");
+    assert_eq!(read.files[&"ab".repeat(32)].len(), 2);
+    assert_eq!(read.errors.len(), 1);
+    let malformed = manifest::read(io::Cursor::new("not a checksum\n"), Path::new("SUMS"), Format::Checksums, HashAlgo::Sha256, dir);
+    assert!(matches!(malformed.map(|_| ()).unwrap_err().error_type, ErrorType::Malformed(1)));
+    // Hashes of another algorithm, as long as md5's for sha256, or sha256's for xxh128.
+    let md5 = format!("{:}  a.txt\n", "ab".repeat(16));
+    let read = manifest::read(io::Cursor::new(md5), Path::new("SUMS"), Format::Checksums, HashAlgo::Sha256, dir);
+    assert!(matches!(read.map(|_| ()).unwrap_err().error_type, ErrorType::Malformed(1)));
+    let sha256 = format!("{:}  a.txt\n", "ab".repeat(32));
+    let read = manifest::read(io::Cursor::new(sha256), Path::new("SUMS"), Format::Checksums, HashAlgo::Xxh128, dir);
+    assert!(matches!(read.map(|_| ()).unwrap_err().error_type, ErrorType::Malformed(1)));
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
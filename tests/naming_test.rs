@@ -0,0 +1,58 @@
+use file_deduplicator::{naming, relate::{FileInfo, HashAlgo, RelatedFiles}};
+use std::{collections::HashMap, path::Path, time::{Duration, SystemTime}};
+
+fn info(name: &str) -> FileInfo {
+    FileInfo {
+        name: Path::new(name).into(),
+        size: 10,
+        created: Some(SystemTime::UNIX_EPOCH),
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    }
+}
+
+#[test]
+fn test_mislabeled_copy_is_flagged() {
+    let group = [
+        info("music/Artist - Song.mp3"),
+        info("backup/Artist - Song (2).mp3"),
+        info("phone/Copy of artist - song.MP3"),
+        info("phone/track07.mp3"),
+    ];
+    let mislabels = naming::mislabeled(&group);
+    assert_eq!(mislabels.len(), 1);
+    assert_eq!(mislabels[0].file.name.to_str(), Some("phone/track07.mp3"));
+    assert_eq!(mislabels[0].suggested, "Artist - Song.mp3");
+}
+
+#[test]
+fn test_no_agreement_no_flags() {
+    let group = [info("a/holiday.jpg"), info("b/IMG_0001.jpg")];
+    assert!(naming::mislabeled(&group).is_empty());
+    assert!(naming::similarity("IMG_0001", "IMG_0002") > 0.5);
+}
+
+#[test]
+fn test_same_name_different_contents() {
+    let mut related = RelatedFiles { algo: HashAlgo::Blake3, files: HashMap::new(), partial: false, errors: Vec::new() };
+    let newer = FileInfo { modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(60)), ..info("laptop/notes.txt") };
+    for (hash, fi) in [
+        ("a", info("backup/notes.txt")),
+        ("a", info("old/notes.txt")),
+        ("b", newer),
+        ("c", info("backup/todo.txt")),
+        ("c", info("laptop/todo.txt")),
+        ("d", info("laptop/Notes.txt")),
+    ] {
+        related.files.entry(hash.to_owned()).or_default().insert(fi);
+    }
+    let collisions = naming::collisions(&related);
+    assert_eq!(collisions.len(), 1);
+    assert_eq!(collisions[0].name, "notes.txt");
+    assert_eq!(collisions[0].versions.iter().map(|version| version.hash.as_str()).collect::<Vec<_>>(), ["b", "a"]);
+    let paths = collisions[0].versions[1].files.iter().map(|fi| fi.name.to_str().unwrap()).collect::<Vec<_>>();
+    assert_eq!(paths, ["backup/notes.txt", "old/notes.txt"]);
+}
@@ -0,0 +1,27 @@
+use std::path::{Path, PathBuf};
+use file_deduplicator::paths;
+
+#[test]
+fn test_normalize() {
+    for (path, normal) in [
+        ("/a//b/./c/", "/a/b/c"),
+        ("/a/b/../c", "/a/c"),
+        ("/../a", "/a"),
+        ("a/../../b", "../b"),
+        ("./a/..", "."),
+        ("", ""),
+    ] {
+        assert_eq!(paths::normalize(Path::new(path)), PathBuf::from(normal), "{:}", path);
+    }
+    assert!(paths::same(Path::new("/a/b"), Path::new("/a/./c/../b/")));
+    assert!(paths::starts_with(Path::new("/a/b/../b/c"), Path::new("/a//b")));
+    assert!(!paths::starts_with(Path::new("/a/bc"), Path::new("/a/b")));
+    assert_eq!(paths::same(Path::new("/A/b"), Path::new("/a/B")), paths::CASE_INSENSITIVE);
+}
+
+#[test]
+fn test_distinct_roots() {
+    let roots = ["/b", "/a/c", "/a//", "/b/", "/d"].map(PathBuf::from);
+    assert_eq!(paths::distinct_roots(&roots), ["/b", "/a", "/d"].map(PathBuf::from));
+    assert!(paths::distinct_roots(&[]).is_empty());
+}
@@ -0,0 +1,50 @@
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+#[test]
+fn test_file_uri() {
+    use file_deduplicator::platform;
+    use std::path::Path;
+    assert_eq!(platform::file_uri(Path::new("/home/me/a-b_c.~1")), "file:///home/me/a-b_c.~1");
+    assert_eq!(platform::file_uri(Path::new("/tmp/one, two/ü%.txt")), "file:///tmp/one%2C%20two/%C3%BC%25.txt");
+}
+
+#[test]
+fn test_plain_windows_paths() {
+    use file_deduplicator::platform;
+    use std::path::Path;
+    let plain = |path: &str| platform::plain_path(Path::new(path)).to_string_lossy().into_owned();
+    assert_eq!(plain(r"\\?\C:\Users\me\Photos"), r"C:\Users\me\Photos");
+    assert_eq!(plain(r"\\?\UNC\server\share\Photos"), r"\\server\share\Photos");
+    assert_eq!(plain(r"C:\Users\me"), r"C:\Users\me");
+    // Only the prefix allows these, so it stays.
+    assert_eq!(plain(r"\\?\C:\Users\me\nul.txt"), r"\\?\C:\Users\me\nul.txt");
+    assert_eq!(plain(r"\\?\C:\Users\me\dots..."), r"\\?\C:\Users\me\dots...");
+    assert_eq!(plain(r"\\?\Volume{12345678}\Photos"), r"\\?\Volume{12345678}\Photos");
+    let long = format!(r"\\?\C:\{:}", "a".repeat(300));
+    assert_eq!(plain(&long), long);
+
+    assert!(platform::network_share(Path::new(r"\\server\share\file")));
+    assert!(platform::network_share(Path::new(r"\\?\UNC\server\share\file")));
+    assert!(!platform::network_share(Path::new(r"\\?\C:\file")) && !platform::network_share(Path::new(r"C:\file")));
+}
+
+#[test]
+fn test_set_times() {
+    use file_deduplicator::platform;
+    use std::{fs, path::Path, time::{Duration, SystemTime}};
+    const TIMES_DIR: &str = "scratch/platform_times";
+    let _ = fs::remove_dir_all(TIMES_DIR);
+    let dir = Path::new(TIMES_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    let (old, clone) = (dir.join("old"), dir.join("clone"));
+    fs::write(&old, "hello").expect("Failed to write file");
+    fs::write(&clone, "hello").expect("Failed to write file");
+    let then = SystemTime::UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+    fs::File::options().write(true).open(&old).and_then(|file| file.set_modified(then)).expect("Failed to set modification time");
+    let mut permissions = fs::metadata(&clone).expect("Failed to read metadata").permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&clone, permissions).expect("Failed to make file read-only");
+
+    platform::set_times(&clone, &fs::metadata(&old).expect("Failed to read metadata")).expect("Failed to set times");
+    assert_eq!(fs::metadata(&clone).and_then(|metadata| metadata.modified()).expect("Failed to read metadata"), then);
+    let _ = fs::remove_dir_all(TIMES_DIR);
+}
@@ -0,0 +1,152 @@
+use exif::{experimental::Writer, Field, In, Tag, Value};
+use file_deduplicator::{
+    photo,
+    policy::{KeepPolicy, Resolver},
+    relate::FileInfo,
+};
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+fn info(name: &str, age: u64) -> FileInfo {
+    FileInfo {
+        name: Path::new(name).into(),
+        size: 10,
+        created: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(age)),
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    }
+}
+
+fn group() -> HashSet<FileInfo> {
+    [
+        info("data/abc/def/copy.txt", 3),
+        info("data/b.txt", 2),
+        info("data/abc/original.txt", 1),
+        info("data/zzz/a.txt", 4),
+    ].into_iter().collect()
+}
+
+fn survivor(policy: KeepPolicy) -> String {
+    let group = group();
+    let resolver = Resolver::new(policy).expect("Failed to compile policy");
+    let res = resolver.resolve(&group).expect("Group should not be empty");
+    assert_eq!(res.remove.len(), group.len() - 1);
+    assert!(!res.remove.contains(&res.keep));
+    res.keep.name.to_str().unwrap().to_owned()
+}
+
+#[test]
+fn test_time_policies() {
+    assert_eq!(survivor(KeepPolicy::OldestCreated), "data/abc/original.txt");
+    assert_eq!(survivor(KeepPolicy::NewestCreated), "data/zzz/a.txt");
+}
+
+#[test]
+fn test_time_policies_without_creation_times() {
+    let mut group = group();
+    let modified = |name: &str, age: u64| FileInfo { created: None, modified: Some(SystemTime::UNIX_EPOCH + Duration::from_secs(age)), ..info(name, 0) };
+    group.insert(modified("data/old.txt", 0));
+    group.insert(modified("data/new.txt", 5));
+    group.insert(FileInfo { created: None, ..info("data/unknown.txt", 0) });
+    let survivor = |policy| Resolver::new(policy).expect("Failed to compile policy").survivor(&group).map(|fi| fi.name.clone());
+    assert_eq!(survivor(KeepPolicy::OldestCreated), Some(Path::new("data/old.txt").into()));
+    assert_eq!(survivor(KeepPolicy::NewestCreated), Some(Path::new("data/new.txt").into()));
+}
+
+#[test]
+fn test_path_policies() {
+    assert_eq!(survivor(KeepPolicy::ShortestPath), "data/b.txt");
+    assert_eq!(survivor(KeepPolicy::ShallowestDirectory), "data/b.txt");
+    assert_eq!(survivor(KeepPolicy::Alphabetical), "data/abc/def/copy.txt");
+}
+
+#[test]
+fn test_regex_priority() {
+    let policy = KeepPolicy::RegexPriority(vec!["/zzz/".to_owned(), "original".to_owned()]);
+    assert_eq!(survivor(policy), "data/zzz/a.txt");
+    let policy = KeepPolicy::RegexPriority(vec!["nothing".to_owned(), "original".to_owned()]);
+    assert_eq!(survivor(policy), "data/abc/original.txt");
+    assert!(Resolver::new(KeepPolicy::RegexPriority(vec!["(".to_owned()])).is_err());
+}
+
+#[test]
+fn test_sparse_copy_is_kept() {
+    let mut group = group();
+    let mut sparse = info("data/zzz/sparse.txt", 5);
+    sparse.allocated = Some(0);
+    group.insert(sparse);
+    let resolver = Resolver::new(KeepPolicy::OldestCreated).expect("Failed to compile policy");
+    assert_eq!(resolver.resolve(&group).expect("Group should not be empty").keep.name.to_str(), Some("data/zzz/sparse.txt"));
+    let resolver = Resolver::new(KeepPolicy::RegexPriority(vec!["original".to_owned()])).expect("Failed to compile policy");
+    assert_eq!(resolver.resolve(&group).expect("Group should not be empty").keep.name.to_str(), Some("data/abc/original.txt"));
+}
+
+#[test]
+fn test_reference_copies_are_never_removed() {
+    let group = group();
+    let resolver = Resolver::new(KeepPolicy::NewestCreated).expect("Failed to compile policy").with_reference("data/abc".into());
+    let res = resolver.resolve(&group).expect("Group should not be empty");
+    assert!(res.keep.name.starts_with("data/abc"));
+    let removed = res.remove.iter().map(|fi| fi.name.to_str().unwrap()).collect::<Vec<&str>>();
+    assert_eq!(removed, vec!["data/b.txt", "data/zzz/a.txt"]);
+}
+
+#[test]
+fn test_archive_members_are_never_removed() {
+    let mut group = group();
+    let mut member = info("data/old.zip/b.txt", 0);
+    member.archive = Some(Path::new("data/old.zip").into());
+    group.insert(member.clone());
+    let resolver = Resolver::new(KeepPolicy::OldestCreated).expect("Failed to compile policy");
+    let res = resolver.resolve(&group).expect("Group should not be empty");
+    assert_eq!(res.keep.name.to_str(), Some("data/abc/original.txt"));
+    assert!(!res.remove.contains(&&member));
+    assert_eq!(res.remove.len(), 3);
+
+    let res = resolver.resolve_against_archives(&group).expect("Group has an archive member");
+    assert_eq!(res.keep, &member);
+    assert_eq!(res.remove.len(), 4);
+    group.remove(&member);
+    assert!(resolver.resolve_against_archives(&group).is_none());
+}
+
+/// A TIFF photo without pixels, taken at `captured' as EXIF puts it.
+fn photo(path: &Path, captured: &str) {
+    let field = Field { tag: Tag::DateTimeOriginal, ifd_num: In::PRIMARY, value: Value::Ascii(vec![captured.into()]) };
+    let mut writer = Writer::new();
+    writer.push_field(&field);
+    let mut tiff = io::Cursor::new(Vec::new());
+    writer.write(&mut tiff, true).expect("Failed to write EXIF");
+    fs::create_dir_all(path.parent().unwrap()).expect("Failed to create test directory");
+    fs::write(path, tiff.into_inner()).expect("Failed to write photo");
+}
+
+#[test]
+fn test_capture_date_policies() {
+    const TEST_DIR: &str = "scratch/photos";
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    let paths = ["a/copy.tif", "a/IMG_20230514_101500.tif", "b/2023/05 May/z.tif", "c/2023-05-14 trip/z.tif"].map(|name| dir.join(name));
+    for path in &paths {
+        photo(path, "2023:05:14 10:15:00");
+    }
+    let photo_info = photo::read(&paths[0]).expect("Failed to read EXIF");
+    assert_eq!(photo_info.captured.map(|time| time.to_string()), Some("2023-05-14 10:15:00".to_owned()));
+    assert!(photo::in_dated_folder(&paths[3], photo_info.captured.unwrap().date()));
+    let group = paths.iter().map(|path| FileInfo { name: path.as_path().into(), ..info("", 0) }).collect::<HashSet<FileInfo>>();
+    let survivor = |policy| Resolver::new(policy).expect("Failed to compile policy").survivor(&group).map(|fi| fi.name.clone());
+    assert_eq!(survivor(KeepPolicy::CaptureDateName), Some(paths[1].as_path().into()));
+    assert_eq!(survivor(KeepPolicy::DatedFolder), Some(paths[2].as_path().into()));
+    fs::write(&paths[1], "not a photo").expect("Failed to write file");
+    fs::write(&paths[2], "not a photo").expect("Failed to write file");
+    // The date still comes from the copies that can be read.
+    assert_eq!(survivor(KeepPolicy::CaptureDateName), Some(paths[1].as_path().into()));
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
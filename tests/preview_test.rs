@@ -0,0 +1,36 @@
+use file_deduplicator::{
+    preview::{self, Content, THUMBNAIL_SIZE},
+    relate::WalkInfo,
+};
+use image::{GrayImage, Luma};
+use std::{fs, path::Path};
+
+const TEST_DIR: &str = "scratch/preview";
+
+#[test]
+fn test_preview() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    fs::create_dir_all(TEST_DIR).expect("Failed to create test directory");
+    let dir = Path::new(TEST_DIR);
+    GrayImage::from_fn(512, 128, |x, _| Luma([x as u8])).save(dir.join("wide.png")).expect("Failed to write image");
+    fs::write(dir.join("notes.txt"), "first\nsecond\n").expect("Failed to write file");
+    fs::write(dir.join("data.bin"), [0u8, 159, 146, 150]).expect("Failed to write file");
+    fs::write(dir.join("broken.jpg"), "not an image").expect("Failed to write file");
+
+    let walk = WalkInfo::walk(dir.into());
+    let content = |name: &str| {
+        let file = walk.files.iter().find(|fi| fi.name.ends_with(name)).expect("Walked the file");
+        preview::preview(file).content
+    };
+    match content("wide.png") {
+        Content::Image(thumbnail) => {
+            assert_eq!((thumbnail.width, thumbnail.height), (THUMBNAIL_SIZE, THUMBNAIL_SIZE / 4));
+            assert_eq!(thumbnail.rgba.len(), (thumbnail.width * thumbnail.height * 4) as usize);
+        },
+        other => panic!("Expected a thumbnail, got {:?}", other),
+    }
+    assert_eq!(content("notes.txt"), Content::Text(vec!["first".into(), "second".into()]));
+    assert_eq!(content("data.bin"), Content::Binary);
+    assert!(matches!(content("broken.jpg"), Content::Unreadable(_)));
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
@@ -0,0 +1,252 @@
+use file_deduplicator::{
+    actions::{Action, Outcome, Step},
+    persist,
+    policy::{KeepPolicy, Resolver},
+    project::{self, GroupStatus, Project},
+    relate::{FileInfo, HashAlgo, RelatedFiles, WalkInfo},
+    store::{GroupSummary, Store},
+};
+use std::{collections::{HashMap, HashSet}, fs, io, path::Path, time::{Duration, SystemTime}};
+
+const CONF_DIR: &'static str = "scratch/project_conf";
+
+#[test]
+fn test_save_list_and_resume() {
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+
+    let (projects, errors) = project::list(conf_dir).expect("Listing a missing projects directory should succeed");
+    assert!(projects.is_empty() && errors.is_empty());
+
+    let mut project = Project::open(conf_dir, "some/folder".into()).expect("Failed to start project");
+    project.decisions.remove.entry("abc".to_owned()).or_default().insert("some/folder/copy.txt".into());
+    project.preferences.action = Some(Action::HardLink);
+    project.preferences.keep = Some(KeepPolicy::RegexPriority(vec!["/originals/".to_owned()]));
+    project.save(conf_dir).expect("Failed to save project");
+    Project::open(conf_dir, "other/folder".into()).expect("Failed to start project");
+    // A project file of an earlier version, which can't be moved into the database.
+    fs::create_dir_all(project::projects_dir(conf_dir)).expect("Failed to create projects directory");
+    fs::write(project::projects_dir(conf_dir).join("broken.json"), "{").expect("Failed to write broken project");
+
+    let (projects, errors) = project::list(conf_dir).expect("Failed to list projects");
+    assert_eq!(projects.len(), 2);
+    assert_eq!(errors.len(), 1);
+    assert_eq!(projects[0].path, Path::new("other/folder"));
+    assert!(projects.iter().all(|summary| !summary.related));
+
+    let resumed = Project::open(conf_dir, "some/folder".into()).expect("Failed to resume project");
+    assert_eq!(resumed.decisions, project.decisions);
+    assert_eq!(resumed.preferences, project.preferences);
+    let loaded = Project::load(conf_dir, Path::new("some/folder")).expect("Failed to load project");
+    assert_eq!(loaded.expect("The project was not saved").decisions, project.decisions);
+    assert!(Project::load(conf_dir, Path::new("unknown/folder")).expect("Failed to look for a project").is_none());
+
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
+
+#[test]
+fn test_legacy_projects_are_imported() {
+    const CONF_DIR: &'static str = "scratch/project_import_conf";
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+    fs::create_dir_all(project::projects_dir(conf_dir)).expect("Failed to create projects directory");
+
+    let mut project = Project::new("some/folder".into());
+    let file = project.legacy_file(conf_dir);
+    persist::write_atomic(&file, &serde_json::to_vec(&project).expect("Failed to write project")).expect("Failed to save project");
+    project.decisions.remove.entry("abc".to_owned()).or_default().insert("some/folder/copy.txt".into());
+    persist::write_atomic(&file, &serde_json::to_vec(&project).expect("Failed to write project")).expect("Failed to save project");
+    // A save cut short, and a file damaged since the last good save.
+    fs::write(persist::temp_path(&file), "{\"pa").expect("Failed to write partial project");
+    fs::write(&file, "{\"pa").expect("Failed to damage project");
+
+    let loaded = Project::load(conf_dir, Path::new("some/folder")).expect("Failed to import project");
+    let loaded = loaded.expect("The project file was not imported");
+    assert!(loaded.decisions.remove.is_empty(), "Recovered the damaged save rather than the one before it");
+    assert!(!persist::temp_path(&file).exists());
+    assert!(!file.exists(), "The project file will be imported again");
+    let (projects, errors) = project::list(conf_dir).expect("Failed to list projects");
+    assert_eq!((projects.len(), errors.len()), (1, 0));
+
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
+
+#[test]
+fn test_scan_results_round_trip() {
+    const CONF_DIR: &'static str = "scratch/project_store_conf";
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+    let info = |name: &str, file_id| FileInfo {
+        name: Path::new(name).into(),
+        size: 5,
+        created: Some(SystemTime::UNIX_EPOCH - Duration::from_secs(60)),
+        modified: Some(SystemTime::UNIX_EPOCH + Duration::from_nanos(1_700_000_000_123_456_789)),
+        allocated: Some(4096),
+        link_target: None,
+        file_id,
+        archive: None,
+    };
+
+    let mut project = Project::open(conf_dir, "some/folder".into()).expect("Failed to start project");
+    project.extra_roots = vec!["backup/folder".into(), "elsewhere".into()];
+    let (a, b, c) = (info("some/folder/a", Some((1, u64::MAX))), info("some/folder/b", None), info("some/folder/c", None));
+    let link = FileInfo { link_target: Some("a".into()), ..info("some/folder/link", None) };
+    project.walk = Some(WalkInfo {
+        total_size: 15,
+        files: HashSet::from([a.clone(), b.clone(), c.clone()]),
+        links: HashSet::from([link]),
+        hard_links: vec![vec!["some/folder/a".into(), "some/folder/hard".into()]],
+        partial: false,
+        errors: Vec::new(),
+    });
+    // The trashed copy is gone from the group, but not from the walk.
+    let files = HashMap::from([("abc".to_owned(), HashSet::from([a, b])), ("def".to_owned(), HashSet::from([info("elsewhere/d", None)]))]);
+    project.related = Some(RelatedFiles { algo: HashAlgo::Blake3, files, partial: true, errors: Vec::new() });
+    project.decisions.done.entry("abc".to_owned()).or_default().insert("some/folder/c".into());
+    project.save(conf_dir).expect("Failed to save project");
+    // Saving again replaces the scan rather than adding to it.
+    project.save(conf_dir).expect("Failed to save project");
+
+    let loaded = Project::load(conf_dir, Path::new("some/folder")).expect("Failed to load project").expect("The project was not saved");
+    let (walk, loaded_walk) = (project.walk.as_ref().unwrap(), loaded.walk.as_ref().unwrap());
+    assert_eq!(
+        (&loaded_walk.files, &loaded_walk.links, &loaded_walk.hard_links, loaded_walk.total_size),
+        (&walk.files, &walk.links, &walk.hard_links, walk.total_size),
+    );
+    let (related, loaded_related) = (project.related.as_ref().unwrap(), loaded.related.as_ref().unwrap());
+    assert_eq!((&loaded_related.files, loaded_related.algo, loaded_related.partial), (&related.files, related.algo, related.partial));
+    assert_eq!(loaded.decisions, project.decisions);
+    assert_eq!(loaded.extra_roots, project.extra_roots);
+    let (projects, _) = project::list(conf_dir).expect("Failed to list projects");
+    assert!(projects[0].related);
+
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
+
+fn outcome(action: Action, target: &str, result: io::Result<()>) -> Outcome {
+    let step = Step {
+        action,
+        hash: "abc".to_owned(),
+        target: target.into(),
+        keep: "a".into(),
+        size: 5,
+        allocated: 5,
+        modified: None,
+    };
+    Outcome { step, result }
+}
+
+#[test]
+fn test_partly_resolved_groups() {
+    let mut project = Project::new("some/folder".into());
+    let group = ["a", "b", "c", "d", "e"].into_iter().map(|name| FileInfo {
+        name: Path::new(name).into(),
+        size: 5,
+        created: Some(SystemTime::UNIX_EPOCH),
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    });
+    let files = HashMap::from([("abc".to_owned(), group.collect())]);
+    project.related = Some(RelatedFiles { algo: HashAlgo::Sha256, files, partial: false, errors: Vec::new() });
+    project.decisions.remove.insert("abc".to_owned(), ["b", "c", "d"].into_iter().map(Into::into).collect());
+    assert_eq!(project.group_status("abc"), GroupStatus::Open);
+
+    // Only part of the selection was acted on.
+    project.record(&[
+        outcome(Action::Trash, "b", Ok(())),
+        outcome(Action::Trash, "c", Err(io::ErrorKind::PermissionDenied.into())),
+    ]);
+    assert_eq!(project.group_status("abc"), GroupStatus::Partial { done: 1, remaining: 4 });
+    assert_eq!(project.related.as_ref().unwrap().files["abc"].len(), 4, "The trashed copy is still in its group");
+    assert_eq!(project.decisions.remove["abc"].len(), 2, "The trashed copy is still marked");
+
+    project.record(&[outcome(Action::HardLink, "c", Ok(())), outcome(Action::HardLink, "d", Ok(()))]);
+    assert_eq!(project.group_status("abc"), GroupStatus::Partial { done: 3, remaining: 2 });
+    assert!(!project.decisions.remove.contains_key("abc"));
+    project.record(&[outcome(Action::Delete, "e", Ok(()))]);
+    assert_eq!(project.group_status("abc"), GroupStatus::Resolved);
+}
+
+#[test]
+fn test_decisions_saved_without_the_scan() {
+    const CONF_DIR: &'static str = "scratch/project_decisions_conf";
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+    let info = |name: &str| FileInfo {
+        name: Path::new(name).into(),
+        size: 5,
+        created: None,
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    };
+    let group = HashSet::from([info("a"), info("b"), info("c")]);
+
+    let mut project = Project::open(conf_dir, "some/folder".into()).expect("Failed to start project");
+    project.walk = Some(WalkInfo { total_size: 20, files: group.clone(), links: HashSet::new(), hard_links: Vec::new(), partial: false, errors: Vec::new() });
+    let files = HashMap::from([("abc".to_owned(), group), ("def".to_owned(), HashSet::from([info("d")]))]);
+    project.related = Some(RelatedFiles { algo: HashAlgo::Sha256, files, partial: false, errors: Vec::new() });
+    project.save(conf_dir).expect("Failed to save project");
+    let store = Store::open(conf_dir).expect("Failed to open store");
+    assert_eq!(store.groups(&project.path).expect("Failed to list groups"), [GroupSummary { hash: "abc".to_owned(), size: 5, copies: 3 }]);
+    assert_eq!(store.group(&project.path, "abc").expect("Failed to read group").len(), 3);
+    assert_eq!(store.hash_algo(&project.path).expect("Failed to read algorithm"), Some(HashAlgo::Sha256));
+
+    project.decisions.remove.insert("abc".to_owned(), ["b", "c"].into_iter().map(Into::into).collect());
+    project.save_decisions(conf_dir, &[]).expect("Failed to save decisions");
+    let unscanned = Project::open_unscanned(conf_dir, "some/folder".into(), &Default::default()).expect("Failed to open project");
+    assert!(unscanned.walk.is_none() && unscanned.related.is_none());
+    assert_eq!(unscanned.decisions, project.decisions);
+    let duplicates = Project::load_duplicates(conf_dir, Path::new("some/folder")).expect("Failed to load project").expect("The project was not saved");
+    assert!(duplicates.walk.is_none());
+    let related = duplicates.related.expect("The hashes were not read");
+    assert_eq!(related.files.keys().collect::<Vec<_>>(), ["abc"], "The file with contents of its own was read");
+    assert_eq!(related.algo, HashAlgo::Sha256);
+
+    let outcomes = [outcome(Action::Trash, "b", Ok(())), outcome(Action::HardLink, "c", Ok(()))];
+    project.record(&outcomes);
+    project.save_decisions(conf_dir, &outcomes).expect("Failed to save decisions");
+    let loaded = Project::load(conf_dir, Path::new("some/folder")).expect("Failed to load project").expect("The project was not saved");
+    assert_eq!(loaded.decisions, project.decisions);
+    assert_eq!(loaded.related.as_ref().unwrap().files, project.related.as_ref().unwrap().files, "The trashed copy is still in its group");
+    assert_eq!(loaded.walk.as_ref().unwrap().files.len(), 3, "The trashed copy left the walk");
+    assert_eq!(store.groups(&project.path).expect("Failed to list groups")[0].copies, 2);
+
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
+
+/// Marking as the terminal review does, one copy at a time with Space, and all but one with `a'.
+#[test]
+fn test_marking_spares_the_reference() {
+    let info = |name: &str| FileInfo {
+        name: Path::new(name).into(),
+        size: 5,
+        created: None,
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    };
+    let mut project = Project::new("some/folder".into());
+    project.set_reference(Some("some/reference".into()));
+    let (copy, kept) = (Path::new("some/folder/a"), Path::new("some/./reference/b"));
+
+    assert!(project.toggle_mark("abc", copy));
+    assert_eq!(project.decisions.remove["abc"], [copy.to_path_buf()].into());
+    assert!(project.toggle_mark("abc", copy));
+    assert!(project.decisions.remove.is_empty(), "A group with nothing marked is still listed");
+    assert!(!project.toggle_mark("abc", kept), "A copy in the reference folder was marked");
+    assert!(project.in_reference(kept) && project.decisions.remove.is_empty());
+
+    let files = HashMap::from([("abc".to_owned(), HashSet::from([info("some/folder/a"), info("some/./reference/b")]))]);
+    let related = RelatedFiles { algo: HashAlgo::Sha256, files, partial: false, errors: Vec::new() };
+    let resolver = Resolver::new(KeepPolicy::ShortestPath).expect("Only regexes can be invalid").with_reference("some/reference".into());
+    let resolved = resolver.resolve_all(&related);
+    assert_eq!(resolved[0].1.remove.iter().map(|fi| &*fi.name).collect::<Vec<&Path>>(), [copy]);
+}
@@ -0,0 +1,182 @@
+use file_deduplicator::{
+    actions::{Action, Outcome, Step},
+    analysis::{Analysis, FolderStats},
+    policy::{KeepPolicy, Reclaim, Resolver},
+    project::Project,
+    relate::{self, ErrorKind, FileInfo, HashAlgo, RelatedFiles},
+    report::{self, Report},
+};
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    path::Path,
+    time::SystemTime,
+};
+
+fn info(name: &str, size: u64) -> FileInfo {
+    FileInfo {
+        name: Path::new(name).into(),
+        size,
+        created: Some(SystemTime::UNIX_EPOCH),
+        modified: None,
+        allocated: None,
+        link_target: None,
+        file_id: None,
+        archive: None,
+    }
+}
+
+#[test]
+fn test_json_report() {
+    let mut project = Project::new("data".into());
+    assert!(Report::new(&project).is_none());
+    project.related = Some(RelatedFiles {
+        algo: HashAlgo::Blake3,
+        files: HashMap::from([
+            ("aaa".to_owned(), HashSet::from([info("data/a", 3), info("data/b", 3)])),
+            ("bbb".to_owned(), HashSet::from([info("data/c", 5)])),
+        ]),
+        partial: false,
+        errors: Vec::new(),
+    });
+
+    let mut out = Vec::new();
+    report::to_json(&mut out, &Report::new(&project).expect("Scanned project has no report")).expect("Failed to write report");
+    let json: serde_json::Value = serde_json::from_slice(&out).expect("Report is not valid JSON");
+    assert_eq!(json["path"], "data");
+    assert_eq!(json["algo"], "Blake3");
+    assert_eq!(json["partial"], false);
+    let groups = json["groups"].as_array().expect("Report has no groups");
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0]["hash"], "aaa");
+    assert_eq!(groups[0]["files"][1]["name"], "data/b");
+    assert_eq!(json["total_reclaimable"]["size"], 3);
+}
+
+#[test]
+fn test_reclaimable() {
+    let linked = |name: &str, id: u64| FileInfo { file_id: Some((1, id)), ..info(name, 10) };
+    let group = HashSet::from([linked("data/a", 1), linked("data/b", 1), linked("data/c", 2), linked("data/d", 2), linked("data/e", 3)]);
+    let resolver = Resolver::new(KeepPolicy::Alphabetical).expect("Only regexes can be invalid");
+    let res = resolver.resolve(&group).expect("Group has no survivor");
+    assert_eq!(res.keep.name.to_str(), Some("data/a"));
+    // b is the kept file under another name, and c and d are one file.
+    assert_eq!(res.reclaimable(), Reclaim { size: 20, allocated: 20 });
+}
+
+#[test]
+fn test_csv_report() {
+    let mut project = Project::new("data".into());
+    project.related = Some(RelatedFiles {
+        algo: HashAlgo::Sha256,
+        files: HashMap::from([
+            ("aaa".to_owned(), HashSet::from([info("data/a", 3), info("data/b,c", 3)])),
+            ("bbb".to_owned(), HashSet::from([info("data/d", 1), info("data/e", 1)])),
+        ]),
+        partial: false,
+        errors: Vec::new(),
+    });
+    project.decisions.remove.insert("aaa".to_owned(), ["data/b,c".into()].into());
+
+    let mut out = Vec::new();
+    report::to_csv(&mut out, &Report::new(&project).expect("Scanned project has no report")).expect("Failed to write report");
+    assert_eq!(String::from_utf8(out).expect("Report is not UTF-8"), concat!(
+        "group_id,hash,path,size,created,action\n",
+        "1,aaa,data/a,3,1970-01-01T00:00:00+00:00,keep\n",
+        "1,aaa,\"data/b,c\",3,1970-01-01T00:00:00+00:00,remove\n",
+        "2,bbb,data/d,1,1970-01-01T00:00:00+00:00,\n",
+        "2,bbb,data/e,1,1970-01-01T00:00:00+00:00,\n",
+    ));
+
+    let mut out = Vec::new();
+    report::to_fdupes(&mut out, &Report::new(&project).expect("Scanned project has no report")).expect("Failed to write report");
+    assert_eq!(String::from_utf8(out).expect("Report is not UTF-8"), "data/a\ndata/b,c\n\ndata/d\ndata/e\n\n");
+
+    let step = |target: &str, keep: &str| Step {
+        action: Action::HardLink, hash: "aaa".to_owned(), target: target.into(), keep: keep.into(), size: 3, allocated: 4096, modified: None,
+    };
+    let outcomes = [
+        Outcome { step: step("data/b,c", "data/a"), result: Ok(()) },
+        Outcome { step: step("data/d", "data/e"), result: Err(io::Error::other("in use, by \"x\"")) },
+        Outcome { step: step("data/f", "data/e"), result: Err(io::ErrorKind::ResourceBusy.into()) },
+    ];
+    let mut out = Vec::new();
+    report::outcomes_to_csv(&mut out, &outcomes).expect("Failed to write outcomes");
+    assert_eq!(String::from_utf8(out).expect("Outcomes are not UTF-8"), concat!(
+        "action,path,keep,size,allocated,outcome,error\n",
+        "link,\"data/b,c\",data/a,3,4096,done,\n",
+        "link,data/d,data/e,3,4096,failed,\"in use, by \"\"x\"\"\"\n",
+        "link,data/f,data/e,3,4096,skipped,resource busy\n",
+    ));
+}
+
+#[test]
+fn test_errors_by_kind() {
+    let missing = relate::hash_from_file_info(&info("no/such/file", 1), HashAlgo::default()).expect_err("File shouldn't exist");
+    assert_eq!(missing.code(), "io");
+    assert!(matches!(missing.error_type(), relate::ErrorType::IO(_)));
+    let source = std::error::Error::source(&missing).expect("Error has no source");
+    assert_eq!(missing.to_string(), format!("no/such/file: {:}", source));
+    let kinds = report::errors_by_kind([&missing]);
+    assert_eq!(kinds.keys().collect::<Vec<_>>(), [&ErrorKind::Vanished]);
+    let mut out = Vec::new();
+    report::write_errors(&mut out, [&missing]).expect("Failed to write errors");
+    assert!(String::from_utf8(out).unwrap().starts_with("Gone before it was read (1):\n  no/such/file: "));
+}
+
+#[test]
+fn test_duplication_by_top_folder() {
+    let related = RelatedFiles {
+        algo: HashAlgo::Sha256,
+        files: HashMap::from([
+            ("aaa".to_owned(), HashSet::from([info("data/Downloads/a", 10), info("data/Downloads/old/a", 10), info("data/Pictures/a", 10)])),
+            ("bbb".to_owned(), HashSet::from([info("data/Pictures/b", 4), info("data/Pictures/c", 4)])),
+            ("ccc".to_owned(), HashSet::from([info("data/d", 1), info("data/Downloads/d", 1)])),
+            ("ddd".to_owned(), HashSet::from([info("data/Music/e", 100)])),
+        ]),
+        partial: false,
+        errors: Vec::new(),
+    };
+
+    let folders = related.by_top_folder("data".as_ref());
+    let summary = folders
+        .iter()
+        .map(|folder| (folder.folder.to_str().unwrap(), folder.wasted, folder.files, folder.groups.len()))
+        .collect::<Vec<_>>();
+    assert_eq!(summary, [
+        ("data/Downloads", 21, 3, 2),
+        ("data/Pictures", 14, 3, 2),
+        ("data", 1, 1, 1),
+    ]);
+}
+
+#[test]
+fn test_duplication_by_folder() {
+    let related = RelatedFiles {
+        algo: HashAlgo::Sha256,
+        files: HashMap::from([
+            ("aaa".to_owned(), HashSet::from([info("data/Downloads/a", 10), info("data/Downloads/old/a", 10), info("data/Pictures/a", 10)])),
+            ("bbb".to_owned(), HashSet::from([info("data/Pictures/b", 4), info("data/Pictures/c", 4)])),
+            ("ccc".to_owned(), HashSet::from([info("data/d", 1), info("other/d", 1)])),
+            ("ddd".to_owned(), HashSet::from([info("data/Music/e", 100)])),
+        ]),
+        partial: false,
+        errors: Vec::new(),
+    };
+
+    let analysis = Analysis::new(&related, "data".as_ref());
+    let summary = |folders: Vec<&FolderStats>| {
+        folders.iter().map(|stats| (stats.folder.to_str().unwrap().to_owned(), stats.wasted, stats.duplicates, stats.groups)).collect::<Vec<_>>()
+    };
+    assert_eq!(summary(analysis.most_wasteful()), [
+        ("data".to_owned(), 25, 6, 3),
+        ("data/Downloads".to_owned(), 20, 2, 1),
+        ("data/Pictures".to_owned(), 14, 3, 2),
+        ("data/Downloads/old".to_owned(), 10, 1, 1),
+        ("other".to_owned(), 1, 1, 1),
+    ]);
+    assert_eq!(summary(analysis.children("data".as_ref())), summary(analysis.most_wasteful()[1..3].to_vec()));
+    assert!(analysis.has_children("data/Downloads".as_ref()));
+    assert!(!analysis.has_children("data/Pictures".as_ref()));
+    assert_eq!(analysis.get("data/Music".as_ref()), None);
+}
@@ -0,0 +1,54 @@
+use file_deduplicator::{
+    policy::{KeepPolicy, Resolver},
+    relate::{FileInfo, HashAlgo, RelatedFiles},
+    rules::{self, Rule, Target, Verb},
+};
+use std::{collections::{BTreeSet, HashMap}, path::{Path, PathBuf}};
+
+fn info(name: &str) -> FileInfo {
+    FileInfo { name: Path::new(name).into(), size: 10, created: None, modified: None, allocated: None, link_target: None, file_id: None, archive: None }
+}
+
+#[test]
+fn test_rules_round_trip() {
+    let rule = "prefer containing /my originals/".parse::<Rule>().expect("Failed to parse rule");
+    assert_eq!(rule, Rule { verb: Verb::Prefer, target: Target::Containing("/my originals/".to_owned()) });
+    assert_eq!(rule.to_string(), "prefer containing /my originals/");
+    assert!("mark under".parse::<Rule>().is_err());
+    assert!("drop under /tmp".parse::<Rule>().is_err());
+}
+
+#[test]
+fn test_rules_mark_copies() {
+    let mut related = RelatedFiles { algo: HashAlgo::Blake3, files: HashMap::new(), partial: false, errors: Vec::new() };
+    for (hash, name) in [
+        ("a", "/home/a.jpg"), ("a", "/mnt/backup/a.jpg"), ("a", "/mnt/backup/a.jpg.orig"),
+        ("b", "/mnt/backup/b.jpg"), ("b", "/mnt/backup/old/b.jpg"),
+        ("c", "/home/c.jpg"), ("c", "/home/originals/c.jpg"), ("c", "/mnt/backup/c.jpg"),
+    ] {
+        related.files.entry(hash.to_owned()).or_default().insert(info(name));
+    }
+    let rules = ["mark under /mnt/backup", "keep matching *.orig", "prefer containing /originals/"].map(|rule| rule.parse::<Rule>().unwrap());
+    let resolver = Resolver::new(KeepPolicy::Alphabetical).expect("Only regexes can be invalid");
+    let selected = rules::select(&related, &rules, &resolver).expect("Failed to select");
+    let paths = |names: &[&str]| names.iter().map(PathBuf::from).collect::<BTreeSet<PathBuf>>();
+    assert_eq!(selected["a"], paths(&["/mnt/backup/a.jpg"]));
+    // Marking every copy would leave none, so the one the policy keeps stays.
+    assert_eq!(selected["b"], paths(&["/mnt/backup/old/b.jpg"]));
+    assert_eq!(selected["c"], paths(&["/home/c.jpg", "/mnt/backup/c.jpg"]));
+}
+
+#[test]
+fn test_rules_under_relative_folders() {
+    let rule = "mark under ./backup/".parse::<Rule>().expect("Failed to parse rule");
+    assert_eq!(rule.target, Target::Under("backup".into()));
+    // Scanned paths are absolute, as the command line makes them.
+    let absolute = |name: &str| std::path::absolute(name).expect("Failed to make path absolute");
+    let mut related = RelatedFiles { algo: HashAlgo::Blake3, files: HashMap::new(), partial: false, errors: Vec::new() };
+    for name in ["photos/a.jpg", "backup/a.jpg", "backups/a.jpg"] {
+        related.files.entry("a".to_owned()).or_default().insert(info(absolute(name).to_str().unwrap()));
+    }
+    let resolver = Resolver::new(KeepPolicy::Alphabetical).expect("Only regexes can be invalid");
+    let selected = rules::select(&related, &[rule], &resolver).expect("Failed to select");
+    assert_eq!(selected["a"], BTreeSet::from([absolute("backup/a.jpg")]));
+}
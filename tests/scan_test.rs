@@ -0,0 +1,33 @@
+use file_deduplicator::{
+    dedupe::{self, Action, DedupOptions, KeepPolicy, Permit},
+    scan::{self, RelateConf, Strategy, WalkConf},
+};
+use std::{fs, path::Path, sync::mpsc};
+
+const TEST_DIR: &'static str = "scratch/facade";
+
+#[test]
+fn test_scan_and_plan_through_the_facade() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir.join("backup")).expect("Failed to create test directory");
+    for name in ["a.txt", "backup/a.txt", "b.log"] {
+        fs::write(dir.join(name), "contents").expect("Failed to write file");
+    }
+    let walk_conf = WalkConf { include: vec!["*.txt".to_owned()], ..Default::default() };
+    let conf = RelateConf { strategy: Strategy::Sequential, ..Default::default() };
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let found = scan::scan(&[dir.into(), dir.join("backup")], &walk_conf, &conf, progress_tx).expect("Failed to scan");
+    assert_eq!(found.related.duplicate_groups().len(), 1);
+    assert_eq!(found.related.duplicate_groups()[0].files.len(), 2, "The log was not filtered out, or a file was found twice");
+    assert_eq!(scan::duplicates(&[dir.into()]).duplicate_groups()[0].files.len(), 3);
+
+    let plan = dedupe::plan(&found.related, KeepPolicy::ShortestPath, Action::Delete).expect("Failed to plan");
+    let permit = Permit::unless_auditing(false).expect("Not auditing");
+    let outcomes = plan.execute(&DedupOptions { verify: true, dry_run: true }, &permit);
+    assert_eq!(outcomes.len(), 1);
+    assert_eq!(outcomes[0].step.target, dir.join("backup/a.txt"));
+    assert!(dir.join("backup/a.txt").exists(), "A dry run removed a file");
+    assert!(dedupe::plan(&found.related, KeepPolicy::RegexPriority(vec!["(".to_owned()]), Action::Delete).is_err());
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
@@ -0,0 +1,84 @@
+use file_deduplicator::{
+    actions::Action,
+    policy::KeepPolicy,
+    project::Project,
+    relate::{HashAlgo, Strategy, SymlinkPolicy, WalkFilter, WalkInfo},
+    settings::{Accent, Profile, Settings, Theme},
+};
+use std::{fs, path::Path};
+
+const CONF_DIR: &str = "scratch/settings";
+
+#[test]
+fn test_settings_round_trip() {
+    let _ = fs::remove_dir_all(CONF_DIR);
+    let conf_dir = Path::new(CONF_DIR);
+    fs::create_dir_all(conf_dir).expect("Failed to create test directory");
+    assert_eq!(Settings::load(conf_dir).expect("Missing settings should load as defaults"), Settings::default());
+
+    let mut settings = Settings { threads: Some(1), hash_algo: HashAlgo::Blake3, theme: Theme::Dark, ..Default::default() };
+    settings.accent = Some(Accent { red: 0x33, green: 0x66, blue: 0xcc });
+    settings.filters.exclude = vec!["*.tmp".to_owned()];
+    settings.filters.min_size = Some(1);
+    settings.io_limit = Some(50_000_000);
+    settings.save(conf_dir).expect("Failed to save settings");
+    assert_eq!(Settings::load(conf_dir).expect("Failed to load settings"), settings);
+    assert_eq!((settings.relate_conf().strategy, settings.relate_conf().io_limit), (Strategy::Sequential, Some(50_000_000)));
+
+    // Written by hand, leaving out whatever keeps its default.
+    fs::write(Settings::file(conf_dir), "threads = 4\naccent = \"#FF8800\"\n\n[filters]\nsymlinks = \"Follow\"\n").expect("Failed to write settings");
+    let settings = Settings::load(conf_dir).expect("Failed to load settings");
+    assert_eq!(settings.strategy(), Strategy::Parallel { threads: 4 });
+    assert_eq!(settings.filters.symlinks, SymlinkPolicy::Follow);
+    assert_eq!(settings.hash_algo, HashAlgo::Sha256);
+    assert_eq!((settings.theme, settings.accent.map(|accent| accent.to_string())), (Theme::System, Some("#ff8800".to_owned())));
+    assert!("#36c".parse::<Accent>().is_err());
+    let _ = fs::remove_dir_all(CONF_DIR);
+}
+
+#[test]
+fn test_settings_profiles() {
+    const PROFILES_DIR: &str = "scratch/settings_profiles";
+    let _ = fs::remove_dir_all(PROFILES_DIR);
+    let conf_dir = Path::new(PROFILES_DIR);
+    fs::create_dir_all(conf_dir).expect("Failed to create test directory");
+    let mut photos = Profile { name: "Photos cleanup".to_owned(), keep: Some(KeepPolicy::OldestCreated), action: Some(Action::HardLink), ..Default::default() };
+    photos.filters.include = vec!["*.jpg".to_owned(), "*.png".to_owned()];
+    let mut downloads = Profile { name: "Downloads purge".to_owned(), action: Some(Action::Trash), ..Default::default() };
+    downloads.filters.min_size = Some(1 << 20);
+    let mut settings = Settings::default();
+    settings.save_profile(photos.clone());
+    settings.save_profile(downloads.clone());
+    settings.save(conf_dir).expect("Failed to save settings");
+    let mut settings = Settings::load(conf_dir).expect("Failed to load settings");
+    assert_eq!(settings.profile("photos CLEANUP"), Some(&photos));
+
+    let mut project = Project::new("scratch/settings_profiles/project".into());
+    project.preferences.keep = Some(KeepPolicy::ShortestPath);
+    project.apply_profile(&downloads);
+    assert_eq!(project.walk_conf.min_size, Some(1 << 20));
+    // The profile leaves the keep policy to the project.
+    assert_eq!((project.preferences.keep, project.preferences.action), (Some(KeepPolicy::ShortestPath), Some(Action::Trash)));
+
+    settings.save_profile(Profile { name: "photos cleanup".to_owned(), ..photos });
+    assert_eq!(settings.profiles.len(), 2);
+    assert!(settings.remove_profile("Downloads purge"));
+    assert!(!settings.remove_profile("Downloads purge"));
+    assert_eq!(settings.profiles.len(), 1);
+    let _ = fs::remove_dir_all(PROFILES_DIR);
+}
+
+#[test]
+fn test_default_filters_skip_mac_metadata() {
+    const MAC_DIR: &str = "scratch/settings_mac";
+    let _ = fs::remove_dir_all(MAC_DIR);
+    let dir = Path::new(MAC_DIR);
+    fs::create_dir_all(dir.join("album/.Trashes")).expect("Failed to create test directory");
+    for name in ["album/photo.jpg", "album/._photo.jpg", "album/.DS_Store", ".DS_Store", "album/.Trashes/old.jpg"] {
+        fs::write(dir.join(name), "contents").expect("Failed to write file");
+    }
+    let filter = WalkFilter::new(&Settings::default().filters).expect("Failed to compile filter");
+    let walk = WalkInfo::walk_filtered(dir.to_path_buf(), &filter);
+    assert_eq!(walk.files.iter().filter(|fi| fi.name.is_file()).map(|fi| fi.name.to_path_buf()).collect::<Vec<_>>(), [dir.join("album/photo.jpg")]);
+    let _ = fs::remove_dir_all(MAC_DIR);
+}
@@ -0,0 +1,46 @@
+use file_deduplicator::{
+    relate::{HashAlgo, Strategy, WalkConf},
+    telemetry::{self, Event, ScanConf, ScanLog, ScanOutcome},
+};
+use std::{fs, io::Write, path::{Path, PathBuf}};
+
+const TEST_DIR: &'static str = "scratch/telemetry";
+
+fn conf(dir: &str) -> ScanConf {
+    ScanConf {
+        dir: PathBuf::from(dir),
+        extra_roots: Vec::new(),
+        reference: None,
+        walk: WalkConf::default(),
+        strategy: Strategy::Parallel { threads: 4 },
+        device_threads: None,
+        hash_algo: HashAlgo::Blake3,
+        max_duration: None,
+        mmap_threshold: None,
+        io_limit: None,
+        resumed: false,
+        pipelined: false,
+    }
+}
+
+#[test]
+fn test_scan_log_shows_unfinished_scans() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let conf_dir = Path::new(TEST_DIR);
+
+    let finished = ScanLog::start(conf_dir, conf("/finished")).expect("Failed to start logging");
+    finished.walked(3, 30).expect("Failed to log the walk");
+    let crashed = ScanLog::start(conf_dir, conf("/crashed")).expect("Failed to start logging");
+    finished.finished(ScanOutcome { groups: 1, errors: 0, partial: false, cancelled: false, usage: None }).expect("Failed to log the end");
+    drop(crashed);
+    // A crash in the middle of appending.
+    let mut file = fs::OpenOptions::new().append(true).open(telemetry::log_path(conf_dir)).expect("Failed to open the log");
+    file.write_all(b"{\"time\":").expect("Failed to write the log");
+
+    let records = telemetry::records(conf_dir).expect("Failed to read the log");
+    assert_eq!(records.len(), 4);
+    assert_eq!(records[0].event, Event::Started(conf("/finished")));
+    let unfinished = telemetry::unfinished(&records);
+    assert_eq!(unfinished.len(), 1);
+    assert_eq!(unfinished[0].event, Event::Started(conf("/crashed")));
+}
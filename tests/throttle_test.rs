@@ -0,0 +1,39 @@
+use file_deduplicator::{
+    relate::{RelateConf, RelatedFiles, Strategy, WalkInfo},
+    throttle::Throttle,
+};
+use std::{fs, path::Path, sync::mpsc, time::{Duration, Instant}};
+
+const TEST_DIR: &'static str = "scratch/throttle";
+
+#[test]
+fn test_throttle_reserve() {
+    let throttle = Throttle::new(1000);
+    let start = Instant::now();
+    // A second's worth goes through at once, the rest waits its turn.
+    assert_eq!(throttle.reserve(start, 1000), Duration::ZERO);
+    assert_eq!(throttle.reserve(start, 500), Duration::from_millis(500));
+    assert_eq!(throttle.reserve(start, 500), Duration::from_secs(1));
+    // Waited out, and no more saved up than a second's worth.
+    assert_eq!(throttle.reserve(start + Duration::from_secs(10), 1000), Duration::ZERO);
+    assert_eq!(throttle.reserve(start + Duration::from_secs(10), 100), Duration::from_millis(100));
+    assert_eq!(Throttle::new(0).bytes_per_sec(), 1);
+}
+
+#[test]
+fn test_throttled_relate() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    let dir = Path::new(TEST_DIR);
+    fs::create_dir_all(dir).expect("Failed to create test directory");
+    for (name, contents) in [("a", "same"), ("b", "same"), ("c", "other")] {
+        fs::write(dir.join(name), contents).expect("Failed to write file");
+    }
+    let walk = WalkInfo::walk(dir.into());
+    for strategy in [Strategy::Sequential, Strategy::Parallel { threads: 2 }] {
+        let conf = RelateConf { strategy, io_limit: Some(1024 * 1024), low_priority: true, ..Default::default() };
+        let related = RelatedFiles::relate(&walk, &conf, mpsc::channel().0);
+        let groups = related.duplicate_groups();
+        assert_eq!((groups.len(), groups[0].files.len()), (1, 2), "{:?}", strategy);
+    }
+    let _ = fs::remove_dir_all(TEST_DIR);
+}
@@ -0,0 +1,49 @@
+use file_deduplicator::{
+    relate::{RelateConf, RelatedFiles, WalkFilter, WalkInfo},
+    watch::{self, Watch},
+};
+use std::{
+    collections::BTreeSet,
+    fs,
+    path::PathBuf,
+    sync::mpsc,
+    time::Duration,
+};
+
+const TEST_DIR: &str = "scratch/watch";
+
+#[test]
+fn test_watch_keeps_results_up_to_date() {
+    let _ = fs::remove_dir_all(TEST_DIR);
+    fs::create_dir_all(TEST_DIR).expect("Failed to create test directory");
+    let dir = fs::canonicalize(TEST_DIR).expect("Failed to find test directory");
+    fs::write(dir.join("a"), "same").expect("Failed to write file");
+    fs::write(dir.join("b"), "other").expect("Failed to write file");
+    let mut walk = WalkInfo::walk(dir.clone());
+    let (progress_tx, _progress_rx) = mpsc::channel();
+    let mut related = RelatedFiles::relate_sequential(&walk, &RelateConf::default(), progress_tx);
+    assert!(related.duplicate_groups().is_empty());
+
+    let watch = Watch::start(&[&dir]).expect("Failed to watch test directory");
+    fs::write(dir.join("c"), "same").expect("Failed to write file");
+    fs::hard_link(dir.join("a"), dir.join("d")).expect("Failed to link file");
+    fs::remove_file(dir.join("b")).expect("Failed to remove file");
+    let expected = ["b", "c", "d"].map(|name| dir.join(name));
+    let mut changed = BTreeSet::new();
+    for _ in 0..10 {
+        changed.extend(watch.changed(Duration::from_secs(1)));
+        if expected.iter().all(|path| changed.contains(path)) {
+            break;
+        }
+    }
+    let updates = watch.examine(&changed, &WalkFilter::default(), related.algo);
+    watch::apply(updates, &mut walk, &mut related);
+
+    let groups = related.duplicate_groups();
+    assert_eq!(groups.len(), 1);
+    let names = groups[0].files.iter().map(|fi| fi.name.to_path_buf()).collect::<BTreeSet<PathBuf>>();
+    assert_eq!(names, [dir.join("a"), dir.join("c")].into());
+    assert_eq!(walk.hard_links, [vec![dir.join("a"), dir.join("d")]]);
+    assert!(!walk.files.iter().any(|fi| *fi.name == dir.join("b")));
+    let _ = fs::remove_dir_all(TEST_DIR);
+}